@@ -0,0 +1,151 @@
+//! Switching between a light and a dark theme.
+//!
+//! With a `[theme_switch]` section, the color scheme decides on the theme:
+//! either the `color-scheme` setting of the desktop portal, which desktops
+//! change along with their own appearance, or a time of day schedule. The bar
+//! swaps `theme` for the `light` or `dark` theme, and rebuilds the blocks
+//! whenever the scheme changes.
+
+use std::thread;
+use std::time::Duration;
+
+use chan::Sender;
+use chrono::{Local, NaiveTime};
+use dbus::arg::{RefArg, Variant};
+use dbus::{BusType, Connection, Message, MessageType};
+
+use config::{Config, SchemeSource, ThemeSwitchConfig};
+use errors::*;
+
+const PORTAL_NAME: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SETTINGS_INTERFACE: &str = "org.freedesktop.portal.Settings";
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+const COLOR_SCHEME_KEY: &str = "color-scheme";
+
+/// The schedule is checked at least this often, sleeps don't count time
+/// spent suspended.
+const SCHEDULE_CHECK_SECS: u64 = 60;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Scheme {
+    Light,
+    Dark,
+}
+
+/// The scheme at `time` of day, according to the schedule of `switch`.
+fn scheduled(switch: &ThemeSwitchConfig, time: NaiveTime) -> Scheme {
+    let (light_from, dark_from) = (switch.light_from, switch.dark_from);
+    let light = if light_from <= dark_from {
+        light_from <= time && time < dark_from
+    } else {
+        !(dark_from <= time && time < light_from)
+    };
+    if light {
+        Scheme::Light
+    } else {
+        Scheme::Dark
+    }
+}
+
+/// The scheme a `color-scheme` value stands for. It's 1 for dark, 2 for light
+/// and 0 for no preference, which we take as light. Depending on the portal
+/// version, the value comes wrapped in a variant once or twice.
+fn portal_scheme(value: &RefArg) -> Option<Scheme> {
+    match value.as_u64() {
+        Some(1) => Some(Scheme::Dark),
+        Some(_) => Some(Scheme::Light),
+        None => value
+            .as_iter()
+            .and_then(|mut inner| inner.next().and_then(|inner| portal_scheme(inner))),
+    }
+}
+
+fn read_portal(c: &Connection) -> Result<Scheme> {
+    let m = Message::new_method_call(PORTAL_NAME, PORTAL_PATH, SETTINGS_INTERFACE, "Read")
+        .internal_error("appearance", "failed to create message")?
+        .append2(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY);
+    let r = c.send_with_reply_and_block(m, 1000)
+        .internal_error("appearance", "failed to read the color scheme from the portal")?;
+    let value: Variant<Box<RefArg>> = r.get1()
+        .internal_error("appearance", "invalid color scheme from the portal")?;
+    portal_scheme(&*value.0).internal_error("appearance", "invalid color scheme from the portal")
+}
+
+/// The scheme right now. If the portal can't tell, it's light.
+pub fn current(switch: &ThemeSwitchConfig) -> Scheme {
+    match switch.source {
+        SchemeSource::Schedule => scheduled(switch, Local::now().time()),
+        SchemeSource::Portal => Connection::get_private(BusType::Session)
+            .internal_error("appearance", "failed to connect to the session bus")
+            .and_then(|c| read_portal(&c))
+            .unwrap_or_else(|error| {
+                warn!("failed to get the color scheme, going with light: {:?}", error);
+                Scheme::Light
+            }),
+    }
+}
+
+/// Make the theme of `scheme` the theme of `config`, if it switches themes.
+pub fn apply(config: &mut Config, scheme: Scheme) {
+    if let Some(ref switch) = config.theme_switch {
+        config.theme = match scheme {
+            Scheme::Light => switch.light.clone(),
+            Scheme::Dark => switch.dark.clone(),
+        };
+    }
+}
+
+fn listen_portal(tx: &Sender<Scheme>) -> Result<()> {
+    let c = Connection::get_private(BusType::Session).internal_error("appearance", "failed to connect to the session bus")?;
+    c.add_match(&format!(
+        "type='signal',interface='{}',member='SettingChanged',arg0='{}',arg1='{}'",
+        SETTINGS_INTERFACE,
+        APPEARANCE_NAMESPACE,
+        COLOR_SCHEME_KEY
+    )).internal_error("appearance", "failed to add match rule")?;
+
+    loop {
+        for msg in c.incoming(60 * 1000) {
+            if msg.msg_type() != MessageType::Signal ||
+                msg.member().map_or(true, |member| &*member != "SettingChanged")
+            {
+                continue;
+            }
+            if let (Some(namespace), Some(key), Some(value)) = msg.get3::<String, String, Variant<Box<RefArg>>>() {
+                if namespace != APPEARANCE_NAMESPACE || key != COLOR_SCHEME_KEY {
+                    continue;
+                }
+                if let Some(scheme) = portal_scheme(&*value.0) {
+                    debug!("color scheme changed to {:?}", scheme);
+                    tx.send(scheme);
+                }
+            }
+        }
+    }
+}
+
+fn follow_schedule(switch: &ThemeSwitchConfig, tx: &Sender<Scheme>) {
+    let mut last = scheduled(switch, Local::now().time());
+    loop {
+        thread::sleep(Duration::from_secs(SCHEDULE_CHECK_SECS));
+        let scheme = scheduled(switch, Local::now().time());
+        if scheme != last {
+            debug!("color scheme changed to {:?} by schedule", scheme);
+            tx.send(scheme);
+            last = scheme;
+        }
+    }
+}
+
+/// Send the scheme through `tx` whenever it changes. Failing to listen is not
+/// fatal, the theme just stays as it is then.
+pub fn spawn_listener(switch: &ThemeSwitchConfig, tx: Sender<Scheme>) {
+    let switch = switch.clone();
+    thread::spawn(move || match switch.source {
+        SchemeSource::Portal => if let Err(error) = listen_portal(&tx) {
+            warn!("changes of the color scheme can't be followed: {:?}", error);
+        },
+        SchemeSource::Schedule => follow_schedule(&switch, &tx),
+    });
+}
@@ -0,0 +1,227 @@
+//! Serving several bars from one process.
+//!
+//! Besides the bar on stdout, the configuration may define further bars, each
+//! showing some of the blocks and served on a Unix socket:
+//!
+//! ```toml
+//! [[bar]]
+//! name = "laptop"
+//! socket = "${XDG_RUNTIME_DIR}/i3status-rs-laptop.sock"
+//! blocks = ["battery", "time"]
+//! ```
+//!
+//! The bar itself connects to the socket, e.g. with `status_command socat -
+//! UNIX-CONNECT:/run/user/1000/i3status-rs-laptop.sock`. Every client gets
+//! the status line and may send click events, just like on stdin. A bar
+//! without a socket picks the blocks shown on stdout, which are all of them
+//! otherwise.
+//!
+//! Blocks are updated once, no matter how many bars show them.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::mem;
+use std::io::{BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use chan::Sender;
+
+use block::Block;
+use config::{BarConfig, Config};
+use errors::*;
+use input::{read_events, I3BarEvent};
+use output::{self, OutputFormat};
+use reload::BlockSpec;
+use util;
+
+/// The blocks of `order` and `specs` named in `names`, by type or id, in the
+/// order of `names`. All blocks if there are no names.
+pub fn select<'a>(
+    names: Option<&[String]>,
+    order: &'a [String],
+    specs: &'a [BlockSpec],
+) -> Vec<(&'a String, &'a BlockSpec)> {
+    let all = order.iter().zip(specs);
+    match names {
+        Some(names) => names
+            .iter()
+            .flat_map(|name| all.clone().filter(move |&(_, spec)| spec.matches(name)))
+            .collect(),
+        None => all.collect(),
+    }
+}
+
+/// The blocks shown on stdout, if a bar without a socket picks them.
+pub fn stdout_blocks(config: &Config) -> Option<&[String]> {
+    config
+        .bars
+        .iter()
+        .find(|bar| bar.socket.is_none())
+        .map(|bar| &bar.blocks[..])
+}
+
+/// Make sure the bars of `config` make sense, before anything is started.
+pub fn check(config: &Config) -> Result<()> {
+    let mut names = HashSet::new();
+    for bar in &config.bars {
+        if !names.insert(&bar.name) {
+            return Err(bar_error(format!("there are several bars named '{}'", bar.name)));
+        }
+        for name in &bar.blocks {
+            let known = config.blocks.iter().any(|&(ref block, ref block_config)| {
+                block == name || block_config.get("id").and_then(|id| id.as_str()) == Some(name.as_str())
+            });
+            if !known {
+                return Err(bar_error(format!(
+                    "bar '{}' shows the block '{}', which isn't configured",
+                    bar.name,
+                    name
+                )));
+            }
+        }
+    }
+    if config.bars.iter().filter(|bar| bar.socket.is_none()).count() > 1 {
+        return Err(bar_error("only one bar can go without a `socket`".to_owned()));
+    }
+    Ok(())
+}
+
+fn bar_error(message: String) -> Error {
+    ConfigurationError(message.clone(), (message.clone(), message))
+}
+
+struct Clients {
+    streams: Vec<UnixStream>,
+    /// What was sent last, for clients connecting later on
+    last_line: Option<String>,
+}
+
+/// A bar served on a socket.
+pub struct Bar {
+    config: BarConfig,
+    output: Box<OutputFormat>,
+    clients: Arc<Mutex<Clients>>,
+    /// The widgets and the line of the last redraw, reused for the next
+    cache: util::RenderCache,
+    line: String,
+}
+
+impl Bar {
+    /// Start serving the bar. Click events of its clients are sent to
+    /// `tx_clicks`.
+    pub fn spawn(config: &BarConfig, tx_clicks: Sender<I3BarEvent>) -> Result<Bar> {
+        let path = config
+            .socket
+            .clone()
+            .internal_error("bars", "bar without a socket can't be served")?;
+        // The bar runs a program connecting to the socket, signals would go
+        // to that.
+        let output = output::from_name(&config.format, false)?;
+        let header = output.header();
+        let click_events = output.click_events();
+        let clients = Arc::new(Mutex::new(Clients {
+            streams: Vec::new(),
+            last_line: None,
+        }));
+
+        // A socket left behind by a previous run would make binding fail.
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).internal_error("bars", &format!("failed to bind {}", path))?;
+
+        {
+            let clients = clients.clone();
+            thread::spawn(move || for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                // A client that doesn't read its status lines mustn't hold up
+                // the other bars.
+                if stream.set_write_timeout(Some(Duration::from_secs(1))).is_err() {
+                    continue;
+                }
+                if click_events {
+                    if let Ok(reader) = stream.try_clone() {
+                        let tx_clicks = tx_clicks.clone();
+                        thread::spawn(move || read_events(BufReader::new(reader), &tx_clicks));
+                    }
+                }
+
+                if let Ok(mut clients) = clients.lock() {
+                    if let Some(ref header) = header {
+                        if stream.write_all(header.as_bytes()).is_err() {
+                            continue;
+                        }
+                    }
+                    if let Some(ref line) = clients.last_line {
+                        if writeln!(stream, "{}", line).is_err() {
+                            continue;
+                        }
+                    }
+                    clients.streams.push(stream);
+                }
+            });
+        }
+
+        Ok(Bar {
+            config: config.clone(),
+            output,
+            clients,
+            cache: util::RenderCache::default(),
+            line: String::new(),
+        })
+    }
+
+    /// Start serving the bars of `config` that have a socket.
+    pub fn spawn_all(config: &Config, tx_clicks: &Sender<I3BarEvent>) -> Result<Vec<Bar>> {
+        config
+            .bars
+            .iter()
+            .filter(|bar| bar.socket.is_some())
+            .map(|bar| Bar::spawn(bar, tx_clicks.clone()))
+            .collect()
+    }
+
+    /// Pick up the blocks shown by the bar of the same name in a reloaded
+    /// `config`. Bars can't be added or removed this way, nor change their
+    /// socket.
+    pub fn reload(&mut self, config: &Config) {
+        if let Some(bar) = config.bars.iter().find(|bar| bar.name == self.config.name) {
+            self.config.blocks = bar.blocks.clone();
+        }
+    }
+
+    /// Send the status line to all clients, if it changed.
+    pub fn redraw(
+        &mut self,
+        order: &[String],
+        specs: &[BlockSpec],
+        block_map: &HashMap<String, Box<Block>>,
+        hidden: &HashSet<String>,
+        config: &Config,
+    ) -> Result<()> {
+        let blocks = select(Some(&self.config.blocks[..]), order, specs);
+        self.output
+            .write_line(self.cache.render(blocks, block_map, hidden)?, config, &mut self.line)?;
+
+        let mut clients = self.clients
+            .lock()
+            .internal_error("bars", "failed to lock clients")?;
+        if clients.last_line.as_ref() == Some(&self.line) {
+            return Ok(());
+        }
+        // Clients that went away are dropped.
+        let line = &mut self.line;
+        clients
+            .streams
+            .retain(|stream| writeln!(&mut &*stream, "{}", line).is_ok());
+        // The old line is the buffer for the next one
+        let mut last_line = clients.last_line.take().unwrap_or_default();
+        mem::swap(&mut last_line, line);
+        clients.last_line = Some(last_line);
+        Ok(())
+    }
+}
@@ -1,10 +1,17 @@
+use chrono::Weekday;
 use config::Config;
+use de::{deserialize_opt_active_hours, deserialize_opt_duration, deserialize_opt_weekdays};
+use std::collections::HashMap;
 use errors::*;
-use scheduler::Task;
+use scheduler::{ActiveHours, Task, UpdateAlign};
 use chan::Sender;
 use std::time::Duration;
-use input::I3BarEvent;
+use input::{I3BarEvent, MouseButton};
 use widget::I3BarWidget;
+use serde::de::Deserialize;
+use signals;
+use state::BlockState;
+use toml::value::{Table, Value};
 
 pub trait Block {
     /// Updates the internal state of a Block
@@ -15,20 +22,222 @@ pub trait Block {
     fn view(&self) -> Vec<&I3BarWidget>;
 
     #[allow(unused_variables)]
-    /// This function is called on every block for every click.
-    /// Filter events by using the event.name property (matches the ButtonWidget name)
+    /// This function is called on the block whose widget was clicked only.
+    /// Tell its widgets apart by using the event.name property (matches the ButtonWidget name)
+    /// or event.widget_index, the position of the clicked widget in the view.
+    /// The event also tells about modifier keys and double clicks.
     fn click(&mut self, event: &I3BarEvent) -> Result<()> {
         Ok(())
     }
 
+    #[allow(unused_variables)]
+    /// Replaces the text of the block from outside the bar, e.g. through the
+    /// control socket. Only blocks that display arbitrary text support this.
+    fn set_text(&mut self, text: String) -> Result<()> {
+        Err(InternalError(
+            "block".to_owned(),
+            "this block does not support setting its text".to_owned(),
+            None,
+        ))
+    }
+
+    /// Called when the system resumed from suspend, right before all blocks
+    /// are updated. Blocks computing rates from the previous update should
+    /// start over here, as the time in between would skew them.
+    fn resumed(&mut self) {}
+
+    /// The numbers the last update measured, for the metrics file, see
+    /// `metrics`. Names follow the Prometheus conventions and end in their
+    /// unit, e.g. `battery_charge_percent`.
+    fn metrics(&self) -> Vec<(&'static str, f64)> {
+        Vec::new()
+    }
+
     /// This function returns a unique id.
     fn id(&self) -> &str;
 }
 
+/// Options every block accepts in addition to its own.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CommonBlockConfig {
+    /// Name to address the block by from outside the bar. Several blocks may
+    /// share an id, commands then apply to all of them.
+    pub id: Option<String>,
+
+    /// Update the block whenever `SIGRTMIN+signal` is received
+    pub signal: Option<i32>,
+
+    /// Theme colors to use for this block instead of the global ones
+    pub theme_overrides: Option<HashMap<String, String>>,
+
+    /// Icons to use for this block instead of the global ones
+    pub icons_overrides: Option<HashMap<String, String>>,
+
+    /// Minimum width of each of the block's widgets, keeps it from changing
+    /// width along with its value
+    pub min_width: Option<MinWidth>,
+
+    /// Alignment of the text within `min_width`
+    pub align: Option<Align>,
+
+    /// Whether i3bar draws its separator line after the block
+    pub separator: Option<bool>,
+
+    /// Gap after the block, in pixels
+    pub separator_block_width: Option<u32>,
+
+    /// Only show the block on this page, see `paging`
+    pub page: Option<u32>,
+
+    /// Shell commands to run when the block is clicked, before the block
+    /// itself gets to handle the click. See `util::spawn_click_command`.
+    pub on_click: Option<String>,
+    pub on_right_click: Option<String>,
+    pub on_scroll_up: Option<String>,
+    pub on_scroll_down: Option<String>,
+    /// Run on a double left click instead of `on_click`, whose clicks are
+    /// then held back for the double click interval
+    pub on_double_click: Option<String>,
+
+    /// Move the block's updates to the nearest full second, minute, hour or
+    /// day, see `scheduler::aligned`
+    pub update_align: Option<UpdateAlign>,
+
+    /// Delay the block's updates by a random time up to this long, so that
+    /// blocks polling the same service don't all do so at once
+    #[serde(default, deserialize_with = "deserialize_opt_duration")]
+    pub update_jitter: Option<Duration>,
+
+    /// Show a desktop notification with the block's text whenever it turns
+    /// critical, see `supervisor::Supervised`
+    #[serde(default)]
+    pub notify_on_critical: bool,
+
+    /// Only update and show the block within these hours of the day, see
+    /// `scheduler::is_active`
+    #[serde(default, deserialize_with = "deserialize_opt_active_hours")]
+    pub active_hours: Option<ActiveHours>,
+
+    /// Only update and show the block on these days of the week
+    #[serde(default, deserialize_with = "deserialize_opt_weekdays")]
+    pub active_days: Option<Vec<Weekday>>,
+
+    /// Keep showing the previous state of a widget until a new one held for
+    /// this many updates in a row, see `supervisor::Debounce`
+    pub state_debounce: Option<u32>,
+
+    /// Updates taking longer than this are logged, overrides the top level
+    /// `update_budget` for the block
+    #[serde(default, deserialize_with = "deserialize_opt_duration")]
+    pub update_budget: Option<Duration>,
+
+    /// Update interval for blocks without an `interval` option of their own,
+    /// set by `reload::resolve_interval`
+    #[serde(skip)]
+    pub interval: Option<Duration>,
+}
+
+/// The i3bar `min_width` of a widget.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum MinWidth {
+    Pixels(u32),
+    /// As wide as the text would be
+    Text(String),
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl CommonBlockConfig {
+    pub const KEYS: &'static [&'static str] = &[
+        "id",
+        "signal",
+        "theme_overrides",
+        "icons_overrides",
+        "min_width",
+        "align",
+        "separator",
+        "separator_block_width",
+        "page",
+        "on_click",
+        "on_right_click",
+        "on_scroll_up",
+        "on_scroll_down",
+        "on_double_click",
+        "update_align",
+        "update_jitter",
+        "notify_on_critical",
+        "active_hours",
+        "active_days",
+        "state_debounce",
+        "update_budget",
+    ];
+
+    /// Split the common options off a block's configuration, leaving only the
+    /// block's own options in `block_config`.
+    pub fn extract(block_config: &mut Value) -> Result<CommonBlockConfig> {
+        let mut common = Table::new();
+        if let Value::Table(ref mut table) = *block_config {
+            for key in Self::KEYS {
+                if let Some(value) = table.remove(*key) {
+                    common.insert((*key).to_owned(), value);
+                }
+            }
+        }
+
+        let common = CommonBlockConfig::deserialize(Value::Table(common))
+            .configuration_error("failed to deserialize common block config")?;
+
+        if let Some(signal) = common.signal {
+            let max = signals::sigrtmax() - signals::sigrtmin();
+            if signal < 0 || signal > max {
+                return Err(ConfigurationError(
+                    format!("signal must be between 0 and {}", max),
+                    (format!("{}", signal), format!("{:?}", signal)),
+                ));
+            }
+        }
+
+        Ok(common)
+    }
+
+    /// The command to run for `event`, if any.
+    pub fn click_command(&self, event: &I3BarEvent) -> Option<&String> {
+        match event.button {
+            MouseButton::Left if event.double_click && self.on_double_click.is_some() => self.on_double_click.as_ref(),
+            MouseButton::Left => self.on_click.as_ref(),
+            MouseButton::Right => self.on_right_click.as_ref(),
+            MouseButton::WheelUp => self.on_scroll_up.as_ref(),
+            MouseButton::WheelDown => self.on_scroll_down.as_ref(),
+            _ => None,
+        }
+    }
+}
+
 pub trait ConfigBlock: Block {
     type Config;
 
     fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self>
     where
         Self: Sized;
+    #[allow(unused_variables)]
+    /// Like `new`, for blocks that keep state across restarts of the bar.
+    fn new_with_state(
+        block_config: Self::Config,
+        config: Config,
+        tx_update_request: Sender<Task>,
+        state: BlockState,
+    ) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::new(block_config, config, tx_update_request)
+    }
 }
@@ -0,0 +1,258 @@
+//! A block for the ambient light sensor of laptops, which can also set the
+//! brightness of the backlight to match.
+//!
+//! The sensor is read through [iio-sensor-proxy](https://gitlab.freedesktop.org/hadess/iio-sensor-proxy),
+//! which only reports it while a client claims it, as the block does for as
+//! long as it lives. The backlight is set through the same `sysfs` files as
+//! the [`Backlight`](../backlight/struct.Backlight.html) block uses, which
+//! then shows the new brightness.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use blocks::backlight::BacklitDevice;
+use blocks::dbus::arg::{RefArg, Variant};
+use blocks::dbus::{BusType, Connection, Message};
+use config::Config;
+use dbus_manager;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Unit, Value};
+use input::{I3BarEvent, MouseButton};
+use scheduler::Task;
+use widget::{I3BarWidget, State};
+use widgets::button::ButtonWidget;
+
+const SENSOR_PROXY_NAME: &str = "net.hadess.SensorProxy";
+const SENSOR_PROXY_PATH: &str = "/net/hadess/SensorProxy";
+
+/// Shows the ambient light level, and adjusts the backlight to it while
+/// automatic brightness is on, see `AmbientLightConfig`.
+pub struct AmbientLight {
+    id: String,
+    output: ButtonWidget,
+    format: FormatTemplate,
+    /// Whether the backlight follows the ambient light
+    auto: bool,
+    device: BacklitDevice,
+    min_brightness: u64,
+    max_brightness: u64,
+    max_lux: f64,
+    step_width: u64,
+    step_interval: Duration,
+    dbus_conn: Connection,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AmbientLightConfig {
+    /// Format override, `{lux}` and `{brightness}` are available
+    #[serde(default = "AmbientLightConfig::default_format")]
+    pub format: String,
+
+    /// Whether automatic brightness is on from the start
+    #[serde(default = "AmbientLightConfig::default_auto")]
+    pub auto: bool,
+
+    /// The backlight device in `/sys/class/backlight/` to adjust, the first
+    /// one if not given
+    #[serde(default = "AmbientLightConfig::default_device")]
+    pub device: Option<String>,
+
+    /// The brightness in the dark, as a percent
+    #[serde(default = "AmbientLightConfig::default_min_brightness")]
+    pub min_brightness: u64,
+
+    /// The brightness at `max_lux` and above, as a percent
+    #[serde(default = "AmbientLightConfig::default_max_brightness")]
+    pub max_brightness: u64,
+
+    /// The light level at which the brightness reaches `max_brightness`
+    #[serde(default = "AmbientLightConfig::default_max_lux")]
+    pub max_lux: f64,
+
+    /// How much the brightness changes at a time, as a percent
+    #[serde(default = "AmbientLightConfig::default_step_width")]
+    pub step_width: u64,
+
+    /// How long to wait between steps, in seconds, so that the brightness
+    /// fades instead of jumping
+    #[serde(default = "AmbientLightConfig::default_step_interval", deserialize_with = "deserialize_duration")]
+    pub step_interval: Duration,
+}
+
+impl AmbientLightConfig {
+    fn default_format() -> String {
+        "{lux:.0} lx".to_owned()
+    }
+
+    fn default_auto() -> bool {
+        false
+    }
+
+    fn default_device() -> Option<String> {
+        None
+    }
+
+    fn default_min_brightness() -> u64 {
+        10
+    }
+
+    fn default_max_brightness() -> u64 {
+        100
+    }
+
+    fn default_max_lux() -> f64 {
+        1000.
+    }
+
+    fn default_step_width() -> u64 {
+        2
+    }
+
+    fn default_step_interval() -> Duration {
+        Duration::from_millis(100)
+    }
+}
+
+fn sensor_property(c: &Connection, name: &str) -> Result<Variant<Box<RefArg>>> {
+    let m = Message::new_method_call(
+        SENSOR_PROXY_NAME,
+        SENSOR_PROXY_PATH,
+        "org.freedesktop.DBus.Properties",
+        "Get",
+    ).block_error("ambient_light", "failed to create D-Bus message")?
+        .append2(SENSOR_PROXY_NAME, name);
+    let r = c.send_with_reply_and_block(m, 1000)
+        .block_error("ambient_light", "failed to get a property from iio-sensor-proxy")?;
+    r.get1()
+        .block_error("ambient_light", &format!("invalid property {} from iio-sensor-proxy", name))
+}
+
+impl AmbientLight {
+    /// The brightness for the light level `lux`. Eyes perceive light
+    /// logarithmically, so does the brightness follow it.
+    fn brightness_for(&self, lux: f64) -> u64 {
+        let ratio = ((1. + lux.max(0.)).ln() / (1. + self.max_lux).ln()).min(1.);
+        let range = self.max_brightness.saturating_sub(self.min_brightness) as f64;
+        self.min_brightness + (ratio * range).round() as u64
+    }
+
+    /// Take one step from the current brightness towards the one for `lux`.
+    /// Returns whether there are more to take.
+    fn step_towards(&self, lux: f64) -> Result<bool> {
+        let target = self.brightness_for(lux);
+        let current = self.device.brightness()?;
+        let next = if current < target {
+            (current + self.step_width).min(target)
+        } else if current > target {
+            current.saturating_sub(self.step_width).max(target)
+        } else {
+            return Ok(false);
+        };
+        self.device.set_brightness(next)?;
+        Ok(next != target)
+    }
+}
+
+impl ConfigBlock for AmbientLight {
+    type Config = AmbientLightConfig;
+
+    fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        let device = match block_config.device {
+            Some(device) => BacklitDevice::from_device(device),
+            None => BacklitDevice::default(),
+        }?;
+
+        let dbus_conn = Connection::get_private(BusType::System)
+            .block_error("ambient_light", "failed to connect to the system bus")?;
+        let has_light = sensor_property(&dbus_conn, "HasAmbientLight")?
+            .0
+            .as_u64()
+            .map_or(false, |has_light| has_light != 0);
+        if !has_light {
+            return Err(BlockError(
+                "ambient_light".to_owned(),
+                "no ambient light sensor found".to_owned(),
+            ));
+        }
+        // The light level is only updated while claimed, until the
+        // connection closes
+        let m = Message::new_method_call(SENSOR_PROXY_NAME, SENSOR_PROXY_PATH, SENSOR_PROXY_NAME, "ClaimLight")
+            .block_error("ambient_light", "failed to create D-Bus message")?;
+        dbus_conn
+            .send_with_reply_and_block(m, 1000)
+            .block_error("ambient_light", "failed to claim the ambient light sensor")?;
+        dbus_manager::subscribe(
+            BusType::System,
+            &format!(
+                "type='signal',interface='org.freedesktop.DBus.Properties',member='PropertiesChanged',path='{}'",
+                SENSOR_PROXY_PATH
+            ),
+            &id,
+            &send,
+        )?;
+
+        Ok(AmbientLight {
+            output: ButtonWidget::new(config, &id).with_icon("ambient_light"),
+            id,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("ambient_light", "Invalid format specified for ambient_light")?,
+            auto: block_config.auto,
+            device,
+            min_brightness: block_config.min_brightness.min(100),
+            max_brightness: block_config.max_brightness.min(100),
+            max_lux: block_config.max_lux,
+            step_width: block_config.step_width.max(1),
+            step_interval: block_config.step_interval,
+            dbus_conn,
+            tx_update_request: send,
+        })
+    }
+}
+
+impl Block for AmbientLight {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        // In lux, or some unit of the sensor if its unit is "vendor"
+        let lux = sensor_property(&self.dbus_conn, "LightLevel")?
+            .0
+            .as_f64()
+            .unwrap_or(0.);
+        let stepping = self.auto && self.step_towards(lux)?;
+
+        let mut values = HashMap::new();
+        values.insert("{lux}", Value::Number(lux, Unit::None));
+        values.insert("{brightness}", Value::Number(self.device.brightness()? as f64, Unit::Percents));
+        self.output.set_text(self.format.render_values(&values)?);
+        self.output.set_state(if self.auto { State::Info } else { State::Idle });
+
+        // The sensor tells about changes, but the fade needs further steps
+        Ok(if stepping { Some(self.step_interval) } else { None })
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.output]
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if event.name.as_ref().map_or(false, |name| name == &self.id) && event.button == MouseButton::Left {
+            self.auto = !self.auto;
+            // Starts the fade, if the backlight doesn't match
+            self.tx_update_request.send(Task {
+                id: self.id.clone(),
+                update_time: Instant::now(),
+            });
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
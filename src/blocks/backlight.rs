@@ -6,22 +6,25 @@
 //! does not depend on `xrandr` (and thus it works on Wayland). To set
 //! brightness levels using `xrandr`, see the
 //! [`Xrandr`](../xrandr/struct.Xrandr.html) block.
+//!
+//! Several devices can be set together, e.g. the screen along with the
+//! keyboard, whose backlight is an LED in `sysfs`, and monitors through the
+//! `ddcci` driver, which makes them backlit devices as well.
 
 use std::fs::OpenOptions;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
-use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use chan::Sender;
-use inotify::{EventMask, Inotify, WatchMask};
+use inotify::WatchMask;
 use uuid::Uuid;
 
 use block::{Block, ConfigBlock};
 use config::Config;
 use errors::*;
 use input::{I3BarEvent, MouseButton};
-use scheduler::Task;
+use scheduler::{self, Task};
 use widget::I3BarWidget;
 use widgets::button::ButtonWidget;
 
@@ -82,10 +85,14 @@ impl BacklitDevice {
         })
     }
 
-    /// Use the backlit device `device`. Returns an error if a directory for
-    /// that device is not found.
+    /// Use the backlit device `device`, or else the LED `device`, like a
+    /// keyboard backlight. Returns an error if a directory for that device is
+    /// not found.
     pub fn from_device(device: String) -> Result<Self> {
-        let device_path = Path::new("/sys/class/backlight").join(device);
+        let mut device_path = Path::new("/sys/class/backlight").join(&device);
+        if !device_path.exists() {
+            device_path = Path::new("/sys/class/leds").join(&device);
+        }
         if !device_path.exists() {
             return Err(BlockError(
                 "backlight".to_string(),
@@ -143,12 +150,38 @@ impl BacklitDevice {
     }
 }
 
+/// A device set along with the one shown, see `BacklightConfig::sync`.
+struct Synced {
+    device: BacklitDevice,
+    min: u64,
+}
+
 /// A block for displaying the brightness of a backlit device.
 pub struct Backlight {
     id: String,
     output: ButtonWidget,
     device: BacklitDevice,
     step_width: u64,
+    min: u64,
+    synced: Vec<Synced>,
+}
+
+/// A further device to set along with `device`, see `BacklightConfig::sync`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SyncedConfig {
+    /// The device in `/sys/class/backlight/` or `/sys/class/leds/`
+    pub device: String,
+
+    /// The lowest brightness the device is set to, as a percent
+    #[serde(default = "SyncedConfig::default_min")]
+    pub min: u64,
+}
+
+impl SyncedConfig {
+    fn default_min() -> u64 {
+        0
+    }
 }
 
 /// Configuration for the [`Backlight`](./struct.Backlight.html) block.
@@ -162,6 +195,15 @@ pub struct BacklightConfig {
     /// The steps brightness is in/decreased for the selected screen (When greater than 50 it gets limited to 50)
     #[serde(default = "BacklightConfig::default_step_width")]
     pub step_width: u64,
+
+    /// The lowest brightness scrolling sets `device` to, as a percent
+    #[serde(default = "BacklightConfig::default_min")]
+    pub min: u64,
+
+    /// Devices to set together with `device` on scrolling. Their brightness
+    /// is scaled along, from their `min` at 0% to 100%.
+    #[serde(default = "BacklightConfig::default_sync")]
+    pub sync: Vec<SyncedConfig>,
 }
 
 impl BacklightConfig {
@@ -172,6 +214,14 @@ impl BacklightConfig {
     fn default_step_width() -> u64 {
         5
     }
+
+    fn default_min() -> u64 {
+        0
+    }
+
+    fn default_sync() -> Vec<SyncedConfig> {
+        Vec::new()
+    }
 }
 
 impl ConfigBlock for Backlight {
@@ -183,6 +233,14 @@ impl ConfigBlock for Backlight {
             None => BacklitDevice::default(),
         }?;
 
+        let mut synced = Vec::new();
+        for sync in block_config.sync {
+            synced.push(Synced {
+                device: BacklitDevice::from_device(sync.device)?,
+                min: sync.min.min(100),
+            });
+        }
+
         let id = Uuid::new_v4().simple().to_string();
         let brightness_file = device.brightness_file();
 
@@ -191,38 +249,39 @@ impl ConfigBlock for Backlight {
             id: id.clone(),
             device,
             step_width: block_config.step_width,
+            min: block_config.min.min(100),
+            synced,
         };
 
-        // Spin up a thread to watch for changes to the brightness file for the
-        // device, and schedule an update if needed.
-        thread::spawn(move || {
-            let mut notify = Inotify::init().expect("Failed to start inotify");
-            notify
-                .add_watch(brightness_file, WatchMask::MODIFY)
-                .expect("Failed to watch brightness file");
-
-            let mut buffer = [0; 1024];
-            loop {
-                let mut events = notify.read_events_blocking(&mut buffer).expect(
-                    "Error while reading inotify events",
-                );
-
-                if events.any(|event| event.mask.contains(EventMask::MODIFY)) {
-                    tx_update_request.send(Task {
-                        id: id.clone(),
-                        update_time: Instant::now(),
-                    });
-                }
-
-                // Avoid update spam.
-                thread::sleep(Duration::from_millis(250))
-            }
-        });
+        // Watch for changes to the brightness file for the device, and
+        // schedule an update if needed. Sliding the brightness writes it
+        // many times in a row, at most 4 updates a second are enough.
+        scheduler::watch_file_throttled(
+            &brightness_file,
+            WatchMask::MODIFY,
+            Duration::from_millis(250),
+            &id,
+            &tx_update_request,
+        )?;
 
         Ok(backlight)
     }
 }
 
+impl Backlight {
+    /// Set the brightness of the device, and of the synced ones to match.
+    fn set_brightness(&self, value: u64) -> Result<()> {
+        self.device.set_brightness(value)?;
+        for synced in &self.synced {
+            let value = value.min(100);
+            synced
+                .device
+                .set_brightness(synced.min + (100 - synced.min) * value / 100)?;
+        }
+        Ok(())
+    }
+}
+
 impl Block for Backlight {
     fn update(&mut self) -> Result<Option<Duration>> {
         let brightness = self.device.brightness()?;
@@ -248,12 +307,13 @@ impl Block for Backlight {
                 match event.button {
                     MouseButton::WheelUp => {
                         if brightness < 100 {
-                            self.device.set_brightness(brightness + self.step_width)?;
+                            self.set_brightness(brightness + self.step_width)?;
                         }
                     }
                     MouseButton::WheelDown => {
-                        if brightness > self.step_width {
-                            self.device.set_brightness(brightness - self.step_width)?;
+                        let lowest = self.min.max(1);
+                        if brightness > lowest {
+                            self.set_brightness(brightness.saturating_sub(self.step_width).max(lowest))?;
                         }
                     }
                     _ => {}
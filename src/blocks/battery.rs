@@ -4,10 +4,11 @@
 //! display the status, capacity, and time remaining for (dis)charge for an
 //! internal power supply.
 
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use util::FormatTemplate;
+use std::process::{Command, Stdio};
+use formatting::FormatTemplate;
 use std::time::{Duration, Instant};
-use std::thread;
 
 use chan::Sender;
 use blocks::dbus;
@@ -15,12 +16,15 @@ use uuid::Uuid;
 
 use block::{Block, ConfigBlock};
 use config::Config;
+use dbus_manager;
 use de::deserialize_duration;
 use errors::*;
+use input::{I3BarEvent, MouseButton};
 use scheduler::Task;
-use util::read_file;
+use udev;
+use util::{read_file, secs};
 use widget::{I3BarWidget, State};
-use widgets::text::TextWidget;
+use widgets::button::ButtonWidget;
 
 /// A battery device can be queried for a few properties relevant to the user.
 pub trait BatteryDevice {
@@ -38,6 +42,9 @@ pub trait BatteryDevice {
 
     /// Query the current power consumption, in uW.
     fn power_consumption(&self) -> Result<u64>;
+
+    /// Query the energy left and the energy when full, in uWh.
+    fn energy(&self) -> Result<(f64, f64)>;
 }
 
 /// Represents a physical power supply device, as known to sysfs.
@@ -192,11 +199,17 @@ impl BatteryDevice for PowerSupplyDevice {
 
     fn power_consumption(&self) -> Result<u64> {
         let power_path = self.device_path.join("power_now");
+        let current_path = self.device_path.join("current_now");
 
         if power_path.exists() {
             Ok(read_file("battery", &power_path)?
                 .parse::<u64>()
                 .block_error("battery", "failed to parse power_now")?)
+        } else if current_path.exists() {
+            let current = read_file("battery", &current_path)?
+                .parse::<f64>()
+                .block_error("battery", "failed to parse current_now")?;
+            Ok((current * self.voltage()? / 1_000_000.0) as u64)
         } else {
             Err(BlockError(
                 "battery".to_string(),
@@ -204,6 +217,101 @@ impl BatteryDevice for PowerSupplyDevice {
             ))
         }
     }
+
+    fn energy(&self) -> Result<(f64, f64)> {
+        let energy_path = self.device_path.join("energy_now");
+        let charge_path = self.device_path.join("charge_now");
+
+        if energy_path.exists() && self.energy_full.is_some() {
+            let energy = read_file("battery", &energy_path)?
+                .parse::<f64>()
+                .block_error("battery", "failed to parse energy_now")?;
+            Ok((energy, self.energy_full.unwrap() as f64))
+        } else if charge_path.exists() && self.charge_full.is_some() {
+            // Charge in uAh only becomes energy with the voltage
+            let charge = read_file("battery", &charge_path)?
+                .parse::<f64>()
+                .block_error("battery", "failed to parse charge_now")?;
+            let voltage = self.voltage()? / 1_000_000.0;
+            Ok((charge * voltage, self.charge_full.unwrap() as f64 * voltage))
+        } else {
+            Err(BlockError(
+                "battery".to_string(),
+                "Device does not support reading energy".to_string(),
+            ))
+        }
+    }
+}
+
+impl PowerSupplyDevice {
+    /// Query the current voltage, in uV.
+    fn voltage(&self) -> Result<f64> {
+        let voltage_path = self.device_path.join("voltage_now");
+
+        if voltage_path.exists() {
+            read_file("battery", &voltage_path)?
+                .parse::<f64>()
+                .block_error("battery", "failed to parse voltage_now")
+        } else {
+            Err(BlockError(
+                "battery".to_string(),
+                "Device does not support reading voltage".to_string(),
+            ))
+        }
+    }
+}
+
+/// The charge threshold of a battery, above which it isn't charged any
+/// further. Exposed by ThinkPads, ASUS and a few other laptops.
+pub struct ChargeLimit {
+    threshold_path: PathBuf,
+}
+
+impl ChargeLimit {
+    /// The threshold of the battery `device` in `/sys/class/power_supply/`,
+    /// if it has one.
+    pub fn from_device(device: &str) -> Option<Self> {
+        let device_path = Path::new("/sys/class/power_supply").join(device);
+        // Older kernels named it after the ThinkPad driver
+        ["charge_control_end_threshold", "charge_stop_threshold"]
+            .iter()
+            .map(|name| device_path.join(name))
+            .find(|path| path.exists())
+            .map(|threshold_path| ChargeLimit { threshold_path })
+    }
+
+    /// Query the threshold, as a percent.
+    pub fn get(&self) -> Result<u64> {
+        read_file("battery", &self.threshold_path)?
+            .parse::<u64>()
+            .block_error("battery", "failed to parse the charge threshold")
+    }
+
+    /// Set the threshold to `percentage`. Only root may, so this goes through
+    /// `pkexec`, which asks polkit for permission.
+    pub fn set(&self, percentage: u64) -> Result<()> {
+        let mut child = Command::new("pkexec")
+            .arg("tee")
+            .arg(&self.threshold_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .block_error("battery", "failed to run pkexec")?;
+        if let Some(ref mut stdin) = child.stdin {
+            write!(stdin, "{}", percentage).block_error("battery", "failed to write the charge threshold")?;
+        }
+        // Closes stdin, for tee to finish
+        child.stdin = None;
+        let status = child.wait().block_error("battery", "failed to run pkexec")?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(BlockError(
+                "battery".to_string(),
+                format!("failed to set the charge threshold ({})", status),
+            ))
+        }
+    }
 }
 
 fn get_upower_property(con: &dbus::Connection, device_path: &str, property: &str) -> Result<dbus::Message> {
@@ -260,39 +368,17 @@ impl UpowerDevice {
         })
     }
 
-    /// Monitor UPower property changes in a separate thread and send updates
-    /// via the `update_request` channel.
-    pub fn monitor(&self, id: String, update_request: Sender<Task>) {
-        let path = self.device_path.clone();
-        thread::spawn(move || {
-            let con = dbus::Connection::get_private(dbus::BusType::System)
-                .expect("Failed to establish D-Bus connection.");
-            let rule = format!(
-                "type='signal',\
-                 path='{}',\
-                 interface='org.freedesktop.DBus.Properties',\
-                 member='PropertiesChanged'",
-                path
-            );
-
-            // First we're going to get an (irrelevant) NameAcquired event.
-            con.incoming(10_000).next();
-
-            con.add_match(&rule)
-                .expect("Failed to add D-Bus match rule.");
-
-            loop {
-                if con.incoming(10_000).next().is_some() {
-                    update_request.send(Task {
-                        id: id.clone(),
-                        update_time: Instant::now(),
-                    });
-                    // Avoid update spam.
-                    // TODO: Is this necessary?
-                    thread::sleep(Duration::from_millis(1000))
-                }
-            }
-        });
+    /// Monitor UPower property changes and send updates via the
+    /// `update_request` channel.
+    pub fn monitor(&self, id: &str, update_request: &Sender<Task>) -> Result<()> {
+        let rule = format!(
+            "type='signal',\
+             path='{}',\
+             interface='org.freedesktop.DBus.Properties',\
+             member='PropertiesChanged'",
+            self.device_path
+        );
+        dbus_manager::subscribe(dbus::BusType::System, &rule, id, update_request)
     }
 }
 
@@ -344,16 +430,49 @@ impl BatteryDevice for UpowerDevice {
         // FIXME: Might want to make the interface send Watts instead.
         Ok((energy_rate.0 * 1_000_000.0) as u64)
     }
+
+    fn energy(&self) -> Result<(f64, f64)> {
+        let energy: dbus::arg::Variant<f64> =
+            get_upower_property(&self.con, &self.device_path, "Energy")?
+                .get1()
+                .block_error("battery", "Failed to read UPower Energy property.")?;
+        let energy_full: dbus::arg::Variant<f64> =
+            get_upower_property(&self.con, &self.device_path, "EnergyFull")?
+                .get1()
+                .block_error("battery", "Failed to read UPower EnergyFull property.")?;
+        // UPower has Wh
+        Ok((energy.0 * 1_000_000.0, energy_full.0 * 1_000_000.0))
+    }
 }
 
+/// Shown next to the percentage while a charge limit is active
+const LIMIT_SYMBOL: &str = "\u{23da}";
+
 /// A block for displaying information about an internal power supply.
 pub struct Battery {
-    output: TextWidget,
+    output: ButtonWidget,
     id: String,
     update_interval: Duration,
     device: Box<BatteryDevice>,
     format: FormatTemplate,
+    short_format: Option<FormatTemplate>,
     upower: bool,
+    gradient: bool,
+    urgent_below: Option<u64>,
+    smoothing: Duration,
+    /// The smoothed power draw, in uW
+    power: Option<f64>,
+    /// When `power` last took in a reading
+    last_sample: Option<Instant>,
+    /// The energy left, and since when, to derive the power draw from
+    last_energy: Option<(Instant, f64)>,
+    /// The status `power` is about, it starts over on changes
+    last_status: String,
+    charge_limit: Option<ChargeLimit>,
+    /// The threshold a click switches to from full charge
+    limit: Option<u64>,
+    /// What the last update measured, see `Block::metrics`
+    metrics: Vec<(&'static str, f64)>,
 }
 
 /// Configuration for the [`Battery`](./struct.Battery.html) block.
@@ -378,9 +497,31 @@ pub struct BatteryConfig {
     #[serde(default = "BatteryConfig::default_format")]
     pub format: String,
 
+    /// Shown instead of `format` when the bar runs out of space, same
+    /// placeholders
+    #[serde(default = "BatteryConfig::default_short_format")]
+    pub short_format: Option<String>,
+
     /// Use UPower to monitor battery status and events.
     #[serde(default = "BatteryConfig::default_upower")]
     pub upower: bool,
+
+    /// Color the text by charge instead of by state while discharging.
+    #[serde(default = "BatteryConfig::default_gradient")]
+    pub gradient: bool,
+
+    /// Mark the block urgent while discharging below this percentage.
+    #[serde(default = "BatteryConfig::default_urgent_below")]
+    pub urgent_below: Option<u64>,
+
+    /// How long it takes for a change of the power draw to mostly show in
+    /// `{power}` and `{time}`, in seconds. 0 shows the readings as they are.
+    #[serde(default = "BatteryConfig::default_smoothing", deserialize_with = "deserialize_duration")]
+    pub smoothing: Duration,
+
+    /// The charge threshold a click switches to, and back to full charge.
+    #[serde(default = "BatteryConfig::default_charge_limit")]
+    pub charge_limit: Option<u64>,
 }
 
 impl BatteryConfig {
@@ -396,9 +537,29 @@ impl BatteryConfig {
         "{percentage}%".into()
     }
 
+    fn default_short_format() -> Option<String> {
+        None
+    }
+
     fn default_upower() -> bool {
         false
     }
+
+    fn default_gradient() -> bool {
+        false
+    }
+
+    fn default_urgent_below() -> Option<u64> {
+        None
+    }
+
+    fn default_smoothing() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_charge_limit() -> Option<u64> {
+        None
+    }
 }
 
 impl ConfigBlock for Battery {
@@ -421,55 +582,183 @@ impl ConfigBlock for Battery {
             None => block_config.format
         };
 
+        if let Some(limit) = block_config.charge_limit {
+            if limit == 0 || limit >= 100 {
+                return Err(BlockError(
+                    "battery".into(),
+                    "charge_limit must be between 1 and 99".into(),
+                ));
+            }
+        }
+
         let id = Uuid::new_v4().simple().to_string();
         let device: Box<BatteryDevice> = if block_config.upower {
             let out = UpowerDevice::from_device(&block_config.device)?;
-            out.monitor(id.clone(), update_request);
+            out.monitor(&id, &update_request)?;
             Box::new(out)
         } else {
+            // Plugging the charger in or out shows right away, polling is
+            // still needed for the charge
+            if let Err(error) = udev::subscribe("power_supply", &id, &update_request) {
+                warn!("battery won't notice changes before its next update: {:?}", error);
+            }
             Box::new(PowerSupplyDevice::from_device(&block_config.device)?)
 
         };
 
         Ok(Battery {
+            output: ButtonWidget::new(config, &id),
             id,
             update_interval: block_config.interval,
-            output: TextWidget::new(config),
             device,
             format: FormatTemplate::from_string(&format)?,
+            short_format: match block_config.short_format {
+                Some(format) => Some(FormatTemplate::from_string(&format)?),
+                None => None,
+            },
             upower: block_config.upower,
+            gradient: block_config.gradient,
+            urgent_below: block_config.urgent_below,
+            smoothing: block_config.smoothing,
+            power: None,
+            last_sample: None,
+            last_energy: None,
+            last_status: String::new(),
+            charge_limit: ChargeLimit::from_device(&block_config.device),
+            limit: block_config.charge_limit,
+            metrics: Vec::new(),
         })
     }
 }
 
+impl Battery {
+    /// Forget the power draw, for it to start over with the next reading.
+    fn reset_power(&mut self) {
+        self.power = None;
+        self.last_sample = None;
+        self.last_energy = None;
+    }
+
+    /// Take in the power draw, as reported by the device or else derived from
+    /// how fast `energy` changes, into an exponentially weighted moving
+    /// average. Returns the average, in uW.
+    fn sample_power(&mut self, status: &str, energy: Option<f64>) -> Option<f64> {
+        if status != self.last_status {
+            self.reset_power();
+            self.last_status = status.to_owned();
+        }
+        if status != "Charging" && status != "Discharging" {
+            return None;
+        }
+
+        let now = Instant::now();
+        // Readings of 0 come up while the kernel doesn't know yet
+        let measured = match self.device.power_consumption() {
+            Ok(power) if power > 0 => Some(power as f64),
+            _ => None,
+        };
+        // The energy is only updated every so often, so it's the time between
+        // changes that counts.
+        let derived = match (energy, self.last_energy) {
+            (Some(energy), Some((then, last))) if energy != last => {
+                Some((energy - last).abs() / (secs(now.duration_since(then)) / 3600.0))
+            }
+            _ => None,
+        };
+        if let Some(energy) = energy {
+            if self.last_energy.map_or(true, |(_, last)| last != energy) {
+                self.last_energy = Some((now, energy));
+            }
+        }
+
+        if let Some(reading) = measured.or(derived) {
+            let smoothing = secs(self.smoothing);
+            self.power = Some(match (self.power, self.last_sample) {
+                (Some(power), Some(then)) if smoothing > 0.0 => {
+                    let weight = 1.0 - (-secs(now.duration_since(then)) / smoothing).exp();
+                    power + weight * (reading - power)
+                }
+                _ => reading,
+            });
+            self.last_sample = Some(now);
+        }
+        self.power
+    }
+}
+
 impl Block for Battery {
     fn update(&mut self) -> Result<Option<Duration>> {
         // TODO: Maybe use dbus to immediately signal when the battery state changes.
 
         let status = self.device.status()?;
+        // Only shown while it holds back charging
+        let limit = match self.charge_limit {
+            Some(ref charge_limit) => match charge_limit.get()? {
+                limit if limit < 100 => Some(limit),
+                _ => None,
+            },
+            None => None,
+        };
 
+        self.metrics.clear();
         if status == "Full" || status == "Not charging" {
+            if let Ok(capacity) = self.device.capacity() {
+                self.metrics.push(("battery_charge_percent", capacity as f64));
+            }
+            // Stopping at the limit looks the same as being full
+            let text = match limit {
+                Some(limit) => format!("{}% {}", limit, LIMIT_SYMBOL),
+                None => "".to_string(),
+            };
             self.output.set_icon("bat_full");
-            self.output.set_text("".to_string());
+            self.output.set_text(text.clone());
+            if self.short_format.is_some() {
+                self.output.set_short_text(text);
+            }
             self.output.set_state(State::Good);
+            self.output.set_color(None);
+            self.output.set_urgent(false);
         } else {
             let capacity = self.device.capacity();
             let percentage = match capacity {
                 Ok(capacity) => format!("{}", capacity),
                 Err(_) => "×".into(),
             };
-            let time = match self.device.time_remaining() {
+            let energy = self.device.energy().ok();
+            let power = self.sample_power(&status, energy.map(|(now, _)| now));
+            // Estimated from the smoothed power draw where possible, what the
+            // device estimates jumps with every reading
+            let estimate = match (power, energy) {
+                (Some(power), Some((now, full))) if power > 0.0 => match status.as_str() {
+                    "Discharging" => Some((now / power * 60.0) as u64),
+                    "Charging" => Some(((full - now).max(0.0) / power * 60.0) as u64),
+                    _ => None,
+                },
+                _ => None,
+            };
+            let time = match estimate.map_or_else(|| self.device.time_remaining(), Ok) {
                 Ok(time) => format!("{}:{:02}", time / 60, time % 60),
                 Err(_) => "×".into(),
             };
-            let power = match self.device.power_consumption() {
-                Ok(power) => format!("{:.2}", power as f64 / 1000.0 / 1000.0),
+            let power = power.map_or_else(|| self.device.power_consumption().map(|power| power as f64), Ok);
+            if let Ok(capacity) = capacity {
+                self.metrics.push(("battery_charge_percent", capacity as f64));
+            }
+            if let Ok(power) = power {
+                self.metrics.push(("battery_power_watts", power / 1000.0 / 1000.0));
+            }
+            let power = match power {
+                Ok(power) => format!("{:.2}", power / 1000.0 / 1000.0),
                 Err(_) => "×".into(),
             };
             let values = map!("{percentage}" => percentage,
                               "{time}" => time,
-                              "{power}" => power);
+                              "{power}" => power,
+                              "{limit}" => if limit.is_some() { LIMIT_SYMBOL.to_string() } else { "".to_string() });
             self.output.set_text(self.format.render_static_str(&values)?);
+            if let Some(ref short_format) = self.short_format {
+                self.output.set_short_text(short_format.render_static_str(&values)?);
+            }
 
             // Check if the battery is in charging mode and change the state to Good.
             // Otherwise, adjust the state depeding the power percentance.
@@ -486,6 +775,18 @@ impl Block for Battery {
                 }
             }
 
+            match capacity {
+                Ok(capacity) if self.gradient && status != "Charging" => {
+                    self.output.set_gradient(capacity as f64, true)
+                }
+                _ => self.output.set_color(None),
+            }
+
+            self.output.set_urgent(match (self.urgent_below, &capacity) {
+                (Some(below), &Ok(capacity)) => status == "Discharging" && capacity < below,
+                _ => false,
+            });
+
             self.output.set_icon(match status.as_str() {
                 "Discharging" => "bat_discharging",
                 "Charging" => "bat_charging",
@@ -504,6 +805,27 @@ impl Block for Battery {
         vec![&self.output]
     }
 
+    fn metrics(&self) -> Vec<(&'static str, f64)> {
+        self.metrics.clone()
+    }
+
+    fn resumed(&mut self) {
+        // The battery went on draining while suspended
+        self.reset_power();
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if !event.matches_name(&self.id) || event.button != MouseButton::Left {
+            return Ok(());
+        }
+        if let (Some(charge_limit), Some(limit)) = (self.charge_limit.as_ref(), self.limit) {
+            let target = if charge_limit.get()? < 100 { 100 } else { limit };
+            charge_limit.set(target)?;
+        }
+        self.update()?;
+        Ok(())
+    }
+
     fn id(&self) -> &str {
         &self.id
     }
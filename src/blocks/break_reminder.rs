@@ -0,0 +1,230 @@
+//! A block reminding to take breaks from the screen, like the 20-20-20 rule:
+//! every 20 minutes, look at something 20 feet away for 20 seconds.
+//!
+//! The countdown to the next break pauses while something inhibits going
+//! idle, which video players and browsers in a video call do through
+//! systemd-logind. It wouldn't be the time for a break then.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use blocks::dbus::{BusType, Connection, Message};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Value};
+use input::{I3BarEvent, MouseButton};
+use notifications::{self, Urgency};
+use scheduler::Task;
+use widget::{I3BarWidget, State};
+use widgets::button::ButtonWidget;
+
+/// What, who, why, mode, user and process of an inhibitor
+type Inhibitor = (String, String, String, String, u32, u32);
+
+#[derive(Debug, Copy, Clone)]
+enum Phase {
+    /// Time left until the break
+    Work(Duration),
+    /// Time left of the break
+    Break(Duration),
+}
+
+/// Counts down to the next break and reminds to take it, see
+/// `BreakReminderConfig`.
+pub struct BreakReminder {
+    id: String,
+    output: ButtonWidget,
+    format: FormatTemplate,
+    work: Duration,
+    break_length: Duration,
+    snooze: Duration,
+    phase: Phase,
+    /// When the countdown was last updated
+    last: Instant,
+    /// The system bus, to look for idle inhibitors if asked to
+    dbus_conn: Option<Connection>,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BreakReminderConfig {
+    /// Time between breaks, in seconds
+    #[serde(default = "BreakReminderConfig::default_work", deserialize_with = "deserialize_duration")]
+    pub work: Duration,
+
+    /// Length of a break, in seconds
+    #[serde(default = "BreakReminderConfig::default_break_length", deserialize_with = "deserialize_duration")]
+    pub break_length: Duration,
+
+    /// How long a left click puts off a break, in seconds
+    #[serde(default = "BreakReminderConfig::default_snooze", deserialize_with = "deserialize_duration")]
+    pub snooze: Duration,
+
+    /// Stop the countdown while idling is inhibited, e.g. in a video call
+    #[serde(default = "BreakReminderConfig::default_pause_on_inhibit")]
+    pub pause_on_inhibit: bool,
+
+    /// Format override, `{left}` is available
+    #[serde(default = "BreakReminderConfig::default_format")]
+    pub format: String,
+}
+
+impl BreakReminderConfig {
+    fn default_work() -> Duration {
+        Duration::from_secs(20 * 60)
+    }
+
+    fn default_break_length() -> Duration {
+        Duration::from_secs(20)
+    }
+
+    fn default_snooze() -> Duration {
+        Duration::from_secs(5 * 60)
+    }
+
+    fn default_pause_on_inhibit() -> bool {
+        true
+    }
+
+    fn default_format() -> String {
+        "{left}".to_owned()
+    }
+}
+
+/// Whether anything inhibits going idle.
+fn idle_inhibited(c: &Connection) -> Result<bool> {
+    let m = Message::new_method_call(
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+        "ListInhibitors",
+    ).block_error("break_reminder", "failed to create D-Bus message")?;
+    let r = c.send_with_reply_and_block(m, 1000)
+        .block_error("break_reminder", "failed to get the inhibitors from logind")?;
+    let inhibitors: Vec<Inhibitor> = r.get1()
+        .block_error("break_reminder", "invalid inhibitors from logind")?;
+    // What is a colon separated list, like "sleep:idle"
+    Ok(inhibitors
+        .iter()
+        .any(|&(ref what, _, _, _, _, _)| what.split(':').any(|what| what == "idle")))
+}
+
+impl ConfigBlock for BreakReminder {
+    type Config = BreakReminderConfig;
+
+    fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        let dbus_conn = if block_config.pause_on_inhibit {
+            Some(Connection::get_private(BusType::System)
+                .block_error("break_reminder", "failed to connect to the system bus")?)
+        } else {
+            None
+        };
+
+        Ok(BreakReminder {
+            output: ButtonWidget::new(config, &id).with_icon("break_reminder"),
+            id,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("break_reminder", "Invalid format specified for break_reminder")?,
+            work: block_config.work,
+            break_length: block_config.break_length,
+            snooze: block_config.snooze,
+            phase: Phase::Work(block_config.work),
+            last: Instant::now(),
+            dbus_conn,
+            tx_update_request: send,
+        })
+    }
+}
+
+impl Block for BreakReminder {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last);
+        self.last = now;
+        let paused = match self.dbus_conn {
+            Some(ref c) => idle_inhibited(c)?,
+            None => false,
+        };
+
+        // A break runs out even in a call, the reminder has been given
+        self.phase = match self.phase {
+            Phase::Work(left) if paused => Phase::Work(left),
+            Phase::Work(left) => match left.checked_sub(elapsed) {
+                Some(left) if left > Duration::from_secs(0) => Phase::Work(left),
+                _ => {
+                    notifications::notify(
+                        "Time for a break",
+                        &format!("Look away from the screen for {} seconds", self.break_length.as_secs()),
+                        Urgency::Normal,
+                    );
+                    Phase::Break(self.break_length)
+                }
+            },
+            Phase::Break(left) => match left.checked_sub(elapsed) {
+                Some(left) if left > Duration::from_secs(0) => Phase::Break(left),
+                _ => Phase::Work(self.work),
+            },
+        };
+
+        // Minutes, rounded up, until the break, and seconds of the break
+        let (left, next_update) = match self.phase {
+            Phase::Work(left) => {
+                let minutes = (left.as_secs() + 59) / 60;
+                let until_next_minute = left - Duration::from_secs(minutes.saturating_sub(1) * 60);
+                (format!("{}m", minutes), until_next_minute)
+            }
+            Phase::Break(left) => (format!("{}s", left.as_secs()), Duration::from_secs(1).min(left)),
+        };
+        let mut values = HashMap::new();
+        values.insert("{left}", Value::Text(left));
+        self.output.set_text(self.format.render_values(&values)?);
+        let on_break = match self.phase {
+            Phase::Break(_) => true,
+            Phase::Work(_) => false,
+        };
+        self.output.set_state(if on_break {
+            State::Critical
+        } else if paused {
+            State::Info
+        } else {
+            State::Idle
+        });
+        self.output.set_urgent(on_break);
+
+        // Inhibitors come and go without telling
+        Ok(Some(next_update.min(Duration::from_secs(60))))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.output]
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if !event.name.as_ref().map_or(false, |name| name == &self.id) {
+            return Ok(());
+        }
+        // Snooze puts off the break, skip starts over
+        self.phase = match (&event.button, &self.phase) {
+            (&MouseButton::Left, &Phase::Break(_)) => Phase::Work(self.snooze),
+            (&MouseButton::Left, &Phase::Work(left)) => Phase::Work(left + self.snooze),
+            (&MouseButton::Right, _) => Phase::Work(self.work),
+            _ => return Ok(()),
+        };
+        self.tx_update_request.send(Task {
+            id: self.id.clone(),
+            update_time: Instant::now(),
+        });
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
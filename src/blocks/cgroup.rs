@@ -0,0 +1,164 @@
+//! A block for the CPU and memory used by a cgroup, like `user.slice` or a
+//! scope started with `systemd-run --scope`.
+//!
+//! Only the unified hierarchy of cgroup v2 is supported, mounted at
+//! `/sys/fs/cgroup` as systemd does. Scopes come and go with what runs in
+//! them, so the block is hidden while its cgroup doesn't exist.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Unit, Value};
+use scheduler::Task;
+use util::read_file;
+use widget::I3BarWidget;
+use widgets::text::TextWidget;
+
+/// The CPU time used by all tasks in the cgroup so far, in microseconds.
+fn cpu_usage(path: &Path) -> Result<u64> {
+    let stat = read_file("cgroup", &path.join("cpu.stat"))?;
+    stat.lines()
+        .find(|line| line.starts_with("usage_usec "))
+        .and_then(|line| line["usage_usec ".len()..].trim().parse().ok())
+        .block_error("cgroup", "no CPU usage in cpu.stat")
+}
+
+/// The memory used by all tasks in the cgroup, and its limit if any, in
+/// bytes.
+fn memory_usage(path: &Path) -> Result<(u64, Option<u64>)> {
+    let current = read_file("cgroup", &path.join("memory.current"))?
+        .trim()
+        .parse()
+        .block_error("cgroup", "invalid memory.current")?;
+    // `max` when there's no limit, and no file at all for the root cgroup
+    let max = read_file("cgroup", &path.join("memory.max"))
+        .ok()
+        .and_then(|max| max.trim().parse().ok());
+    Ok((current, max))
+}
+
+/// Shows the usage of a cgroup, see `CgroupConfig`.
+pub struct Cgroup {
+    id: String,
+    text: TextWidget,
+    format: FormatTemplate,
+    path: PathBuf,
+    interval: Duration,
+    /// Whether the cgroup existed at the last update
+    exists: bool,
+    /// The CPU usage at the last update, and when it was read
+    prev_cpu: Option<(u64, Instant)>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CgroupConfig {
+    /// The cgroup, relative to `/sys/fs/cgroup`, e.g. `user.slice`
+    pub cgroup: String,
+
+    /// Update interval in seconds
+    #[serde(default = "CgroupConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{name}`, `{cpu}`, `{memory}` and `{memory_max}`
+    /// are available
+    #[serde(default = "CgroupConfig::default_format")]
+    pub format: String,
+}
+
+impl CgroupConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn default_format() -> String {
+        "{name} {cpu:02} {memory:.1;bin}".to_owned()
+    }
+}
+
+impl ConfigBlock for Cgroup {
+    type Config = CgroupConfig;
+
+    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        if !Path::new("/sys/fs/cgroup/cgroup.controllers").exists() {
+            return Err(BlockError(
+                "cgroup".to_owned(),
+                "no cgroup v2 hierarchy mounted at /sys/fs/cgroup".to_owned(),
+            ));
+        }
+
+        Ok(Cgroup {
+            id: Uuid::new_v4().simple().to_string(),
+            text: TextWidget::new(config).with_icon("cgroup"),
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("cgroup", "Invalid format specified for cgroup")?,
+            path: Path::new("/sys/fs/cgroup").join(block_config.cgroup.trim_matches('/')),
+            interval: block_config.interval,
+            exists: false,
+            prev_cpu: None,
+        })
+    }
+}
+
+impl Block for Cgroup {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        self.exists = self.path.is_dir();
+        if !self.exists {
+            self.prev_cpu = None;
+            return Ok(Some(self.interval));
+        }
+
+        let now = Instant::now();
+        let usage = cpu_usage(&self.path)?;
+        // Like systemd-cgtop, 100% is one core fully used
+        let cpu = match self.prev_cpu {
+            Some((prev, since)) => {
+                let elapsed = now.duration_since(since);
+                let elapsed = elapsed.as_secs() * 1_000_000 + u64::from(elapsed.subsec_nanos()) / 1000;
+                usage.saturating_sub(prev) as f64 / elapsed.max(1) as f64 * 100.
+            }
+            None => 0.,
+        };
+        self.prev_cpu = Some((usage, now));
+        let (memory, memory_max) = memory_usage(&self.path)?;
+
+        let name = self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut values = HashMap::new();
+        values.insert("{name}", Value::Text(name));
+        values.insert("{cpu}", Value::Number(cpu, Unit::Percents));
+        values.insert("{memory}", Value::Number(memory as f64, Unit::Bytes));
+        values.insert(
+            "{memory_max}",
+            match memory_max {
+                Some(max) => Value::Number(max as f64, Unit::Bytes),
+                None => Value::Text(String::new()),
+            },
+        );
+        self.text.set_text(self.format.render_values(&values)?);
+
+        Ok(Some(self.interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        if self.exists {
+            vec![&self.text]
+        } else {
+            vec![]
+        }
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -1,13 +1,19 @@
 use chan::Sender;
 use scheduler::Task;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 use block::{Block, ConfigBlock};
 use config::Config;
 use de::deserialize_duration;
 use errors::*;
-use widget::{I3BarWidget, State};
-use widgets::text::TextWidget;
+use formatting::{FormatTemplate, Unit, Value};
+use input::{I3BarEvent, MouseButton};
+use processes;
+use widget::{I3BarWidget, Thresholds};
+use widgets::button::ButtonWidget;
+use widgets::graph::GraphWidget;
+use widgets::list::ListWidget;
 
 use std::fs::File;
 use std::io::prelude::*;
@@ -16,15 +22,28 @@ use std::io::BufReader;
 use uuid::Uuid;
 
 pub struct Cpu {
-    utilization: TextWidget,
+    utilization: ButtonWidget,
+    graph: Option<GraphWidget>,
+    cores: Option<ListWidget>,
     prev_idle: u64,
     prev_non_idle: u64,
+    /// Idle and non-idle time of each core at the last update
+    prev_cores: Vec<(u64, u64)>,
     id: String,
     update_interval: Duration,
-    minimum_info: u64,
-    minimum_warning: u64,
-    minimum_critical: u64,
+    thresholds: Thresholds,
     frequency: bool,
+    format: FormatTemplate,
+    short_format: Option<FormatTemplate>,
+    gradient: bool,
+    /// What the last update measured, see `Block::metrics`
+    metrics: Vec<(&'static str, f64)>,
+    top_duration: Duration,
+    /// CPU time of each process and in total since a click, until enough
+    /// of it passed to tell which process used the most
+    top_sample: Option<(HashMap<u32, u64>, u64)>,
+    /// The process that used the most, its usage and until when it's shown
+    top: Option<(String, f64, Instant)>,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -46,9 +65,37 @@ pub struct CpuConfig {
     #[serde(default = "CpuConfig::default_critical")]
     pub critical: u64,
 
+    /// Replaces `info`, `warning` and `critical` if set
+    #[serde(default = "CpuConfig::default_thresholds")]
+    pub thresholds: Option<Thresholds>,
+
     /// Display frequency
     #[serde(default = "CpuConfig::default_frequency")]
     pub frequency: bool,
+
+    /// Color the text by utilization instead of by state
+    #[serde(default = "CpuConfig::default_gradient")]
+    pub gradient: bool,
+
+    /// Format override, `{utilization}` and `{frequency}` are available
+    #[serde(default = "CpuConfig::default_format")]
+    pub format: Option<String>,
+
+    /// Shown instead of `format` when the bar runs out of space
+    #[serde(default = "CpuConfig::default_short_format")]
+    pub short_format: Option<String>,
+
+    /// Show a graph of the utilization over the last updates
+    #[serde(default = "CpuConfig::default_graph")]
+    pub graph: bool,
+
+    /// Show the utilization of each core as well
+    #[serde(default = "CpuConfig::default_per_core")]
+    pub per_core: bool,
+
+    /// How long, in seconds, the top process is shown after a click
+    #[serde(default = "CpuConfig::default_top_duration", deserialize_with = "deserialize_duration")]
+    pub top_duration: Duration,
 }
 
 impl CpuConfig {
@@ -68,25 +115,91 @@ impl CpuConfig {
         90
     }
 
+    fn default_thresholds() -> Option<Thresholds> {
+        None
+    }
+
     fn default_frequency() -> bool {
         false
     }
+
+    fn default_gradient() -> bool {
+        false
+    }
+
+    fn default_format() -> Option<String> {
+        None
+    }
+
+    fn default_short_format() -> Option<String> {
+        None
+    }
+
+    fn default_graph() -> bool {
+        false
+    }
+
+    fn default_per_core() -> bool {
+        false
+    }
+
+    fn default_top_duration() -> Duration {
+        Duration::from_secs(5)
+    }
 }
 
 impl ConfigBlock for Cpu {
     type Config = CpuConfig;
 
     fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        let frequency = block_config.frequency;
+        let format = FormatTemplate::from_string(&block_config.format.unwrap_or_else(|| {
+            if frequency {
+                "{utilization:03} {frequency:.1;G}".to_owned()
+            } else {
+                "{utilization:03}".to_owned()
+            }
+        }))?;
+
+        let short_format = match block_config.short_format {
+            Some(format) => Some(FormatTemplate::from_string(&format)?),
+            None => None,
+        };
+
         Ok(Cpu {
-            id: Uuid::new_v4().simple().to_string(),
             update_interval: block_config.interval,
-            utilization: TextWidget::new(config).with_icon("cpu"),
+            graph: if block_config.graph {
+                Some(GraphWidget::new(config.clone()).with_range(Some(0.), Some(100.)))
+            } else {
+                None
+            },
+            cores: if block_config.per_core {
+                Some(ListWidget::new(config.clone()))
+            } else {
+                None
+            },
+            utilization: ButtonWidget::new(config, &id).with_icon("cpu"),
+            id,
             prev_idle: 0,
             prev_non_idle: 0,
-            minimum_info: block_config.info,
-            minimum_warning: block_config.warning,
-            minimum_critical: block_config.critical,
-            frequency: block_config.frequency,
+            prev_cores: Vec::new(),
+            thresholds: block_config.thresholds.unwrap_or(Thresholds {
+                good: None,
+                info: Some(block_config.info as f64),
+                warning: Some(block_config.warning as f64),
+                critical: Some(block_config.critical as f64),
+                reversed: false,
+            }),
+            frequency: format.contains("{frequency}")
+                || short_format.as_ref().map_or(false, |format| format.contains("{frequency}")),
+            format,
+            short_format,
+            gradient: block_config.gradient,
+            metrics: Vec::new(),
+            top_duration: block_config.top_duration,
+            top_sample: None,
+            top: None,
         })
     }
 }
@@ -112,62 +225,143 @@ impl Block for Cpu {
                 }
             }
             // get the average
-            freq = (freq / (cores as f32) / 1000.0) as f32;
+            freq = freq / (cores as f32);
         }
         let mut utilization = 0;
+        let mut cores = Vec::new();
 
         for line in f.lines().scan((), |_, x| x.ok()) {
             if line.starts_with("cpu ") {
-                let data: Vec<u64> = (&line).split(' ').collect::<Vec<&str>>().iter().skip(2).filter_map(|x| x.parse::<u64>().ok()).collect::<Vec<_>>();
-
-                // idle = idle + iowait
-                let idle = data[3] + data[4];
-                let non_idle = data[0] + // user
-                                data[1] + // nice
-                                data[2] + // system
-                                data[5] + // irq
-                                data[6] + // softirq
-                                data[7]; // steal
-
-                let prev_total = self.prev_idle + self.prev_non_idle;
-                let total = idle + non_idle;
+                let (idle, non_idle) = cpu_times(&line);
+                utilization = utilization_since((self.prev_idle, self.prev_non_idle), (idle, non_idle));
+                self.prev_idle = idle;
+                self.prev_non_idle = non_idle;
+            } else if line.starts_with("cpu") && self.cores.is_some() {
+                let times = cpu_times(&line);
+                let prev = self.prev_cores.get(cores.len()).cloned().unwrap_or((0, 0));
+                cores.push((utilization_since(prev, times), times));
+            }
+        }
 
-                // This check is needed because the new values may be reset, for
-                // example after hibernation.
+        if let Some(ref mut list) = self.cores {
+            self.prev_cores = cores.iter().map(|&(_, times)| times).collect();
+            let thresholds = &self.thresholds;
+            list.set_items(cores.iter().map(|&(core, _)| (format!("{:02}%", core), thresholds.state(core as f64))));
+        }
 
-                let (total_delta, idle_delta) = if prev_total < total && self.prev_idle <= idle {
-                    (total - prev_total, idle - self.prev_idle)
-                } else {
-                    (1, 1)
-                };
+        self.utilization.set_state(self.thresholds.state(utilization as f64));
+        if self.gradient {
+            self.utilization.set_gradient(utilization as f64, false);
+        }
 
-                utilization = (((total_delta - idle_delta) as f64 / total_delta as f64) * 100.) as u64;
+        let now = Instant::now();
+        if let Some((before, total_before)) = self.top_sample.take() {
+            let total = processes::total_ticks().unwrap_or(total_before);
+            // Right after the click too little time passed for the numbers
+            // to mean anything, the next update will tell
+            if total < total_before + 10 {
+                self.top_sample = Some((before, total_before));
+            } else if let Some((name, usage)) = processes::top_cpu(&before, &processes::cpu_ticks(), total - total_before) {
+                self.top = Some((name, usage, now + self.top_duration));
+            }
+        }
+        let top = match self.top {
+            Some((ref name, usage, until)) if until > now => Some(format!("{} {:.0}%", name, usage)),
+            _ => None,
+        };
 
-                self.prev_idle = idle;
-                self.prev_non_idle = non_idle;
+        if let Some(top) = top {
+            self.utilization.set_text(top.clone());
+            self.utilization.set_short_text(top);
+        } else {
+            self.top = None;
+            let mut values = HashMap::new();
+            values.insert("{utilization}", Value::Number(utilization as f64, Unit::Percents));
+            values.insert("{frequency}", Value::Number(f64::from(freq) * 1e6, Unit::Hertz));
+            self.utilization.set_text(self.format.render_values(&values)?);
+            if let Some(ref short_format) = self.short_format {
+                self.utilization.set_short_text(short_format.render_values(&values)?);
             }
         }
+        if let Some(ref mut graph) = self.graph {
+            graph.push(utilization as f64);
+            graph.set_state(self.thresholds.state(utilization as f64));
+        }
 
-        self.utilization.set_state(match utilization {
-            x if x > self.minimum_critical => State::Critical,
-            x if x > self.minimum_warning => State::Warning,
-            x if x > self.minimum_info => State::Info,
-            _ => State::Idle,
-        });
+        self.metrics = vec![("cpu_utilization_percent", utilization as f64)];
         if self.frequency {
-            let frequency = format!("{:.*}", 1, freq);
-            self.utilization.set_text(format!("{:03}% {}GHz", utilization, frequency));
-        } else {
-            self.utilization.set_text(format!("{:03}%", utilization));
+            self.metrics.push(("cpu_frequency_hertz", f64::from(freq) * 1e6));
         }
-        Ok(Some(self.update_interval))
+        // Back to the utilization in time
+        Ok(Some(match self.top {
+            Some((_, _, until)) => self.update_interval.min(until - now),
+            None => self.update_interval,
+        }))
     }
 
     fn view(&self) -> Vec<&I3BarWidget> {
-        vec![&self.utilization]
+        let mut widgets: Vec<&I3BarWidget> = vec![&self.utilization];
+        if let Some(ref graph) = self.graph {
+            widgets.push(graph);
+        }
+        if let Some(ref cores) = self.cores {
+            widgets.extend(cores.view());
+        }
+        widgets
+    }
+
+    fn metrics(&self) -> Vec<(&'static str, f64)> {
+        self.metrics.clone()
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if event.name.as_ref().map_or(false, |name| name == &self.id) && event.button == MouseButton::Left {
+            // Which process is busiest only shows over some time, so count
+            // from here until the next update
+            if let Some(total) = processes::total_ticks() {
+                self.top_sample = Some((processes::cpu_ticks(), total));
+                self.top = None;
+            }
+        }
+        Ok(())
     }
 
     fn id(&self) -> &str {
         &self.id
     }
 }
+
+/// The idle and non-idle time in a `cpu` line of `/proc/stat`.
+fn cpu_times(line: &str) -> (u64, u64) {
+    let data: Vec<u64> = line.split_whitespace().skip(1).filter_map(|x| x.parse::<u64>().ok()).collect();
+    if data.len() < 8 {
+        return (0, 0);
+    }
+
+    // idle = idle + iowait
+    let idle = data[3] + data[4];
+    let non_idle = data[0] + // user
+                    data[1] + // nice
+                    data[2] + // system
+                    data[5] + // irq
+                    data[6] + // softirq
+                    data[7]; // steal
+    (idle, non_idle)
+}
+
+/// The utilization in percent between two readings of `cpu_times`.
+fn utilization_since(prev: (u64, u64), (idle, non_idle): (u64, u64)) -> u64 {
+    let (prev_idle, prev_non_idle) = prev;
+    let prev_total = prev_idle + prev_non_idle;
+    let total = idle + non_idle;
+
+    // This check is needed because the new values may be reset, for
+    // example after hibernation.
+    let (total_delta, idle_delta) = if prev_total < total && prev_idle <= idle {
+        (total - prev_total, idle - prev_idle)
+    } else {
+        (1, 1)
+    };
+
+    (((total_delta - idle_delta) as f64 / total_delta as f64) * 100.) as u64
+}
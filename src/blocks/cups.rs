@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::process::Command;
+use std::time::{Duration, Instant};
+use chan::Sender;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use widgets::text::TextWidget;
+use widget::{I3BarWidget, State};
+use scheduler::Task;
+
+use uuid::Uuid;
+
+pub struct Cups {
+    text: TextWidget,
+    id: String,
+    update_interval: Duration,
+    printer: Option<String>,
+    stuck_after: Duration,
+    /// Maps job ids to the time we first saw them in the queue.
+    jobs: HashMap<String, Instant>,
+    visible: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CupsConfig {
+    /// Update interval in seconds
+    #[serde(default = "CupsConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Only show jobs and errors of this printer (default is all printers)
+    #[serde(default = "CupsConfig::default_printer")]
+    pub printer: Option<String>,
+
+    /// Time after which a job still in the queue is considered stuck
+    #[serde(default = "CupsConfig::default_stuck_after", deserialize_with = "deserialize_duration")]
+    pub stuck_after: Duration,
+}
+
+impl CupsConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    fn default_printer() -> Option<String> {
+        None
+    }
+
+    fn default_stuck_after() -> Duration {
+        Duration::from_secs(300)
+    }
+}
+
+/// Run `lpstat` with the given arguments and return its stdout.
+fn lpstat(args: &[&str]) -> Result<String> {
+    Command::new("lpstat")
+        .args(args)
+        .env("LC_ALL", "C")
+        .output()
+        .block_error("cups", "failed to run lpstat")
+        .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+}
+
+/// Translate a CUPS printer-state-reason into something short and readable.
+fn describe_alert(alert: &str) -> Option<&'static str> {
+    // https://www.iana.org/assignments/ipp-registrations (printer-state-reasons)
+    let reason = alert.trim_right_matches("-error").trim_right_matches("-warning").trim_right_matches("-report");
+    match reason {
+        "media-empty" | "media-needed" => Some("out of paper"),
+        "media-jam" => Some("paper jam"),
+        "toner-empty" | "marker-supply-empty" => Some("out of toner"),
+        "door-open" | "cover-open" => Some("cover open"),
+        "offline" | "connecting-to-device" => Some("offline"),
+        "paused" => Some("paused"),
+        _ => None,
+    }
+}
+
+impl ConfigBlock for Cups {
+    type Config = CupsConfig;
+
+    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        Ok(Cups {
+            id: Uuid::new_v4().simple().to_string(),
+            update_interval: block_config.interval,
+            text: TextWidget::new(config).with_icon("printer"),
+            printer: block_config.printer,
+            stuck_after: block_config.stuck_after,
+            jobs: HashMap::new(),
+            visible: false,
+        })
+    }
+}
+
+impl Cups {
+    /// Returns the ids of all jobs currently in the queue.
+    fn queued_jobs(&self) -> Result<Vec<String>> {
+        let output = match self.printer {
+            Some(ref printer) => lpstat(&["-o", printer])?,
+            None => lpstat(&["-o"])?,
+        };
+        // Lines look like: "HP_LaserJet-42  user  1024  Tue 15 Oct 2019 10:00:00 AM CEST"
+        Ok(output
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .map(|job| job.to_owned())
+            .collect())
+    }
+
+    /// Returns a readable description of every error condition reported by
+    /// the printers, e.g. "out of paper".
+    fn printer_errors(&self) -> Result<Vec<String>> {
+        let output = match self.printer {
+            Some(ref printer) => lpstat(&["-l", "-p", printer])?,
+            None => lpstat(&["-l", "-p"])?,
+        };
+
+        let mut errors = Vec::new();
+        for line in output.lines() {
+            let line = line.trim();
+            if line.starts_with("printer ") && line.contains(" disabled") {
+                errors.push("disabled".to_owned());
+            } else if line.starts_with("Alerts:") {
+                for alert in line.trim_left_matches("Alerts:").split_whitespace() {
+                    if let Some(description) = describe_alert(alert) {
+                        errors.push(description.to_owned());
+                    }
+                }
+            }
+        }
+        // Several printers may well report the same condition
+        errors.sort();
+        errors.dedup();
+        Ok(errors)
+    }
+}
+
+impl Block for Cups {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let queued = self.queued_jobs()?;
+        let errors = self.printer_errors()?;
+
+        // Forget finished jobs and remember when we first saw new ones.
+        let now = Instant::now();
+        self.jobs.retain(|job, _| queued.contains(job));
+        for job in &queued {
+            self.jobs.entry(job.clone()).or_insert(now);
+        }
+
+        let stuck_after = self.stuck_after;
+        let stuck = self.jobs
+            .values()
+            .any(|&seen| now.duration_since(seen) >= stuck_after);
+
+        self.visible = !queued.is_empty() || !errors.is_empty();
+
+        let mut text = format!("{}", queued.len());
+        if !errors.is_empty() {
+            text = format!("{} {}", text, errors.join(", "));
+        }
+        self.text.set_text(text);
+        self.text.set_state(if stuck {
+            State::Critical
+        } else if !errors.is_empty() {
+            State::Warning
+        } else {
+            State::Info
+        });
+        self.text.set_urgent(stuck);
+
+        Ok(Some(self.update_interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        if self.visible {
+            vec![&self.text]
+        } else {
+            vec![]
+        }
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
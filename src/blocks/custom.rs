@@ -21,7 +21,6 @@ pub struct Custom {
     update_interval: Duration,
     output: ButtonWidget,
     command: Option<String>,
-    on_click: Option<String>,
     cycle: Option<Peekable<Cycle<vec::IntoIter<String>>>>,
     tx_update_request: Sender<Task>,
 }
@@ -36,9 +35,6 @@ pub struct CustomConfig {
     /// Shell Command to execute & display
     pub command: Option<String>,
 
-    /// Command to execute when the button is clicked
-    pub on_click: Option<String>,
-
     /// Commands to execute and change when the button is clicked
     pub cycle: Option<Vec<String>>,
 }
@@ -58,16 +54,11 @@ impl ConfigBlock for Custom {
             update_interval: block_config.interval,
             output: ButtonWidget::new(config.clone(), ""),
             command: None,
-            on_click: None,
             cycle: None,
             tx_update_request: tx,
         };
         custom.output = ButtonWidget::new(config, &custom.id);
 
-        if let Some(on_click) = block_config.on_click {
-            custom.on_click = Some(on_click.to_string())
-        };
-
         if let Some(cycle) = block_config.cycle {
             custom.cycle = Some(cycle.into_iter().cycle().peekable());
             return Ok(custom);
@@ -83,6 +74,11 @@ impl ConfigBlock for Custom {
 
 impl Block for Custom {
     fn update(&mut self) -> Result<Option<Duration>> {
+        // Without a command, the text is set through the control socket.
+        if self.cycle.is_none() && self.command.is_none() {
+            return Ok(None);
+        }
+
         let command_str = self.cycle
             .as_mut()
             .map(|c| c.peek().cloned().unwrap_or_else(|| "".to_owned()))
@@ -113,20 +109,9 @@ impl Block for Custom {
             return Ok(());
         }
 
-        let mut update = false;
-
-        if let Some(ref on_click) = self.on_click {
-            Command::new(env::var("SHELL").unwrap_or_else(|_|"sh".to_owned()))
-                    .args(&["-c", on_click]).output().ok();
-            update = true;
-        }
-
+        // Click commands of the common options update the block on their own.
         if let Some(ref mut cycle) = self.cycle {
             cycle.next();
-            update = true;
-        }
-
-        if update {
             self.tx_update_request.send(Task {
                 id: self.id.clone(),
                 update_time: Instant::now(),
@@ -136,6 +121,12 @@ impl Block for Custom {
         Ok(())
     }
 
+    fn set_text(&mut self, text: String) -> Result<()> {
+        // Commands will overwrite this on their next run.
+        self.output.set_text(text);
+        Ok(())
+    }
+
     fn id(&self) -> &str {
         &self.id
     }
@@ -0,0 +1,158 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use dbus_manager;
+use errors::*;
+use scheduler::Task;
+use formatting::FormatTemplate;
+use widget::I3BarWidget;
+use widgets::text::TextWidget;
+use blocks::dbus::{arg, stdintf, BusType, Connection};
+use self::stdintf::OrgFreedesktopDBusProperties;
+
+#[derive(Deserialize, Copy, Clone, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Bus {
+    Session,
+    System,
+}
+
+impl From<Bus> for BusType {
+    fn from(bus: Bus) -> Self {
+        match bus {
+            Bus::Session => BusType::Session,
+            Bus::System => BusType::System,
+        }
+    }
+}
+
+pub struct DbusProperty {
+    id: String,
+    output: TextWidget,
+    dbus_conn: Rc<Connection>,
+    destination: String,
+    path: String,
+    interface: String,
+    property: String,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DbusPropertyConfig {
+    /// The bus to connect to, `session` or `system`
+    #[serde(default = "DbusPropertyConfig::default_bus")]
+    pub bus: Bus,
+
+    /// Bus name of the service owning the object, e.g. `org.freedesktop.UPower`
+    pub destination: String,
+
+    /// Object path, e.g. `/org/freedesktop/UPower`
+    pub path: String,
+
+    /// Interface the property belongs to
+    pub interface: String,
+
+    /// Name of the property
+    pub property: String,
+
+    /// Format string, the value is available as {value}
+    #[serde(default = "DbusPropertyConfig::default_format")]
+    pub format: String,
+
+    /// Icon to display in front of the value
+    #[serde(default = "DbusPropertyConfig::default_icon")]
+    pub icon: String,
+}
+
+impl DbusPropertyConfig {
+    fn default_bus() -> Bus {
+        Bus::Session
+    }
+
+    fn default_format() -> String {
+        "{value}".to_owned()
+    }
+
+    fn default_icon() -> String {
+        "".to_owned()
+    }
+}
+
+/// Render an arbitrary D-Bus value for display. Containers are flattened into
+/// a comma separated list.
+fn refarg_to_string(value: &arg::RefArg) -> String {
+    if let Some(s) = value.as_str() {
+        s.to_owned()
+    } else if let Some(i) = value.as_i64() {
+        format!("{}", i)
+    } else if let Some(u) = value.as_u64() {
+        format!("{}", u)
+    } else if let Some(f) = value.as_f64() {
+        format!("{:.1}", f)
+    } else if let Some(iter) = value.as_iter() {
+        iter.map(|v| refarg_to_string(v)).collect::<Vec<_>>().join(", ")
+    } else {
+        "?".to_owned()
+    }
+}
+
+impl ConfigBlock for DbusProperty {
+    type Config = DbusPropertyConfig;
+
+    fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        let bus: BusType = block_config.bus.into();
+        let dbus_conn = dbus_manager::connection(bus)?;
+
+        let rule = format!(
+            "type='signal',\
+             path='{}',\
+             interface='org.freedesktop.DBus.Properties',\
+             member='PropertiesChanged',\
+             arg0='{}'",
+            block_config.path,
+            block_config.interface
+        );
+        dbus_manager::subscribe(bus, &rule, &id, &send)?;
+
+        Ok(DbusProperty {
+            id,
+            output: TextWidget::new(config).with_icon(&block_config.icon),
+            dbus_conn,
+            destination: block_config.destination,
+            path: block_config.path,
+            interface: block_config.interface,
+            property: block_config.property,
+            format: FormatTemplate::from_string(&block_config.format)?,
+        })
+    }
+}
+
+impl Block for DbusProperty {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let value: arg::Variant<Box<arg::RefArg>> = self.dbus_conn
+            .with_path(self.destination.as_str(), self.path.as_str(), 1000)
+            .get(&self.interface, &self.property)
+            .block_error("dbus_property", &format!("failed to read property '{}'", self.property))?;
+
+        let values = map!("{value}" => refarg_to_string(&*value.0));
+        self.output.set_text(self.format.render_static_str(&values)?);
+
+        // We are notified through the subscription when the property changes.
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.output]
+    }
+}
@@ -1,14 +1,18 @@
+use std::collections::HashMap;
 use std::time::Duration;
 use std::path::Path;
+use btrfs;
 use chan::Sender;
 use scheduler::Task;
+use udev;
 
 use block::{Block, ConfigBlock};
 use config::Config;
 use de::deserialize_duration;
 use errors::*;
+use formatting::{self, FormatTemplate, Value};
 use widgets::text::TextWidget;
-use widget::{I3BarWidget, State};
+use widget::{I3BarWidget, State, Thresholds};
 
 use uuid::Uuid;
 
@@ -54,9 +58,12 @@ pub struct DiskSpace {
     path: String,
     info_type: InfoType,
     unit: Unit,
-    warning: f64,
-    alert: f64,
+    thresholds: Thresholds,
     show_percentage: bool,
+    format: Option<FormatTemplate>,
+    btrfs: bool,
+    /// What the last update measured, see `Block::metrics`
+    metrics: Vec<(&'static str, f64)>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -90,9 +97,21 @@ pub struct DiskSpaceConfig {
     #[serde(default = "DiskSpaceConfig::default_alert")]
     pub alert: f64,
 
+    /// Replaces `warning` and `alert` if set, compared to GB or percents
+    #[serde(default = "DiskSpaceConfig::default_thresholds")]
+    pub thresholds: Option<Thresholds>,
+
     /// Show percentage
     #[serde(default = "DiskSpaceConfig::default_show_percentage")]
     pub show_percentage: bool,
+
+    /// Format override, replaces `unit` and `show_percentage`
+    #[serde(default = "DiskSpaceConfig::default_format")]
+    pub format: Option<String>,
+
+    /// Ask btrfs for its space, as `statvfs` can't tell how much is left
+    #[serde(default = "DiskSpaceConfig::default_btrfs")]
+    pub btrfs: bool,
 }
 
 impl DiskSpaceConfig {
@@ -124,77 +143,90 @@ impl DiskSpaceConfig {
         10.
     }
 
+    fn default_thresholds() -> Option<Thresholds> {
+        None
+    }
+
     fn default_show_percentage() -> bool {
         false
     }
-}
 
-impl DiskSpace {
-    fn compute_state(&self, bytes: u64, warning: f64, alert: f64) -> State {
-        let value = if self.unit == Unit::Percent { bytes as f64 } else { Unit::bytes_in_unit(Unit::GB, bytes) };
-        match self.unit {
-            Unit::Percent => {
-                match self.info_type {
-                    InfoType::Available | InfoType::Free | InfoType::Total | InfoType::Used => if value > alert {
-                        State::Critical
-                    } else if value <= alert && value > warning {
-                        State::Warning
-                    } else {
-                        State::Idle
-                    }
-                }
-            }
-            _ => {
-                match self.info_type {
-                    InfoType::Available | InfoType::Free | InfoType::Total | InfoType::Used => if 0. <= value && value < alert {
-                        State::Critical
-                    } else if alert <= value && value < warning {
-                        State::Warning
-                    } else {
-                        State::Idle
-                    }
-                }
-            }
-        }
+    fn default_format() -> Option<String> {
+        None
+    }
+
+    fn default_btrfs() -> bool {
+        false
     }
 }
 
 impl ConfigBlock for DiskSpace {
     type Config = DiskSpaceConfig;
 
-    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        let id = Uuid::new_v4().simple().to_string();
+        // Drives coming and going may well change what's mounted at `path`
+        if let Err(error) = udev::subscribe("block", &id, &tx_update_request) {
+            warn!("disk_space won't notice new drives before its next update: {:?}", error);
+        }
         Ok(DiskSpace {
-            id: Uuid::new_v4().simple().to_string(),
+            id,
             update_interval: block_config.interval,
             disk_space: TextWidget::new(config).with_text("DiskSpace"),
             alias: block_config.alias,
             path: block_config.path,
             info_type: block_config.info_type,
             unit: block_config.unit,
-            warning: block_config.warning,
-            alert: block_config.alert,
+            // Little space left is bad, while percentages count used space
+            thresholds: block_config.thresholds.unwrap_or(Thresholds {
+                good: None,
+                info: None,
+                warning: Some(block_config.warning),
+                critical: Some(block_config.alert),
+                reversed: block_config.unit != Unit::Percent,
+            }),
             show_percentage: block_config.show_percentage,
+            format: match block_config.format {
+                Some(format) => Some(FormatTemplate::from_string(&format)?),
+                None => None,
+            },
+            btrfs: block_config.btrfs,
+            metrics: Vec::new(),
         })
     }
 }
 
 impl Block for DiskSpace {
     fn update(&mut self) -> Result<Option<Duration>> {
-        let statvfs = statvfs(Path::new(self.path.as_str()))
-            .block_error("disk_space", "failed to retrieve statvfs")?;
+        let path = Path::new(self.path.as_str());
+        let (available, free, used, total, space, progress) = if self.btrfs {
+            // There's no space kept back for root, so free space is all
+            // available
+            let space = btrfs::space("disk_space", path)?;
+            let total = space.data_used + space.data_free;
+            (space.data_free, space.data_free, space.data_used, total, Some(space), btrfs::progress("disk_space", path)?)
+        } else {
+            let statvfs = statvfs(path).block_error("disk_space", "failed to retrieve statvfs")?;
+            (
+                statvfs.blocks_available() * statvfs.block_size(),
+                statvfs.blocks_free() * statvfs.block_size(),
+                (statvfs.blocks() - statvfs.blocks_free()) * statvfs.fragment_size(),
+                statvfs.blocks() * statvfs.fragment_size(),
+                None,
+                None,
+            )
+        };
         let mut result;
         let mut converted = 0.0f64;
         let mut converted_str = String::new();
-        let total = statvfs.blocks() * statvfs.fragment_size();
-        let used = (statvfs.blocks() - statvfs.blocks_free()) * statvfs.fragment_size();
 
         match self.info_type {
             InfoType::Available => {
-                result = statvfs.blocks_available() * statvfs.block_size();
+                result = available;
                 converted = Unit::bytes_in_unit(self.unit, result);
             }
             InfoType::Free => {
-                result = statvfs.blocks_free() * statvfs.block_size();
+                result = free;
                 converted = Unit::bytes_in_unit(self.unit, result);
             }
             InfoType::Total => {
@@ -220,31 +252,76 @@ impl Block for DiskSpace {
         }
 
         if self.unit == Unit::Percent {
-            self.disk_space.set_text(format!("{0} {1:.2}%",
-                self.alias,
-                percentage
-            ));
             result = percentage as u64;
-        } else if self.show_percentage {
-            self.disk_space.set_text(format!(
+        }
+
+        // Without metadata space, nothing can be written any more however
+        // much space data has left
+        let metadata = space.map(|space| {
+            let metadata_total = space.metadata_used + space.metadata_free;
+            space.metadata_used as f64 / metadata_total.max(1) as f64 * 100.
+        });
+        let progress = progress.map(|(operation, done)| format!("{} {:.0}%", operation, done));
+
+        if let Some(ref format) = self.format {
+            let bytes = |value: u64| Value::Number(value as f64, formatting::Unit::Bytes);
+            let mut values = HashMap::new();
+            values.insert("{alias}", Value::Text(self.alias.clone()));
+            values.insert("{path}", Value::Text(self.path.clone()));
+            values.insert("{available}", bytes(available));
+            values.insert("{free}", bytes(free));
+            values.insert("{used}", bytes(used));
+            values.insert("{total}", bytes(total));
+            values.insert("{percentage}", Value::Number(f64::from(percentage), formatting::Unit::Percents));
+            values.insert("{metadata}", Value::Number(metadata.unwrap_or(0.), formatting::Unit::Percents));
+            values.insert("{profile}", Value::Text(space.map_or("", |space| space.profile).to_owned()));
+            values.insert("{progress}", Value::Text(progress.clone().unwrap_or_default()));
+            self.disk_space.set_text(format.render_values(&values)?);
+        } else {
+            let text = if self.unit == Unit::Percent {
+                format!("{0} {1:.2}%",
+                    self.alias,
+                    percentage
+                )
+            } else if self.show_percentage {
+                format!(
                     "{0} {1} ({2:.2}%) {3:?}",
                     self.alias,
                     converted_str,
                     percentage,
                     self.unit
-                    ));
-        } else {
-            self.disk_space.set_text(format!(
+                    )
+            } else {
+                format!(
                     "{0} {1} {2:?}",
                     self.alias,
                     converted_str,
                     self.unit
-                    ));
+                    )
+            };
+            self.disk_space.set_text(match progress {
+                Some(ref progress) => format!("{} {}", text, progress),
+                None => text,
+            });
         }
 
-        let state = self.compute_state(result, self.warning, self.alert);
-        self.disk_space.set_state(state);
+        let value = if self.unit == Unit::Percent {
+            result as f64
+        } else {
+            Unit::bytes_in_unit(Unit::GB, result)
+        };
+        if metadata.map_or(false, |metadata| metadata > 90.) {
+            self.disk_space.set_state(State::Critical);
+        } else {
+            self.disk_space.set_state(self.thresholds.state(value));
+        }
 
+        self.metrics = vec![
+            ("disk_available_bytes", available as f64),
+            ("disk_free_bytes", free as f64),
+            ("disk_used_bytes", used as f64),
+            ("disk_size_bytes", total as f64),
+        ];
         Ok(Some(self.update_interval))
     }
 
@@ -252,6 +329,10 @@ impl Block for DiskSpace {
         vec![&self.disk_space]
     }
 
+    fn metrics(&self) -> Vec<(&'static str, f64)> {
+        self.metrics.clone()
+    }
+
     fn id(&self) -> &str {
         &self.id
     }
@@ -0,0 +1,385 @@
+//! A block for switching between display layouts, like only the laptop's
+//! screen, or extending it to a monitor.
+//!
+//! The current layout is detected from the connected outputs, so it's shown
+//! correctly even when it was set up elsewhere. Outputs are configured over
+//! the IPC of sway, or with `xrandr` on X11.
+
+use std::collections::HashMap;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Value};
+use input::{I3BarEvent, MouseButton};
+use scheduler::Task;
+use widget::I3BarWidget;
+use widgets::button::ButtonWidget;
+
+extern crate i3ipc;
+use self::i3ipc::I3Connection;
+
+/// A way of using the connected outputs.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Only the internal output
+    Internal,
+    /// Only the external outputs
+    External,
+    /// All outputs, next to each other
+    Extend,
+    /// All outputs, showing the same
+    Mirror,
+}
+
+impl Profile {
+    fn name(self) -> &'static str {
+        match self {
+            Profile::Internal => "internal",
+            Profile::External => "external",
+            Profile::Extend => "extend",
+            Profile::Mirror => "mirror",
+        }
+    }
+}
+
+/// How outputs are queried and configured.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Sway,
+    Xrandr,
+}
+
+/// A connected output.
+struct Output {
+    name: String,
+    /// Whether it shows anything
+    active: bool,
+    x: i32,
+    y: i32,
+    width: i32,
+}
+
+fn sway_outputs(c: &mut I3Connection) -> Result<Vec<Output>> {
+    let outputs = c.get_outputs()
+        .block_error("display_profile", "failed to get the outputs from sway")?;
+    Ok(outputs
+        .outputs
+        .into_iter()
+        .map(|output| Output {
+            name: output.name,
+            active: output.active,
+            x: output.rect.0,
+            y: output.rect.1,
+            width: output.rect.2,
+        })
+        .collect())
+}
+
+fn sway_command(c: &mut I3Connection, command: &str) -> Result<()> {
+    let reply = c.run_command(command)
+        .block_error("display_profile", "failed to send a command to sway")?;
+    match reply.outcomes.into_iter().find(|outcome| !outcome.success) {
+        Some(outcome) => Err(BlockError(
+            "display_profile".to_owned(),
+            outcome.error.unwrap_or_else(|| format!("'{}' failed", command)),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// The connected outputs, from lines like
+/// `HDMI-1 connected primary 1920x1080+1920+0 (normal left ...) 527mm x 296mm`.
+/// Outputs that are connected but off have no geometry.
+fn xrandr_outputs() -> Result<Vec<Output>> {
+    let output = Command::new("xrandr")
+        .arg("--query")
+        .output()
+        .block_error("display_profile", "failed to run xrandr")?;
+    let mut outputs = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let mut fields = line.split_whitespace();
+        let name = match (fields.next(), fields.next()) {
+            (Some(name), Some("connected")) => name,
+            _ => continue,
+        };
+        let geometry = fields
+            .take_while(|field| !field.starts_with('('))
+            .filter_map(|field| {
+                let mut parts = field.split(|c| c == 'x' || c == '+');
+                match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                    (Some(width), Some(_), Some(x), Some(y)) => Some((width.parse::<i32>().ok()?, x.parse::<i32>().ok()?, y.parse::<i32>().ok()?)),
+                    _ => None,
+                }
+            })
+            .next();
+        outputs.push(match geometry {
+            Some((width, x, y)) => Output {
+                name: name.to_owned(),
+                active: true,
+                x,
+                y,
+                width,
+            },
+            None => Output {
+                name: name.to_owned(),
+                active: false,
+                x: 0,
+                y: 0,
+                width: 0,
+            },
+        });
+    }
+    Ok(outputs)
+}
+
+/// The profile the outputs are set up as, if any.
+fn detect(outputs: &[Output], internal: &str) -> Option<Profile> {
+    let active: Vec<&Output> = outputs.iter().filter(|output| output.active).collect();
+    let internal_active = active.iter().any(|output| output.name == internal);
+    let has_external = outputs.iter().any(|output| output.name != internal);
+    if active.is_empty() {
+        None
+    } else if active.len() == 1 && internal_active {
+        Some(Profile::Internal)
+    } else if !internal_active {
+        Some(Profile::External)
+    } else if !has_external || active.len() < outputs.len() {
+        // Some of the outputs are left out, which none of the profiles does
+        None
+    } else if active.iter().all(|output| (output.x, output.y) == (active[0].x, active[0].y)) {
+        Some(Profile::Mirror)
+    } else {
+        Some(Profile::Extend)
+    }
+}
+
+/// Switches between display layouts on a click, see `DisplayProfileConfig`.
+pub struct DisplayProfile {
+    id: String,
+    output: ButtonWidget,
+    format: FormatTemplate,
+    backend: Backend,
+    internal: String,
+    profiles: Vec<Profile>,
+    /// The profile detected on the last update
+    current: Option<Profile>,
+    interval: Duration,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DisplayProfileConfig {
+    /// Update interval in seconds
+    #[serde(default = "DisplayProfileConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{profile}` and `{outputs}` are available
+    #[serde(default = "DisplayProfileConfig::default_format")]
+    pub format: String,
+
+    /// How to configure the outputs, `sway` or `xrandr`
+    #[serde(default = "DisplayProfileConfig::default_backend")]
+    pub backend: Backend,
+
+    /// The name of the laptop's own output
+    #[serde(default = "DisplayProfileConfig::default_internal")]
+    pub internal: String,
+
+    /// The profiles cycled through, in order
+    #[serde(default = "DisplayProfileConfig::default_profiles")]
+    pub profiles: Vec<Profile>,
+}
+
+impl DisplayProfileConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn default_format() -> String {
+        "{profile}".to_owned()
+    }
+
+    fn default_backend() -> Backend {
+        Backend::Sway
+    }
+
+    fn default_internal() -> String {
+        "eDP-1".to_owned()
+    }
+
+    fn default_profiles() -> Vec<Profile> {
+        vec![Profile::Internal, Profile::Extend, Profile::Mirror]
+    }
+}
+
+impl DisplayProfile {
+    fn outputs(&self) -> Result<Vec<Output>> {
+        match self.backend {
+            Backend::Sway => {
+                let mut c = I3Connection::connect()
+                    .block_error("display_profile", "failed to connect to sway")?;
+                sway_outputs(&mut c)
+            }
+            Backend::Xrandr => xrandr_outputs(),
+        }
+    }
+
+    /// Set up the outputs as `profile`. The external outputs go right of the
+    /// internal one, in the order they are listed.
+    fn apply(&self, profile: Profile) -> Result<()> {
+        let outputs = self.outputs()?;
+        let (internal, external): (Vec<&Output>, Vec<&Output>) =
+            outputs.iter().partition(|output| output.name == self.internal);
+        let enabled: Vec<&Output> = match profile {
+            Profile::Internal => internal.clone(),
+            Profile::External => external.clone(),
+            Profile::Extend | Profile::Mirror => internal.iter().chain(&external).cloned().collect(),
+        };
+        if enabled.is_empty() {
+            return Err(BlockError(
+                "display_profile".to_owned(),
+                format!("no outputs for the {} profile", profile.name()),
+            ));
+        }
+        let disabled = outputs
+            .iter()
+            .filter(|output| !enabled.iter().any(|enabled| enabled.name == output.name));
+
+        match self.backend {
+            Backend::Sway => {
+                let mut c = I3Connection::connect()
+                    .block_error("display_profile", "failed to connect to sway")?;
+                // Enable first, disabled outputs have no width to place them by
+                for output in &enabled {
+                    sway_command(&mut c, &format!("output {} enable", output.name))?;
+                }
+                let widths: HashMap<String, i32> = sway_outputs(&mut c)?
+                    .into_iter()
+                    .map(|output| (output.name, output.width))
+                    .collect();
+                let mut x = 0;
+                for output in &enabled {
+                    sway_command(&mut c, &format!("output {} pos {} 0", output.name, x))?;
+                    if profile != Profile::Mirror {
+                        x += widths.get(&output.name).cloned().unwrap_or(0);
+                    }
+                }
+                for output in disabled {
+                    sway_command(&mut c, &format!("output {} disable", output.name))?;
+                }
+            }
+            Backend::Xrandr => {
+                let mut args: Vec<String> = Vec::new();
+                for (i, output) in enabled.iter().enumerate() {
+                    args.extend(vec!["--output".to_owned(), output.name.clone(), "--auto".to_owned()]);
+                    if i == 0 {
+                        args.extend(vec!["--pos".to_owned(), "0x0".to_owned()]);
+                    } else {
+                        let relation = if profile == Profile::Mirror { "--same-as" } else { "--right-of" };
+                        args.extend(vec![relation.to_owned(), enabled[i - 1].name.clone()]);
+                    }
+                }
+                for output in disabled {
+                    args.extend(vec!["--output".to_owned(), output.name.clone(), "--off".to_owned()]);
+                }
+                let status = Command::new("xrandr")
+                    .args(&args)
+                    .status()
+                    .block_error("display_profile", "failed to run xrandr")?;
+                if !status.success() {
+                    return Err(BlockError(
+                        "display_profile".to_owned(),
+                        format!("xrandr failed to set up the {} profile", profile.name()),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ConfigBlock for DisplayProfile {
+    type Config = DisplayProfileConfig;
+
+    fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        if block_config.profiles.is_empty() {
+            return Err(BlockError(
+                "display_profile".to_owned(),
+                "at least one profile is needed".to_owned(),
+            ));
+        }
+
+        Ok(DisplayProfile {
+            output: ButtonWidget::new(config, &id).with_icon("display_profile"),
+            id,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("display_profile", "Invalid format specified for display_profile")?,
+            backend: block_config.backend,
+            internal: block_config.internal,
+            profiles: block_config.profiles,
+            current: None,
+            interval: block_config.interval,
+            tx_update_request: send,
+        })
+    }
+}
+
+impl Block for DisplayProfile {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let outputs = self.outputs()?;
+        self.current = detect(&outputs, &self.internal);
+
+        let active: Vec<&str> = outputs
+            .iter()
+            .filter(|output| output.active)
+            .map(|output| output.name.as_str())
+            .collect();
+        let mut values = HashMap::new();
+        values.insert("{profile}", Value::Text(self.current.map_or("custom", |profile| profile.name()).to_owned()));
+        values.insert("{outputs}", Value::Text(active.join(" ")));
+        self.output.set_text(self.format.render_values(&values)?);
+
+        Ok(Some(self.interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.output]
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if !event.name.as_ref().map_or(false, |name| name == &self.id) {
+            return Ok(());
+        }
+        // A custom layout goes to the first profile either way
+        let position = self.current.and_then(|current| self.profiles.iter().position(|&profile| profile == current));
+        let count = self.profiles.len();
+        let next = match (&event.button, position) {
+            (&MouseButton::Left, Some(position)) => (position + 1) % count,
+            (&MouseButton::Right, Some(position)) => (position + count - 1) % count,
+            (&MouseButton::Left, None) | (&MouseButton::Right, None) => 0,
+            _ => return Ok(()),
+        };
+
+        self.apply(self.profiles[next])?;
+        self.tx_update_request.send(Task {
+            id: self.id.clone(),
+            update_time: Instant::now(),
+        });
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
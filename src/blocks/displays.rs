@@ -0,0 +1,206 @@
+//! A block for the connected displays, and whether the laptop is docked.
+//!
+//! Outputs are read from the DRM devices in `sysfs`, so this works the same
+//! on X11 and Wayland. A dock is recognized by the monitor connected through
+//! it, or by the USB id of the dock itself.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Value};
+use scheduler::Task;
+use udev;
+use util::read_file;
+use widget::{I3BarWidget, State};
+use widgets::text::TextWidget;
+
+/// A connected output, e.g. `card0-HDMI-A-1`.
+struct Output {
+    /// The monitor's name and serial number, from its EDID
+    monitor: Vec<String>,
+}
+
+/// The text descriptors of an EDID, its monitor name and serial number. They
+/// are four 18 byte descriptors at offset 54, text ones have a type of 0xFC
+/// for the name or 0xFF for the serial, followed by up to 13 characters,
+/// ended by a newline.
+fn edid_names(edid: &[u8]) -> Vec<String> {
+    let mut names = Vec::new();
+    for offset in &[54, 72, 90, 108] {
+        let descriptor = match edid.get(*offset..*offset + 18) {
+            Some(descriptor) => descriptor,
+            None => break,
+        };
+        if descriptor[..3] != [0, 0, 0] || (descriptor[3] != 0xfc && descriptor[3] != 0xff) {
+            continue;
+        }
+        let text: Vec<u8> = descriptor[5..].iter().cloned().take_while(|&byte| byte != b'\n').collect();
+        names.push(String::from_utf8_lossy(&text).trim().to_owned());
+    }
+    names
+}
+
+fn connected_outputs() -> Result<Vec<Output>> {
+    let entries = fs::read_dir("/sys/class/drm").block_error("displays", "failed to read /sys/class/drm")?;
+    let mut outputs = Vec::new();
+    for entry in entries {
+        let path = entry.block_error("displays", "failed to read /sys/class/drm")?.path();
+        // Cards themselves, like `card0`, have no status
+        let connected = read_file("displays", &path.join("status")).map_or(false, |status| status == "connected");
+        if !connected {
+            continue;
+        }
+        let mut edid = Vec::new();
+        if let Ok(mut file) = File::open(path.join("edid")) {
+            let _ = file.read_to_end(&mut edid);
+        }
+        outputs.push(Output {
+            monitor: edid_names(&edid),
+        });
+    }
+    Ok(outputs)
+}
+
+/// The USB ids of all connected devices, like `17ef:3082`.
+fn usb_ids() -> Vec<String> {
+    let entries = match fs::read_dir("/sys/bus/usb/devices") {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let vendor = read_file("displays", &path.join("idVendor")).ok()?;
+            let product = read_file("displays", &path.join("idProduct")).ok()?;
+            Some(format!("{}:{}", vendor, product).to_lowercase())
+        })
+        .collect()
+}
+
+/// Shows how many displays are connected, and whether a dock is, see
+/// `DisplaysConfig`.
+pub struct Displays {
+    id: String,
+    text: TextWidget,
+    format: FormatTemplate,
+    dock_monitors: Vec<String>,
+    dock_usb_ids: Vec<String>,
+    interval: Duration,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DisplaysConfig {
+    /// Update interval in seconds. Displays and docks coming and going show
+    /// right away.
+    #[serde(default = "DisplaysConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{count}` and `{dock}` are available
+    #[serde(default = "DisplaysConfig::default_format")]
+    pub format: String,
+
+    /// Names or serial numbers of monitors that are only connected through
+    /// a dock
+    #[serde(default = "DisplaysConfig::default_dock_monitors")]
+    pub dock_monitors: Vec<String>,
+
+    /// USB ids of docks, like `17ef:3082`
+    #[serde(default = "DisplaysConfig::default_dock_usb_ids")]
+    pub dock_usb_ids: Vec<String>,
+}
+
+impl DisplaysConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_format() -> String {
+        "{count}".to_owned()
+    }
+
+    fn default_dock_monitors() -> Vec<String> {
+        Vec::new()
+    }
+
+    fn default_dock_usb_ids() -> Vec<String> {
+        Vec::new()
+    }
+}
+
+impl ConfigBlock for Displays {
+    type Config = DisplaysConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        if !Path::new("/sys/class/drm").exists() {
+            return Err(BlockError("displays".to_owned(), "no graphics devices found".to_owned()));
+        }
+        // Plugging in a monitor changes its card, plugging in a dock adds USB
+        // devices
+        for subsystem in &["drm", "usb"] {
+            if let Err(error) = udev::subscribe(subsystem, &id, &tx_update_request) {
+                warn!("displays won't notice {} changes before its next update: {:?}", subsystem, error);
+            }
+        }
+
+        Ok(Displays {
+            id,
+            text: TextWidget::new(config),
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("displays", "Invalid format specified for displays")?,
+            dock_monitors: block_config.dock_monitors,
+            dock_usb_ids: block_config.dock_usb_ids.iter().map(|id| id.to_lowercase()).collect(),
+            interval: block_config.interval,
+        })
+    }
+}
+
+impl Block for Displays {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let outputs = connected_outputs()?;
+        let dock_monitor = outputs
+            .iter()
+            .flat_map(|output| output.monitor.iter())
+            .find(|name| self.dock_monitors.contains(name))
+            .cloned();
+        let dock = match dock_monitor {
+            Some(monitor) => Some(monitor),
+            None if !self.dock_usb_ids.is_empty() => usb_ids().into_iter().find(|id| self.dock_usb_ids.contains(id)),
+            None => None,
+        };
+
+        let mut values = HashMap::new();
+        values.insert("{count}", Value::Text(outputs.len().to_string()));
+        values.insert("{dock}", Value::Text(dock.clone().unwrap_or_default()));
+        self.text.set_text(self.format.render_values(&values)?);
+        if dock.is_some() {
+            self.text.set_icon("docked");
+            self.text.set_state(State::Info);
+        } else {
+            self.text.set_icon("displays");
+            self.text.set_state(State::Idle);
+        }
+
+        Ok(Some(self.interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
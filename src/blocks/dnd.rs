@@ -0,0 +1,227 @@
+//! A block for pausing notifications, by hand or on a schedule.
+//!
+//! Clicking the block pauses or resumes the notifications of dunst or mako.
+//! Quiet hours pause them when they begin and resume them when they end, so
+//! notifications can still be resumed by hand in between, or paused outside
+//! of them.
+
+use std::collections::HashMap;
+use std::env;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use chrono::{DateTime, Duration as ChronoDuration, Local, Weekday};
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::{deserialize_duration, deserialize_opt_active_hours, deserialize_opt_weekdays};
+use errors::*;
+use formatting::{FormatTemplate, Value};
+use input::{I3BarEvent, MouseButton};
+use scheduler::{self, ActiveHours, Task};
+use widget::{I3BarWidget, State};
+use widgets::button::ButtonWidget;
+
+/// The notification daemon to pause.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Daemon {
+    Dunst,
+    /// Paused by its `do-not-disturb` mode, which has to hide notifications
+    Mako,
+}
+
+impl Daemon {
+    fn run(self, command: &str) -> Result<String> {
+        let output = Command::new(env::var("SHELL").unwrap_or_else(|_| "sh".to_owned()))
+            .args(&["-c", command])
+            .output()
+            .block_error("dnd", &format!("failed to run '{}'", command))?;
+        if !output.status.success() {
+            return Err(BlockError("dnd".to_owned(), format!("'{}' failed", command)));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn is_paused(self) -> Result<bool> {
+        Ok(match self {
+            Daemon::Dunst => self.run("dunstctl is-paused")?.trim() == "true",
+            Daemon::Mako => self.run("makoctl mode")?.lines().any(|mode| mode.trim() == "do-not-disturb"),
+        })
+    }
+
+    fn set_paused(self, paused: bool) -> Result<()> {
+        self.run(match (self, paused) {
+            (Daemon::Dunst, true) => "dunstctl set-paused true",
+            (Daemon::Dunst, false) => "dunstctl set-paused false",
+            (Daemon::Mako, true) => "makoctl mode -a do-not-disturb",
+            (Daemon::Mako, false) => "makoctl mode -r do-not-disturb",
+        }).map(|_| ())
+    }
+}
+
+/// Quiet hours, on some days of the week or every day.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct QuietHours {
+    /// Like `"22:00-07:00"`, all day if not given
+    #[serde(default, deserialize_with = "deserialize_opt_active_hours")]
+    pub hours: Option<ActiveHours>,
+
+    /// Like `["mon-fri"]`, every day if not given
+    #[serde(default, deserialize_with = "deserialize_opt_weekdays")]
+    pub days: Option<Vec<Weekday>>,
+}
+
+/// Whether any of `quiet` has notifications paused at `now`.
+fn is_quiet(quiet: &[QuietHours], now: DateTime<Local>) -> bool {
+    quiet.iter().any(|quiet| scheduler::is_active(quiet.hours, &quiet.days, now))
+}
+
+/// How long from `now` until quiet hours that run at `now` are over, looking
+/// ahead a week at most, as back to back ones keep it quiet.
+fn quiet_left(quiet: &[QuietHours], now: DateTime<Local>) -> Duration {
+    let mut end = now;
+    while is_quiet(quiet, end) && end - now < ChronoDuration::weeks(1) {
+        let step = quiet
+            .iter()
+            .map(|quiet| scheduler::until_active_change(quiet.hours, &quiet.days, end))
+            .min()
+            .unwrap_or_else(|| Duration::from_secs(60));
+        end = end + ChronoDuration::from_std(step).unwrap_or_else(|_| ChronoDuration::minutes(1));
+    }
+    (end - now).to_std().unwrap_or_else(|_| Duration::from_secs(0))
+}
+
+/// Shows whether notifications are paused, and for how long quiet hours
+/// still last, see `DndConfig`.
+pub struct Dnd {
+    id: String,
+    output: ButtonWidget,
+    format: FormatTemplate,
+    daemon: Daemon,
+    quiet: Vec<QuietHours>,
+    /// Whether it was quiet hours on the last update, `None` before the first
+    was_quiet: Option<bool>,
+    interval: Duration,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DndConfig {
+    /// Update interval in seconds, for the quiet time left and for pausing
+    /// from elsewhere to show
+    #[serde(default = "DndConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{left}` is available
+    #[serde(default = "DndConfig::default_format")]
+    pub format: String,
+
+    /// The notification daemon, `dunst` or `mako`
+    #[serde(default = "DndConfig::default_daemon")]
+    pub daemon: Daemon,
+
+    /// When to pause notifications on their own
+    #[serde(default = "DndConfig::default_quiet")]
+    pub quiet: Vec<QuietHours>,
+}
+
+impl DndConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_format() -> String {
+        "{left}".to_owned()
+    }
+
+    fn default_daemon() -> Daemon {
+        Daemon::Dunst
+    }
+
+    fn default_quiet() -> Vec<QuietHours> {
+        Vec::new()
+    }
+}
+
+impl ConfigBlock for Dnd {
+    type Config = DndConfig;
+
+    fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        Ok(Dnd {
+            output: ButtonWidget::new(config, &id),
+            id,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("dnd", "Invalid format specified for dnd")?,
+            daemon: block_config.daemon,
+            quiet: block_config.quiet,
+            was_quiet: None,
+            interval: block_config.interval,
+            tx_update_request: send,
+        })
+    }
+}
+
+impl Block for Dnd {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let now = Local::now();
+        let quiet = is_quiet(&self.quiet, now);
+        // Only the start and end of quiet hours pause and resume, so that
+        // pausing by hand sticks in between
+        if self.was_quiet != Some(quiet) && (quiet || self.was_quiet.is_some()) {
+            self.daemon.set_paused(quiet)?;
+        }
+        self.was_quiet = Some(quiet);
+
+        let paused = self.daemon.is_paused()?;
+        let left = if paused && quiet {
+            let minutes = (quiet_left(&self.quiet, now).as_secs() + 59) / 60;
+            format!("{}:{:02}", minutes / 60, minutes % 60)
+        } else {
+            String::new()
+        };
+        let mut values = HashMap::new();
+        values.insert("{left}", Value::Text(left));
+        self.output.set_text(self.format.render_values(&values)?);
+        if paused {
+            self.output.set_icon("dnd_on");
+            self.output.set_state(State::Info);
+        } else {
+            self.output.set_icon("dnd_off");
+            self.output.set_state(State::Idle);
+        }
+
+        // Right on time for quiet hours to begin or end
+        let next_change = self.quiet
+            .iter()
+            .map(|quiet| scheduler::until_active_change(quiet.hours, &quiet.days, now))
+            .min()
+            .unwrap_or(self.interval);
+        Ok(Some(next_change.min(self.interval)))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.output]
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if event.name.as_ref().map_or(false, |name| name == &self.id) && event.button == MouseButton::Left {
+            let paused = self.daemon.is_paused()?;
+            self.daemon.set_paused(!paused)?;
+            self.tx_update_request.send(Task {
+                id: self.id.clone(),
+                update_time: Instant::now(),
+            });
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,182 @@
+//! A block for what was downloaded lately, as a nudge to clean up the
+//! downloads folder every now and then.
+//!
+//! Files count as added when they last changed status, which is when they
+//! were created or moved into the folder, so downloads keeping the time they
+//! were modified on the server count too. The folder is watched with
+//! inotify, so downloads show right away.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chan::Sender;
+use inotify::WatchMask;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Unit, Value};
+use input::{I3BarEvent, MouseButton};
+use scheduler::{self, Task};
+use util::{read_file, shell_quote, spawn_click_command};
+use widget::I3BarWidget;
+use widgets::button::ButtonWidget;
+
+/// The downloads folder of the XDG user directories, `~/Downloads` unless
+/// `user-dirs.dirs` says otherwise.
+fn downloads_dir() -> PathBuf {
+    let home = env::var("HOME").unwrap_or_else(|_| "/tmp".to_owned());
+    let config = match env::var("XDG_CONFIG_HOME") {
+        Ok(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => Path::new(&home).join(".config"),
+    };
+    // A shell script setting variables, like
+    // `XDG_DOWNLOAD_DIR="$HOME/Downloads"`
+    let dirs = read_file("downloads", &config.join("user-dirs.dirs")).unwrap_or_default();
+    dirs.lines()
+        .find(|line| line.starts_with("XDG_DOWNLOAD_DIR="))
+        .map(|line| PathBuf::from(line["XDG_DOWNLOAD_DIR=".len()..].trim_matches('"').replace("$HOME", &home)))
+        .unwrap_or_else(|| Path::new(&home).join("Downloads"))
+}
+
+/// Shows how many files were downloaded lately and opens the folder on a
+/// click, see `DownloadsConfig`.
+pub struct Downloads {
+    id: String,
+    output: ButtonWidget,
+    format: FormatTemplate,
+    path: PathBuf,
+    within: Duration,
+    command: String,
+    interval: Duration,
+    count: usize,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DownloadsConfig {
+    /// The downloads folder, the one of the XDG user directories by default
+    #[serde(default = "DownloadsConfig::default_path")]
+    pub path: Option<String>,
+
+    /// How long, in hours, files count as added lately
+    #[serde(default = "DownloadsConfig::default_hours")]
+    pub hours: u64,
+
+    /// Update interval in seconds, for files to stop counting
+    #[serde(default = "DownloadsConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{count}` and `{size}` are available
+    #[serde(default = "DownloadsConfig::default_format")]
+    pub format: String,
+
+    /// Command run on a left click, `{path}` is replaced by the folder
+    #[serde(default = "DownloadsConfig::default_command")]
+    pub command: String,
+}
+
+impl DownloadsConfig {
+    fn default_path() -> Option<String> {
+        None
+    }
+
+    fn default_hours() -> u64 {
+        24
+    }
+
+    fn default_interval() -> Duration {
+        Duration::from_secs(600)
+    }
+
+    fn default_format() -> String {
+        "{count} {size:.1;bin}".to_owned()
+    }
+
+    fn default_command() -> String {
+        "xdg-open {path}".to_owned()
+    }
+}
+
+impl ConfigBlock for Downloads {
+    type Config = DownloadsConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        let path = block_config.path.map_or_else(downloads_dir, PathBuf::from);
+        // Browsers download to a temporary file, which is renamed when done
+        let mask = WatchMask::CREATE | WatchMask::DELETE | WatchMask::MOVED_TO | WatchMask::MOVED_FROM | WatchMask::CLOSE_WRITE;
+        scheduler::watch_file(&path, mask, &id, &tx_update_request)?;
+
+        Ok(Downloads {
+            output: ButtonWidget::new(config, &id).with_icon("downloads"),
+            id,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("downloads", "Invalid format specified for downloads")?,
+            path,
+            within: Duration::from_secs(block_config.hours * 3600),
+            command: block_config.command,
+            interval: block_config.interval,
+            count: 0,
+            tx_update_request,
+        })
+    }
+}
+
+impl Block for Downloads {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .internal_error("downloads", "the clock is before 1970")?;
+        let since = now.as_secs().saturating_sub(self.within.as_secs()) as i64;
+        let entries = fs::read_dir(&self.path)
+            .block_error("downloads", &format!("failed to read {}", self.path.to_string_lossy()))?;
+        let mut count = 0;
+        let mut size = 0;
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.ctime() >= since {
+                count += 1;
+                size += metadata.len();
+            }
+        }
+        self.count = count;
+
+        let mut values = HashMap::new();
+        values.insert("{count}", Value::Number(count as f64, Unit::None));
+        values.insert("{size}", Value::Number(size as f64, Unit::Bytes));
+        self.output.set_text(self.format.render_values(&values)?);
+
+        Ok(Some(self.interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        if self.count > 0 {
+            vec![&self.output]
+        } else {
+            vec![]
+        }
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if event.name.as_ref().map_or(false, |name| name == &self.id) && event.button == MouseButton::Left {
+            let command = self.command.replace("{path}", &shell_quote(&self.path.to_string_lossy()));
+            spawn_click_command(&command, "", &event.button, self.id.clone(), self.tx_update_request.clone());
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
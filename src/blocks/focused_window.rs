@@ -1,6 +1,5 @@
 use std::time::{Duration, Instant};
 use chan::Sender;
-use std::thread;
 use std::sync::{Arc, Mutex};
 
 use block::{Block, ConfigBlock};
@@ -9,15 +8,48 @@ use errors::*;
 use widgets::text::TextWidget;
 use widget::I3BarWidget;
 use scheduler::Task;
+use i3_events;
 
 use uuid::Uuid;
 
 extern crate i3ipc;
-use self::i3ipc::I3EventListener;
 use self::i3ipc::Subscription;
 use self::i3ipc::event::Event;
 use self::i3ipc::event::inner::{WindowChange, WorkspaceChange};
 
+/// Keep `title` on the focused window, and request an update of the block `id`
+/// whenever it changes.
+fn handle_event(event: &Event, title: &Mutex<String>, id: &str, tx: &Sender<Task>) {
+    let new_title = match *event {
+        Event::WindowEvent(ref e) => match e.change {
+            WindowChange::Focus => e.container.name.clone(),
+            WindowChange::Title => if e.container.focused {
+                e.container.name.clone()
+            } else {
+                None
+            },
+            WindowChange::Close => match e.container.name {
+                Some(ref name) if *name == *title.lock().unwrap() => Some(String::from("")),
+                _ => None,
+            },
+            _ => None,
+        },
+        Event::WorkspaceEvent(ref e) => match e.change {
+            WorkspaceChange::Init => Some(String::from("")),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    if let Some(new_title) = new_title {
+        *title.lock().unwrap() = new_title;
+        tx.send(Task {
+            id: id.to_owned(),
+            update_time: Instant::now(),
+        });
+    }
+}
+
 pub struct FocusedWindow {
     text: TextWidget,
     title: Arc<Mutex<String>>,
@@ -44,69 +76,16 @@ impl ConfigBlock for FocusedWindow {
 
     fn new(block_config: Self::Config, config: Config, tx: Sender<Task>) -> Result<Self> {
         let id = Uuid::new_v4().simple().to_string();
-        let id_clone = id.clone();
-
-        let title_original = Arc::new(Mutex::new(String::from("")));
-        let title = title_original.clone();
-
-        thread::spawn(move || {
-            // establish connection.
-            let mut listener = I3EventListener::connect().unwrap();
-
-            // subscribe to a couple events.
-            let subs = [Subscription::Window, Subscription::Workspace];
-            listener.subscribe(&subs).unwrap();
-
-            // handle them
-            for event in listener.listen() {
-                match event.unwrap() {
-                    Event::WindowEvent(e) => {
-                        match e.change {
-                            WindowChange::Focus => if let Some(name) = e.container.name {
-                                let mut title = title_original.lock().unwrap();
-                                *title = name;
-                                tx.send(Task {
-                                    id: id_clone.clone(),
-                                    update_time: Instant::now(),
-                                });
-                            },
-                            WindowChange::Title => if e.container.focused {
-                                if let Some(name) = e.container.name {
-                                    let mut title = title_original.lock().unwrap();
-                                    *title = name;
-                                    tx.send(Task {
-                                        id: id_clone.clone(),
-                                        update_time: Instant::now(),
-                                    });
-                                }
-                            },
-                            WindowChange::Close => if let Some(name) = e.container.name {
-                                let mut title = title_original.lock().unwrap();
-                                if name == *title {
-                                    *title = String::from("");
-                                    tx.send(Task {
-                                        id: id_clone.clone(),
-                                        update_time: Instant::now(),
-                                    });
-                                }
-                            },
-                            _ => {}
-                        };
-                    }
-                    Event::WorkspaceEvent(e) => {
-                        if let WorkspaceChange::Init = e.change {
-                            let mut title = title_original.lock().unwrap();
-                            *title = String::from("");
-                            tx.send(Task {
-                                id: id_clone.clone(),
-                                update_time: Instant::now(),
-                            });
-                        }
-                    },
-                    _ => unreachable!(),
-                }
+        let title = Arc::new(Mutex::new(String::from("")));
+
+        for subscription in vec![Subscription::Window, Subscription::Workspace] {
+            let (shared, block_id, tx) = (title.clone(), id.clone(), tx.clone());
+            let handler = Box::new(move |event: &Event| handle_event(event, &shared, &block_id, &tx));
+            if let Err(error) = i3_events::subscribe(subscription, &id, handler) {
+                error!("failed to follow i3 or sway: {:?}", error);
+                break;
             }
-        });
+        }
 
         Ok(FocusedWindow {
             id,
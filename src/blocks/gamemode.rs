@@ -0,0 +1,120 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use dbus_manager;
+use errors::*;
+use scheduler::Task;
+use widget::{I3BarWidget, State};
+use widgets::text::TextWidget;
+use blocks::dbus::{BusType, Connection, Message, MessageItem};
+use blocks::dbus::arg::Variant;
+
+const GAMEMODE_NAME: &str = "com.feralinteractive.GameMode";
+const GAMEMODE_PATH: &str = "/com/feralinteractive/GameMode";
+
+pub struct Gamemode {
+    id: String,
+    output: TextWidget,
+    dbus_conn: Rc<Connection>,
+    hide_inactive: bool,
+    active: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GamemodeConfig {
+    /// Whether to hide the block while gamemode is not active.
+    #[serde(default = "GamemodeConfig::default_hide_inactive")]
+    pub hide_inactive: bool,
+}
+
+impl GamemodeConfig {
+    fn default_hide_inactive() -> bool {
+        true
+    }
+}
+
+/// Query the number of games currently registered with the gamemode daemon.
+fn client_count(c: &Connection) -> Result<i32> {
+    let m = Message::new_method_call(
+        GAMEMODE_NAME,
+        GAMEMODE_PATH,
+        "org.freedesktop.DBus.Properties",
+        "Get")
+        .block_error("gamemode", "Failed to create message")?
+        .append2(
+            MessageItem::Str(GAMEMODE_NAME.to_string()),
+            MessageItem::Str("ClientCount".to_string())
+        );
+
+    let r = c.send_with_reply_and_block(m, 1000)
+        .block_error("gamemode", "Failed to retrieve property")?;
+
+    let count: Variant<i32> = r.get1()
+        .block_error("gamemode", "Failed to read property")?;
+
+    Ok(count.0)
+}
+
+impl ConfigBlock for Gamemode {
+    type Config = GamemodeConfig;
+
+    fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        let dbus_conn = dbus_manager::connection(BusType::Session)?;
+
+        // Games (un)registering and the daemon appearing or vanishing all
+        // change what we display.
+        let rules = [
+            format!("type='signal',path='{}',interface='{}'", GAMEMODE_PATH, GAMEMODE_NAME),
+            format!("type='signal',path='{}',interface='org.freedesktop.DBus.Properties',member='PropertiesChanged'", GAMEMODE_PATH),
+            format!("type='signal',interface='org.freedesktop.DBus',member='NameOwnerChanged',arg0='{}'", GAMEMODE_NAME),
+        ];
+        for rule in &rules {
+            dbus_manager::subscribe(BusType::Session, rule, &id, &send)?;
+        }
+
+        Ok(Gamemode {
+            id,
+            output: TextWidget::new(config).with_icon("gamemode"),
+            dbus_conn,
+            hide_inactive: block_config.hide_inactive,
+            active: false,
+        })
+    }
+}
+
+impl Block for Gamemode {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Duration>> {
+        // The daemon is D-Bus activated, so if it isn't running no game is
+        // using it either.
+        let count = client_count(&self.dbus_conn).unwrap_or(0);
+
+        self.active = count > 0;
+        self.output.set_text(format!("{}", count));
+        self.output.set_state(if self.active {
+            State::Warning
+        } else {
+            State::Idle
+        });
+
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        if self.active || !self.hide_inactive {
+            vec![&self.output]
+        } else {
+            vec![]
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! A block for the state of local git repositories: the branch checked out,
+//! how many files changed and how far the branch is ahead of and behind its
+//! upstream.
+//!
+//! Repositories are read with libgit2. Their git directory is watched, so
+//! commits, checkouts, staging and fetches show right away, while changes to
+//! files in the working tree show with the next update.
+
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chan::Sender;
+use git2::{Branch, Repository, StatusOptions};
+use inotify::WatchMask;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Value};
+use input::{I3BarEvent, MouseButton};
+use scheduler::{self, Task};
+use util::{shell_quote, spawn_click_command};
+use widget::{I3BarWidget, State};
+use widgets::text::TextWidget;
+
+/// The state of a repository.
+struct Status {
+    /// The branch checked out, or the commit if none is
+    branch: String,
+    /// Files changed, staged or not, and untracked files
+    dirty: usize,
+    /// Commits ahead of and behind the upstream of the branch, if it has one
+    ahead: usize,
+    behind: usize,
+}
+
+fn status(repository: &Repository) -> Result<Status> {
+    let mut status = Status {
+        branch: String::new(),
+        dirty: 0,
+        ahead: 0,
+        behind: 0,
+    };
+    // A new repository has no commits, and thus no head yet
+    if let Ok(head) = repository.head() {
+        status.branch = match (head.is_branch(), head.shorthand(), head.target()) {
+            (true, Some(name), _) => name.to_owned(),
+            (_, _, Some(oid)) => oid.to_string().chars().take(7).collect(),
+            _ => String::new(),
+        };
+        if head.is_branch() {
+            let branch = Branch::wrap(head);
+            let local = branch.get().target();
+            let upstream = branch.upstream().ok().and_then(|upstream| upstream.get().target());
+            if let (Some(local), Some(upstream)) = (local, upstream) {
+                let (ahead, behind) = repository
+                    .graph_ahead_behind(local, upstream)
+                    .block_error("git", "failed to compare the branch to its upstream")?;
+                status.ahead = ahead;
+                status.behind = behind;
+            }
+        }
+    }
+
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).exclude_submodules(true);
+    status.dirty = repository
+        .statuses(Some(&mut options))
+        .block_error("git", "failed to get the status of the repository")?
+        .len();
+    Ok(status)
+}
+
+/// A repository shown, see `GitConfig::repositories`.
+struct Repo {
+    path: PathBuf,
+    name: String,
+    text: TextWidget,
+}
+
+/// Shows the state of each repository, and opens a terminal in one on a
+/// click, see `GitConfig`.
+pub struct Git {
+    id: String,
+    repositories: Vec<Repo>,
+    format: FormatTemplate,
+    command: String,
+    interval: Duration,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GitConfig {
+    /// Paths to the working trees of the repositories
+    pub repositories: Vec<String>,
+
+    /// Update interval in seconds, for changes in the working trees to show
+    #[serde(default = "GitConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{name}`, `{branch}`, `{dirty}`, `{ahead}` and
+    /// `{behind}` are available
+    #[serde(default = "GitConfig::default_format")]
+    pub format: String,
+
+    /// Command run on a left click, `{path}` is replaced by the repository
+    #[serde(default = "GitConfig::default_command")]
+    pub command: String,
+}
+
+impl GitConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(30)
+    }
+
+    fn default_format() -> String {
+        "{name} {branch} {dirty} {ahead}/{behind}".to_owned()
+    }
+
+    fn default_command() -> String {
+        "cd {path} && exec i3-sensible-terminal".to_owned()
+    }
+}
+
+impl ConfigBlock for Git {
+    type Config = GitConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        if block_config.repositories.is_empty() {
+            return Err(BlockError("git".to_owned(), "no repositories given".to_owned()));
+        }
+        let home = env::var("HOME").unwrap_or_default();
+        let mut repositories = Vec::new();
+        for path in &block_config.repositories {
+            let path = if path.starts_with("~/") {
+                PathBuf::from(&home).join(&path[2..])
+            } else {
+                PathBuf::from(path)
+            };
+            let repository = Repository::open(&path)
+                .block_error("git", &format!("no repository at {}", path.to_string_lossy()))?;
+            // Git replaces files by renaming new ones over them, like `HEAD`
+            // and the index, and fetching writes `FETCH_HEAD`
+            let mask = WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::DELETE;
+            scheduler::watch_file(repository.path(), mask, &id, &tx_update_request)?;
+            repositories.push(Repo {
+                name: path.file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                path,
+                text: TextWidget::new(config.clone()).with_icon("git"),
+            });
+        }
+
+        Ok(Git {
+            id,
+            repositories,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("git", "Invalid format specified for git")?,
+            command: block_config.command,
+            interval: block_config.interval,
+            tx_update_request,
+        })
+    }
+}
+
+impl Block for Git {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        for repo in &mut self.repositories {
+            let repository = Repository::open(&repo.path)
+                .block_error("git", &format!("no repository at {}", repo.path.to_string_lossy()))?;
+            let status = status(&repository)?;
+
+            let mut values = HashMap::new();
+            values.insert("{name}", Value::Text(repo.name.clone()));
+            values.insert("{branch}", Value::Text(status.branch));
+            values.insert("{dirty}", Value::Text(status.dirty.to_string()));
+            values.insert("{ahead}", Value::Text(status.ahead.to_string()));
+            values.insert("{behind}", Value::Text(status.behind.to_string()));
+            repo.text.set_text(self.format.render_values(&values)?);
+            // Work that isn't committed, or isn't pushed
+            repo.text.set_state(if status.dirty > 0 || status.ahead > 0 {
+                State::Info
+            } else {
+                State::Idle
+            });
+        }
+
+        Ok(Some(self.interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        self.repositories.iter().map(|repo| &repo.text as &I3BarWidget).collect()
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if event.button != MouseButton::Left {
+            return Ok(());
+        }
+        let repo = match event.widget_index(&self.id).and_then(|index| self.repositories.get(index)) {
+            Some(repo) => repo,
+            None => return Ok(()),
+        };
+        let command = self.command.replace("{path}", &shell_quote(&repo.path.to_string_lossy()));
+        spawn_click_command(&command, "", &event.button, self.id.clone(), self.tx_update_request.clone());
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
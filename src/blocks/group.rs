@@ -0,0 +1,217 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chan;
+use chan::Sender;
+use scheduler::Task;
+use toml::value::{Table, Value};
+
+use block::{Block, CommonBlockConfig, ConfigBlock};
+use blocks::create_block;
+use config::Config;
+use errors::*;
+use input::{I3BarEvent, MouseButton};
+use state::BlockState;
+use widget::{self, I3BarWidget};
+use widgets::button::ButtonWidget;
+
+use uuid::Uuid;
+
+struct Child {
+    block: Box<Block>,
+    /// When the block is due for its next update, `None` for never
+    next_update: Option<Instant>,
+}
+
+pub struct Group {
+    header: ButtonWidget,
+    children: Vec<Child>,
+    /// Ids of the children that asked for an update on their own
+    requested: Arc<Mutex<HashSet<String>>>,
+    collapsed: bool,
+    /// Remembers `collapsed` across restarts
+    state: BlockState,
+    id: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GroupConfig {
+    /// The grouped blocks, configured just like top level blocks
+    pub blocks: Vec<Table>,
+
+    /// Icon shown in the header
+    #[serde(default = "GroupConfig::default_icon")]
+    pub icon: String,
+
+    /// Text shown in the header
+    #[serde(default = "GroupConfig::default_text")]
+    pub text: String,
+
+    /// Collapsed by default? Once toggled, the group stays as it was left.
+    #[serde(default = "GroupConfig::default_collapsed")]
+    pub collapsed: bool,
+}
+
+impl GroupConfig {
+    fn default_icon() -> String {
+        "".to_owned()
+    }
+
+    fn default_text() -> String {
+        "\u{2026}".to_owned()
+    }
+
+    fn default_collapsed() -> bool {
+        true
+    }
+}
+
+impl ConfigBlock for Group {
+    type Config = GroupConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        Group::new_with_state(block_config, config, tx_update_request, BlockState::new("group"))
+    }
+
+    fn new_with_state(
+        block_config: Self::Config,
+        config: Config,
+        tx_update_request: Sender<Task>,
+        state: BlockState,
+    ) -> Result<Self> {
+        let id = Uuid::new_v4().simple().to_string();
+        let requested = Arc::new(Mutex::new(HashSet::new()));
+
+        // The bar only knows about the group, so update requests of the
+        // children are turned into requests for the group. The thread ends
+        // once all children are dropped.
+        let (tx_children, rx_children) = chan::async::<Task>();
+        {
+            let requested = requested.clone();
+            let id = id.clone();
+            thread::spawn(move || for task in rx_children.iter() {
+                if let Ok(mut requested) = requested.lock() {
+                    requested.insert(task.id);
+                }
+                tx_update_request.send(Task {
+                    id: id.clone(),
+                    update_time: task.update_time,
+                });
+            });
+        }
+
+        let mut children = Vec::new();
+        for (index, mut table) in block_config.blocks.into_iter().enumerate() {
+            let name = match table.remove("block") {
+                Some(Value::String(name)) => name,
+                _ => {
+                    return Err(BlockError(
+                        "group".to_owned(),
+                        "grouped blocks need a `block` type".to_owned(),
+                    ))
+                }
+            };
+            let mut child_config = Value::Table(table);
+            // Common options only apply to the group as a whole.
+            CommonBlockConfig::extract(&mut child_config)?;
+            children.push(Child {
+                block: create_block(
+                    &name,
+                    child_config,
+                    config.clone(),
+                    tx_children.clone(),
+                    state.child(&format!("{}.{}", name, index)),
+                )?,
+                next_update: Some(Instant::now()),
+            });
+        }
+
+        Ok(Group {
+            header: ButtonWidget::new(config, &id)
+                .with_icon(&block_config.icon)
+                .with_text(&block_config.text),
+            children,
+            requested,
+            collapsed: state.get().unwrap_or(block_config.collapsed),
+            state,
+            id,
+        })
+    }
+}
+
+impl Block for Group {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let now = Instant::now();
+        let requested: HashSet<String> = match self.requested.lock() {
+            Ok(mut requested) => requested.drain().collect(),
+            Err(_) => HashSet::new(),
+        };
+
+        for child in &mut self.children {
+            let due = child.next_update.map_or(false, |next| next <= now);
+            if due || requested.contains(child.block.id()) {
+                child.next_update = child.block.update()?.map(|interval| now + interval);
+            }
+        }
+
+        Ok(self.children
+            .iter()
+            .filter_map(|child| child.next_update)
+            .min()
+            .map(|next| if next > now { next - now } else { Duration::from_secs(0) }))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        let mut widgets: Vec<&I3BarWidget> = vec![&self.header];
+        if !self.collapsed {
+            for child in &self.children {
+                widgets.extend(child.block.view());
+            }
+        }
+        widgets
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if e.matches_name(&self.id) {
+            match e.button {
+                MouseButton::Left => self.collapsed = !self.collapsed,
+                MouseButton::WheelUp => self.collapsed = false,
+                MouseButton::WheelDown => self.collapsed = true,
+                _ => return Ok(()),
+            }
+            // Not worth taking down the bar for
+            if let Err(error) = self.state.set(&self.collapsed) {
+                warn!("failed to save group state: {:?}", error);
+            }
+        } else if let (false, Some(index)) = (self.collapsed, e.widget_index(&self.id)) {
+            // The bar only knows the group's widgets, so the click is passed
+            // on to the child owning the widget, as if on its own widget.
+            let mut offset = 1;
+            for child in &mut self.children {
+                let count = child.block.view().len();
+                if index < offset + count {
+                    let mut event = e.clone();
+                    event.instance = Some(widget::instance_name(child.block.id(), index - offset));
+                    return child.block.click(&event);
+                }
+                offset += count;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resumed(&mut self) {
+        for child in &mut self.children {
+            child.block.resumed();
+            child.next_update = Some(Instant::now());
+        }
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
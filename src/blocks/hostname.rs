@@ -0,0 +1,133 @@
+//! A block for the host the bar runs on, which stands out when that's a
+//! remote one, so a bar shown over SSH, e.g. with waypipe, isn't taken for
+//! the local one.
+//!
+//! The bar counts as remote when run from an SSH session, which sets
+//! `SSH_CONNECTION` to the addresses and ports of both ends.
+
+use std::collections::HashMap;
+use std::env;
+use std::path::Path;
+use std::time::Duration;
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use errors::*;
+use formatting::{FormatTemplate, Value};
+use scheduler::Task;
+use util::read_file;
+use widget::{I3BarWidget, State};
+use widgets::text::TextWidget;
+
+/// Shows the host and whether it's remote, see `HostnameConfig`.
+pub struct Hostname {
+    id: String,
+    text: TextWidget,
+    format: FormatTemplate,
+    label: Option<String>,
+    /// The address of the SSH client, if run from SSH
+    client: Option<String>,
+    hide_local: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HostnameConfig {
+    /// Shown instead of the host name
+    #[serde(default = "HostnameConfig::default_label")]
+    pub label: Option<String>,
+
+    /// Whether the host is remote, found from `SSH_CONNECTION` by default
+    #[serde(default = "HostnameConfig::default_remote")]
+    pub remote: Option<bool>,
+
+    /// Whether to hide the block on a local host
+    #[serde(default = "HostnameConfig::default_hide_local")]
+    pub hide_local: bool,
+
+    /// Format override, `{hostname}` and `{client}` are available
+    #[serde(default = "HostnameConfig::default_format")]
+    pub format: String,
+}
+
+impl HostnameConfig {
+    fn default_label() -> Option<String> {
+        None
+    }
+
+    fn default_remote() -> Option<bool> {
+        None
+    }
+
+    fn default_hide_local() -> bool {
+        false
+    }
+
+    fn default_format() -> String {
+        "{hostname}".to_owned()
+    }
+}
+
+impl ConfigBlock for Hostname {
+    type Config = HostnameConfig;
+
+    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        // Like `192.0.2.1 51234 192.0.2.2 22`
+        let client = env::var("SSH_CONNECTION")
+            .ok()
+            .and_then(|connection| connection.split_whitespace().next().map(|client| client.to_owned()));
+        let client = match block_config.remote {
+            Some(true) => Some(client.unwrap_or_default()),
+            Some(false) => None,
+            None => client,
+        };
+
+        Ok(Hostname {
+            id,
+            text: TextWidget::new(config).with_icon("hostname"),
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("hostname", "Invalid format specified for hostname")?,
+            label: block_config.label,
+            client,
+            hide_local: block_config.hide_local,
+        })
+    }
+}
+
+impl Block for Hostname {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let hostname = match self.label {
+            Some(ref label) => label.clone(),
+            None => read_file("hostname", Path::new("/proc/sys/kernel/hostname"))?,
+        };
+
+        let mut values = HashMap::new();
+        values.insert("{hostname}", Value::Text(hostname));
+        values.insert("{client}", Value::Text(self.client.clone().unwrap_or_default()));
+        self.text.set_text(self.format.render_values(&values)?);
+        self.text.set_state(if self.client.is_some() {
+            State::Warning
+        } else {
+            State::Idle
+        });
+
+        // The host name hardly ever changes, SIGUSR1 updates it if it does
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        if self.client.is_none() && self.hide_local {
+            vec![]
+        } else {
+            vec![&self.text]
+        }
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
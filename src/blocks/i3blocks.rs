@@ -0,0 +1,211 @@
+use std::env;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use input::I3BarEvent;
+use scheduler::Task;
+use widget::I3BarWidget;
+use widgets::button::ButtonWidget;
+
+use uuid::Uuid;
+
+/// The exit code of a script asking for attention
+const URGENT_EXIT_CODE: i32 = 33;
+
+/// Runs a script written for i3blocks, see `I3blocksConfig`.
+pub struct I3blocks {
+    id: String,
+    output: ButtonWidget,
+    /// Whether the script printed anything to show
+    visible: bool,
+    command: String,
+    name: String,
+    instance: String,
+    interval: Duration,
+    once: bool,
+    /// The click to tell the script about on its next run
+    click: Option<I3BarEvent>,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct I3blocksConfig {
+    /// The script, run with the shell
+    pub command: String,
+
+    /// Passed as `BLOCK_NAME`, like the section name in i3blocks
+    #[serde(default = "I3blocksConfig::default_name")]
+    pub name: String,
+
+    /// Passed as `BLOCK_INSTANCE`
+    #[serde(default = "I3blocksConfig::default_instance")]
+    pub instance: String,
+
+    /// Update interval in seconds
+    #[serde(default = "I3blocksConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Run the script only on start and on clicks, like `interval=once`
+    #[serde(default = "I3blocksConfig::default_once")]
+    pub once: bool,
+}
+
+impl I3blocksConfig {
+    fn default_name() -> String {
+        "".to_owned()
+    }
+
+    fn default_instance() -> String {
+        "".to_owned()
+    }
+
+    fn default_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    fn default_once() -> bool {
+        false
+    }
+}
+
+impl ConfigBlock for I3blocks {
+    type Config = I3blocksConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        let id = Uuid::new_v4().simple().to_string();
+        Ok(I3blocks {
+            output: ButtonWidget::new(config, &id),
+            id,
+            visible: false,
+            command: block_config.command,
+            name: block_config.name,
+            instance: block_config.instance,
+            interval: block_config.interval,
+            once: block_config.once,
+            click: None,
+            tx_update_request,
+        })
+    }
+}
+
+impl I3blocks {
+    /// The script, with the environment i3blocks would give it.
+    fn script(&self) -> Command {
+        let mut command = Command::new(env::var("SHELL").unwrap_or_else(|_| "sh".to_owned()));
+        command
+            .args(&["-c", &self.command])
+            .env("BLOCK_NAME", &self.name)
+            .env("BLOCK_INSTANCE", &self.instance)
+            .env("BLOCK_INTERVAL", self.interval.as_secs().to_string())
+            .env("name", &self.name)
+            .env("instance", &self.instance)
+            .env("interval", self.interval.as_secs().to_string());
+
+        // Older scripts know the click by `BLOCK_*` variables, newer ones
+        // by the names of the i3bar protocol.
+        if let Some(ref click) = self.click {
+            let button = click.button.number().to_string();
+            let (x, y) = (click.x.to_string(), click.y.to_string());
+            command
+                .env("BLOCK_BUTTON", &button)
+                .env("BLOCK_X", &x)
+                .env("BLOCK_Y", &y)
+                .env("button", &button)
+                .env("x", &x)
+                .env("y", &y)
+                .env("modifiers", click.modifiers.join(","));
+            let extra = [
+                ("relative_x", click.relative_x),
+                ("relative_y", click.relative_y),
+                ("width", click.width),
+                ("height", click.height),
+            ];
+            for &(key, value) in &extra {
+                if let Some(value) = value {
+                    command.env(key, value.to_string());
+                }
+            }
+        }
+        command
+    }
+}
+
+impl Block for I3blocks {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let output = self.script()
+            .output()
+            .block_error("i3blocks", &format!("failed to run '{}'", self.command))?;
+        self.click = None;
+
+        let code = output.status.code();
+        if !output.status.success() && code != Some(URGENT_EXIT_CODE) {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let message = stderr.lines().next().unwrap_or("").trim().to_owned();
+            return Err(BlockError(
+                "i3blocks".to_owned(),
+                if message.is_empty() {
+                    format!("'{}' failed with {}", self.command, output.status)
+                } else {
+                    message
+                },
+            ));
+        }
+
+        // One line each for the text, the short text and the color
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines();
+        let full_text = lines.next().unwrap_or("").to_owned();
+        let short_text = lines.next().unwrap_or("").to_owned();
+        let color = lines.next().map(|color| color.trim()).unwrap_or("");
+
+        self.visible = !full_text.is_empty();
+        // Without a short text, the bar falls back to the text itself
+        self.output.set_short_text(if short_text.is_empty() {
+            full_text.clone()
+        } else {
+            short_text
+        });
+        self.output.set_text(full_text);
+        self.output.set_color(if color.is_empty() {
+            None
+        } else {
+            Some(color.to_owned())
+        });
+        self.output.set_urgent(code == Some(URGENT_EXIT_CODE));
+
+        Ok(if self.once { None } else { Some(self.interval) })
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        // i3blocks hides blocks that print nothing
+        if self.visible {
+            vec![&self.output]
+        } else {
+            vec![]
+        }
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if !event.matches_name(&self.id) {
+            return Ok(());
+        }
+        // The script takes care of the click on its next run
+        self.click = Some(event.clone());
+        self.tx_update_request.send(Task {
+            id: self.id.clone(),
+            update_time: Instant::now(),
+        });
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
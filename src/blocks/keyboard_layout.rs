@@ -0,0 +1,320 @@
+//! A block for the keyboard layout, which can also remember the layout of
+//! each window.
+//!
+//! Layouts are global, so they follow the focus from one window to the next.
+//! With `remember`, the block keeps track of the layout used in each window,
+//! or each application, and switches back to it when it's focused again. The
+//! focus is followed through the IPC of i3 or sway.
+
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use serde_json;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Value};
+use i3_events;
+use input::{I3BarEvent, MouseButton};
+use scheduler::Task;
+use widget::I3BarWidget;
+use widgets::button::ButtonWidget;
+
+extern crate i3ipc;
+use self::i3ipc::event::inner::WindowChange;
+use self::i3ipc::event::Event;
+use self::i3ipc::reply::Node;
+use self::i3ipc::Subscription;
+
+/// How the layout is read and switched.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// The keyboards of sway, over its IPC
+    Sway,
+    /// `xkb-switch`, on X11
+    XkbSwitch,
+}
+
+/// What layouts are remembered for.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Remember {
+    Window,
+    /// Windows by their class. Native Wayland windows have none, they're
+    /// remembered one by one.
+    App,
+}
+
+impl Remember {
+    fn key(self, container: &Node) -> String {
+        let class = match self {
+            Remember::App => container.window.and_then(window_class),
+            Remember::Window => None,
+        };
+        match (self, class) {
+            (Remember::App, Some(class)) => format!("class:{}", class),
+            _ => format!("window:{}", container.id),
+        }
+    }
+}
+
+/// The class of an X11 window, which IPC doesn't tell, from `xprop`.
+fn window_class(window: i32) -> Option<String> {
+    // Like `WM_CLASS(STRING) = "navigator", "Firefox"`, instance then class
+    let output = run("xprop", &["-id", &window.to_string(), "WM_CLASS"]).ok()?;
+    match output.rsplit('"').nth(1) {
+        Some(class) if !class.is_empty() => Some(class.to_owned()),
+        _ => None,
+    }
+}
+
+/// Focus changes seen by the event handler, for the next update.
+#[derive(Default)]
+struct Focus {
+    /// Key of the focused window
+    focused: Option<String>,
+    /// Keys of windows that were closed
+    closed: Vec<String>,
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .block_error("keyboard_layout", &format!("failed to run {}", program))?;
+    if !output.status.success() {
+        return Err(BlockError(
+            "keyboard_layout".to_owned(),
+            format!("{} {} failed", program, args.join(" ")),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+impl Backend {
+    /// The current layout, along with all layouts to switch between.
+    fn layouts(self) -> Result<(String, Vec<String>)> {
+        match self {
+            Backend::Sway => {
+                let inputs: serde_json::Value = serde_json::from_str(&run("swaymsg", &["-t", "get_inputs", "-r"])?)
+                    .block_error("keyboard_layout", "invalid inputs from sway")?;
+                let keyboard = inputs
+                    .as_array()
+                    .and_then(|inputs| {
+                        inputs
+                            .iter()
+                            .find(|input| input["type"] == "keyboard" && input["xkb_active_layout_name"].is_string())
+                    })
+                    .block_error("keyboard_layout", "no keyboard found")?;
+                let names = keyboard["xkb_layout_names"]
+                    .as_array()
+                    .map(|names| names.iter().filter_map(|name| name.as_str()).map(|name| name.to_owned()).collect())
+                    .unwrap_or_else(Vec::new);
+                let current = keyboard["xkb_active_layout_name"].as_str().unwrap_or("").to_owned();
+                Ok((current, names))
+            }
+            Backend::XkbSwitch => {
+                let names = run("xkb-switch", &["-l"])?.lines().map(|name| name.to_owned()).collect();
+                Ok((run("xkb-switch", &["-p"])?, names))
+            }
+        }
+    }
+
+    fn switch_to(self, layout: &str, names: &[String]) -> Result<()> {
+        match self {
+            Backend::Sway => match names.iter().position(|name| name == layout) {
+                Some(index) => run(
+                    "swaymsg",
+                    &["input", "type:keyboard", "xkb_switch_layout", &index.to_string()],
+                ).map(|_| ()),
+                None => Ok(()),
+            },
+            Backend::XkbSwitch => run("xkb-switch", &["-s", layout]).map(|_| ()),
+        }
+    }
+
+    fn switch_next(self) -> Result<()> {
+        match self {
+            Backend::Sway => run("swaymsg", &["input", "type:keyboard", "xkb_switch_layout", "next"]),
+            Backend::XkbSwitch => run("xkb-switch", &["-n"]),
+        }.map(|_| ())
+    }
+}
+
+/// Shows the keyboard layout and switches to the next one on a click, see
+/// `KeyboardLayoutConfig`.
+pub struct KeyboardLayout {
+    id: String,
+    output: ButtonWidget,
+    format: FormatTemplate,
+    backend: Backend,
+    interval: Duration,
+    /// Shared with the event handler, if layouts are remembered
+    focus: Option<Arc<Mutex<Focus>>>,
+    /// The window focused as of the last update
+    focused: Option<String>,
+    /// The layout last used in each window
+    layouts: HashMap<String, String>,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct KeyboardLayoutConfig {
+    /// Update interval in seconds, for switches from elsewhere to show
+    #[serde(default = "KeyboardLayoutConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{layout}` is available
+    #[serde(default = "KeyboardLayoutConfig::default_format")]
+    pub format: String,
+
+    /// How to read and switch the layout, `sway` or `xkbswitch`
+    #[serde(default = "KeyboardLayoutConfig::default_backend")]
+    pub backend: Backend,
+
+    /// Remember the layout of each `window` or `app`, and switch back to it
+    #[serde(default = "KeyboardLayoutConfig::default_remember")]
+    pub remember: Option<Remember>,
+}
+
+impl KeyboardLayoutConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn default_format() -> String {
+        "{layout}".to_owned()
+    }
+
+    fn default_backend() -> Backend {
+        Backend::Sway
+    }
+
+    fn default_remember() -> Option<Remember> {
+        None
+    }
+}
+
+/// Follow the focus, and tell the block about every change.
+fn handle_event(event: &Event, remember: Remember, focus: &Mutex<Focus>, id: &str, tx: &Sender<Task>) {
+    let event = match *event {
+        Event::WindowEvent(ref event) => event,
+        _ => return,
+    };
+    let key = remember.key(&event.container);
+    {
+        let mut focus = match focus.lock() {
+            Ok(focus) => focus,
+            Err(_) => return,
+        };
+        match event.change {
+            WindowChange::Focus => focus.focused = Some(key),
+            // Apps are remembered as long as the bar runs
+            WindowChange::Close if remember == Remember::Window => focus.closed.push(key),
+            _ => return,
+        }
+    }
+    tx.send(Task {
+        id: id.to_owned(),
+        update_time: Instant::now(),
+    });
+}
+
+impl ConfigBlock for KeyboardLayout {
+    type Config = KeyboardLayoutConfig;
+
+    fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        let focus = match block_config.remember {
+            Some(remember) => {
+                let focus = Arc::new(Mutex::new(Focus::default()));
+                let (shared, block_id, tx) = (focus.clone(), id.clone(), send.clone());
+                let handler = Box::new(move |event: &Event| handle_event(event, remember, &shared, &block_id, &tx));
+                if let Err(error) = i3_events::subscribe(Subscription::Window, &id, handler) {
+                    error!("can't remember keyboard layouts: {:?}", error);
+                }
+                Some(focus)
+            }
+            None => None,
+        };
+
+        Ok(KeyboardLayout {
+            output: ButtonWidget::new(config, &id).with_icon("keyboard_layout"),
+            id,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("keyboard_layout", "Invalid format specified for keyboard_layout")?,
+            backend: block_config.backend,
+            interval: block_config.interval,
+            focus,
+            focused: None,
+            layouts: HashMap::new(),
+            tx_update_request: send,
+        })
+    }
+}
+
+impl Block for KeyboardLayout {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let (mut current, names) = self.backend.layouts()?;
+
+        let changes = match self.focus {
+            Some(ref focus) => {
+                let mut focus = focus.lock().internal_error("keyboard_layout", "failed to lock focus")?;
+                Some((focus.focused.clone(), focus.closed.split_off(0)))
+            }
+            None => None,
+        };
+        if let Some((focused, closed)) = changes {
+            if focused != self.focused {
+                // The layout is still the one used in the window left, as
+                // layouts follow the focus
+                if let Some(ref left) = self.focused {
+                    self.layouts.insert(left.clone(), current.clone());
+                }
+                if let Some(layout) = focused.as_ref().and_then(|focused| self.layouts.get(focused)) {
+                    if *layout != current {
+                        self.backend.switch_to(layout, &names)?;
+                        current = layout.clone();
+                    }
+                }
+                self.focused = focused;
+            }
+            for key in closed {
+                self.layouts.remove(&key);
+            }
+        }
+
+        let mut values = HashMap::new();
+        values.insert("{layout}", Value::Text(current));
+        self.output.set_text(self.format.render_values(&values)?);
+
+        Ok(Some(self.interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.output]
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if event.name.as_ref().map_or(false, |name| name == &self.id) && event.button == MouseButton::Left {
+            self.backend.switch_next()?;
+            self.tx_update_request.send(Task {
+                id: self.id.clone(),
+                update_time: Instant::now(),
+            });
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -6,7 +6,7 @@ use de::deserialize_duration;
 use errors::*;
 use widgets::text::TextWidget;
 use widget::{I3BarWidget, State};
-use util::FormatTemplate;
+use formatting::FormatTemplate;
 use chan::Sender;
 use scheduler::Task;
 
@@ -20,8 +20,11 @@ pub struct Load {
     text: TextWidget,
     logical_cores: u32,
     format: FormatTemplate,
+    short_format: Option<FormatTemplate>,
     id: String,
     update_interval: Duration,
+    /// What the last update measured, see `Block::metrics`
+    metrics: Vec<(&'static str, f64)>,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -29,6 +32,8 @@ pub struct Load {
 pub struct LoadConfig {
     #[serde(default = "LoadConfig::default_format")]
     pub format: String,
+    #[serde(default = "LoadConfig::default_short_format")]
+    pub short_format: Option<String>,
     #[serde(default = "LoadConfig::default_interval", deserialize_with = "deserialize_duration")]
     pub interval: Duration,
 }
@@ -38,6 +43,10 @@ impl LoadConfig {
         "{1m}".to_owned()
     }
 
+    fn default_short_format() -> Option<String> {
+        None
+    }
+
     fn default_interval() -> Duration {
         Duration::from_secs(5)
     }
@@ -74,7 +83,13 @@ impl ConfigBlock for Load {
             update_interval: block_config.interval,
             format: FormatTemplate::from_string(&block_config.format)
                 .block_error("load", "Invalid format specified for load")?,
+            short_format: match block_config.short_format {
+                Some(format) => Some(FormatTemplate::from_string(&format)
+                    .block_error("load", "Invalid short_format specified for load")?),
+                None => None,
+            },
             text,
+            metrics: Vec::new(),
         })
     }
 }
@@ -110,7 +125,15 @@ impl Block for Load {
         );
 
         self.text.set_text(self.format.render_static_str(&values)?);
+        if let Some(ref short_format) = self.short_format {
+            self.text.set_short_text(short_format.render_static_str(&values)?);
+        }
 
+        self.metrics = ["load1", "load5", "load15"]
+            .iter()
+            .zip(&split)
+            .filter_map(|(&name, value)| value.parse().ok().map(|value| (name, value)))
+            .collect();
         Ok(Some(self.update_interval))
     }
 
@@ -118,6 +141,10 @@ impl Block for Load {
         vec![&self.text]
     }
 
+    fn metrics(&self) -> Vec<(&'static str, f64)> {
+        self.metrics.clone()
+    }
+
     fn id(&self) -> &str {
         &self.id
     }
@@ -0,0 +1,174 @@
+//! A block for the login session, as systemd-logind knows it: its type and
+//! seat, and the other sessions on the machine.
+//!
+//! Sessions of other users are a warning, as someone else being logged in,
+//! say over SSH, may well be a surprise. Sessions come and go with signals
+//! from logind, so the block shows them right away.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chan::Sender;
+use nix::libc;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use blocks::dbus::{BusType, Connection, Message, Path};
+use blocks::dbus::arg::{RefArg, Variant};
+use config::Config;
+use dbus_manager;
+use errors::*;
+use formatting::{FormatTemplate, Unit, Value};
+use scheduler::Task;
+use widget::{I3BarWidget, State};
+use widgets::text::TextWidget;
+
+const LOGIND_NAME: &str = "org.freedesktop.login1";
+const MANAGER_PATH: &str = "/org/freedesktop/login1";
+const SESSION_PATH: &str = "/org/freedesktop/login1/session/auto";
+
+/// Id, user, user name, seat and path of a session
+type SessionEntry<'a> = (String, u32, String, String, Path<'a>);
+
+/// A property of the session at `path`.
+fn property(c: &Connection, path: &str, name: &str) -> Result<Variant<Box<RefArg>>> {
+    let m = Message::new_method_call(LOGIND_NAME, path, "org.freedesktop.DBus.Properties", "Get")
+        .block_error("login", "failed to create D-Bus message")?
+        .append2("org.freedesktop.login1.Session", name);
+    let r = c.send_with_reply_and_block(m, 1000)
+        .block_error("login", "failed to get the session from logind")?;
+    r.get1().block_error("login", &format!("invalid {} from logind", name))
+}
+
+/// A string property of the session at `path`.
+fn string_property(c: &Connection, path: &str, name: &str) -> Result<String> {
+    let value = property(c, path, name)?;
+    value
+        .0
+        .as_str()
+        .map(|value| value.to_owned())
+        .block_error("login", &format!("invalid {} from logind", name))
+}
+
+/// How a session type is usually written.
+fn pretty_type(session_type: &str) -> String {
+    match session_type {
+        "wayland" => "Wayland".to_owned(),
+        "x11" => "X11".to_owned(),
+        "tty" => "TTY".to_owned(),
+        other => other.to_owned(),
+    }
+}
+
+/// Shows the type and seat of the session and the other sessions, see
+/// `LoginConfig`.
+pub struct Login {
+    id: String,
+    text: TextWidget,
+    format: FormatTemplate,
+    dbus_conn: Connection,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LoginConfig {
+    /// Format override, `{type}`, `{seat}`, `{others}` and `{users}` are
+    /// available
+    #[serde(default = "LoginConfig::default_format")]
+    pub format: String,
+}
+
+impl LoginConfig {
+    fn default_format() -> String {
+        "{type} {seat} {others}".to_owned()
+    }
+}
+
+impl ConfigBlock for Login {
+    type Config = LoginConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        let dbus_conn = Connection::get_private(BusType::System)
+            .block_error("login", "failed to connect to the system bus")?;
+        // SessionNew and SessionRemoved
+        dbus_manager::subscribe(
+            BusType::System,
+            &format!("type='signal',interface='org.freedesktop.login1.Manager',path='{}'", MANAGER_PATH),
+            &id,
+            &tx_update_request,
+        )?;
+
+        Ok(Login {
+            id,
+            text: TextWidget::new(config).with_icon("login"),
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("login", "Invalid format specified for login")?,
+            dbus_conn,
+        })
+    }
+}
+
+impl Block for Login {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let c = &self.dbus_conn;
+        let own = string_property(c, SESSION_PATH, "Id")?;
+        let session_type = string_property(c, SESSION_PATH, "Type")?;
+        // The seat id and path, both empty for sessions without a seat, like
+        // those over SSH
+        let seat = property(c, SESSION_PATH, "Seat")?
+            .0
+            .as_iter()
+            .and_then(|mut seat| seat.next().and_then(|id| id.as_str().map(|id| id.to_owned())))
+            .unwrap_or_default();
+
+        let m = Message::new_method_call(LOGIND_NAME, MANAGER_PATH, "org.freedesktop.login1.Manager", "ListSessions")
+            .block_error("login", "failed to create D-Bus message")?;
+        let r = c.send_with_reply_and_block(m, 1000)
+            .block_error("login", "failed to list the sessions from logind")?;
+        let sessions: Vec<SessionEntry> = r.get1().block_error("login", "invalid sessions from logind")?;
+
+        let uid = unsafe { libc::getuid() };
+        let mut others = 0;
+        let mut users: Vec<String> = Vec::new();
+        for &(ref id, user, ref name, _, ref path) in &sessions {
+            if id == &own {
+                continue;
+            }
+            // Left out are sessions on the way out, and those of the display
+            // manager or of services like `systemd --user`
+            let state = string_property(c, path, "State").unwrap_or_default();
+            let class = string_property(c, path, "Class").unwrap_or_default();
+            if state == "closing" || class != "user" {
+                continue;
+            }
+            others += 1;
+            if user != uid && !users.contains(name) {
+                users.push(name.clone());
+            }
+        }
+
+        let mut values = HashMap::new();
+        values.insert("{type}", Value::Text(pretty_type(&session_type)));
+        values.insert("{seat}", Value::Text(seat));
+        values.insert("{others}", Value::Number(others as f64, Unit::None));
+        values.insert("{users}", Value::Text(users.join(",")));
+        self.text.set_text(self.format.render_values(&values)?);
+        self.text.set_state(if users.is_empty() {
+            State::Idle
+        } else {
+            State::Warning
+        });
+
+        // Updated by logind's signals
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -6,6 +6,10 @@
 //! accordingly). That behaviour can be changed within config.json.
 //!
 //! This module keeps track of both Swap and Memory. By default, a click switches between them.
+//! A right click shows the process with the largest resident set for a few seconds.
+//!
+//! Besides the usage, the block turns warning or critical on memory pressure, when tasks have to
+//! wait for memory to be reclaimed or swapped in, as the kernel tells since Linux 4.20.
 //!
 //!
 //! **Example**
@@ -31,6 +35,9 @@
 //! warning_swap | Percentage of swap usage, where state is set to warning | No | 80.0
 //! critical_mem | Percentage of memory usage, where state is set to critical | No | 95.0
 //! critical_swap | Percentage of swap usage, where state is set to critical | No | 95.0
+//! warning_pressure | Percentage of time some tasks waited for memory, where state is set to warning | No | 20.0
+//! critical_pressure | Percentage of time all tasks waited for memory, where state is set to critical | No | 10.0
+//! top_duration | How long, in seconds, the largest process is shown after a right click | No | 5
 //!
 //! ### Format string specification
 //!
@@ -70,11 +77,14 @@
 //! {SUm}  | Swap used (MiB)
 //! {SUp}  | Swap used (%)
 //! {SUpi} | Swap used (%) as integer
+//! {PSp}  | Share of time some tasks waited for memory over the last 10 seconds (%)
+//! {PFp}  | Share of time all tasks waited for memory over the last 10 seconds (%)
 
 //!
 use std::time::{Duration, Instant};
 use std::collections::HashMap;
 use util::*;
+use formatting::FormatTemplate;
 use chan::Sender;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -87,6 +97,8 @@ use std::fmt;
 use config::Config;
 use de::deserialize_duration;
 use errors::*;
+use pressure;
+use processes;
 use widgets::button::ButtonWidget;
 use widget::{I3BarWidget, State};
 use scheduler::Task;
@@ -235,6 +247,11 @@ pub struct Memory {
     values: HashMap<String, String>,
     warning: (f64, f64),
     critical: (f64, f64),
+    warning_pressure: f64,
+    critical_pressure: f64,
+    top_duration: Duration,
+    /// The largest process, its size in KiB and until when it's shown
+    top: Option<(String, u64, Instant)>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -278,6 +295,18 @@ pub struct MemoryConfig {
     /// Percentage of swap usage, where state is set to critical
     #[serde(default = "MemoryConfig::default_critical_swap")]
     pub critical_swap: f64,
+
+    /// Percentage of time some tasks waited for memory, where state is set to warning
+    #[serde(default = "MemoryConfig::default_warning_pressure")]
+    pub warning_pressure: f64,
+
+    /// Percentage of time all tasks waited for memory, where state is set to critical
+    #[serde(default = "MemoryConfig::default_critical_pressure")]
+    pub critical_pressure: f64,
+
+    /// How long, in seconds, the largest process is shown after a right click
+    #[serde(default = "MemoryConfig::default_top_duration", deserialize_with = "deserialize_duration")]
+    pub top_duration: Duration,
 }
 
 impl MemoryConfig {
@@ -320,6 +349,18 @@ impl MemoryConfig {
     fn default_critical_swap() -> f64 {
         95.0
     }
+
+    fn default_warning_pressure() -> f64 {
+        20.0
+    }
+
+    fn default_critical_pressure() -> f64 {
+        10.0
+    }
+
+    fn default_top_duration() -> Duration {
+        Duration::from_secs(5)
+    }
 }
 
 impl Memory {
@@ -442,19 +483,30 @@ impl Memory {
             format!("{:02}", cached.percent(mem_total) as i32),
         );
 
+        // Kernels without pressure stall information never turn the block
+        // warning or critical by pressure
+        let pressure = pressure::system("memory", "memory").ok();
+        let (some, full) = match pressure {
+            Some(pressure) => (pressure.some.avg10, pressure.full.map_or(0.0, |full| full.avg10)),
+            None => (0.0, 0.0),
+        };
+        self.values.insert("{PSp}".to_string(), format!("{:.2}", some));
+        self.values.insert("{PFp}".to_string(), format!("{:.2}", full));
+
+        let (used, warning, critical) = match self.memtype {
+            Memtype::Memory => (mem_used.percent(mem_total), self.warning.0, self.critical.0),
+            Memtype::Swap => (swap_used.percent(swap_total), self.warning.1, self.critical.1),
+        };
+        // Stalling on memory means swapping or the OOM killer are close,
+        // however much of it is in use
+        let state = match f64::from(used) {
+            x if x > critical || full > self.critical_pressure => State::Critical,
+            x if x > warning || some > self.warning_pressure => State::Warning,
+            _ => State::Idle,
+        };
         match self.memtype {
-            Memtype::Memory => self.output.0.set_state(match mem_used.percent(mem_total) {
-                x if f64::from(x) > self.critical.0 => State::Critical,
-                x if f64::from(x) > self.warning.0 => State::Warning,
-                _ => State::Idle,
-            }),
-            Memtype::Swap => self.output.1.set_state(
-                match swap_used.percent(swap_total) {
-                    x if f64::from(x)  > self.critical.1 => State::Critical,
-                    x if f64::from(x) > self.warning.1 => State::Warning,
-                    _ => State::Idle,
-                },
-            ),
+            Memtype::Memory => self.output.0.set_state(state),
+            Memtype::Swap => self.output.1.set_state(state),
         };
 
         if_debug!({
@@ -509,6 +561,10 @@ impl ConfigBlock for Memory {
             values: HashMap::<String, String>::new(),
             warning: (block_config.warning_mem, block_config.warning_swap),
             critical: (block_config.critical_mem, block_config.critical_swap),
+            warning_pressure: block_config.warning_pressure,
+            critical_pressure: block_config.critical_pressure,
+            top_duration: block_config.top_duration,
+            top: None,
         })
     }
 }
@@ -620,7 +676,16 @@ impl Block for Memory {
         }
 
         // Now, create the string to be shown
-        let output_text = self.format_insert_values(mem_state)?;
+        let mut output_text = self.format_insert_values(mem_state)?;
+        let now = Instant::now();
+        let top = match self.top {
+            Some((ref name, size, until)) if until > now => Some(format!("{} {}MB", name, Unit::KiB(size).mib())),
+            _ => None,
+        };
+        match top {
+            Some(top) => output_text = top,
+            None => self.top = None,
+        }
 
         match self.memtype {
             Memtype::Memory => self.output.0.set_text(output_text),
@@ -636,7 +701,11 @@ impl Block for Memory {
             writeln!(f, "Updated: {:?}", self)
                 .block_error("memory", "failed to write to /tmp/i3log")?;
         });
-        Ok(Some(self.update_interval))
+        // Back to the usage in time
+        Ok(Some(match self.top {
+            Some((_, _, until)) => self.update_interval.min(until - now),
+            None => self.update_interval,
+        }))
     }
 
     fn click(&mut self, event: &I3BarEvent) -> Result<()> {
@@ -658,6 +727,13 @@ impl Block for Memory {
                     id: self.id.clone(),
                     update_time: Instant::now(),
                 });
+            } else if event.button == MouseButton::Right && *s == "memory" {
+                self.top = processes::top_rss()
+                    .map(|(name, size)| (name, size / 1024, Instant::now() + self.top_duration));
+                self.tx_update_request.send(Task {
+                    id: self.id.clone(),
+                    update_time: Instant::now(),
+                });
             }
         }
 
@@ -21,6 +21,43 @@ mod uptime;
 pub mod nvidia_gpu;
 pub mod maildir;
 mod networkmanager;
+mod cups;
+mod gamemode;
+mod wine;
+mod node_sync;
+mod weechat;
+mod dbus_property;
+mod sysfs;
+mod group;
+mod pager;
+mod separator;
+mod spacer;
+mod i3blocks;
+mod persistent;
+mod removable;
+mod peripherals;
+mod ambient_light;
+mod rotation;
+mod display_profile;
+mod dnd;
+mod break_reminder;
+mod screen_time;
+mod keyboard_layout;
+mod displays;
+mod presence;
+mod psi;
+mod cgroup;
+mod thrashing;
+mod trash;
+mod downloads;
+mod git;
+mod remote;
+mod night_light;
+mod session;
+mod login;
+mod hostname;
+mod timer;
+mod watchdog;
 
 use config::Config;
 use self::time::*;
@@ -47,10 +84,48 @@ use self::uptime::*;
 use self::nvidia_gpu::*;
 use self::maildir::*;
 use self::networkmanager::*;
+use self::cups::*;
+use self::gamemode::*;
+use self::wine::*;
+use self::node_sync::*;
+use self::weechat::*;
+use self::dbus_property::*;
+use self::sysfs::*;
+use self::group::*;
+use self::pager::*;
+use self::separator::*;
+use self::spacer::*;
+use self::i3blocks::*;
+use self::persistent::*;
+use self::removable::*;
+use self::peripherals::*;
+use self::ambient_light::*;
+use self::rotation::*;
+use self::display_profile::*;
+use self::dnd::*;
+use self::break_reminder::*;
+use self::screen_time::*;
+use self::keyboard_layout::*;
+use self::displays::*;
+use self::presence::*;
+use self::psi::*;
+use self::cgroup::*;
+use self::thrashing::*;
+use self::trash::*;
+use self::downloads::*;
+use self::git::*;
+use self::remote::*;
+use self::night_light::*;
+use self::session::*;
+use self::login::*;
+use self::hostname::*;
+use self::timer::*;
+use self::watchdog::*;
 
 use super::block::{Block, ConfigBlock};
 use errors::*;
 use super::scheduler::Task;
+use state::BlockState;
 
 extern crate dbus;
 
@@ -59,49 +134,108 @@ use chan::Sender;
 use toml::value::Value;
 
 macro_rules! block {
-    ($block_type:ident, $block_config:expr, $config:expr, $tx_update_request:expr) => {{
+    ($block_type:ident, ($block_config:expr, $config:expr, $tx_update_request:expr, $state:expr)) => {{
         let block_config: <$block_type as ConfigBlock>::Config = <$block_type as ConfigBlock>::Config::deserialize($block_config)
             .configuration_error("failed to deserialize block config")?;
-        Ok(Box::new($block_type::new(block_config, $config, $tx_update_request)?) as Box<Block>)
+        Ok(Box::new($block_type::new_with_state(block_config, $config, $tx_update_request, $state)?) as Box<Block>)
     }}
 }
 
+macro_rules! check {
+    ($block_type:ident, ($block_config:expr)) => {
+        <$block_type as ConfigBlock>::Config::deserialize($block_config)
+            .map(|_| ())
+            .configuration_error("failed to deserialize block config")
+    }
+}
+
+/// Match `name` against all block types and invoke `$make` with the type and
+/// `$args` for the one found.
 macro_rules! blocks {
-    ( $name:ident, $block_config:ident, $config:ident, $tx_update_request:ident ; $( $block_name:expr => $block_type:ident ),+ ) => {
+    ( $make:ident, $name:ident, $args:tt ; $( $block_name:expr => $block_type:ident ),+ ) => {
         match $name {
             $(
-                $block_name => block!($block_type, $block_config, $config, $tx_update_request),
+                $block_name => $make!($block_type, $args),
              )*
             _ => Err(BlockError($name.to_string(), "Unknown block!".to_string())),
         }
     }
 }
 
-pub fn create_block(name: &str, block_config: Value, config: Config, tx_update_request: Sender<Task>) -> Result<Box<Block>> {
-    blocks!(name, block_config, config, tx_update_request;
-            "time" => Time,
-            "template" => Template,
-            "music" => Music,
-            "static_music" => StaticMusic,
-            "load" => Load,
-            "memory" => Memory,
-            "cpu" => Cpu,
-            "pacman" => Pacman,
-            "battery" => Battery,
-            "custom" => Custom,
-            "disk_space" => DiskSpace,
-            "toggle" => Toggle,
-            "sound" => Sound,
-            "speedtest" => SpeedTest,
-            "temperature" => Temperature,
-            "focused_window" => FocusedWindow,
-            "xrandr" => Xrandr,
-            "net" => Net,
-            "backlight" => Backlight,
-            "weather" => Weather,
-            "uptime" => Uptime,
-            "nvidia_gpu" => NvidiaGpu,
-            "maildir" => Maildir,
-            "networkmanager" => NetworkManager
-    )
+/// `blocks!` for all block types.
+macro_rules! all_blocks {
+    ( $make:ident, $name:ident, $args:tt ) => {
+        blocks!($make, $name, $args;
+                "time" => Time,
+                "template" => Template,
+                "music" => Music,
+                "static_music" => StaticMusic,
+                "load" => Load,
+                "memory" => Memory,
+                "cpu" => Cpu,
+                "pacman" => Pacman,
+                "battery" => Battery,
+                "custom" => Custom,
+                "disk_space" => DiskSpace,
+                "toggle" => Toggle,
+                "sound" => Sound,
+                "speedtest" => SpeedTest,
+                "temperature" => Temperature,
+                "focused_window" => FocusedWindow,
+                "xrandr" => Xrandr,
+                "net" => Net,
+                "backlight" => Backlight,
+                "weather" => Weather,
+                "uptime" => Uptime,
+                "nvidia_gpu" => NvidiaGpu,
+                "maildir" => Maildir,
+                "networkmanager" => NetworkManager,
+                "cups" => Cups,
+                "gamemode" => Gamemode,
+                "wine" => Wine,
+                "node_sync" => NodeSync,
+                "weechat" => Weechat,
+                "dbus_property" => DbusProperty,
+                "sysfs" => Sysfs,
+                "group" => Group,
+                "pager" => Pager,
+                "separator" => Separator,
+                "spacer" => Spacer,
+                "i3blocks" => I3blocks,
+                "persistent" => Persistent,
+                "removable" => Removable,
+                "peripherals" => Peripherals,
+                "ambient_light" => AmbientLight,
+                "rotation" => Rotation,
+                "display_profile" => DisplayProfile,
+                "dnd" => Dnd,
+                "break_reminder" => BreakReminder,
+                "screen_time" => ScreenTime,
+                "keyboard_layout" => KeyboardLayout,
+                "displays" => Displays,
+                "presence" => Presence,
+                "psi" => Psi,
+                "cgroup" => Cgroup,
+                "thrashing" => Thrashing,
+                "trash" => Trash,
+                "downloads" => Downloads,
+                "git" => Git,
+                "remote" => Remote,
+                "night_light" => NightLight,
+                "session" => Session,
+                "login" => Login,
+                "hostname" => Hostname,
+                "timer" => Timer,
+                "watchdog" => Watchdog
+        )
+    }
+}
+
+pub fn create_block(name: &str, block_config: Value, config: Config, tx_update_request: Sender<Task>, state: BlockState) -> Result<Box<Block>> {
+    all_blocks!(block, name, (block_config, config, tx_update_request, state))
+}
+
+/// Check the options of a block of type `name`, without creating it.
+pub fn check_block(name: &str, block_config: Value) -> Result<()> {
+    all_blocks!(check, name, (block_config))
 }
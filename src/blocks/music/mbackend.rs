@@ -1,35 +1,28 @@
-use std::time::Instant;
 use chan::Sender;
-use std::thread;
 use std::boxed::Box;
 use std::result;
 
+use dbus_manager;
 use errors::*;
 use scheduler::Task;
 
-use blocks::dbus::{Error, arg, stdintf, BusType, Connection, ConnPath, ConnectionItem, Message};
+use blocks::dbus::{Error, arg, stdintf, BusType, Connection, ConnPath, Message};
 use self::stdintf::OrgFreedesktopDBusProperties;
 
-/// Spawn a thread to alert on changes to the player state
-pub fn spawn_listener(id: String, send: Sender<Task>) {
-    thread::spawn(move || {
-        let c = Connection::get_private(BusType::Session).unwrap();
-        c.add_match(
-            "interface='org.freedesktop.DBus.Properties',member='PropertiesChanged'",
-        ).unwrap();
-        loop {
-            for ci in c.iter(100_000) {
-                if let ConnectionItem::Signal(msg) = ci {
-                    if &*msg.path().unwrap() == "/org/mpris/MediaPlayer2" && &*msg.member().unwrap() == "PropertiesChanged" {
-                        send.send(Task {
-                            id: id.clone(),
-                            update_time: Instant::now(),
-                        });
-                    }
-                }
-            }
-        }
-    });
+/// Bus names of players start with this, followed by the player's name
+const PLAYER_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+/// Request an update whenever the player state changes
+pub fn subscribe(id: &str, send: &Sender<Task>) -> Result<()> {
+    dbus_manager::subscribe(
+        BusType::Session,
+        "type='signal',\
+         path='/org/mpris/MediaPlayer2',\
+         interface='org.freedesktop.DBus.Properties',\
+         member='PropertiesChanged'",
+        id,
+        send,
+    )
 }
 
 /// Establish a connection to the D-Bus
@@ -41,12 +34,42 @@ pub fn dbus_connection() -> Result<Connection> {
 /// Obtain a connection to the media player interface
 pub fn player_connection<'c>(dbus_conn: &'c Connection, player: &str) -> ConnPath<'c, &'c Connection> {
     dbus_conn.with_path(
-        format!("org.mpris.MediaPlayer2.{}", player),
+        format!("{}{}", PLAYER_PREFIX, player),
         "/org/mpris/MediaPlayer2",
         1000,
     )
 }
 
+/// The player to drive when none is configured: the first one playing, or
+/// else the first one on the bus, if any
+pub fn active_player(dbus_conn: &Connection) -> Result<Option<String>> {
+    let m = Message::new_method_call(
+        "org.freedesktop.DBus",
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus",
+        "ListNames",
+    ).block_error("music", "failed to create D-Bus method call")?;
+    let r = dbus_conn
+        .send_with_reply_and_block(m, 1000)
+        .block_error("music", "failed to list the names on the D-Bus")?;
+    let names: Vec<String> = r.get1().block_error("music", "invalid names on the D-Bus")?;
+
+    // Sorted, so the first one doesn't change from one update to the next
+    let mut players: Vec<String> = names
+        .into_iter()
+        .filter(|name| name.starts_with(PLAYER_PREFIX))
+        .map(|name| name[PLAYER_PREFIX.len()..].to_owned())
+        .collect();
+    players.sort();
+    let playing = players.iter().find(|player| {
+        playback_data(&player_connection(dbus_conn, player))
+            .ok()
+            .and_then(|data| data.0.as_str().map(|status| status == "Playing"))
+            .unwrap_or(false)
+    });
+    Ok(playing.or_else(|| players.first()).cloned())
+}
+
 /// Type alias for data returned by ConnPath.get(....)
 pub type PlayerData = arg::Variant<Box<arg::RefArg>>;
 
@@ -108,7 +131,7 @@ pub fn extract_from_metadata(metadata: &PlayerData) -> Result<(String, String)>
 fn music_action(player: &str, dbus_conn: &mut Connection, action: &str) -> Result<()> {
     if action != "" {
         let m = Message::new_method_call(
-            format!("org.mpris.MediaPlayer2.{}", player),
+            format!("{}{}", PLAYER_PREFIX, player),
             "/org/mpris/MediaPlayer2",
             "org.mpris.MediaPlayer2.Player",
             action,
@@ -26,14 +26,19 @@ pub struct Music {
     dbus_conn: Connection,
     player_avail: bool,
     marquee: bool,
-    player: String,
+    /// The player configured, if any
+    player: Option<String>,
+    /// The player driven, the configured one or else the one found active
+    active: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct MusicConfig {
     /// Name of the music player.Must be the same name the player<br/> is registered with the MediaPlayer2 Interface.
-    pub player: String,
+    /// If unset, the one playing is found, or else the first one there.
+    #[serde(default = "MusicConfig::default_player")]
+    pub player: Option<String>,
 
     /// Max width of the block in characters, not including the buttons
     #[serde(default = "MusicConfig::default_max_width")]
@@ -57,6 +62,10 @@ pub struct MusicConfig {
 }
 
 impl MusicConfig {
+    fn default_player() -> Option<String> {
+        None
+    }
+
     fn default_max_width() -> usize {
         21
     }
@@ -83,8 +92,7 @@ impl ConfigBlock for Music {
 
     fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
         let id: String = Uuid::new_v4().simple().to_string();
-        let listener_id = id.clone();
-        mbackend::spawn_listener(listener_id, send);
+        mbackend::subscribe(&id, &send)?;
         
         let (play, prev, next) = utils::create_buttons(&block_config.buttons, &config)?;
         
@@ -102,6 +110,7 @@ impl ConfigBlock for Music {
             next,
             dbus_conn: mbackend::dbus_connection()?,
             player_avail: false,
+            active: block_config.player.clone(),
             player: block_config.player,
             marquee: block_config.marquee,
         })
@@ -121,10 +130,15 @@ impl Block for Music {
         };
 
         if !rotated {
-            let player_conn = mbackend::player_connection(&self.dbus_conn, &self.player);
+            // Another player may have started or stopped playing
+            if self.player.is_none() {
+                self.active = mbackend::active_player(&self.dbus_conn)?;
+            }
+            let active = self.active.clone().unwrap_or_default();
+            let player_conn = mbackend::player_connection(&self.dbus_conn, &active);
             let data = mbackend::music_data(&player_conn);
 
-            if data.is_err() {
+            if self.active.is_none() || data.is_err() {
                 self.current_song.set_text(String::from(""));
                 self.player_avail = false;
             } else {
@@ -154,11 +168,19 @@ impl Block for Music {
     }
 
     fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        // The buttons are named the same in every music block
+        if event.widget_index(&self.id).is_none() {
+            return Ok(());
+        }
+        let player = match self.active {
+            Some(ref player) => player,
+            None => return Ok(()),
+        };
         if let Some(ref name) = event.name {
             match name as &str {
-                "play" => mbackend::music_play(&self.player, &mut self.dbus_conn),
-                "next" => mbackend::music_next(&self.player, &mut self.dbus_conn),
-                "prev" => mbackend::music_prev(&self.player, &mut self.dbus_conn),
+                "play" => mbackend::music_play(player, &mut self.dbus_conn),
+                "next" => mbackend::music_next(player, &mut self.dbus_conn),
+                "prev" => mbackend::music_prev(player, &mut self.dbus_conn),
                 _ => Ok(()),
             }?
             
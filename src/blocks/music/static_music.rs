@@ -58,8 +58,7 @@ impl ConfigBlock for StaticMusic {
 
     fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
         let id: String = Uuid::new_v4().simple().to_string();
-        let listener_id = id.clone();
-        mbackend::spawn_listener(listener_id, send);
+        mbackend::subscribe(&id, &send)?;
         
         let (play, prev, next) = utils::create_buttons(&block_config.buttons, &config)?;
         
@@ -195,6 +194,10 @@ impl Block for StaticMusic {
     }
 
     fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        // The buttons are named the same in every music block
+        if event.widget_index(&self.id).is_none() {
+            return Ok(());
+        }
         if let Some(ref name) = event.name {
             match name as &str {
                 "play" => mbackend::music_play(&self.player, &mut self.dbus_conn),
@@ -12,10 +12,21 @@ use errors::*;
 use widgets::text::TextWidget;
 use widgets::graph::GraphWidget;
 use widget::I3BarWidget;
+use routes;
 use scheduler::Task;
 
 use uuid::Uuid;
 
+/// The flag of routes in use, in /proc/net/route and /proc/net/ipv6_route
+const RTF_UP: u32 = 0x1;
+
+/// The hardware type of tun and WireGuard interfaces in /sys/class/net/*/type
+const ARPHRD_NONE: &str = "65534";
+
+/// Where traffic is checked to be routed to, for the `vpn` indicator. Any
+/// public address would do, nothing is sent to it.
+const PROBE_ADDRESS: &str = "1.1.1.1";
+
 pub struct NetworkDevice {
     device: String,
     device_path: PathBuf,
@@ -57,7 +68,8 @@ impl NetworkDevice {
             Ok(false)
         } else {
             let operstate = read_file(&operstate_file)?;
-            Ok(operstate == "up")
+            // Tunnels don't know about their carrier, they stay `unknown`
+            Ok(operstate == "up" || (operstate == "unknown" && self.is_tunnel()))
         }
     }
 
@@ -75,6 +87,16 @@ impl NetworkDevice {
             .block_error("net", "Failed to parse rx_bytes")
     }
 
+    pub fn name(&self) -> &str {
+        &self.device
+    }
+
+    /// Checks whether this device is a tun or WireGuard tunnel.
+    pub fn is_tunnel(&self) -> bool {
+        self.device_path.join("tun_flags").exists() ||
+            read_file(&self.device_path.join("type")).map(|kind| kind == ARPHRD_NONE).unwrap_or(false)
+    }
+
     /// Checks whether this device is wireless.
     pub fn is_wireless(&self) -> bool {
         self.wireless
@@ -201,6 +223,9 @@ pub struct Net {
     max_ssid_width: usize,
     ip_addr: Option<TextWidget>,
     bitrate: Option<TextWidget>,
+    vpn: Option<TextWidget>,
+    /// The tunnel traffic goes through, if any
+    tunnel: Option<String>,
     output_tx: Option<TextWidget>,
     graph_tx: Option<GraphWidget>,
     output_rx: Option<TextWidget>,
@@ -208,8 +233,12 @@ pub struct Net {
     id: String,
     update_interval: Duration,
     device: NetworkDevice,
-    tx_buff: Vec<u64>,
-    rx_buff: Vec<u64>,
+    /// Whether `device` follows the default route
+    auto: bool,
+    /// Whether route changes are followed, if `auto` or `vpn`, see `routes`
+    routes_followed: bool,
+    /// `routes::changes` when the tunnel was last looked up
+    routes_seen: Option<usize>,
     tx_bytes: u64,
     rx_bytes: u64,
     active: bool,
@@ -224,7 +253,8 @@ pub struct NetConfig {
     #[serde(default = "NetConfig::default_interval", deserialize_with = "deserialize_duration")]
     pub interval: Duration,
 
-    /// Which interface in /sys/class/net/ to read from.
+    /// Which interface in /sys/class/net/ to read from, or `auto` for the
+    /// one the default route goes through.
     #[serde(default = "NetConfig::default_device")]
     pub device: String,
 
@@ -244,6 +274,10 @@ pub struct NetConfig {
     #[serde(default = "NetConfig::default_ip")]
     pub ip: bool,
 
+    /// Whether to show a lock while traffic goes through a tunnel (tun/wg).
+    #[serde(default = "NetConfig::default_vpn")]
+    pub vpn: bool,
+
     /// Whether to hide networks that are down/inactive completely.
     #[serde(default = "NetConfig::default_hide_inactive")]
     pub hide_inactive: bool,
@@ -294,6 +328,10 @@ impl NetConfig {
         false
     }
 
+    fn default_vpn() -> bool {
+        false
+    }
+
     fn default_speed_up() -> bool {
         true
     }
@@ -314,21 +352,39 @@ impl NetConfig {
 impl ConfigBlock for Net {
     type Config = NetConfig;
 
-    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
-        let device = NetworkDevice::from_device(block_config.device)?;
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        let id = Uuid::new_v4().simple().to_string();
+        let auto = block_config.device == "auto";
+        // Without a default route, loopback stands in until there is one.
+        let device = NetworkDevice::from_device(if auto {
+            default_route_device()?.unwrap_or_else(|| "lo".to_owned())
+        } else {
+            block_config.device
+        })?;
         let init_rx_bytes = device.rx_bytes()?;
         let init_tx_bytes = device.tx_bytes()?;
         let wireless = device.is_wireless();
+        // Without route changes, they're only noticed on regular updates
+        let routes_followed = (auto || block_config.vpn) && match routes::subscribe(&id, &tx_update_request) {
+            Ok(()) => true,
+            Err(error) => {
+                warn!("can't watch for route changes: {:?}", error);
+                false
+            }
+        };
         Ok(Net {
-            id: Uuid::new_v4().simple().to_string(),
+            routes_followed,
+            routes_seen: None,
+            auto,
+            id,
             update_interval: block_config.interval,
             network: TextWidget::new(config.clone()).with_icon(if wireless {
                 "net_wireless" } else {
                 "net_wired"
             }),
             // Might want to signal an error if the user wants the SSID of a
-            // wired connection instead.
-            ssid: if block_config.ssid && wireless {
+            // wired connection instead. With `auto`, it may become wireless.
+            ssid: if block_config.ssid && (wireless || auto) {
                 Some(TextWidget::new(config.clone())) } else {
                 None
             },
@@ -341,6 +397,11 @@ impl ConfigBlock for Net {
                 Some(TextWidget::new(config.clone())) } else {
                 None
             },
+            vpn: if block_config.vpn {
+                Some(TextWidget::new(config.clone()).with_icon("net_vpn")) } else {
+                None
+            },
+            tunnel: None,
             output_tx: if block_config.speed_up {
                 Some(TextWidget::new(config.clone()).with_icon("net_up")) } else {
                 None
@@ -358,8 +419,6 @@ impl ConfigBlock for Net {
                 None
             },
             device,
-            rx_buff: vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-            tx_buff: vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
             rx_bytes: init_rx_bytes,
             tx_bytes: init_tx_bytes,
             active: true,
@@ -390,6 +449,75 @@ fn read_file(path: &Path) -> Result<String> {
     Ok(content)
 }
 
+/// The interface the default route goes through, if there is one. IPv4 is
+/// preferred, and the route with the lowest metric if there are several.
+fn default_route_device() -> Result<Option<String>> {
+    // Iface Destination Gateway Flags RefCnt Use Metric Mask ..., in hex
+    let routes = read_file(Path::new("/proc/net/route"))?;
+    let ipv4 = routes
+        .lines()
+        .skip(1)
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .filter(|fields| {
+            fields.len() >= 8 && fields[1] == "00000000" && fields[7] == "00000000" &&
+                u32::from_str_radix(fields[3], 16).map_or(false, |flags| flags & RTF_UP != 0)
+        })
+        .min_by_key(|fields| fields[6].parse::<u32>().unwrap_or(u32::max_value()))
+        .map(|fields| fields[0].to_owned());
+    if ipv4.is_some() {
+        return Ok(ipv4);
+    }
+
+    // Destination PrefixLength Source PrefixLength NextHop Metric RefCnt Use
+    // Flags Iface, in hex. Unreachable default routes go through loopback.
+    let routes = match read_file(Path::new("/proc/net/ipv6_route")) {
+        Ok(routes) => routes,
+        Err(_) => return Ok(None),
+    };
+    Ok(routes
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .filter(|fields| {
+            fields.len() >= 10 && fields[0].chars().all(|c| c == '0') && fields[1] == "00" && fields[9] != "lo" &&
+                u32::from_str_radix(fields[8], 16).map_or(false, |flags| flags & RTF_UP != 0)
+        })
+        .min_by_key(|fields| u32::from_str_radix(fields[5], 16).unwrap_or(u32::max_value()))
+        .map(|fields| fields[9].to_owned()))
+}
+
+/// The interface traffic to the internet leaves through, as the kernel would
+/// route it. Unlike the main routing table, this takes the policy routing of
+/// WireGuard into account.
+fn routed_device() -> Result<Option<String>> {
+    let output = match Command::new("ip").args(&["route", "get", PROBE_ADDRESS]).output() {
+        Ok(ref output) if output.status.success() => String::from_utf8_lossy(&output.stdout).into_owned(),
+        // No route, or no `ip` to ask
+        _ => return default_route_device(),
+    };
+    let mut words = output.split_whitespace();
+    Ok(words
+        .position(|word| word == "dev")
+        .and_then(|_| words.next())
+        .map(|device| device.to_owned()))
+}
+
+/// The tunnel traffic to the internet goes through, if any.
+fn tunnel_device() -> Result<Option<String>> {
+    Ok(match routed_device()? {
+        Some(device) => {
+            let tunnel = NetworkDevice::from_device(device.clone())
+                .map(|device| device.is_tunnel())
+                .unwrap_or(false);
+            if tunnel {
+                Some(device)
+            } else {
+                None
+            }
+        }
+        None => None,
+    })
+}
+
 fn convert_speed(speed: u64) -> (f64, &'static str) {
     // the values for the match are so the speed doesn't go above 3 characters
     let (speed, unit) = match speed {
@@ -401,10 +529,49 @@ fn convert_speed(speed: u64) -> (f64, &'static str) {
     (speed, unit)
 }
 
+impl Net {
+    /// Follow the default route to `device`.
+    fn switch_device(&mut self, device: String) -> Result<()> {
+        debug!("default route moved from {} to {}", self.device.name(), device);
+        self.device = NetworkDevice::from_device(device)?;
+        self.tx_bytes = self.device.tx_bytes()?;
+        self.rx_bytes = self.device.rx_bytes()?;
+        self.network.set_icon(if self.device.is_wireless() {
+            "net_wireless"
+        } else {
+            "net_wired"
+        });
+        // Everything shown is about the old device
+        self.last_update = Instant::now() - Duration::from_secs(31);
+        Ok(())
+    }
+}
+
 impl Block for Net {
     fn update(&mut self) -> Result<Option<Duration>> {
+        let mut is_up = true;
+        if self.auto {
+            match default_route_device()? {
+                Some(ref device) if device == self.device.name() => {}
+                Some(device) => self.switch_device(device)?,
+                None => is_up = false,
+            }
+        }
+
+        // Without following them, the routes might have changed on every
+        // update
+        let changes = routes::changes();
+        if self.vpn.is_some() && (!self.routes_followed || self.routes_seen != Some(changes)) {
+            self.routes_seen = Some(changes);
+            let tunnel = tunnel_device()?;
+            if tunnel != self.tunnel {
+                debug!("traffic goes through tunnel {:?}", tunnel);
+                self.tunnel = tunnel;
+            }
+        }
+
         // Skip updating tx/rx if device is not up.
-        let is_up = self.device.is_up()?;
+        let is_up = is_up && self.device.is_up()?;
         if !is_up {
             self.active = false;
             self.network.set_text("×".to_string());
@@ -423,7 +590,7 @@ impl Block for Net {
 
         // Update SSID and IP address every 30s and the bitrate every 10s
         let now = Instant::now();
-        if now.duration_since(self.last_update).as_secs() % 10 == 0 {
+        if now.duration_since(self.last_update).as_secs() % 10 == 0 && self.device.is_wireless() {
             if let Some(ref mut bitrate_widget) = self.bitrate {
                 let bitrate = self.device.bitrate()?;
                 if bitrate.is_some() {
@@ -432,12 +599,14 @@ impl Block for Net {
             }
         }
         if now.duration_since(self.last_update).as_secs() > 30 {
-            if let Some(ref mut ssid_widget) = self.ssid {
-                let ssid = self.device.ssid()?;
-                if ssid.is_some() {
-                    let mut truncated = ssid.unwrap();
-                    truncated.truncate(self.max_ssid_width);
-                    ssid_widget.set_text(truncated);
+            if self.device.is_wireless() {
+                if let Some(ref mut ssid_widget) = self.ssid {
+                    let ssid = self.device.ssid()?;
+                    if ssid.is_some() {
+                        let mut truncated = ssid.unwrap();
+                        truncated.truncate(self.max_ssid_width);
+                        ssid_widget.set_text(truncated);
+                    }
                 }
             }
             if let Some(ref mut ip_addr_widget) = self.ip_addr {
@@ -454,7 +623,8 @@ impl Block for Net {
         let update_interval = (self.update_interval.as_secs() as f64) + (self.update_interval.subsec_nanos() as f64 / 1_000_000_000.0);
         if self.output_tx.is_some() || self.graph_tx.is_some() {
             let current_tx = self.device.tx_bytes()?;
-            let tx_bytes = ((current_tx - self.tx_bytes) as f64 / update_interval) as u64;
+            // Counters start over if the device was recreated
+            let tx_bytes = (current_tx.saturating_sub(self.tx_bytes) as f64 / update_interval) as u64;
             let (tx_speed, tx_unit) = convert_speed(tx_bytes);
             self.tx_bytes = current_tx;
 
@@ -463,14 +633,12 @@ impl Block for Net {
             };
 
             if let Some(ref mut graph_tx_widget) = self.graph_tx {
-                self.tx_buff.remove(0);
-                self.tx_buff.push(tx_bytes);
-                graph_tx_widget.set_values(&self.tx_buff, None, None);
+                graph_tx_widget.push(tx_bytes as f64);
             }
         }
         if self.output_rx.is_some() || self.graph_rx.is_some() {
             let current_rx = self.device.rx_bytes()?;
-            let rx_bytes = ((current_rx - self.rx_bytes) as f64 / update_interval) as u64;
+            let rx_bytes = (current_rx.saturating_sub(self.rx_bytes) as f64 / update_interval) as u64;
             let (rx_speed, rx_unit) = convert_speed(rx_bytes);
             self.rx_bytes = current_rx;
 
@@ -479,9 +647,7 @@ impl Block for Net {
             };
 
             if let Some(ref mut graph_rx_widget) = self.graph_rx {
-                self.rx_buff.remove(0);
-                self.rx_buff.push(rx_bytes);
-                graph_rx_widget.set_values(&self.rx_buff, None, None);
+                graph_rx_widget.push(rx_bytes as f64);
             }
         }
 
@@ -490,13 +656,20 @@ impl Block for Net {
 
     fn view(&self) -> Vec<&I3BarWidget> {
         if self.active {
-            let mut widgets: Vec<&I3BarWidget> = Vec::with_capacity(7);
+            let mut widgets: Vec<&I3BarWidget> = Vec::with_capacity(8);
             widgets.push(&self.network);
-            if let Some(ref ssid_widget) = self.ssid {
-                widgets.push(ssid_widget);
-            };
-            if let Some(ref bitrate_widget) = self.bitrate {
-                widgets.push(bitrate_widget);
+            if self.device.is_wireless() {
+                if let Some(ref ssid_widget) = self.ssid {
+                    widgets.push(ssid_widget);
+                };
+                if let Some(ref bitrate_widget) = self.bitrate {
+                    widgets.push(bitrate_widget);
+                }
+            }
+            if self.tunnel.is_some() {
+                if let Some(ref vpn_widget) = self.vpn {
+                    widgets.push(vpn_widget);
+                }
             }
             if let Some(ref ip_addr_widget) = self.ip_addr {
                 widgets.push(ip_addr_widget);
@@ -521,6 +694,19 @@ impl Block for Net {
         }
     }
 
+    fn resumed(&mut self) {
+        // The bytes counted before the suspend would count towards the first
+        // update after it.
+        if let Ok(tx_bytes) = self.device.tx_bytes() {
+            self.tx_bytes = tx_bytes;
+        }
+        if let Ok(rx_bytes) = self.device.rx_bytes() {
+            self.rx_bytes = rx_bytes;
+        }
+        // Likely on another network now
+        self.last_update = Instant::now() - Duration::from_secs(31);
+    }
+
     fn id(&self) -> &str {
         &self.id
     }
@@ -1,11 +1,12 @@
 use std::fmt;
-use std::time::{Duration, Instant};
-use std::thread;
+use std::rc::Rc;
+use std::time::Duration;
 
 use chan::Sender;
 use uuid::Uuid;
 
 use config::Config;
+use dbus_manager;
 use errors::*;
 use scheduler::Task;
 use block::{Block, ConfigBlock};
@@ -130,7 +131,7 @@ impl ConnectionManager {
 pub struct NetworkManager {
     id: String,
     output: TextWidget,
-    dbus_conn: Connection,
+    dbus_conn: Rc<Connection>,
     manager: ConnectionManager,
     show_type: bool,
 }
@@ -154,34 +155,21 @@ impl ConfigBlock for NetworkManager {
 
     fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
         let id: String = Uuid::new_v4().simple().to_string();
-        let id_copy = id.clone();
-        let dbus_conn = Connection::get_private(BusType::System)
-            .block_error("networkmanager", "failed to establish D-Bus connection")?;
+        let dbus_conn = dbus_manager::connection(BusType::System)?;
         let manager = ConnectionManager::new();
 
-        thread::spawn(move || {
-            let c = Connection::get_private(BusType::System).unwrap();
-            let rule = "type='signal',\
-                 path='/org/freedesktop/NetworkManager',\
-                 interface='org.freedesktop.NetworkManager',\
-                 member='StateChanged'";
-
-            c.add_match(&rule).unwrap();
-
-            loop {
-                let timeout = 100_000;
-
-                for _event in c.iter(timeout) {
-                    send.send(Task {
-                        id: id.clone(),
-                        update_time: Instant::now(),
-                    });
-                }
-            }
-        });
+        dbus_manager::subscribe(
+            BusType::System,
+            "type='signal',\
+             path='/org/freedesktop/NetworkManager',\
+             interface='org.freedesktop.NetworkManager',\
+             member='StateChanged'",
+            &id,
+            &send,
+        )?;
 
         Ok(NetworkManager {
-            id: id_copy,
+            id,
             output: TextWidget::new(config),
             dbus_conn,
             manager,
@@ -0,0 +1,214 @@
+//! A block for the color temperature of the screens, as set by a night light
+//! program, which is changed by scrolling.
+//!
+//! Neither gammastep nor wlsunset can change the temperature while running,
+//! so the block runs the program itself, and starts it over with each
+//! change. It shouldn't be started elsewhere as well, the two would fight
+//! over the screens.
+
+use std::collections::HashMap;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use chan::Sender;
+use nix::libc;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use errors::*;
+use formatting::{FormatTemplate, Value};
+use input::{I3BarEvent, MouseButton};
+use scheduler::Task;
+use widget::I3BarWidget;
+use widgets::button::ButtonWidget;
+
+#[derive(Deserialize, Copy, Clone, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum NightLightProgram {
+    Gammastep,
+    Wlsunset,
+}
+
+impl NightLightProgram {
+    /// The program keeping the screens at `temperature`, whatever the sun
+    /// does.
+    fn command(&self, temperature: u32) -> Command {
+        match *self {
+            NightLightProgram::Gammastep => {
+                let mut command = Command::new("gammastep");
+                command.args(&["-P", "-O", &temperature.to_string()]);
+                command
+            }
+            // The temperature of the day has to be above the one of the
+            // night
+            NightLightProgram::Wlsunset => {
+                let mut command = Command::new("wlsunset");
+                command.args(&["-t", &temperature.to_string(), "-T", &(temperature + 1).to_string()]);
+                command
+            }
+        }
+    }
+}
+
+/// Shows the color temperature and changes it on a scroll, see
+/// `NightLightConfig`.
+pub struct NightLight {
+    id: String,
+    output: ButtonWidget,
+    format: FormatTemplate,
+    program: NightLightProgram,
+    temperature: u32,
+    step: u32,
+    min: u32,
+    max: u32,
+    child: Option<Child>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NightLightConfig {
+    /// The night light program run, `gammastep` or `wlsunset`
+    #[serde(default = "NightLightConfig::default_program")]
+    pub program: NightLightProgram,
+
+    /// The color temperature to start with, in Kelvin
+    #[serde(default = "NightLightConfig::default_temperature")]
+    pub temperature: u32,
+
+    /// How much a scroll changes the temperature, in Kelvin
+    #[serde(default = "NightLightConfig::default_step")]
+    pub step: u32,
+
+    /// The lowest and highest temperature scrolling goes to, in Kelvin
+    #[serde(default = "NightLightConfig::default_min")]
+    pub min: u32,
+    #[serde(default = "NightLightConfig::default_max")]
+    pub max: u32,
+
+    /// Format override, `{temperature}` is available
+    #[serde(default = "NightLightConfig::default_format")]
+    pub format: String,
+}
+
+impl NightLightConfig {
+    fn default_program() -> NightLightProgram {
+        NightLightProgram::Gammastep
+    }
+
+    fn default_temperature() -> u32 {
+        4500
+    }
+
+    fn default_step() -> u32 {
+        250
+    }
+
+    fn default_min() -> u32 {
+        1000
+    }
+
+    fn default_max() -> u32 {
+        6500
+    }
+
+    fn default_format() -> String {
+        "{temperature}K".to_owned()
+    }
+}
+
+impl NightLight {
+    /// Start the program over at the current temperature.
+    fn apply(&mut self) -> Result<()> {
+        // Killed outright, so it doesn't put back the colors it found
+        // after the new one set its own
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        let child = self.program
+            .command(self.temperature)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .block_error("night_light", &format!("failed to run {:?}", self.program))?;
+        self.child = Some(child);
+        Ok(())
+    }
+}
+
+impl ConfigBlock for NightLight {
+    type Config = NightLightConfig;
+
+    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        if block_config.min > block_config.max {
+            return Err(BlockError("night_light".to_owned(), "min is above max".to_owned()));
+        }
+
+        let mut night_light = NightLight {
+            output: ButtonWidget::new(config, &id).with_icon("night_light"),
+            id,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("night_light", "Invalid format specified for night_light")?,
+            program: block_config.program,
+            temperature: block_config.temperature.max(block_config.min).min(block_config.max),
+            step: block_config.step,
+            min: block_config.min,
+            max: block_config.max,
+            child: None,
+        };
+        night_light.apply()?;
+        Ok(night_light)
+    }
+}
+
+impl Drop for NightLight {
+    fn drop(&mut self) {
+        // Asked to stop this time, so it puts back the colors of the
+        // screens
+        if let Some(mut child) = self.child.take() {
+            unsafe {
+                libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+            }
+            thread::spawn(move || child.wait());
+        }
+    }
+}
+
+impl Block for NightLight {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let mut values = HashMap::new();
+        values.insert("{temperature}", Value::Text(self.temperature.to_string()));
+        self.output.set_text(self.format.render_values(&values)?);
+
+        // Only changes with a scroll
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.output]
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if !event.name.as_ref().map_or(false, |name| name == &self.id) {
+            return Ok(());
+        }
+        let temperature = match event.button {
+            MouseButton::WheelUp => (self.temperature + self.step).min(self.max),
+            MouseButton::WheelDown => self.temperature.saturating_sub(self.step).max(self.min),
+            _ => return Ok(()),
+        };
+        if temperature != self.temperature {
+            self.temperature = temperature;
+            self.apply()?;
+            self.update()?;
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,216 @@
+use std::time::Duration;
+use chan::Sender;
+use serde_json;
+use serde_json::value::Value;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use http;
+use scheduler::Task;
+use formatting::FormatTemplate;
+use widgets::text::TextWidget;
+use widget::{I3BarWidget, State};
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "name", rename_all = "lowercase")]
+pub enum NodeService {
+    Geth {
+        #[serde(default = "NodeService::default_geth_url")]
+        url: String,
+    },
+    Bitcoind {
+        #[serde(default = "NodeService::default_bitcoind_url")]
+        url: String,
+        user: String,
+        password: String,
+    },
+}
+
+impl NodeService {
+    fn default_geth_url() -> String {
+        "http://127.0.0.1:8545".to_owned()
+    }
+
+    fn default_bitcoind_url() -> String {
+        "http://127.0.0.1:8332".to_owned()
+    }
+}
+
+/// The sync status of a node.
+struct NodeStatus {
+    height: u64,
+    target: u64,
+    peers: u64,
+}
+
+pub struct NodeSync {
+    id: String,
+    text: TextWidget,
+    format: FormatTemplate,
+    service: NodeService,
+    update_interval: Duration,
+    min_peers: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NodeSyncConfig {
+    /// The node to query
+    pub service: NodeService,
+
+    /// Format string, placeholders: {height}, {target}, {behind}, {progress} and {peers}
+    #[serde(default = "NodeSyncConfig::default_format")]
+    pub format: String,
+
+    /// Update interval in seconds
+    #[serde(default = "NodeSyncConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Number of peers below which the state is set to warning
+    #[serde(default = "NodeSyncConfig::default_min_peers")]
+    pub min_peers: u64,
+}
+
+impl NodeSyncConfig {
+    fn default_format() -> String {
+        "{height} ({peers})".to_owned()
+    }
+
+    fn default_interval() -> Duration {
+        Duration::from_secs(30)
+    }
+
+    fn default_min_peers() -> u64 {
+        3
+    }
+}
+
+/// Perform a JSON-RPC call against `url` and return the `result` member.
+fn rpc_call(url: &str, auth: Option<(&str, &str)>, method: &str) -> Result<Value> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": [],
+    });
+
+    let mut request = http::Request::post(url, &request.to_string()).with_header("Content-Type", "application/json");
+    if let Some((user, password)) = auth {
+        request = request.with_basic_auth(user, password);
+    }
+    let output = request.send()?;
+
+    let response: Value = serde_json::from_str(&output)
+        .block_error("node_sync", &format!("Failed to parse response of '{}'.", method))?;
+
+    if !response["error"].is_null() {
+        return Err(BlockError(
+            "node_sync".to_owned(),
+            format!("'{}' failed: {}", method, response["error"]),
+        ));
+    }
+
+    Ok(response["result"].clone())
+}
+
+/// Parse a `0x` prefixed hex quantity as returned by the Ethereum JSON-RPC.
+fn parse_quantity(value: &Value) -> Result<u64> {
+    let s = value.as_str().block_error("node_sync", "Expected a hex quantity.")?;
+    u64::from_str_radix(s.trim_left_matches("0x"), 16).block_error("node_sync", "Invalid hex quantity.")
+}
+
+impl NodeSync {
+    fn status(&self) -> Result<NodeStatus> {
+        match self.service {
+            NodeService::Geth { ref url } => {
+                let height = parse_quantity(&rpc_call(url, None, "eth_blockNumber")?)?;
+                let peers = parse_quantity(&rpc_call(url, None, "net_peerCount")?)?;
+                // `eth_syncing` is `false` when the node is in sync.
+                let syncing = rpc_call(url, None, "eth_syncing")?;
+                let target = if syncing.is_object() {
+                    parse_quantity(&syncing["highestBlock"])?
+                } else {
+                    height
+                };
+                Ok(NodeStatus { height, target, peers })
+            }
+            NodeService::Bitcoind {
+                ref url,
+                ref user,
+                ref password,
+            } => {
+                let auth = Some((user.as_str(), password.as_str()));
+                let info = rpc_call(url, auth, "getblockchaininfo")?;
+                let height = info["blocks"].as_u64().block_error("node_sync", "Missing block height.")?;
+                let target = info["headers"].as_u64().block_error("node_sync", "Missing header height.")?;
+                let peers = rpc_call(url, auth, "getconnectioncount")?
+                    .as_u64()
+                    .block_error("node_sync", "Missing connection count.")?;
+                Ok(NodeStatus { height, target, peers })
+            }
+        }
+    }
+}
+
+impl ConfigBlock for NodeSync {
+    type Config = NodeSyncConfig;
+
+    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        Ok(NodeSync {
+            id: Uuid::new_v4().simple().to_string(),
+            text: TextWidget::new(config).with_icon("node"),
+            format: FormatTemplate::from_string(&block_config.format)?,
+            service: block_config.service,
+            update_interval: block_config.interval,
+            min_peers: block_config.min_peers,
+        })
+    }
+}
+
+impl Block for NodeSync {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        match self.status() {
+            Ok(status) => {
+                let behind = status.target.saturating_sub(status.height);
+                let progress = if status.target == 0 {
+                    0.
+                } else {
+                    status.height as f64 / status.target as f64 * 100.
+                };
+                let values = map!("{height}" => format!("{}", status.height),
+                                  "{target}" => format!("{}", status.target),
+                                  "{behind}" => format!("{}", behind),
+                                  "{progress}" => format!("{:.1}", progress),
+                                  "{peers}" => format!("{}", status.peers));
+                self.text.set_text(self.format.render_static_str(&values)?);
+                self.text.set_state(if status.peers == 0 {
+                    State::Critical
+                } else if behind > 0 || status.peers < self.min_peers {
+                    State::Warning
+                } else {
+                    State::Good
+                });
+            }
+            // A node that is down or still starting up shouldn't take down
+            // the bar.
+            Err(error) => {
+                warn!("node_sync couldn't get the node's status: {:?}", error);
+                self.text.set_text("×".to_owned());
+                self.text.set_state(State::Critical);
+            }
+        }
+
+        Ok(Some(self.update_interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
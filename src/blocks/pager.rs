@@ -0,0 +1,110 @@
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use scheduler::Task;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_opt_duration;
+use errors::*;
+use formatting::FormatTemplate;
+use input::{I3BarEvent, MouseButton};
+use paging;
+use widget::I3BarWidget;
+use widgets::button::ButtonWidget;
+
+use uuid::Uuid;
+
+pub struct Pager {
+    text: ButtonWidget,
+    format: FormatTemplate,
+    interval: Option<Duration>,
+    last_turn: Instant,
+    id: String,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PagerConfig {
+    /// Turn to the next page after this long, if set
+    #[serde(default = "PagerConfig::default_interval", deserialize_with = "deserialize_opt_duration")]
+    pub interval: Option<Duration>,
+
+    /// Format override, `{current}` and `{count}` are available
+    #[serde(default = "PagerConfig::default_format")]
+    pub format: String,
+}
+
+impl PagerConfig {
+    fn default_interval() -> Option<Duration> {
+        None
+    }
+
+    fn default_format() -> String {
+        "{current}/{count}".to_owned()
+    }
+}
+
+impl Pager {
+    fn display(&mut self) -> Result<()> {
+        let values = map!("{current}" => paging::current(),
+                          "{count}" => paging::count());
+        self.text.set_text(self.format.render_static_str(&values)?);
+        Ok(())
+    }
+}
+
+impl ConfigBlock for Pager {
+    type Config = PagerConfig;
+
+    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        let id = Uuid::new_v4().simple().to_string();
+        Ok(Pager {
+            text: ButtonWidget::new(config, &id),
+            format: FormatTemplate::from_string(&block_config.format)?,
+            interval: block_config.interval,
+            last_turn: Instant::now(),
+            id,
+        })
+    }
+}
+
+impl Block for Pager {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        // Updates may also be requested by signals, only turn the page once
+        // the interval has passed.
+        if let Some(interval) = self.interval {
+            if self.last_turn.elapsed() >= interval {
+                paging::turn(1);
+                self.last_turn = Instant::now();
+            }
+        }
+        self.display()?;
+
+        // The page count changes when the configuration is reloaded, so
+        // refresh the text every now and then even without an interval.
+        Ok(Some(self.interval.unwrap_or_else(|| Duration::from_secs(5))))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if e.matches_name(&self.id) {
+            match e.button {
+                MouseButton::Left | MouseButton::WheelDown => paging::turn(1),
+                MouseButton::Right | MouseButton::WheelUp => paging::turn(-1),
+                _ => return Ok(()),
+            }
+            self.last_turn = Instant::now();
+            self.display()?;
+        }
+
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use blocks::dbus::arg::{RefArg, Variant};
+use blocks::dbus::{BusType, Connection, Message, Path};
+use config::Config;
+use dbus_manager;
+use errors::*;
+use formatting::{FormatTemplate, Unit, Value};
+use scheduler::Task;
+use widget::{I3BarWidget, State};
+use widgets::list::ListWidget;
+
+const UPOWER_NAME: &str = "org.freedesktop.UPower";
+const UPOWER_PATH: &str = "/org/freedesktop/UPower";
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+/// The UPower device types, by the names used in the configuration, see
+/// https://upower.freedesktop.org/docs/Device.html#Device:Type
+const DEVICE_TYPES: &[(&str, u32)] = &[
+    ("mouse", 5),
+    ("keyboard", 6),
+    ("tablet", 10),
+    ("gaming_input", 12),
+    ("pen", 13),
+    ("touchpad", 14),
+    ("headset", 17),
+    ("headphones", 19),
+    ("remote_control", 22),
+];
+
+type Properties = HashMap<String, Variant<Box<RefArg>>>;
+
+/// A wireless device with a battery of its own.
+struct Peripheral {
+    name: String,
+    kind: &'static str,
+    percentage: f64,
+}
+
+/// Shows the battery level of wireless mice, keyboards, tablets and the like,
+/// see `PeripheralsConfig`.
+pub struct Peripherals {
+    id: String,
+    list: ListWidget,
+    types: Vec<u32>,
+    format: FormatTemplate,
+    warning: f64,
+    critical: f64,
+    dbus_conn: Connection,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PeripheralsConfig {
+    /// The kinds of devices shown, see `DEVICE_TYPES`
+    #[serde(default = "PeripheralsConfig::default_types")]
+    pub types: Vec<String>,
+
+    /// Format override, `{name}`, `{type}` and `{percentage}` are available
+    #[serde(default = "PeripheralsConfig::default_format")]
+    pub format: String,
+
+    /// Devices below this percentage are shown as a warning
+    #[serde(default = "PeripheralsConfig::default_warning")]
+    pub warning: f64,
+
+    /// Devices below this percentage are shown as critical
+    #[serde(default = "PeripheralsConfig::default_critical")]
+    pub critical: f64,
+}
+
+impl PeripheralsConfig {
+    fn default_types() -> Vec<String> {
+        ["mouse", "keyboard", "tablet", "gaming_input", "pen", "touchpad"]
+            .iter()
+            .map(|kind| (*kind).to_owned())
+            .collect()
+    }
+
+    fn default_format() -> String {
+        "{name} {percentage}".to_owned()
+    }
+
+    fn default_warning() -> f64 {
+        20.
+    }
+
+    fn default_critical() -> f64 {
+        10.
+    }
+}
+
+fn device_paths(c: &Connection) -> Result<Vec<Path<'static>>> {
+    let m = Message::new_method_call(UPOWER_NAME, UPOWER_PATH, UPOWER_NAME, "EnumerateDevices")
+        .block_error("peripherals", "failed to create D-Bus message")?;
+    let r = c.send_with_reply_and_block(m, 1000)
+        .block_error("peripherals", "failed to get the devices from UPower")?;
+    r.get1().block_error("peripherals", "invalid devices from UPower")
+}
+
+fn device_properties(c: &Connection, path: &Path) -> Result<Properties> {
+    let m = Message::new_method_call(UPOWER_NAME, path.clone(), "org.freedesktop.DBus.Properties", "GetAll")
+        .block_error("peripherals", "failed to create D-Bus message")?
+        .append1(DEVICE_INTERFACE);
+    let r = c.send_with_reply_and_block(m, 1000)
+        .block_error("peripherals", "failed to get a device from UPower")?;
+    r.get1().block_error("peripherals", "invalid device from UPower")
+}
+
+/// The device at `path`, if it's one of `types` and present.
+fn peripheral(c: &Connection, path: &Path, types: &[u32]) -> Result<Option<Peripheral>> {
+    let properties = device_properties(c, path)?;
+    let kind = properties.get("Type").and_then(|kind| kind.0.as_u64()).unwrap_or(0) as u32;
+    let present = properties
+        .get("IsPresent")
+        .and_then(|present| present.0.as_u64())
+        .map_or(false, |present| present != 0);
+    let kind = match DEVICE_TYPES.iter().find(|&&(_, number)| number == kind) {
+        Some(&(name, number)) if present && types.contains(&number) => name,
+        _ => return Ok(None),
+    };
+
+    let name = properties
+        .get("Model")
+        .and_then(|model| model.0.as_str())
+        .and_then(|model| if model.is_empty() { None } else { Some(model.to_owned()) })
+        .unwrap_or_else(|| kind.to_owned());
+    Ok(Some(Peripheral {
+        name,
+        kind,
+        percentage: properties
+            .get("Percentage")
+            .and_then(|percentage| percentage.0.as_f64())
+            .unwrap_or(0.),
+    }))
+}
+
+impl ConfigBlock for Peripherals {
+    type Config = PeripheralsConfig;
+
+    fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        let mut types = Vec::new();
+        for name in &block_config.types {
+            match DEVICE_TYPES.iter().find(|&&(other, _)| other == name) {
+                Some(&(_, number)) => types.push(number),
+                None => {
+                    return Err(BlockError(
+                        "peripherals".to_owned(),
+                        format!("unknown device type '{}'", name),
+                    ))
+                }
+            }
+        }
+
+        let dbus_conn = Connection::get_private(BusType::System)
+            .block_error("peripherals", "failed to connect to the system bus")?;
+        // Devices coming and going, and their batteries draining
+        let rules = [
+            format!("type='signal',interface='{}',path='{}'", UPOWER_NAME, UPOWER_PATH),
+            format!(
+                "type='signal',interface='org.freedesktop.DBus.Properties',member='PropertiesChanged',path_namespace='{}/devices'",
+                UPOWER_PATH
+            ),
+        ];
+        for rule in &rules {
+            dbus_manager::subscribe(BusType::System, rule, &id, &send)?;
+        }
+
+        Ok(Peripherals {
+            id,
+            list: ListWidget::new(config).with_icon("peripherals"),
+            types,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("peripherals", "Invalid format specified for peripherals")?,
+            warning: block_config.warning,
+            critical: block_config.critical,
+            dbus_conn,
+        })
+    }
+}
+
+impl Block for Peripherals {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let mut peripherals = Vec::new();
+        for path in device_paths(&self.dbus_conn)? {
+            // Devices may go away in between
+            if let Ok(Some(peripheral)) = peripheral(&self.dbus_conn, &path, &self.types) {
+                peripherals.push(peripheral);
+            }
+        }
+        peripherals.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut items = Vec::new();
+        for peripheral in &peripherals {
+            let mut values = HashMap::new();
+            values.insert("{name}", Value::Text(peripheral.name.clone()));
+            values.insert("{type}", Value::Text(peripheral.kind.to_owned()));
+            values.insert("{percentage}", Value::Number(peripheral.percentage, Unit::Percents));
+            let state = if peripheral.percentage < self.critical {
+                State::Critical
+            } else if peripheral.percentage < self.warning {
+                State::Warning
+            } else {
+                State::Idle
+            };
+            items.push((self.format.render_values(&values)?, state));
+        }
+        self.list.set_items(items);
+
+        // UPower tells about changes
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        self.list.view()
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,268 @@
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use serde_json::{self, Value};
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use errors::*;
+use input::I3BarEvent;
+use scheduler::Task;
+use widget::{I3BarWidget, State};
+use widgets::button::ButtonWidget;
+
+use uuid::Uuid;
+
+/// The first restart is after this many seconds, each further one in a row
+/// waits twice as long, up to `MAX_RESTART_SECS`.
+const FIRST_RESTART_SECS: u64 = 1;
+const MAX_RESTART_SECS: u64 = 60;
+
+/// A command running at least this long is considered to have worked, its
+/// next restart is quick again.
+const STABLE_SECS: u64 = 60;
+
+/// What the command printed last.
+#[derive(Debug, Clone, Default)]
+struct Status {
+    full_text: String,
+    short_text: Option<String>,
+    color: Option<String>,
+    urgent: bool,
+    /// Whether the command failed, `full_text` says how
+    failed: bool,
+}
+
+impl Status {
+    /// A line is either plain text, or an i3bar block in JSON.
+    fn parse(line: &str) -> Status {
+        let json = if line.trim_left().starts_with('{') {
+            serde_json::from_str::<Value>(line).ok()
+        } else {
+            None
+        };
+        match json {
+            Some(json) => Status {
+                full_text: json["full_text"].as_str().unwrap_or("").to_owned(),
+                short_text: json["short_text"].as_str().map(|text| text.to_owned()),
+                color: json["color"].as_str().map(|color| color.to_owned()),
+                urgent: json["urgent"].as_bool().unwrap_or(false),
+                failed: false,
+            },
+            None => Status {
+                full_text: line.to_owned(),
+                ..Status::default()
+            },
+        }
+    }
+
+    fn failure(message: String) -> Status {
+        Status {
+            full_text: message,
+            failed: true,
+            ..Status::default()
+        }
+    }
+}
+
+/// What the block shares with the thread running the command.
+#[derive(Default)]
+struct Shared {
+    status: Option<Status>,
+    child: Option<Child>,
+    stdin: Option<ChildStdin>,
+    /// Set once the block is gone, the command isn't restarted then
+    stopped: bool,
+}
+
+/// Keeps a command running and shows every line it prints, see
+/// `PersistentConfig`.
+pub struct Persistent {
+    id: String,
+    output: ButtonWidget,
+    shared: Arc<Mutex<Shared>>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PersistentConfig {
+    /// The command, run with the shell
+    pub command: String,
+}
+
+/// Run `command` until it exits. Returns how it ended.
+fn run_once(command: &str, id: &str, shared: &Arc<Mutex<Shared>>, tx: &Sender<Task>) -> Result<String> {
+    let mut child = Command::new(env::var("SHELL").unwrap_or_else(|_| "sh".to_owned()))
+        .args(&["-c", command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .block_error("persistent", &format!("failed to run '{}'", command))?;
+    let stdout = child
+        .stdout
+        .take()
+        .block_error("persistent", "failed to read the output of the command")?;
+    {
+        let mut shared = shared.lock().block_error("persistent", "failed to lock status")?;
+        if shared.stopped {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok("stopped".to_owned());
+        }
+        shared.stdin = child.stdin.take();
+        shared.child = Some(child);
+    }
+
+    for line in BufReader::new(stdout).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if let Ok(mut shared) = shared.lock() {
+            shared.status = Some(Status::parse(&line));
+        }
+        tx.send(Task {
+            id: id.to_owned(),
+            update_time: Instant::now(),
+        });
+    }
+
+    let mut shared = shared.lock().block_error("persistent", "failed to lock status")?;
+    shared.stdin = None;
+    Ok(match shared.child.take().map(|mut child| child.wait()) {
+        Some(Ok(status)) => format!("{}", status),
+        Some(Err(error)) => format!("{}", error),
+        None => "stopped".to_owned(),
+    })
+}
+
+impl ConfigBlock for Persistent {
+    type Config = PersistentConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        let id = Uuid::new_v4().simple().to_string();
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        {
+            let id = id.clone();
+            let shared = shared.clone();
+            let command = block_config.command;
+            thread::spawn(move || {
+                let mut restarts = 0;
+                loop {
+                    let started = Instant::now();
+                    let ended = run_once(&command, &id, &shared, &tx_update_request)
+                        .unwrap_or_else(|error| format!("{:?}", error));
+                    if shared.lock().map(|shared| shared.stopped).unwrap_or(true) {
+                        return;
+                    }
+
+                    if started.elapsed() >= Duration::from_secs(STABLE_SECS) {
+                        restarts = 0;
+                    }
+                    let delay = (FIRST_RESTART_SECS << restarts.min(16)).min(MAX_RESTART_SECS);
+                    restarts += 1;
+                    warn!("'{}' ended ({}), restarting in {}s", command, ended, delay);
+                    if let Ok(mut shared) = shared.lock() {
+                        shared.status = Some(Status::failure(format!("exited, restarting in {}s", delay)));
+                    }
+                    tx_update_request.send(Task {
+                        id: id.clone(),
+                        update_time: Instant::now(),
+                    });
+                    thread::sleep(Duration::from_secs(delay));
+                }
+            });
+        }
+
+        Ok(Persistent {
+            output: ButtonWidget::new(config, &id).with_text("\u{2026}"),
+            id,
+            shared,
+        })
+    }
+}
+
+impl Block for Persistent {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let status = self.shared
+            .lock()
+            .block_error("persistent", "failed to lock status")?
+            .status
+            .take();
+        if let Some(status) = status {
+            let short_text = match status.short_text {
+                Some(short_text) => short_text,
+                None => status.full_text.clone(),
+            };
+            self.output.set_short_text(short_text);
+            self.output.set_text(status.full_text);
+            self.output.set_color(status.color);
+            self.output.set_urgent(status.urgent);
+            self.output.set_state(if status.failed {
+                State::Critical
+            } else {
+                State::Idle
+            });
+        }
+
+        // Updated whenever the command prints a line
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.output]
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if !event.matches_name(&self.id) {
+            return Ok(());
+        }
+
+        // Just like i3bar sends them, but for a single block
+        let line = json!({
+            "name": self.id,
+            "button": event.button.number(),
+            "x": event.x,
+            "y": event.y,
+            "relative_x": event.relative_x,
+            "relative_y": event.relative_y,
+            "width": event.width,
+            "height": event.height,
+            "modifiers": event.modifiers,
+        });
+        let mut shared = self.shared
+            .lock()
+            .block_error("persistent", "failed to lock status")?;
+        // Between restarts, there's nobody to tell
+        let failed = match shared.stdin {
+            Some(ref mut stdin) => writeln!(stdin, "{}", line).and_then(|_| stdin.flush()).is_err(),
+            None => false,
+        };
+        if failed {
+            shared.stdin = None;
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Drop for Persistent {
+    fn drop(&mut self) {
+        // Ends the command, and with it the thread running it
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.stopped = true;
+            if let Some(ref mut child) = shared.child {
+                let _ = child.kill();
+            }
+        }
+    }
+}
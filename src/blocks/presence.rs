@@ -0,0 +1,275 @@
+//! A block publishing whether the user is at the machine, so that home
+//! automation can react to them stepping away.
+//!
+//! The state is `busy` while the session is in use and `away` once it's been
+//! idle for a while, as told by the `IdleHint` of its systemd-logind session,
+//! see the `screen_time` block. A click can also set it by hand. Every change
+//! is published to an MQTT topic with `mosquitto_pub`, to a webhook, or both.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use blocks::dbus::arg::{RefArg, Variant};
+use blocks::dbus::{BusType, Connection, Message};
+use config::Config;
+use dbus_manager;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Value};
+use http;
+use input::{I3BarEvent, MouseButton};
+use scheduler::Task;
+use util::read_file;
+use widget::{I3BarWidget, State};
+use widgets::button::ButtonWidget;
+
+/// Where to publish the state over MQTT.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MqttConfig {
+    pub host: String,
+
+    #[serde(default = "MqttConfig::default_port")]
+    pub port: u16,
+
+    /// Published to as a retained message, so that new subscribers get the
+    /// state right away
+    pub topic: String,
+
+    #[serde(default = "MqttConfig::default_user")]
+    pub user: Option<String>,
+
+    #[serde(default = "MqttConfig::default_password")]
+    pub password: Option<String>,
+}
+
+impl MqttConfig {
+    fn default_port() -> u16 {
+        1883
+    }
+
+    fn default_user() -> Option<String> {
+        None
+    }
+
+    fn default_password() -> Option<String> {
+        None
+    }
+
+    fn publish(&self, state: &str) -> Result<()> {
+        let mut command = Command::new("mosquitto_pub");
+        command
+            .args(&["-h", &self.host, "-p", &self.port.to_string(), "-t", &self.topic, "-m", state, "-r"]);
+        if let Some(ref user) = self.user {
+            command.args(&["-u", user]);
+        }
+        if let Some(ref password) = self.password {
+            command.args(&["-P", password]);
+        }
+        let status = command
+            .status()
+            .block_error("presence", "failed to run mosquitto_pub")?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(BlockError("presence".to_owned(), format!("failed to publish to {}", self.topic)))
+        }
+    }
+}
+
+/// Shows the published state and sets it by hand on a click, see
+/// `PresenceConfig`.
+pub struct Presence {
+    id: String,
+    output: ButtonWidget,
+    format: FormatTemplate,
+    away_after: Duration,
+    mqtt: Option<MqttConfig>,
+    webhook: Option<String>,
+    /// The state set by hand, if any
+    manual: Option<&'static str>,
+    /// Since when the session has been idle
+    idle_since: Option<Instant>,
+    /// The state published last
+    published: Option<&'static str>,
+    dbus_conn: Connection,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PresenceConfig {
+    /// Format override, `{state}` is available
+    #[serde(default = "PresenceConfig::default_format")]
+    pub format: String,
+
+    /// How long, in seconds, the session has to be idle to count as away
+    #[serde(default = "PresenceConfig::default_away_after", deserialize_with = "deserialize_duration")]
+    pub away_after: Duration,
+
+    /// MQTT broker and topic to publish to
+    #[serde(default = "PresenceConfig::default_mqtt")]
+    pub mqtt: Option<MqttConfig>,
+
+    /// URL to `POST` the state to, as JSON
+    #[serde(default = "PresenceConfig::default_webhook")]
+    pub webhook: Option<String>,
+}
+
+impl PresenceConfig {
+    fn default_format() -> String {
+        "{state}".to_owned()
+    }
+
+    fn default_away_after() -> Duration {
+        Duration::from_secs(0)
+    }
+
+    fn default_mqtt() -> Option<MqttConfig> {
+        None
+    }
+
+    fn default_webhook() -> Option<String> {
+        None
+    }
+}
+
+/// Whether the session is idle.
+fn session_idle(c: &Connection) -> Result<bool> {
+    let m = Message::new_method_call(
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1/session/auto",
+        "org.freedesktop.DBus.Properties",
+        "Get",
+    ).block_error("presence", "failed to create D-Bus message")?
+        .append2("org.freedesktop.login1.Session", "IdleHint");
+    let r = c.send_with_reply_and_block(m, 1000)
+        .block_error("presence", "failed to get the session from logind")?;
+    let idle: Variant<Box<RefArg>> = r.get1()
+        .block_error("presence", "invalid idle hint from logind")?;
+    Ok(idle.0.as_u64().map_or(false, |idle| idle != 0))
+}
+
+/// Publish `state` to wherever configured, in the background as brokers and
+/// webhooks may take a while. Failures are only logged.
+fn publish(state: &'static str, mqtt: Option<MqttConfig>, webhook: Option<String>) {
+    thread::spawn(move || {
+        if let Some(mqtt) = mqtt {
+            if let Err(error) = mqtt.publish(state) {
+                warn!("failed to publish presence: {:?}", error);
+            }
+        }
+        if let Some(url) = webhook {
+            let host = read_file("presence", Path::new("/proc/sys/kernel/hostname")).unwrap_or_default();
+            let body = json!({ "state": state, "host": host }).to_string();
+            let request = http::Request::post(&url, &body).with_header("Content-Type", "application/json");
+            if let Err(error) = request.send() {
+                warn!("failed to send presence to webhook: {:?}", error);
+            }
+        }
+    });
+}
+
+impl ConfigBlock for Presence {
+    type Config = PresenceConfig;
+
+    fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        if block_config.mqtt.is_none() && block_config.webhook.is_none() {
+            return Err(BlockError(
+                "presence".to_owned(),
+                "nowhere to publish to, set `mqtt` or `webhook`".to_owned(),
+            ));
+        }
+        let dbus_conn = Connection::get_private(BusType::System)
+            .block_error("presence", "failed to connect to the system bus")?;
+        dbus_manager::subscribe(
+            BusType::System,
+            "type='signal',interface='org.freedesktop.DBus.Properties',member='PropertiesChanged',path_namespace='/org/freedesktop/login1/session'",
+            &id,
+            &send,
+        )?;
+
+        Ok(Presence {
+            output: ButtonWidget::new(config, &id).with_icon("presence"),
+            id,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("presence", "Invalid format specified for presence")?,
+            away_after: block_config.away_after,
+            mqtt: block_config.mqtt,
+            webhook: block_config.webhook,
+            manual: None,
+            idle_since: None,
+            published: None,
+            dbus_conn,
+            tx_update_request: send,
+        })
+    }
+}
+
+impl Block for Presence {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let now = Instant::now();
+        self.idle_since = if session_idle(&self.dbus_conn)? {
+            Some(self.idle_since.unwrap_or(now))
+        } else {
+            None
+        };
+        let idle_for = self.idle_since.map(|since| now.duration_since(since));
+        let automatic = match idle_for {
+            Some(idle_for) if idle_for >= self.away_after => "away",
+            _ => "busy",
+        };
+        let state = self.manual.unwrap_or(automatic);
+
+        if self.published != Some(state) {
+            publish(state, self.mqtt.clone(), self.webhook.clone());
+            self.published = Some(state);
+        }
+
+        let mut values = HashMap::new();
+        values.insert("{state}", Value::Text(state.to_owned()));
+        self.output.set_text(self.format.render_values(&values)?);
+        self.output.set_state(if self.manual.is_some() { State::Info } else { State::Idle });
+
+        // Idle long enough to count as away by then
+        Ok(match idle_for {
+            Some(idle_for) if idle_for < self.away_after => Some(self.away_after - idle_for),
+            _ => None,
+        })
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.output]
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if !event.name.as_ref().map_or(false, |name| name == &self.id) {
+            return Ok(());
+        }
+        // Left clicks go from automatic to busy to away and back, right
+        // clicks back to automatic
+        self.manual = match (&event.button, self.manual) {
+            (&MouseButton::Left, None) => Some("busy"),
+            (&MouseButton::Left, Some("busy")) => Some("away"),
+            (&MouseButton::Left, Some(_)) | (&MouseButton::Right, _) => None,
+            _ => return Ok(()),
+        };
+        self.tx_update_request.send(Task {
+            id: self.id.clone(),
+            update_time: Instant::now(),
+        });
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
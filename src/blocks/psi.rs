@@ -0,0 +1,177 @@
+//! A block for the pressure stall information of the CPU, memory and IO, the
+//! share of time tasks had to wait for them.
+//!
+//! Unlike the load average, which counts tasks no matter whether they get
+//! to run, pressure tells how much work was held up, see `pressure`.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Unit, Value};
+use pressure;
+use scheduler::Task;
+use widget::{I3BarWidget, State, Thresholds};
+use widgets::text::TextWidget;
+
+/// Each resource, with its placeholders for the averages over 10 and 60
+/// seconds and its metric.
+const RESOURCES: &[(&str, &str, &str, &str)] = &[
+    ("cpu", "{cpu}", "{cpu_avg60}", "psi_cpu_percent"),
+    ("memory", "{memory}", "{memory_avg60}", "psi_memory_percent"),
+    ("io", "{io}", "{io_avg60}", "psi_io_percent"),
+];
+
+/// How bad a state is, to pick the worst of the resources.
+fn severity(state: State) -> u8 {
+    match state {
+        State::Idle => 0,
+        State::Good => 1,
+        State::Info => 2,
+        State::Warning => 3,
+        State::Critical => 4,
+    }
+}
+
+/// Shows the pressure on each resource, colored by the one under the most,
+/// see `PsiConfig`.
+pub struct Psi {
+    id: String,
+    text: TextWidget,
+    format: FormatTemplate,
+    interval: Duration,
+    /// In the order of `RESOURCES`
+    thresholds: Vec<Thresholds>,
+    /// What the last update measured, see `Block::metrics`
+    metrics: Vec<(&'static str, f64)>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PsiConfig {
+    /// Update interval in seconds
+    #[serde(default = "PsiConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{cpu}`, `{memory}` and `{io}` are available, each
+    /// also as `{cpu_avg60}` and so on
+    #[serde(default = "PsiConfig::default_format")]
+    pub format: String,
+
+    /// Thresholds for the share of time some tasks waited for the CPU, over
+    /// the last 10 seconds
+    #[serde(default = "PsiConfig::default_cpu")]
+    pub cpu: Thresholds,
+
+    /// Like `cpu`, for memory
+    #[serde(default = "PsiConfig::default_memory")]
+    pub memory: Thresholds,
+
+    /// Like `cpu`, for IO
+    #[serde(default = "PsiConfig::default_io")]
+    pub io: Thresholds,
+}
+
+impl PsiConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn default_format() -> String {
+        "{cpu:02} {memory:02} {io:02}".to_owned()
+    }
+
+    fn default_cpu() -> Thresholds {
+        Thresholds {
+            good: None,
+            info: None,
+            warning: Some(40.),
+            critical: Some(80.),
+            reversed: false,
+        }
+    }
+
+    fn default_memory() -> Thresholds {
+        Thresholds {
+            good: None,
+            info: None,
+            warning: Some(10.),
+            critical: Some(30.),
+            reversed: false,
+        }
+    }
+
+    fn default_io() -> Thresholds {
+        Thresholds {
+            good: None,
+            info: None,
+            warning: Some(20.),
+            critical: Some(50.),
+            reversed: false,
+        }
+    }
+}
+
+impl ConfigBlock for Psi {
+    type Config = PsiConfig;
+
+    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        if !Path::new("/proc/pressure").exists() {
+            return Err(BlockError(
+                "psi".to_owned(),
+                "no pressure stall information, it needs Linux 4.20 or later with CONFIG_PSI".to_owned(),
+            ));
+        }
+        Ok(Psi {
+            id: Uuid::new_v4().simple().to_string(),
+            text: TextWidget::new(config).with_icon("psi"),
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("psi", "Invalid format specified for psi")?,
+            interval: block_config.interval,
+            thresholds: vec![block_config.cpu, block_config.memory, block_config.io],
+            metrics: Vec::new(),
+        })
+    }
+}
+
+impl Block for Psi {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let mut values = HashMap::new();
+        let mut state = State::Idle;
+        self.metrics.clear();
+        for (&(resource, key, key_avg60, metric), thresholds) in RESOURCES.iter().zip(&self.thresholds) {
+            let pressure = pressure::system("psi", resource)?;
+            let resource_state = thresholds.state(pressure.some.avg10);
+            if severity(resource_state) > severity(state) {
+                state = resource_state;
+            }
+            values.insert(key, Value::Number(pressure.some.avg10, Unit::Percents));
+            values.insert(key_avg60, Value::Number(pressure.some.avg60, Unit::Percents));
+            self.metrics.push((metric, pressure.some.avg10));
+        }
+
+        self.text.set_text(self.format.render_values(&values)?);
+        self.text.set_state(state);
+
+        Ok(Some(self.interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn metrics(&self) -> Vec<(&'static str, f64)> {
+        self.metrics.clone()
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
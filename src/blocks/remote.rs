@@ -0,0 +1,239 @@
+//! A block for the load, memory and disk use of a remote host, read over
+//! SSH, as a small dashboard for a home server.
+//!
+//! The connection is kept open between updates as an SSH control master, so
+//! each update only costs running a few commands on the host. The host has
+//! to let in the user without a password, e.g. by a key in the SSH agent.
+
+use std::collections::HashMap;
+use std::fs;
+use std::process::Command;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Unit, Value};
+use scheduler::Task;
+use util::{shell_quote, xdg_dir};
+use widget::{I3BarWidget, State};
+use widgets::text::TextWidget;
+
+/// What the host reported.
+#[derive(Debug, Clone)]
+struct Stats {
+    /// The load average over the last minute
+    load: String,
+    /// Memory and disk in use, in percent
+    memory: f64,
+    disk: f64,
+}
+
+/// The percentage of memory in use, from the `MemTotal` and `MemAvailable`
+/// lines of `/proc/meminfo`.
+fn memory_used(lines: &[&str]) -> Option<f64> {
+    let field = |name: &str| {
+        lines
+            .iter()
+            .find(|line| line.starts_with(name))
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|value| value.parse::<f64>().ok())
+    };
+    let total = field("MemTotal:")?;
+    let available = field("MemAvailable:")?;
+    Some((total - available) / total.max(1.) * 100.)
+}
+
+/// The percentage of disk space in use, from the output of `df -P`. Space
+/// kept back for root counts as used, like `df` does.
+fn disk_used(lines: &[&str]) -> Option<f64> {
+    let fields: Vec<f64> = lines
+        .last()?
+        .split_whitespace()
+        .skip(2)
+        .take(2)
+        .filter_map(|field| field.parse().ok())
+        .collect();
+    if fields.len() == 2 {
+        Some(fields[0] / (fields[0] + fields[1]).max(1.) * 100.)
+    } else {
+        None
+    }
+}
+
+/// Run the commands on `host` over the shared connection, and read what
+/// they print.
+fn fetch(host: &str, disk: &str) -> Result<Stats> {
+    let dir = xdg_dir("XDG_RUNTIME_DIR", ".cache");
+    fs::create_dir_all(&dir).block_error("remote", "failed to create a directory for SSH connections")?;
+    let control_path = format!("ControlPath={}", dir.join("ssh-%C").to_string_lossy());
+    let command = format!(
+        "cat /proc/loadavg && grep -E '^(MemTotal|MemAvailable):' /proc/meminfo && df -Pk {}",
+        shell_quote(disk)
+    );
+    let output = Command::new("ssh")
+        .args(&[
+            "-o", "BatchMode=yes",
+            "-o", "ConnectTimeout=10",
+            "-o", "ControlMaster=auto",
+            "-o", &control_path,
+            "-o", "ControlPersist=10m",
+            host,
+            &command,
+        ])
+        .output()
+        .block_error("remote", "failed to run ssh")?;
+    if !output.status.success() {
+        return Err(BlockError("remote".to_owned(), format!("failed to reach {}", host)));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    let load = lines
+        .first()
+        .and_then(|line| line.split_whitespace().next())
+        .block_error("remote", "no load average from the host")?;
+    Ok(Stats {
+        load: load.to_owned(),
+        memory: memory_used(&lines).block_error("remote", "no memory usage from the host")?,
+        disk: disk_used(&lines).block_error("remote", "no disk usage from the host")?,
+    })
+}
+
+/// Shows how busy a remote host is, see `RemoteConfig`.
+pub struct Remote {
+    id: String,
+    text: TextWidget,
+    format: FormatTemplate,
+    host: String,
+    /// What the fetching thread got last, `None` if the host couldn't be
+    /// reached
+    stats: Arc<Mutex<Option<Option<Stats>>>>,
+    /// Dropped along with the block, which ends the fetching thread
+    _stop: mpsc::Sender<()>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteConfig {
+    /// The host, as given to `ssh`, e.g. `user@server` or an alias from the
+    /// SSH config
+    pub host: String,
+
+    /// Update interval in seconds
+    #[serde(default = "RemoteConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{host}`, `{load}`, `{memory}` and `{disk}` are
+    /// available
+    #[serde(default = "RemoteConfig::default_format")]
+    pub format: String,
+
+    /// The path on the host to show the disk use of
+    #[serde(default = "RemoteConfig::default_disk")]
+    pub disk: String,
+}
+
+impl RemoteConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_format() -> String {
+        "{host} {load} {memory:02} {disk:02}".to_owned()
+    }
+
+    fn default_disk() -> String {
+        "/".to_owned()
+    }
+}
+
+impl ConfigBlock for Remote {
+    type Config = RemoteConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        let stats = Arc::new(Mutex::new(None));
+
+        // Reaching the host may take a while, which the bar shouldn't wait
+        // for
+        let (shared, host, disk, interval) = (stats.clone(), block_config.host.clone(), block_config.disk, block_config.interval);
+        let (id_fetch, tx) = (id.clone(), tx_update_request);
+        let (stop, rx_stop) = mpsc::channel();
+        thread::spawn(move || loop {
+            let result = match fetch(&host, &disk) {
+                Ok(stats) => Some(stats),
+                Err(error) => {
+                    debug!("{}", error);
+                    None
+                }
+            };
+            match shared.lock() {
+                Ok(mut stats) => *stats = Some(result),
+                Err(_) => return,
+            }
+            tx.send(Task {
+                id: id_fetch.clone(),
+                update_time: Instant::now(),
+            });
+            // Nothing is ever sent, the block going away disconnects it
+            if let Err(RecvTimeoutError::Disconnected) = rx_stop.recv_timeout(interval) {
+                return;
+            }
+        });
+
+        Ok(Remote {
+            id,
+            text: TextWidget::new(config).with_icon("remote"),
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("remote", "Invalid format specified for remote")?,
+            host: block_config.host,
+            stats,
+            _stop: stop,
+        })
+    }
+}
+
+impl Block for Remote {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let stats = self.stats
+            .lock()
+            .internal_error("remote", "failed to lock stats")?
+            .clone();
+        match stats {
+            Some(Some(stats)) => {
+                let mut values = HashMap::new();
+                values.insert("{host}", Value::Text(self.host.clone()));
+                values.insert("{load}", Value::Text(stats.load));
+                values.insert("{memory}", Value::Number(stats.memory, Unit::Percents));
+                values.insert("{disk}", Value::Number(stats.disk, Unit::Percents));
+                self.text.set_text(self.format.render_values(&values)?);
+                self.text.set_state(State::Idle);
+            }
+            // Down, or can't be reached
+            Some(None) => {
+                self.text.set_text(format!("{} unreachable", self.host));
+                self.text.set_state(State::Critical);
+            }
+            None => self.text.set_text(self.host.clone()),
+        }
+
+        // Updated by the fetching thread
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
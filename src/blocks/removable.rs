@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::path::Path as FsPath;
+use std::thread;
+use std::time::Duration;
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use blocks::dbus::arg::{RefArg, Variant};
+use blocks::dbus::{BusType, Connection, Message, Path};
+use config::Config;
+use dbus_manager;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Unit, Value};
+use input::{I3BarEvent, MouseButton};
+use notifications::{self, Urgency};
+use scheduler::Task;
+use widget::{I3BarWidget, State};
+use widgets::list::ListWidget;
+
+use nix::sys::statvfs::statvfs;
+
+const UDISKS_NAME: &str = "org.freedesktop.UDisks2";
+const UDISKS_PATH: &str = "/org/freedesktop/UDisks2";
+const BLOCK_INTERFACE: &str = "org.freedesktop.UDisks2.Block";
+const FILESYSTEM_INTERFACE: &str = "org.freedesktop.UDisks2.Filesystem";
+const DRIVE_INTERFACE: &str = "org.freedesktop.UDisks2.Drive";
+
+/// Unmounting waits for everything to be written to the drive
+const UNMOUNT_TIMEOUT_MILLIS: i32 = 60 * 1000;
+
+type Properties = HashMap<String, Variant<Box<RefArg>>>;
+type Objects = HashMap<Path<'static>, HashMap<String, Properties>>;
+
+/// A mounted filesystem on a removable drive.
+#[derive(Clone)]
+struct Mounted {
+    /// The UDisks2 object of the filesystem
+    filesystem: Path<'static>,
+    /// The UDisks2 object of the drive, powered off after unmounting
+    drive: Path<'static>,
+    label: String,
+    mount_point: String,
+}
+
+/// Lists the mounted filesystems of removable drives, and unmounts them on a
+/// click, see `RemovableConfig`.
+pub struct Removable {
+    id: String,
+    list: ListWidget,
+    mounted: Vec<Mounted>,
+    format: FormatTemplate,
+    interval: Duration,
+    power_off: bool,
+    dbus_conn: Connection,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RemovableConfig {
+    /// Update interval in seconds, for the free space. Drives coming and
+    /// going show right away.
+    #[serde(default = "RemovableConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{label}`, `{mount}`, `{free}` and `{size}` are
+    /// available
+    #[serde(default = "RemovableConfig::default_format")]
+    pub format: String,
+
+    /// Power off drives after unmounting them, if they support it
+    #[serde(default = "RemovableConfig::default_power_off")]
+    pub power_off: bool,
+}
+
+impl RemovableConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(30)
+    }
+
+    fn default_format() -> String {
+        "{label} {free}".to_owned()
+    }
+
+    fn default_power_off() -> bool {
+        true
+    }
+}
+
+/// A string property of an object.
+fn string_property(properties: &Properties, name: &str) -> Option<String> {
+    properties
+        .get(name)
+        .and_then(|value| value.0.as_str())
+        .map(|value| value.to_owned())
+}
+
+fn bool_property(properties: &Properties, name: &str) -> bool {
+    properties
+        .get(name)
+        .and_then(|value| value.0.as_u64())
+        .map_or(false, |value| value != 0)
+}
+
+/// A byte string property, like the paths UDisks2 passes around, without
+/// its trailing NUL.
+fn bytes_to_string(value: &RefArg) -> Option<String> {
+    let bytes: Vec<u8> = value
+        .as_iter()?
+        .filter_map(|byte| byte.as_u64())
+        .map(|byte| byte as u8)
+        .take_while(|&byte| byte != 0)
+        .collect();
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Where the filesystem is mounted, if it is.
+fn mount_point(filesystem: &Properties) -> Option<String> {
+    let mount_points = filesystem.get("MountPoints")?;
+    let mut mount_points = mount_points.0.as_iter()?;
+    mount_points.next().and_then(bytes_to_string)
+}
+
+/// Whether the user may unplug the drive, as opposed to the internal ones.
+fn is_removable(drive: &Properties) -> bool {
+    bool_property(drive, "Removable") || bool_property(drive, "MediaRemovable") || bool_property(drive, "Ejectable")
+        || string_property(drive, "ConnectionBus").map_or(false, |bus| bus == "usb")
+}
+
+fn managed_objects(c: &Connection) -> Result<Objects> {
+    let m = Message::new_method_call(UDISKS_NAME, UDISKS_PATH, "org.freedesktop.DBus.ObjectManager", "GetManagedObjects")
+        .block_error("removable", "failed to create D-Bus message")?;
+    let r = c.send_with_reply_and_block(m, 1000)
+        .block_error("removable", "failed to get the drives from UDisks2")?;
+    r.get1().block_error("removable", "invalid drives from UDisks2")
+}
+
+/// The mounted filesystems of removable drives, by mount point.
+fn mounted_filesystems(objects: &Objects) -> Vec<Mounted> {
+    let mut mounted = Vec::new();
+    for (path, interfaces) in objects {
+        let (block, filesystem) = match (interfaces.get(BLOCK_INTERFACE), interfaces.get(FILESYSTEM_INTERFACE)) {
+            (Some(block), Some(filesystem)) => (block, filesystem),
+            _ => continue,
+        };
+        if bool_property(block, "HintIgnore") || bool_property(block, "HintSystem") {
+            continue;
+        }
+        let mount_point = match mount_point(filesystem) {
+            Some(mount_point) => mount_point,
+            None => continue,
+        };
+        let drive = match block.get("Drive").and_then(|drive| drive.0.as_str()) {
+            Some(drive) => Path::from(drive.to_owned()),
+            None => continue,
+        };
+        let removable = objects
+            .get(&drive)
+            .and_then(|interfaces| interfaces.get(DRIVE_INTERFACE))
+            .map_or(false, |drive| is_removable(drive));
+        if !removable {
+            continue;
+        }
+
+        // Unlabeled filesystems go by their device, e.g. sdb1
+        let label = string_property(block, "IdLabel")
+            .and_then(|label| if label.is_empty() { None } else { Some(label) })
+            .unwrap_or_else(|| path.rsplit('/').next().unwrap_or("").to_owned());
+        mounted.push(Mounted {
+            filesystem: path.clone(),
+            drive,
+            label,
+            mount_point,
+        });
+    }
+    mounted.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+    mounted
+}
+
+/// Unmount the filesystem, then power off its drive if asked to. Runs on a
+/// connection of its own, as unmounting may take a while.
+fn unmount(mounted: &Mounted, power_off: bool) -> Result<()> {
+    let c = Connection::get_private(BusType::System)
+        .block_error("removable", "failed to connect to the system bus")?;
+    let options: HashMap<&str, Variant<bool>> = HashMap::new();
+    let m = Message::new_method_call(UDISKS_NAME, mounted.filesystem.clone(), FILESYSTEM_INTERFACE, "Unmount")
+        .block_error("removable", "failed to create D-Bus message")?
+        .append1(options.clone());
+    c.send_with_reply_and_block(m, UNMOUNT_TIMEOUT_MILLIS)
+        .map_err(|error| BlockError("removable".to_owned(), error.message().unwrap_or("failed to unmount").to_owned()))?;
+
+    if power_off {
+        let m = Message::new_method_call(UDISKS_NAME, mounted.drive.clone(), DRIVE_INTERFACE, "PowerOff")
+            .block_error("removable", "failed to create D-Bus message")?
+            .append1(options);
+        // Other filesystems of the drive may still be mounted, or it can't be
+        // powered off at all. It's safe to unplug either way.
+        if let Err(error) = c.send_with_reply_and_block(m, 10 * 1000) {
+            debug!("not powering off {}: {:?}", mounted.label, error);
+        }
+    }
+    Ok(())
+}
+
+impl ConfigBlock for Removable {
+    type Config = RemovableConfig;
+
+    fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        let dbus_conn = Connection::get_private(BusType::System)
+            .block_error("removable", "failed to connect to the system bus")?;
+
+        // Drives coming and going, and filesystems being mounted
+        let rules = [
+            format!("type='signal',sender='{}',interface='org.freedesktop.DBus.ObjectManager'", UDISKS_NAME),
+            format!(
+                "type='signal',sender='{}',interface='org.freedesktop.DBus.Properties',member='PropertiesChanged',path_namespace='{}'",
+                UDISKS_NAME,
+                UDISKS_PATH
+            ),
+        ];
+        for rule in &rules {
+            dbus_manager::subscribe(BusType::System, rule, &id, &send)?;
+        }
+
+        Ok(Removable {
+            id,
+            list: ListWidget::new(config).with_icon("eject"),
+            mounted: Vec::new(),
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("removable", "Invalid format specified for removable")?,
+            interval: block_config.interval,
+            power_off: block_config.power_off,
+            dbus_conn,
+        })
+    }
+}
+
+impl Block for Removable {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        self.mounted = mounted_filesystems(&managed_objects(&self.dbus_conn)?);
+
+        let mut items = Vec::new();
+        for mounted in &self.mounted {
+            let (free, size) = match statvfs(FsPath::new(&mounted.mount_point)) {
+                Ok(stat) => (
+                    stat.blocks_available() * stat.block_size(),
+                    stat.blocks() * stat.fragment_size(),
+                ),
+                Err(_) => (0, 0),
+            };
+            let mut values = HashMap::new();
+            values.insert("{label}", Value::Text(mounted.label.clone()));
+            values.insert("{mount}", Value::Text(mounted.mount_point.clone()));
+            values.insert("{free}", Value::Number(free as f64, Unit::Bytes));
+            values.insert("{size}", Value::Number(size as f64, Unit::Bytes));
+            items.push((self.format.render_values(&values)?, State::Idle));
+        }
+        self.list.set_items(items);
+
+        Ok(Some(self.interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        self.list.view()
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if event.button != MouseButton::Left {
+            return Ok(());
+        }
+        let mounted = match self.list.clicked_item(event, &self.id, 0).and_then(|index| self.mounted.get(index)) {
+            Some(mounted) => mounted.clone(),
+            None => return Ok(()),
+        };
+
+        // UDisks2 tells when it's done, which updates the block
+        let power_off = self.power_off;
+        thread::spawn(move || match unmount(&mounted, power_off) {
+            Ok(()) => notifications::notify(&mounted.label, "can be removed now", Urgency::Low),
+            Err(error) => {
+                let message = match error {
+                    BlockError(_, message) => message,
+                    error => format!("{:?}", error),
+                };
+                notifications::notify(&format!("{} can't be removed", mounted.label), &message, Urgency::Normal);
+            }
+        });
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,231 @@
+//! A block for the screen rotation of convertible laptops.
+//!
+//! The orientation is read from the accelerometer through
+//! [iio-sensor-proxy](https://gitlab.freedesktop.org/hadess/iio-sensor-proxy),
+//! and the screen is rotated to follow it by running a command, so that this
+//! works with sway as well as with `xrandr`.
+
+use std::collections::HashMap;
+use std::env;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use blocks::dbus::arg::{RefArg, Variant};
+use blocks::dbus::{BusType, Connection, Message};
+use config::Config;
+use dbus_manager;
+use errors::*;
+use formatting::{FormatTemplate, Value};
+use input::{I3BarEvent, MouseButton};
+use scheduler::Task;
+use widget::{I3BarWidget, State};
+use widgets::button::ButtonWidget;
+
+const SENSOR_PROXY_NAME: &str = "net.hadess.SensorProxy";
+const SENSOR_PROXY_PATH: &str = "/net/hadess/SensorProxy";
+
+/// An orientation of iio-sensor-proxy, along with the rotation of the screen
+/// that matches it, as `xrandr --rotate` and sway's `transform` call it.
+/// `undefined`, e.g. lying flat on a table, has none.
+const ORIENTATIONS: &[(&str, &str, &str)] = &[
+    ("normal", "normal", "normal"),
+    ("bottom-up", "inverted", "180"),
+    ("left-up", "left", "270"),
+    ("right-up", "right", "90"),
+];
+
+/// Shows the orientation of the laptop, and rotates the screen to follow it
+/// unless locked, see `RotationConfig`.
+pub struct Rotation {
+    id: String,
+    output: ButtonWidget,
+    format: FormatTemplate,
+    command: String,
+    screen: String,
+    locked: bool,
+    /// The orientation the screen was last rotated to
+    applied: Option<String>,
+    dbus_conn: Connection,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RotationConfig {
+    /// Format override, `{orientation}` is available
+    #[serde(default = "RotationConfig::default_format")]
+    pub format: String,
+
+    /// Shell command to rotate the screen. `{output}` is replaced by `output`,
+    /// `{rotation}` by the rotation as `xrandr --rotate` takes it and
+    /// `{transform}` as sway's `output transform` does.
+    #[serde(default = "RotationConfig::default_command")]
+    pub command: String,
+
+    /// The built-in screen, as the command knows it
+    #[serde(default = "RotationConfig::default_output")]
+    pub output: String,
+
+    /// Whether rotation is locked from the start
+    #[serde(default = "RotationConfig::default_locked")]
+    pub locked: bool,
+}
+
+impl RotationConfig {
+    fn default_format() -> String {
+        "{orientation}".to_owned()
+    }
+
+    fn default_command() -> String {
+        "swaymsg output {output} transform {transform}".to_owned()
+    }
+
+    fn default_output() -> String {
+        "eDP-1".to_owned()
+    }
+
+    fn default_locked() -> bool {
+        false
+    }
+}
+
+fn sensor_property(c: &Connection, name: &str) -> Result<Variant<Box<RefArg>>> {
+    let m = Message::new_method_call(
+        SENSOR_PROXY_NAME,
+        SENSOR_PROXY_PATH,
+        "org.freedesktop.DBus.Properties",
+        "Get",
+    ).block_error("rotation", "failed to create D-Bus message")?
+        .append2(SENSOR_PROXY_NAME, name);
+    let r = c.send_with_reply_and_block(m, 1000)
+        .block_error("rotation", "failed to get a property from iio-sensor-proxy")?;
+    r.get1()
+        .block_error("rotation", &format!("invalid property {} from iio-sensor-proxy", name))
+}
+
+impl Rotation {
+    /// Rotate the screen to match `orientation`, unless it already does.
+    fn rotate(&mut self, orientation: &str) -> Result<()> {
+        if self.applied.as_ref().map_or(false, |applied| applied == orientation) {
+            return Ok(());
+        }
+        let (rotation, transform) = match ORIENTATIONS.iter().find(|&&(name, _, _)| name == orientation) {
+            Some(&(_, rotation, transform)) => (rotation, transform),
+            None => return Ok(()),
+        };
+
+        let command = self.command
+            .replace("{output}", &self.screen)
+            .replace("{rotation}", rotation)
+            .replace("{transform}", transform);
+        let status = Command::new(env::var("SHELL").unwrap_or_else(|_| "sh".to_owned()))
+            .args(&["-c", &command])
+            .status()
+            .block_error("rotation", "failed to run rotation command")?;
+        if !status.success() {
+            return Err(BlockError(
+                "rotation".to_owned(),
+                format!("rotation command '{}' failed", command),
+            ));
+        }
+        self.applied = Some(orientation.to_owned());
+        Ok(())
+    }
+}
+
+impl ConfigBlock for Rotation {
+    type Config = RotationConfig;
+
+    fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        let dbus_conn = Connection::get_private(BusType::System)
+            .block_error("rotation", "failed to connect to the system bus")?;
+        let has_accelerometer = sensor_property(&dbus_conn, "HasAccelerometer")?
+            .0
+            .as_u64()
+            .map_or(false, |has_accelerometer| has_accelerometer != 0);
+        if !has_accelerometer {
+            return Err(BlockError("rotation".to_owned(), "no accelerometer found".to_owned()));
+        }
+        // The orientation is only updated while claimed, until the connection
+        // closes
+        let m = Message::new_method_call(SENSOR_PROXY_NAME, SENSOR_PROXY_PATH, SENSOR_PROXY_NAME, "ClaimAccelerometer")
+            .block_error("rotation", "failed to create D-Bus message")?;
+        dbus_conn
+            .send_with_reply_and_block(m, 1000)
+            .block_error("rotation", "failed to claim the accelerometer")?;
+        dbus_manager::subscribe(
+            BusType::System,
+            &format!(
+                "type='signal',interface='org.freedesktop.DBus.Properties',member='PropertiesChanged',path='{}'",
+                SENSOR_PROXY_PATH
+            ),
+            &id,
+            &send,
+        )?;
+
+        Ok(Rotation {
+            output: ButtonWidget::new(config, &id),
+            id,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("rotation", "Invalid format specified for rotation")?,
+            command: block_config.command,
+            screen: block_config.output,
+            locked: block_config.locked,
+            applied: None,
+            dbus_conn,
+            tx_update_request: send,
+        })
+    }
+}
+
+impl Block for Rotation {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let orientation = sensor_property(&self.dbus_conn, "AccelerometerOrientation")?
+            .0
+            .as_str()
+            .unwrap_or("undefined")
+            .to_owned();
+        if !self.locked {
+            self.rotate(&orientation)?;
+        }
+
+        let mut values = HashMap::new();
+        values.insert("{orientation}", Value::Text(orientation));
+        self.output.set_text(self.format.render_values(&values)?);
+        if self.locked {
+            self.output.set_icon("rotation_locked");
+            self.output.set_state(State::Info);
+        } else {
+            self.output.set_icon("rotation_unlocked");
+            self.output.set_state(State::Idle);
+        }
+
+        // The sensor tells about changes
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.output]
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if event.name.as_ref().map_or(false, |name| name == &self.id) && event.button == MouseButton::Left {
+            self.locked = !self.locked;
+            // Unlocking catches up with where the laptop was turned meanwhile
+            self.tx_update_request.send(Task {
+                id: self.id.clone(),
+                update_time: Instant::now(),
+            });
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,193 @@
+//! A block counting how long the session was in use today.
+//!
+//! Whether the session is in use is told by the `IdleHint` of its
+//! systemd-logind session, which swayidle sets when started with
+//! `idlehint <timeout>`, as do GNOME and KDE. The time is kept across
+//! restarts of the bar, and starts over at midnight.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use chrono::Local;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use blocks::dbus::arg::{RefArg, Variant};
+use blocks::dbus::{BusType, Connection, Message};
+use config::Config;
+use dbus_manager;
+use de::{deserialize_duration, deserialize_opt_duration};
+use errors::*;
+use formatting::{FormatTemplate, Value};
+use scheduler::Task;
+use state::BlockState;
+use widget::{I3BarWidget, State};
+use widgets::text::TextWidget;
+
+/// The session of the bar, found by logind through its process or
+/// `XDG_SESSION_ID`
+const SESSION_PATH: &str = "/org/freedesktop/login1/session/auto";
+
+/// What is kept across restarts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Usage {
+    /// The day counted, like `2018-06-30`
+    date: String,
+    seconds: u64,
+}
+
+/// Shows how long the session was in use today, see `ScreenTimeConfig`.
+pub struct ScreenTime {
+    id: String,
+    text: TextWidget,
+    format: FormatTemplate,
+    budget: Option<Duration>,
+    interval: Duration,
+    usage: Usage,
+    /// When the time was last counted, and whether the session was in use
+    last: Instant,
+    active: bool,
+    dbus_conn: Connection,
+    state: BlockState,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ScreenTimeConfig {
+    /// Update interval in seconds. Going idle and coming back show right
+    /// away.
+    #[serde(default = "ScreenTimeConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{time}` is available
+    #[serde(default = "ScreenTimeConfig::default_format")]
+    pub format: String,
+
+    /// Time in seconds per day after which the block turns critical
+    #[serde(default, deserialize_with = "deserialize_opt_duration")]
+    pub budget: Option<Duration>,
+}
+
+impl ScreenTimeConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_format() -> String {
+        "{time}".to_owned()
+    }
+}
+
+fn today() -> String {
+    Local::now().format("%Y-%m-%d").to_string()
+}
+
+/// Whether the session is in use.
+fn session_active(c: &Connection) -> Result<bool> {
+    let m = Message::new_method_call(
+        "org.freedesktop.login1",
+        SESSION_PATH,
+        "org.freedesktop.DBus.Properties",
+        "Get",
+    ).block_error("screen_time", "failed to create D-Bus message")?
+        .append2("org.freedesktop.login1.Session", "IdleHint");
+    let r = c.send_with_reply_and_block(m, 1000)
+        .block_error("screen_time", "failed to get the session from logind")?;
+    let idle: Variant<Box<RefArg>> = r.get1()
+        .block_error("screen_time", "invalid idle hint from logind")?;
+    Ok(idle.0.as_u64().map_or(true, |idle| idle == 0))
+}
+
+impl ConfigBlock for ScreenTime {
+    type Config = ScreenTimeConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        ScreenTime::new_with_state(block_config, config, tx_update_request, BlockState::new("screen_time"))
+    }
+
+    fn new_with_state(
+        block_config: Self::Config,
+        config: Config,
+        tx_update_request: Sender<Task>,
+        state: BlockState,
+    ) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        let dbus_conn = Connection::get_private(BusType::System)
+            .block_error("screen_time", "failed to connect to the system bus")?;
+        let active = session_active(&dbus_conn)?;
+        // The session's own path differs from `SESSION_PATH`
+        dbus_manager::subscribe(
+            BusType::System,
+            "type='signal',interface='org.freedesktop.DBus.Properties',member='PropertiesChanged',path_namespace='/org/freedesktop/login1/session'",
+            &id,
+            &tx_update_request,
+        )?;
+
+        let today = today();
+        let usage = match state.get::<Usage>() {
+            Some(ref usage) if usage.date == today => usage.clone(),
+            _ => Usage {
+                date: today,
+                seconds: 0,
+            },
+        };
+
+        Ok(ScreenTime {
+            id,
+            text: TextWidget::new(config).with_icon("screen_time"),
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("screen_time", "Invalid format specified for screen_time")?,
+            budget: block_config.budget,
+            interval: block_config.interval,
+            usage,
+            last: Instant::now(),
+            active,
+            dbus_conn,
+            state,
+        })
+    }
+}
+
+impl Block for ScreenTime {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let now = Instant::now();
+        // Time in use counts up to now, going idle counts from now on
+        if self.active {
+            self.usage.seconds += now.duration_since(self.last).as_secs();
+            self.last = self.last + Duration::from_secs(now.duration_since(self.last).as_secs());
+        } else {
+            self.last = now;
+        }
+        self.active = session_active(&self.dbus_conn)?;
+
+        let today = today();
+        if self.usage.date != today {
+            self.usage = Usage {
+                date: today,
+                seconds: 0,
+            };
+        }
+        if let Err(error) = self.state.set(&self.usage) {
+            warn!("failed to save screen time: {:?}", error);
+        }
+
+        let minutes = self.usage.seconds / 60;
+        let mut values = HashMap::new();
+        values.insert("{time}", Value::Text(format!("{}:{:02}", minutes / 60, minutes % 60)));
+        self.text.set_text(self.format.render_values(&values)?);
+        let over_budget = self.budget
+            .map_or(false, |budget| self.usage.seconds >= budget.as_secs());
+        self.text.set_state(if over_budget { State::Critical } else { State::Idle });
+
+        Ok(Some(self.interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,60 @@
+use chan::Sender;
+use scheduler::Task;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use errors::*;
+use widget::{I3BarWidget, State};
+use widgets::text::TextWidget;
+
+use uuid::Uuid;
+
+pub struct Separator {
+    text: TextWidget,
+    id: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SeparatorConfig {
+    /// The glyph to show
+    #[serde(default = "SeparatorConfig::default_text")]
+    pub text: String,
+
+    /// Whose theme colors to use
+    #[serde(default = "SeparatorConfig::default_state")]
+    pub state: State,
+}
+
+impl SeparatorConfig {
+    fn default_text() -> String {
+        "|".to_owned()
+    }
+
+    fn default_state() -> State {
+        State::Idle
+    }
+}
+
+impl ConfigBlock for Separator {
+    type Config = SeparatorConfig;
+
+    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        Ok(Separator {
+            text: TextWidget::new(config)
+                .with_text(&block_config.text)
+                .with_state(block_config.state),
+            id: Uuid::new_v4().simple().to_string(),
+        })
+    }
+}
+
+impl Block for Separator {
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,187 @@
+//! A block for ending the session: it locks the screen, suspends or powers
+//! off, through systemd-logind.
+//!
+//! Locking asks logind to lock the session, which it passes on to the screen
+//! locker listening for it, like one run by `xss-lock`. Powering off has to
+//! be confirmed by a second click, and is off unless enabled, as a slip of
+//! the mouse shouldn't end all work.
+
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use blocks::dbus::{BusType, Connection, Message};
+use config::Config;
+use dbus_manager;
+use de::deserialize_duration;
+use errors::*;
+use input::{I3BarEvent, MouseButton};
+use scheduler::Task;
+use widget::{I3BarWidget, State};
+use widgets::button::ButtonWidget;
+
+const LOGIND_NAME: &str = "org.freedesktop.login1";
+const MANAGER_PATH: &str = "/org/freedesktop/login1";
+const SESSION_PATH: &str = "/org/freedesktop/login1/session/auto";
+
+/// Locks, suspends or powers off on a click, see `SessionConfig`.
+pub struct Session {
+    id: String,
+    output: ButtonWidget,
+    lock: bool,
+    suspend: bool,
+    poweroff: bool,
+    confirm: Duration,
+    /// When powering off was asked for, to be confirmed
+    armed: Option<Instant>,
+    dbus_conn: Rc<Connection>,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SessionConfig {
+    /// Whether a left click locks the screen
+    #[serde(default = "SessionConfig::default_lock")]
+    pub lock: bool,
+
+    /// Whether a middle click suspends
+    #[serde(default = "SessionConfig::default_suspend")]
+    pub suspend: bool,
+
+    /// Whether a right click, confirmed by another, powers off
+    #[serde(default = "SessionConfig::default_poweroff")]
+    pub poweroff: bool,
+
+    /// How long, in seconds, powering off waits to be confirmed
+    #[serde(default = "SessionConfig::default_confirm", deserialize_with = "deserialize_duration")]
+    pub confirm: Duration,
+}
+
+impl SessionConfig {
+    fn default_lock() -> bool {
+        true
+    }
+
+    fn default_suspend() -> bool {
+        true
+    }
+
+    fn default_poweroff() -> bool {
+        false
+    }
+
+    fn default_confirm() -> Duration {
+        Duration::from_secs(5)
+    }
+}
+
+impl Session {
+    /// Call `method` of logind, on the session or on the manager.
+    fn call(&self, path: &str, interface: &str, method: &str, interactive: bool) -> Result<()> {
+        let mut m = Message::new_method_call(LOGIND_NAME, path, interface, method)
+            .block_error("session", "failed to create D-Bus message")?;
+        // Methods of the manager ask whether to let the user authenticate,
+        // if policy wants it
+        if path == MANAGER_PATH {
+            m = m.append1(interactive);
+        }
+        self.dbus_conn
+            .send_with_reply_and_block(m, 5000)
+            .block_error("session", &format!("logind refused to {}", method))?;
+        Ok(())
+    }
+
+    fn is_armed(&self) -> bool {
+        self.armed.map_or(false, |armed| armed.elapsed() < self.confirm)
+    }
+}
+
+impl ConfigBlock for Session {
+    type Config = SessionConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+
+        Ok(Session {
+            output: ButtonWidget::new(config, &id).with_icon("session"),
+            id,
+            lock: block_config.lock,
+            suspend: block_config.suspend,
+            poweroff: block_config.poweroff,
+            confirm: block_config.confirm,
+            armed: None,
+            dbus_conn: dbus_manager::connection(BusType::System)?,
+            tx_update_request,
+        })
+    }
+}
+
+impl Block for Session {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        if self.is_armed() {
+            self.output.set_text("Power off?".to_owned());
+            self.output.set_state(State::Critical);
+        } else {
+            self.armed = None;
+            self.output.set_text(String::new());
+            self.output.set_state(State::Idle);
+        }
+
+        // Only changes with a click
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.output]
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if !event.name.as_ref().map_or(false, |name| name == &self.id) {
+            return Ok(());
+        }
+        // Any click but the one confirming calls off powering off
+        let confirmed = event.button == MouseButton::Right && self.is_armed();
+        self.armed = None;
+
+        let result = match event.button {
+            MouseButton::Left if self.lock => {
+                self.call(SESSION_PATH, "org.freedesktop.login1.Session", "Lock", false)
+            }
+            MouseButton::Middle if self.suspend => {
+                self.call(MANAGER_PATH, "org.freedesktop.login1.Manager", "Suspend", true)
+            }
+            MouseButton::Right if self.poweroff && confirmed => {
+                self.call(MANAGER_PATH, "org.freedesktop.login1.Manager", "PowerOff", true)
+            }
+            MouseButton::Right if self.poweroff => {
+                self.armed = Some(Instant::now());
+                let (id, tx, confirm) = (self.id.clone(), self.tx_update_request.clone(), self.confirm);
+                // Calls it off again, unless confirmed by then
+                thread::spawn(move || {
+                    thread::sleep(confirm);
+                    tx.send(Task {
+                        id,
+                        update_time: Instant::now(),
+                    });
+                });
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        // Denied by policy, say, which shouldn't bring down the bar
+        if let Err(error) = result {
+            error!("{:?}", error);
+        }
+        self.update()?;
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
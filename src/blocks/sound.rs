@@ -1,11 +1,11 @@
 #[cfg(feature = "pulseaudio")]
 use std::cmp::min;
 use std::cmp::max;
-use std::io::Read;
-use std::process::{Command, Stdio};
+use std::collections::HashMap;
+use std::process::Command;
+#[cfg(feature = "pulseaudio")]
 use std::thread;
 use std::time::{Duration, Instant};
-use std::ffi::OsStr;
 #[cfg(feature = "pulseaudio")]
 use std::rc::Rc;
 #[cfg(feature = "pulseaudio")]
@@ -13,15 +13,15 @@ use std::cell::RefCell;
 #[cfg(feature = "pulseaudio")]
 use std::sync::Mutex;
 #[cfg(feature = "pulseaudio")]
-use std::collections::HashMap;
-#[cfg(feature = "pulseaudio")]
 use std::ops::Deref;
 use chan::Sender;
 #[cfg(feature = "pulseaudio")]
 use chan::{async, sync};
 
-use scheduler::Task;
+use scheduler::{self, Task};
 use block::{Block, ConfigBlock};
+use blocks::dbus::{BusType, Connection, Message, Path};
+use blocks::dbus::arg::{RefArg, Variant};
 use config::Config;
 use errors::*;
 use widgets::button::ButtonWidget;
@@ -33,7 +33,7 @@ use pulse::mainloop::standard::Mainloop;
 #[cfg(feature = "pulseaudio")]
 use pulse::callbacks::ListResult;
 #[cfg(feature = "pulseaudio")]
-use pulse::context::{Context, flags, State as PulseState, introspect::SinkInfo, introspect::ServerInfo, subscribe::Facility, subscribe::Operation as SubscribeOperation, subscribe::subscription_masks};
+use pulse::context::{Context, flags, State as PulseState, introspect::CardInfo, introspect::SinkInfo, introspect::SinkInputInfo, introspect::ServerInfo, subscribe::Facility, subscribe::Operation as SubscribeOperation, subscribe::subscription_masks};
 #[cfg(feature = "pulseaudio")]
 use pulse::proplist::{properties, Proplist};
 #[cfg(feature = "pulseaudio")]
@@ -43,6 +43,32 @@ use pulse::volume::{ChannelVolumes, VOLUME_NORM, VOLUME_MAX};
 
 use uuid::Uuid;
 
+/// Below this percentage, the battery of a headset shows as a warning
+const HEADSET_LOW: u64 = 20;
+
+/// How long the profile shows after switching to it
+const PROFILE_SHOWN_SECS: u64 = 3;
+
+/// How often the battery of a headset is checked, as it doesn't cause any
+/// sound events
+const HEADSET_INTERVAL: u64 = 60;
+
+/// An application playing sound, with a volume of its own.
+#[derive(Debug, Clone)]
+struct App {
+    index: u32,
+    name: String,
+    volume: u32,
+    muted: bool,
+}
+
+/// A profile of the sound card, like A2DP or HSP/HFP for Bluetooth headsets.
+#[derive(Debug, Clone)]
+struct Profile {
+    name: String,
+    description: String,
+}
+
 trait SoundDevice {
     fn volume(&self) -> u32;
     fn muted(&self) -> bool;
@@ -51,6 +77,60 @@ trait SoundDevice {
     fn set_volume(&mut self, step: i32) -> Result<()>;
     fn toggle(&mut self) -> Result<()>;
     fn monitor(&mut self, id: String, tx_update_request: Sender<Task>) -> Result<()>;
+
+    /// The applications playing sound, in the order they started.
+    fn apps(&self) -> Vec<App> {
+        Vec::new()
+    }
+
+    fn set_app_volume(&mut self, _index: u32, _step: i32) -> Result<()> {
+        Err(BlockError("sound".into(), "applications need PulseAudio".into()))
+    }
+
+    fn toggle_app(&mut self, _index: u32) -> Result<()> {
+        Err(BlockError("sound".into(), "applications need PulseAudio".into()))
+    }
+
+    /// The Bluetooth address of the device, if it's a Bluetooth one.
+    fn bluetooth_address(&self) -> Option<String> {
+        None
+    }
+
+    /// The profiles of the card the device is on, with sound output, and
+    /// the name of the active one.
+    fn profiles(&self) -> (Vec<Profile>, Option<String>) {
+        (Vec::new(), None)
+    }
+
+    fn set_profile(&mut self, _name: &str) -> Result<()> {
+        Err(BlockError("sound".into(), "profiles need PulseAudio".into()))
+    }
+}
+
+/// The battery percentage BlueZ knows for the device at `address`, if any.
+/// Headsets tell theirs through the HFP indicator or GATT, both end up there.
+fn bluetooth_battery(address: &str) -> Result<Option<u64>> {
+    let c = Connection::get_private(BusType::System)
+        .block_error("sound", "failed to connect to the system bus")?;
+    let m = Message::new_method_call("org.bluez", "/", "org.freedesktop.DBus.ObjectManager", "GetManagedObjects")
+        .block_error("sound", "failed to create D-Bus message")?;
+    let r = c.send_with_reply_and_block(m, 1000)
+        .block_error("sound", "failed to get the devices from BlueZ")?;
+    let objects: HashMap<Path<'static>, HashMap<String, HashMap<String, Variant<Box<RefArg>>>>> = r.get1()
+        .block_error("sound", "invalid devices from BlueZ")?;
+
+    for interfaces in objects.values() {
+        let matches = interfaces.get("org.bluez.Device1")
+            .and_then(|device| device.get("Address"))
+            .and_then(|device_address| device_address.0.as_str())
+            .map_or(false, |device_address| device_address.eq_ignore_ascii_case(address));
+        if matches {
+            return Ok(interfaces.get("org.bluez.Battery1")
+                .and_then(|battery| battery.get("Percentage"))
+                .and_then(|percentage| percentage.0.as_u64()));
+        }
+    }
+    Ok(None)
 }
 
 struct AlsaSoundDevice {
@@ -133,35 +213,17 @@ impl SoundDevice for AlsaSoundDevice {
     }
 
     fn monitor(&mut self, id: String, tx_update_request: Sender<Task>) -> Result<()> {
-        // Monitor volume changes in a separate thread.
-        thread::spawn(move || {
-            // Line-buffer to reduce noise.
-            let mut monitor = Command::new("stdbuf")
-                .args(&["-oL", "alsactl", "monitor"])
-                .stdout(Stdio::piped())
-                .spawn()
-                .expect("Failed to start alsactl monitor")
-                .stdout
-                .expect("Failed to pipe alsactl monitor output");
-
-            let mut buffer = [0; 1024]; // Should be more than enough.
-            loop {
-                // Block until we get some output. Doesn't really matter what
-                // the output actually is -- these are events -- we just update
-                // the sound information if *something* happens.
-                if monitor.read(&mut buffer).is_ok() {
-                    tx_update_request.send(Task {
-                        id: id.clone(),
-                        update_time: Instant::now(),
-                    });
-                }
-                // Don't update too often. Wait 1/4 second, fast enough for
-                // volume button mashing but slow enough to skip event spam.
-                thread::sleep(Duration::new(0, 250_000_000))
-            }
-        });
-
-        Ok(())
+        // Doesn't really matter what `alsactl monitor` prints -- these are
+        // events -- we just update the sound information if *something*
+        // happens. Don't update too often though: 1/4 second is fast enough
+        // for volume button mashing but slow enough to skip event spam.
+        scheduler::watch_command(
+            "stdbuf",
+            &["-oL", "alsactl", "monitor"],
+            Duration::from_millis(250),
+            &id,
+            &tx_update_request,
+        )
     }
 }
 
@@ -189,6 +251,24 @@ struct PulseAudioSoundDevice {
 struct PulseAudioSinkInfo {
     volume: ChannelVolumes,
     mute: bool,
+    bluetooth_address: Option<String>,
+    /// The index of the card the sink is on
+    card: Option<u32>,
+}
+
+#[cfg(feature = "pulseaudio")]
+#[derive(Debug)]
+struct PulseAudioCardInfo {
+    profiles: Vec<Profile>,
+    active_profile: Option<String>,
+}
+
+#[cfg(feature = "pulseaudio")]
+#[derive(Debug)]
+struct PulseAudioSinkInputInfo {
+    name: String,
+    volume: ChannelVolumes,
+    mute: bool,
 }
 
 #[cfg(feature = "pulseaudio")]
@@ -199,6 +279,13 @@ enum PulseAudioClientRequest {
     GetSinkInfoByName(String),
     SetSinkVolumeByName(String, ChannelVolumes),
     SetSinkMuteByName(String, bool),
+    GetSinkInputInfoList,
+    GetSinkInputInfo(u32),
+    SetSinkInputVolume(u32, ChannelVolumes),
+    SetSinkInputMute(u32, bool),
+    GetCardInfoList,
+    GetCardInfoByIndex(u32),
+    SetCardProfileByIndex(u32, String),
 }
 
 #[cfg(feature = "pulseaudio")]
@@ -207,6 +294,8 @@ lazy_static! {
     static ref PULSEAUDIO_EVENT_LISTENER: Mutex<HashMap<String, Sender<Task>>> = Mutex::new(HashMap::new());
     static ref PULSEAUDIO_DEFAULT_SINK: Mutex<String> = Mutex::new("@DEFAULT_SINK@".into());
     static ref PULSEAUDIO_SINKS: Mutex<HashMap<String, PulseAudioSinkInfo>> = Mutex::new(HashMap::new());
+    static ref PULSEAUDIO_SINK_INPUTS: Mutex<HashMap<u32, PulseAudioSinkInputInfo>> = Mutex::new(HashMap::new());
+    static ref PULSEAUDIO_CARDS: Mutex<HashMap<u32, PulseAudioCardInfo>> = Mutex::new(HashMap::new());
 }
 
 #[cfg(feature = "pulseaudio")]
@@ -331,6 +420,27 @@ impl PulseAudioClient {
                             PulseAudioClientRequest::SetSinkMuteByName(name, mute) => {
                                 introspector.set_sink_mute_by_name(&name, mute, None);
                             },
+                            PulseAudioClientRequest::GetSinkInputInfoList => {
+                                introspector.get_sink_input_info_list(PulseAudioClient::sink_input_info_callback);
+                            },
+                            PulseAudioClientRequest::GetSinkInputInfo(index) => {
+                                introspector.get_sink_input_info(index, PulseAudioClient::sink_input_info_callback);
+                            },
+                            PulseAudioClientRequest::SetSinkInputVolume(index, volumes) => {
+                                introspector.set_sink_input_volume(index, &volumes, None);
+                            },
+                            PulseAudioClientRequest::SetSinkInputMute(index, mute) => {
+                                introspector.set_sink_input_mute(index, mute, None);
+                            },
+                            PulseAudioClientRequest::GetCardInfoList => {
+                                introspector.get_card_info_list(PulseAudioClient::card_info_callback);
+                            },
+                            PulseAudioClientRequest::GetCardInfoByIndex(index) => {
+                                introspector.get_card_info_by_index(index, PulseAudioClient::card_info_callback);
+                            },
+                            PulseAudioClientRequest::SetCardProfileByIndex(index, profile) => {
+                                introspector.set_card_profile_by_index(index, &profile, None);
+                            },
                         };
 
                         // send request and receive response
@@ -350,7 +460,9 @@ impl PulseAudioClient {
             connection.context.borrow_mut().set_subscribe_callback(Some(Box::new(PulseAudioClient::subscribe_callback)));
             connection.context.borrow_mut().subscribe(
                 subscription_masks::SERVER |
-                subscription_masks::SINK,
+                subscription_masks::SINK |
+                subscription_masks::SINK_INPUT |
+                subscription_masks::MASK_CARD,
                 |_| { }
             );
 
@@ -396,9 +508,22 @@ impl PulseAudioClient {
                 match sink_info.name.clone() {
                     None => {},
                     Some(name) => {
+                        // PipeWire has a property of its own, PulseAudio uses
+                        // the device string of Bluetooth sinks
+                        let proplist = &sink_info.proplist;
+                        let bluetooth_address = proplist.gets("api.bluez5.address").or_else(|| {
+                            if proplist.gets("device.bus").map_or(false, |bus| bus == "bluetooth") {
+                                proplist.gets("device.string")
+                            } else {
+                                None
+                            }
+                        });
                         let info = PulseAudioSinkInfo {
                             volume: sink_info.volume,
                             mute: sink_info.mute,
+                            bluetooth_address,
+                            // Virtual sinks are on no card
+                            card: sink_info.card,
                         };
                         PULSEAUDIO_SINKS.lock().unwrap().insert(name.into(), info);
                         PulseAudioClient::send_update_event();
@@ -408,7 +533,58 @@ impl PulseAudioClient {
         }
     }
 
-    fn subscribe_callback(facility: Option<Facility>, _operation: Option<SubscribeOperation>, index: u32) {
+    fn sink_input_info_callback(result: ListResult<&SinkInputInfo>) {
+        match result {
+            ListResult::End |
+            ListResult::Error => { },
+            ListResult::Item(sink_input_info) => {
+                // Paused streams don't count as playing
+                if sink_input_info.corked || !sink_input_info.has_volume {
+                    PULSEAUDIO_SINK_INPUTS.lock().unwrap().remove(&sink_input_info.index);
+                } else {
+                    let name = sink_input_info.proplist.gets(properties::APPLICATION_NAME)
+                        .or_else(|| sink_input_info.name.clone().map(|name| name.into_owned()))
+                        .unwrap_or_else(|| format!("#{}", sink_input_info.index));
+                    let info = PulseAudioSinkInputInfo {
+                        name,
+                        volume: sink_input_info.volume,
+                        mute: sink_input_info.mute,
+                    };
+                    PULSEAUDIO_SINK_INPUTS.lock().unwrap().insert(sink_input_info.index, info);
+                }
+                PulseAudioClient::send_update_event();
+            },
+        }
+    }
+
+    fn card_info_callback(result: ListResult<&CardInfo>) {
+        match result {
+            ListResult::End |
+            ListResult::Error => { },
+            ListResult::Item(card_info) => {
+                // Profiles without sinks are for recording, or turn the card off
+                let profiles = card_info.profiles
+                    .iter()
+                    .filter(|profile| profile.available && profile.n_sinks > 0)
+                    .filter_map(|profile| profile.name.clone().map(|name| Profile {
+                        description: profile.description.clone().unwrap_or_else(|| name.clone()).into_owned(),
+                        name: name.into_owned(),
+                    }))
+                    .collect();
+                let info = PulseAudioCardInfo {
+                    profiles,
+                    active_profile: card_info.active_profile
+                        .as_ref()
+                        .and_then(|profile| profile.name.clone())
+                        .map(|name| name.into_owned()),
+                };
+                PULSEAUDIO_CARDS.lock().unwrap().insert(card_info.index, info);
+                PulseAudioClient::send_update_event();
+            },
+        }
+    }
+
+    fn subscribe_callback(facility: Option<Facility>, operation: Option<SubscribeOperation>, index: u32) {
         match facility {
             None => { },
             Some(facility) => match facility {
@@ -418,6 +594,23 @@ impl PulseAudioClient {
                 Facility::Sink => {
                     let _ = PulseAudioClient::send(PulseAudioClientRequest::GetSinkInfoByIndex(index));
                 },
+                Facility::Card => match operation {
+                    Some(SubscribeOperation::Removed) => {
+                        PULSEAUDIO_CARDS.lock().unwrap().remove(&index);
+                    },
+                    _ => {
+                        let _ = PulseAudioClient::send(PulseAudioClientRequest::GetCardInfoByIndex(index));
+                    },
+                },
+                Facility::SinkInput => match operation {
+                    Some(SubscribeOperation::Removed) => {
+                        PULSEAUDIO_SINK_INPUTS.lock().unwrap().remove(&index);
+                        PulseAudioClient::send_update_event();
+                    },
+                    _ => {
+                        let _ = PulseAudioClient::send(PulseAudioClientRequest::GetSinkInputInfo(index));
+                    },
+                },
                 _ => { }
             }
         }
@@ -437,6 +630,8 @@ impl PulseAudioClient {
 impl PulseAudioSoundDevice {
     fn new() -> Result<Self> {
         PulseAudioClient::send(PulseAudioClientRequest::GetDefaultDevice)?;
+        PulseAudioClient::send(PulseAudioClientRequest::GetSinkInputInfoList)?;
+        PulseAudioClient::send(PulseAudioClientRequest::GetCardInfoList)?;
 
         let device = PulseAudioSoundDevice {
             name: None,
@@ -452,6 +647,8 @@ impl PulseAudioSoundDevice {
 
     fn with_name(name: String) -> Result<Self> {
         PulseAudioClient::send(PulseAudioClientRequest::GetSinkInfoByName(name.clone()))?;
+        PulseAudioClient::send(PulseAudioClientRequest::GetSinkInputInfoList)?;
+        PulseAudioClient::send(PulseAudioClientRequest::GetCardInfoList)?;
 
         Ok(PulseAudioSoundDevice {
             name: Some(name),
@@ -467,8 +664,23 @@ impl PulseAudioSoundDevice {
 
     fn volume(&mut self, volume: ChannelVolumes) {
         self.volume = Some(volume);
-        self.volume_avg = (volume.avg().0 as f32 / VOLUME_NORM.0 as f32 * 100.0).round() as u32;
+        self.volume_avg = volume_percent(&volume);
+    }
+}
+
+#[cfg(feature = "pulseaudio")]
+fn volume_percent(volume: &ChannelVolumes) -> u32 {
+    (volume.avg().0 as f32 / VOLUME_NORM.0 as f32 * 100.0).round() as u32
+}
+
+/// `volume` changed by `step` percent, on every channel.
+#[cfg(feature = "pulseaudio")]
+fn step_volume(mut volume: ChannelVolumes, step: i32) -> ChannelVolumes {
+    let step = (step as f32 * VOLUME_NORM.0 as f32 / 100.0).round() as i32;
+    for vol in volume.values.iter_mut() {
+        vol.0 = min(max(0, vol.0 as i32 + step) as u32, VOLUME_MAX.0);
     }
+    volume
 }
 
 #[cfg(feature = "pulseaudio")]
@@ -489,17 +701,11 @@ impl SoundDevice for PulseAudioSoundDevice {
     }
 
     fn set_volume(&mut self, step: i32) -> Result<()> {
-        let mut volume = match self.volume {
-            Some(volume) => volume,
+        let volume = match self.volume {
+            Some(volume) => step_volume(volume, step),
             None => return Err(BlockError("sound".into(), "volume unknown".into()))
         };
 
-        // apply step to volumes
-        let step = (step as f32 * VOLUME_NORM.0 as f32 / 100.0).round() as i32;
-        for vol in volume.values.iter_mut() {
-            vol.0 = min(max(0, vol.0 as i32 + step) as u32, VOLUME_MAX.0);
-        }
-
         // update volumes
         self.volume(volume);
         PulseAudioClient::send(PulseAudioClientRequest::SetSinkVolumeByName(self.name(), volume))?;
@@ -518,6 +724,74 @@ impl SoundDevice for PulseAudioSoundDevice {
         PULSEAUDIO_EVENT_LISTENER.lock().unwrap().insert(id, tx_update_request);
         Ok(())
     }
+
+    fn bluetooth_address(&self) -> Option<String> {
+        PULSEAUDIO_SINKS.lock().unwrap()
+            .get(&self.name())
+            .and_then(|sink_info| sink_info.bluetooth_address.clone())
+    }
+
+    fn profiles(&self) -> (Vec<Profile>, Option<String>) {
+        let card = PULSEAUDIO_SINKS.lock().unwrap()
+            .get(&self.name())
+            .and_then(|sink_info| sink_info.card);
+        match card.and_then(|card| PULSEAUDIO_CARDS.lock().unwrap().get(&card).map(|card_info| {
+            (card_info.profiles.clone(), card_info.active_profile.clone())
+        })) {
+            Some(profiles) => profiles,
+            None => (Vec::new(), None),
+        }
+    }
+
+    fn set_profile(&mut self, name: &str) -> Result<()> {
+        let card = PULSEAUDIO_SINKS.lock().unwrap()
+            .get(&self.name())
+            .and_then(|sink_info| sink_info.card)
+            .block_error("sound", "the sound device is on no card")?;
+        PulseAudioClient::send(PulseAudioClientRequest::SetCardProfileByIndex(card, name.to_owned()))?;
+
+        Ok(())
+    }
+
+    fn apps(&self) -> Vec<App> {
+        let mut apps: Vec<App> = PULSEAUDIO_SINK_INPUTS.lock().unwrap()
+            .iter()
+            .map(|(&index, info)| App {
+                index,
+                name: info.name.clone(),
+                volume: volume_percent(&info.volume),
+                muted: info.mute,
+            })
+            .collect();
+        apps.sort_by_key(|app| app.index);
+        apps
+    }
+
+    fn set_app_volume(&mut self, index: u32, step: i32) -> Result<()> {
+        let volume = match PULSEAUDIO_SINK_INPUTS.lock().unwrap().get_mut(&index) {
+            Some(info) => {
+                info.volume = step_volume(info.volume, step);
+                info.volume
+            },
+            None => return Err(BlockError("sound".into(), "application is gone".into()))
+        };
+        PulseAudioClient::send(PulseAudioClientRequest::SetSinkInputVolume(index, volume))?;
+
+        Ok(())
+    }
+
+    fn toggle_app(&mut self, index: u32) -> Result<()> {
+        let mute = match PULSEAUDIO_SINK_INPUTS.lock().unwrap().get_mut(&index) {
+            Some(info) => {
+                info.mute = !info.mute;
+                info.mute
+            },
+            None => return Err(BlockError("sound".into(), "application is gone".into()))
+        };
+        PulseAudioClient::send(PulseAudioClientRequest::SetSinkInputMute(index, mute))?;
+
+        Ok(())
+    }
 }
 
 // TODO: Use the alsa control bindings to implement push updates
@@ -527,7 +801,18 @@ pub struct Sound {
     device: Box<SoundDevice>,
     step_width: u32,
     config: Config,
-    on_click: Option<String>,
+    gradient: bool,
+    /// Whether right clicks go through the applications
+    apps: bool,
+    /// The application shown instead of the device
+    app: Option<u32>,
+    headset_battery: bool,
+    /// Whether a headset battery is shown, for it to be checked regularly
+    headset_shown: bool,
+    /// Whether left clicks switch profiles
+    profiles: bool,
+    /// The profile switched to, shown until the given time
+    profile_shown: Option<(String, Instant)>,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -545,8 +830,22 @@ pub struct SoundConfig {
     #[serde(default = "SoundConfig::default_step_width")]
     pub step_width: u32,
 
-    #[serde(default = "SoundConfig::default_on_click")]
-    pub on_click: Option<String>,
+    /// Color the text by volume instead of by state
+    #[serde(default = "SoundConfig::default_gradient")]
+    pub gradient: bool,
+
+    /// Cycle through the applications playing sound on right click, to show
+    /// and change their volume instead. Middle click mutes then.
+    #[serde(default = "SoundConfig::default_apps")]
+    pub apps: bool,
+
+    /// Show the battery of Bluetooth headsets next to the volume
+    #[serde(default = "SoundConfig::default_headset_battery")]
+    pub headset_battery: bool,
+
+    /// Switch to the next profile of the sound card on left click
+    #[serde(default = "SoundConfig::default_profiles")]
+    pub profiles: bool,
 }
 
 #[derive(Deserialize, Copy, Clone, Debug)]
@@ -573,14 +872,49 @@ impl SoundConfig {
         5
     }
 
-    fn default_on_click() -> Option<String> {
-        None
+    fn default_gradient() -> bool {
+        false
+    }
+
+    fn default_apps() -> bool {
+        false
+    }
+
+    fn default_headset_battery() -> bool {
+        true
+    }
+
+    fn default_profiles() -> bool {
+        false
     }
 }
 
 impl Sound {
     fn display(&mut self) -> Result<()> {
         self.device.get_info()?;
+        self.headset_shown = false;
+
+        if let Some((description, until)) = self.profile_shown.clone() {
+            if Instant::now() < until {
+                self.text.set_icon("volume_full");
+                self.text.set_text(description);
+                self.text.set_state(State::Info);
+                self.text.set_color(None);
+                return Ok(());
+            }
+            self.profile_shown = None;
+        }
+
+        if let Some(index) = self.app {
+            match self.device.apps().into_iter().find(|app| app.index == index) {
+                Some(app) => {
+                    self.display_app(&app);
+                    return Ok(());
+                }
+                // Back to the device once the application stops
+                None => self.app = None,
+            }
+        }
 
         if self.device.muted() {
             self.text.set_icon("volume_empty");
@@ -592,6 +926,7 @@ impl Sound {
                     .to_owned(),
             );
             self.text.set_state(State::Warning);
+            self.text.set_color(None);
         } else {
             let volume = self.device.volume();
             self.text.set_icon(match volume {
@@ -601,10 +936,95 @@ impl Sound {
             });
             self.text.set_text(format!("{:02}%", volume));
             self.text.set_state(State::Idle);
+            if self.gradient {
+                self.text.set_gradient(f64::from(volume), false);
+            }
+        }
+
+        if self.headset_battery {
+            self.display_headset_battery();
+        }
+
+        Ok(())
+    }
+
+    /// Add the battery of a Bluetooth headset to the text. BlueZ not knowing
+    /// it is nothing to fail over, many headsets don't tell.
+    fn display_headset_battery(&mut self) {
+        let battery = match self.device.bluetooth_address() {
+            Some(address) => bluetooth_battery(&address).unwrap_or_else(|error| {
+                debug!("failed to get the headset battery: {:?}", error);
+                None
+            }),
+            None => None,
+        };
+        if let Some(battery) = battery {
+            let icon = self.config.icons.get("headset").map_or("", |icon| icon.trim());
+            let text = if self.device.muted() {
+                self.config.icons.get("volume_muted").map_or("", |icon| icon.as_str()).to_owned()
+            } else {
+                format!("{:02}%", self.device.volume())
+            };
+            self.text.set_text(format!("{} {} {}%", text, icon, battery));
+            if battery < HEADSET_LOW {
+                self.text.set_state(State::Warning);
+            }
+            self.headset_shown = true;
+        }
+    }
+
+    fn display_app(&mut self, app: &App) {
+        self.text.set_icon(match app.volume {
+            _ if app.muted => "volume_empty",
+            0...20 => "volume_empty",
+            21...70 => "volume_half",
+            _ => "volume_full",
+        });
+        if app.muted {
+            let muted = self.config.icons.get("volume_muted").map_or("", |icon| icon.trim());
+            self.text.set_text(format!("{} {}", app.name, muted));
+            self.text.set_state(State::Warning);
+            self.text.set_color(None);
+        } else {
+            self.text.set_text(format!("{} {:02}%", app.name, app.volume));
+            self.text.set_state(State::Info);
+            if self.gradient {
+                self.text.set_gradient(f64::from(app.volume), false);
+            }
         }
+    }
 
+    /// Switch the card to the profile after the active one, and show it for a
+    /// bit.
+    fn next_profile(&mut self) -> Result<()> {
+        let (profiles, active) = self.device.profiles();
+        let position = profiles.iter().position(|profile| Some(&profile.name) == active.as_ref());
+        let next = match position {
+            Some(position) => profiles.get((position + 1) % profiles.len()),
+            None => profiles.first(),
+        };
+        if let Some(next) = next {
+            self.device.set_profile(&next.name)?;
+            self.profile_shown = Some((
+                next.description.clone(),
+                Instant::now() + Duration::from_secs(PROFILE_SHOWN_SECS),
+            ));
+        }
         Ok(())
     }
+
+    /// Show the application after the one shown, or the device after the
+    /// last one.
+    fn next_app(&mut self) {
+        let apps = self.device.apps();
+        self.app = match self.app {
+            None => apps.first().map(|app| app.index),
+            Some(index) => apps.iter()
+                .skip_while(|app| app.index != index)
+                .nth(1)
+                .map(|app| app.index),
+        };
+    }
 }
 
 impl ConfigBlock for Sound {
@@ -646,7 +1066,13 @@ impl ConfigBlock for Sound {
             device,
             step_width,
             config,
-            on_click: block_config.on_click,
+            gradient: block_config.gradient,
+            apps: block_config.apps,
+            app: None,
+            headset_battery: block_config.headset_battery,
+            headset_shown: false,
+            profiles: block_config.profiles,
+            profile_shown: None,
         };
 
         sound.device.monitor(id.clone(), tx_update_request.clone())?;
@@ -661,7 +1087,16 @@ const FILTER: &[char] = &['[', ']', '%'];
 impl Block for Sound {
     fn update(&mut self) -> Result<Option<Duration>> {
         self.display()?;
-        Ok(None) // The monitor thread will call for updates when needed.
+        // The monitor will call for updates when needed, but not on
+        // changes of the headset battery, or to stop showing the profile
+        if let Some((_, until)) = self.profile_shown {
+            let now = Instant::now();
+            Ok(Some(if until > now { until - now } else { Duration::from_secs(0) }))
+        } else if self.headset_shown {
+            Ok(Some(Duration::from_secs(HEADSET_INTERVAL)))
+        } else {
+            Ok(None)
+        }
     }
 
     fn view(&self) -> Vec<&I3BarWidget> {
@@ -671,27 +1106,20 @@ impl Block for Sound {
     fn click(&mut self, e: &I3BarEvent) -> Result<()> {
         if let Some(ref name) = e.name {
             if name.as_str() == self.id {
-                match e.button {
-                    MouseButton::Right => self.device.toggle()?,
-                    MouseButton::Left => {
-                        let mut command =            if self.on_click.is_some() {
-                            self.on_click.clone().unwrap()
-                        } else {
-                            "".to_string()
-                        };
-                        if self.on_click.is_some() {
-                            let command_broken: Vec<&str> = command.split_whitespace().collect();
-                            let mut itr = command_broken.iter();
-                            let mut _cmd = Command::new(OsStr::new(&itr.next().unwrap()))
-                                .args(itr)
-                                .spawn();
-                        }
-                    }
-                    MouseButton::WheelUp => {
-                        self.device.set_volume(self.step_width as i32)?;
+                let step = self.step_width as i32;
+                match (e.button.clone(), self.app) {
+                    (MouseButton::Left, _) if self.profiles => self.next_profile()?,
+                    (MouseButton::Right, _) if self.apps => self.next_app(),
+                    (MouseButton::Middle, Some(index)) => self.device.toggle_app(index)?,
+                    (MouseButton::Middle, None) if self.apps => self.device.toggle()?,
+                    (MouseButton::Right, _) => self.device.toggle()?,
+                    (MouseButton::WheelUp, Some(index)) => self.device.set_app_volume(index, step)?,
+                    (MouseButton::WheelDown, Some(index)) => self.device.set_app_volume(index, -step)?,
+                    (MouseButton::WheelUp, None) => {
+                        self.device.set_volume(step)?;
                     }
-                    MouseButton::WheelDown => {
-                        self.device.set_volume(-(self.step_width as i32))?;
+                    (MouseButton::WheelDown, None) => {
+                        self.device.set_volume(-step)?;
                     }
                     _ => {}
                 }
@@ -0,0 +1,50 @@
+use chan::Sender;
+use scheduler::Task;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use errors::*;
+use widget::I3BarWidget;
+use widgets::text::TextWidget;
+
+use uuid::Uuid;
+
+pub struct Spacer {
+    text: TextWidget,
+    id: String,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SpacerConfig {
+    /// Width of the gap, in characters
+    #[serde(default = "SpacerConfig::default_width")]
+    pub width: usize,
+}
+
+impl SpacerConfig {
+    fn default_width() -> usize {
+        1
+    }
+}
+
+impl ConfigBlock for Spacer {
+    type Config = SpacerConfig;
+
+    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        Ok(Spacer {
+            text: TextWidget::new(config).with_text(&" ".repeat(block_config.width)),
+            id: Uuid::new_v4().simple().to_string(),
+        })
+    }
+}
+
+impl Block for Spacer {
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,192 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chan::Sender;
+use inotify::WatchMask;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_opt_duration;
+use errors::*;
+use formatting::FormatTemplate;
+use scheduler::{self, Task};
+use util::read_file;
+use widget::{I3BarWidget, State};
+use widgets::text::TextWidget;
+
+/// Maps values within `[min, max]` to a widget state.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Range {
+    #[serde(default = "Range::default_min")]
+    pub min: f64,
+    #[serde(default = "Range::default_max")]
+    pub max: f64,
+    pub state: State,
+}
+
+impl Range {
+    fn default_min() -> f64 {
+        ::std::f64::NEG_INFINITY
+    }
+
+    fn default_max() -> f64 {
+        ::std::f64::INFINITY
+    }
+}
+
+pub struct Sysfs {
+    text: TextWidget,
+    id: String,
+    update_interval: Option<Duration>,
+    path: PathBuf,
+    scale: f64,
+    precision: usize,
+    unit: String,
+    format: FormatTemplate,
+    ranges: Vec<Range>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SysfsConfig {
+    /// The file to read, e.g. `/sys/class/hwmon/hwmon0/fan1_input`
+    pub path: String,
+
+    /// Update interval in seconds. May be omitted when `watch` is set.
+    #[serde(default = "SysfsConfig::default_interval", deserialize_with = "deserialize_opt_duration")]
+    pub interval: Option<Duration>,
+
+    /// Whether to watch the file with inotify and update whenever it changes
+    #[serde(default = "SysfsConfig::default_watch")]
+    pub watch: bool,
+
+    /// Factor the value is multiplied with before display
+    #[serde(default = "SysfsConfig::default_scale")]
+    pub scale: f64,
+
+    /// Number of decimal places to display
+    #[serde(default = "SysfsConfig::default_precision")]
+    pub precision: usize,
+
+    /// Unit appended to the value
+    #[serde(default = "SysfsConfig::default_unit")]
+    pub unit: String,
+
+    /// Format string, placeholders: {value}, {unit} and {raw}
+    #[serde(default = "SysfsConfig::default_format")]
+    pub format: String,
+
+    /// Icon to display in front of the value
+    #[serde(default = "SysfsConfig::default_icon")]
+    pub icon: String,
+
+    /// Ranges of the scaled value mapped to states; the first match wins
+    #[serde(default = "SysfsConfig::default_ranges")]
+    pub ranges: Vec<Range>,
+}
+
+impl SysfsConfig {
+    fn default_interval() -> Option<Duration> {
+        Some(Duration::from_secs(5))
+    }
+
+    fn default_watch() -> bool {
+        false
+    }
+
+    fn default_scale() -> f64 {
+        1.
+    }
+
+    fn default_precision() -> usize {
+        0
+    }
+
+    fn default_unit() -> String {
+        "".to_owned()
+    }
+
+    fn default_format() -> String {
+        "{value}{unit}".to_owned()
+    }
+
+    fn default_icon() -> String {
+        "".to_owned()
+    }
+
+    fn default_ranges() -> Vec<Range> {
+        vec![]
+    }
+}
+
+impl ConfigBlock for Sysfs {
+    type Config = SysfsConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        let id = Uuid::new_v4().simple().to_string();
+        let path = PathBuf::from(block_config.path);
+        if !path.exists() {
+            return Err(BlockError(
+                "sysfs".to_string(),
+                format!("File '{}' does not exist", path.to_string_lossy()),
+            ));
+        }
+
+        // Only some attributes notify on change (e.g. those using
+        // sysfs_notify in the kernel), so this is opt-in.
+        if block_config.watch {
+            scheduler::watch_file(&path, WatchMask::MODIFY, &id, &tx_update_request)?;
+        }
+
+        Ok(Sysfs {
+            text: TextWidget::new(config).with_icon(&block_config.icon),
+            id,
+            update_interval: if block_config.watch { None } else { block_config.interval },
+            path,
+            scale: block_config.scale,
+            precision: block_config.precision,
+            unit: block_config.unit,
+            format: FormatTemplate::from_string(&block_config.format)?,
+            ranges: block_config.ranges,
+        })
+    }
+}
+
+impl Block for Sysfs {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let raw = read_file("sysfs", &self.path)?;
+        let raw = raw.trim().to_owned();
+
+        // Non-numeric attributes (e.g. `operstate`) are displayed as they are.
+        let (value, state) = match raw.parse::<f64>() {
+            Ok(number) => {
+                let scaled = number * self.scale;
+                let state = self.ranges
+                    .iter()
+                    .find(|range| scaled >= range.min && scaled <= range.max)
+                    .map(|range| range.state)
+                    .unwrap_or(State::Idle);
+                (format!("{:.*}", self.precision, scaled), state)
+            }
+            Err(_) => (raw.clone(), State::Idle),
+        };
+
+        let values = map!("{value}" => value,
+                          "{unit}" => self.unit.clone(),
+                          "{raw}" => raw);
+        self.text.set_text(self.format.render_static_str(&values)?);
+        self.text.set_state(state);
+
+        Ok(self.update_interval)
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -1,6 +1,6 @@
 use std::time::Duration;
 use std::process::Command;
-use util::FormatTemplate;
+use formatting::FormatTemplate;
 use chan::Sender;
 use scheduler::Task;
 
@@ -9,7 +9,7 @@ use config::Config;
 use de::deserialize_duration;
 use errors::*;
 use widgets::button::ButtonWidget;
-use widget::{I3BarWidget, State};
+use widget::{I3BarWidget, Thresholds};
 use input::{I3BarEvent, MouseButton};
 
 use uuid::Uuid;
@@ -20,11 +20,11 @@ pub struct Temperature {
     collapsed: bool,
     id: String,
     update_interval: Duration,
-    maximum_good: i64,
-    maximum_idle: i64,
-    maximum_info: i64,
-    maximum_warning: i64,
+    thresholds: Thresholds,
+    urgent_above: Option<i64>,
     format: FormatTemplate,
+    /// What the last update measured, see `Block::metrics`
+    metrics: Vec<(&'static str, f64)>,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -54,6 +54,14 @@ pub struct TemperatureConfig {
     #[serde(default = "TemperatureConfig::default_warning")]
     pub warning: i64,
 
+    /// Replaces `good`, `idle`, `info` and `warning` if set
+    #[serde(default = "TemperatureConfig::default_thresholds")]
+    pub thresholds: Option<Thresholds>,
+
+    /// Mark the block urgent while the maximum temperature is above this
+    #[serde(default = "TemperatureConfig::default_urgent_above")]
+    pub urgent_above: Option<i64>,
+
     /// Format override
     #[serde(default = "TemperatureConfig::default_format")]
     pub format: String,
@@ -88,6 +96,13 @@ impl TemperatureConfig {
         80
     }
 
+    fn default_thresholds() -> Option<Thresholds> {
+        None
+    }
+
+    fn default_urgent_above() -> Option<i64> {
+        None
+    }
 }
 
 impl ConfigBlock for Temperature {
@@ -101,12 +116,17 @@ impl ConfigBlock for Temperature {
             output: String::new(),
             collapsed: block_config.collapsed,
             id,
-            maximum_good: block_config.good,
-            maximum_idle: block_config.idle,
-            maximum_info: block_config.info,
-            maximum_warning: block_config.warning,
+            thresholds: block_config.thresholds.unwrap_or(Thresholds {
+                good: Some(block_config.good as f64),
+                info: Some(block_config.idle as f64),
+                warning: Some(block_config.info as f64),
+                critical: Some(block_config.warning as f64),
+                reversed: false,
+            }),
+            urgent_above: block_config.urgent_above,
             format: FormatTemplate::from_string(&block_config.format)
                 .block_error("temperature", "Invalid format specified for temperature")?,
+            metrics: Vec::new(),
         })
     }
 }
@@ -137,7 +157,7 @@ impl Block for Temperature {
                         }
                         Ok(t) => {
                             // This error is recoverable and therefore should not stop the program
-                            eprintln!("Temperature ({}) outside of range ([-100, 150])", t);
+                            warn!("Temperature ({}) outside of range ([-100, 150])", t);
                             Ok(())
                         }
                         Err(_) => Err(BlockError(
@@ -169,15 +189,13 @@ impl Block for Temperature {
                 self.text.set_text(self.output.clone());
             }
 
-            let state = match max {
-                m if m <= self.maximum_good => State::Good,
-                m if m <= self.maximum_idle => State::Idle,
-                m if m <= self.maximum_info => State::Info,
-                m if m <= self.maximum_warning => State::Warning,
-                _ => State::Critical,
-            };
-
-            self.text.set_state(state);
+            self.text.set_state(self.thresholds.state(max as f64));
+            self.text.set_urgent(self.urgent_above.map_or(false, |above| max > above));
+            self.metrics = vec![
+                ("temperature_max_celsius", max as f64),
+                ("temperature_min_celsius", min as f64),
+                ("temperature_average_celsius", avg as f64),
+            ];
         }
 
         Ok(Some(self.update_interval))
@@ -187,6 +205,10 @@ impl Block for Temperature {
         vec![&self.text]
     }
 
+    fn metrics(&self) -> Vec<(&'static str, f64)> {
+        self.metrics.clone()
+    }
+
     fn click(&mut self, e: &I3BarEvent) -> Result<()> {
         if let Some(ref name) = e.name {
             if name.as_str() == self.id && e.button == MouseButton::Left {
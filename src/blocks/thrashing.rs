@@ -0,0 +1,214 @@
+//! A block warning of thrashing, when the machine spends its time moving
+//! memory to and from swap rather than getting work done.
+//!
+//! It's hidden as long as swapping, major page faults and disk latency stay
+//! below their limits, and shows up as critical once any of them goes past.
+//! The counters are read from `/proc/vmstat` and `/proc/diskstats`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::mem;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use nix::libc;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Unit, Value};
+use scheduler::Task;
+use util::{read_file, secs};
+use widget::{I3BarWidget, State};
+use widgets::text::TextWidget;
+
+/// The counters compared between updates, all going up since boot.
+#[derive(Debug, Clone, Copy, Default)]
+struct Counters {
+    /// Pages swapped in and out
+    swap_in: u64,
+    swap_out: u64,
+    /// Page faults that had to read from disk
+    major_faults: u64,
+    /// Reads and writes completed on all disks, and the milliseconds spent
+    /// on them
+    ios: u64,
+    io_ms: u64,
+}
+
+impl Counters {
+    fn read() -> Result<Counters> {
+        let mut counters = Counters::default();
+        for line in read_file("thrashing", Path::new("/proc/vmstat"))?.lines() {
+            let mut fields = line.split_whitespace();
+            let (name, value) = match (fields.next(), fields.next().and_then(|value| value.parse().ok())) {
+                (Some(name), Some(value)) => (name, value),
+                _ => continue,
+            };
+            match name {
+                "pswpin" => counters.swap_in = value,
+                "pswpout" => counters.swap_out = value,
+                "pgmajfault" => counters.major_faults = value,
+                _ => {}
+            }
+        }
+
+        // Partitions are counted in their disk as well, so only disks count
+        let disks: Vec<String> = fs::read_dir("/sys/block")
+            .block_error("thrashing", "failed to read /sys/block")?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| !name.starts_with("loop") && !name.starts_with("ram"))
+            .collect();
+        for line in read_file("thrashing", Path::new("/proc/diskstats"))?.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 11 || !disks.iter().any(|disk| disk == fields[2]) {
+                continue;
+            }
+            let field = |index: usize| fields[index].parse::<u64>().unwrap_or(0);
+            counters.ios += field(3) + field(7);
+            counters.io_ms += field(6) + field(10);
+        }
+        Ok(counters)
+    }
+}
+
+/// Shows swapping, major page faults and disk latency while any of them is
+/// too high, see `ThrashingConfig`.
+pub struct Thrashing {
+    id: String,
+    text: TextWidget,
+    format: FormatTemplate,
+    interval: Duration,
+    swap: f64,
+    major_faults: f64,
+    latency: f64,
+    page_size: f64,
+    /// The counters at the last update, and when they were read
+    prev: Option<(Counters, Instant)>,
+    thrashing: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ThrashingConfig {
+    /// Update interval in seconds
+    #[serde(default = "ThrashingConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{swap_in}`, `{swap_out}`, `{major_faults}` and
+    /// `{latency}` are available
+    #[serde(default = "ThrashingConfig::default_format")]
+    pub format: String,
+
+    /// Pages swapped in and out per second, above which the block shows
+    #[serde(default = "ThrashingConfig::default_swap")]
+    pub swap: f64,
+
+    /// Major page faults per second, above which the block shows
+    #[serde(default = "ThrashingConfig::default_major_faults")]
+    pub major_faults: f64,
+
+    /// Average milliseconds per disk read or write, above which the block
+    /// shows
+    #[serde(default = "ThrashingConfig::default_latency")]
+    pub latency: f64,
+}
+
+impl ThrashingConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn default_format() -> String {
+        "{swap_in:;bin} {swap_out:;bin} {major_faults:.0}/s {latency:.0}ms".to_owned()
+    }
+
+    fn default_swap() -> f64 {
+        256.
+    }
+
+    fn default_major_faults() -> f64 {
+        500.
+    }
+
+    fn default_latency() -> f64 {
+        100.
+    }
+}
+
+impl ConfigBlock for Thrashing {
+    type Config = ThrashingConfig;
+
+    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+
+        Ok(Thrashing {
+            id: Uuid::new_v4().simple().to_string(),
+            text: TextWidget::new(config)
+                .with_icon("thrashing")
+                .with_state(State::Critical),
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("thrashing", "Invalid format specified for thrashing")?,
+            interval: block_config.interval,
+            swap: block_config.swap,
+            major_faults: block_config.major_faults,
+            latency: block_config.latency,
+            page_size: if page_size > 0 { page_size as f64 } else { 4096. },
+            prev: None,
+            thrashing: false,
+        })
+    }
+}
+
+impl Block for Thrashing {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let now = Instant::now();
+        let counters = Counters::read()?;
+        let prev = mem::replace(&mut self.prev, Some((counters, now)));
+        let (prev, since) = match prev {
+            Some(prev) => prev,
+            // Nothing to compare to yet
+            None => return Ok(Some(self.interval)),
+        };
+
+        let elapsed = secs(now.duration_since(since));
+        let rate = |now: u64, before: u64| now.saturating_sub(before) as f64 / elapsed.max(0.001);
+        let swap_in = rate(counters.swap_in, prev.swap_in);
+        let swap_out = rate(counters.swap_out, prev.swap_out);
+        let major_faults = rate(counters.major_faults, prev.major_faults);
+        let ios = counters.ios.saturating_sub(prev.ios);
+        let latency = if ios > 0 {
+            counters.io_ms.saturating_sub(prev.io_ms) as f64 / ios as f64
+        } else {
+            0.
+        };
+
+        self.thrashing = swap_in + swap_out > self.swap || major_faults > self.major_faults || latency > self.latency;
+        if self.thrashing {
+            let mut values = HashMap::new();
+            values.insert("{swap_in}", Value::Number(swap_in * self.page_size, Unit::BytesPerSecond));
+            values.insert("{swap_out}", Value::Number(swap_out * self.page_size, Unit::BytesPerSecond));
+            values.insert("{major_faults}", Value::Number(major_faults, Unit::None));
+            values.insert("{latency}", Value::Number(latency, Unit::None));
+            self.text.set_text(self.format.render_values(&values)?);
+        }
+
+        Ok(Some(self.interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        if self.thrashing {
+            vec![&self.text]
+        } else {
+            vec![]
+        }
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
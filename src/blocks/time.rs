@@ -1,6 +1,4 @@
 use std::time::Duration;
-use std::process::Command;
-use std::ffi::OsStr;
 
 use block::{Block, ConfigBlock};
 use config::Config;
@@ -12,7 +10,6 @@ use scheduler::Task;
 use chan::Sender;
 use widgets::button::ButtonWidget;
 use widget::I3BarWidget;
-use input::I3BarEvent;
 use uuid::Uuid;
 
 pub struct Time {
@@ -20,7 +17,6 @@ pub struct Time {
     id: String,
     update_interval: Duration,
     format: String,
-    on_click: Option<String>,
     timezone: Option<Tz>,
 }
 
@@ -35,9 +31,6 @@ pub struct TimeConfig {
     #[serde(default = "TimeConfig::default_interval", deserialize_with = "deserialize_duration")]
     pub interval: Duration,
 
-    #[serde(default = "TimeConfig::default_on_click")]
-    pub on_click: Option<String>,
-
     #[serde(default = "TimeConfig::default_timezone", deserialize_with = "deserialize_timezone")]
     pub timezone: Option<Tz>,
 }
@@ -51,10 +44,6 @@ impl TimeConfig {
         Duration::from_secs(5)
     }
 
-    fn default_on_click() -> Option<String> {
-        None
-    }
-
     fn default_timezone() -> Option<Tz> {
         None
     }
@@ -72,7 +61,6 @@ impl ConfigBlock for Time {
                 .with_text("")
                 .with_icon("time"),
             update_interval: block_config.interval,
-            on_click: block_config.on_click,
             timezone: block_config.timezone,
         })
     }
@@ -88,27 +76,6 @@ impl Block for Time {
         Ok(Some(self.update_interval))
     }
 
-
-    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
-        let command = if self.on_click.is_some() {
-            self.on_click.clone().unwrap()
-        } else {
-            "".to_string()
-        };
-
-
-        if let Some(ref name) = e.name {
-            if name.as_str() == self.id && self.on_click.is_some() {
-                let command_broken: Vec<&str> = command.split_whitespace().collect();
-                let mut itr = command_broken.iter();
-                let mut _cmd = Command::new(OsStr::new(&itr.next().unwrap()))
-                    .args(itr)
-                    .spawn();
-            }
-        }
-        Ok(())
-    }
-
     fn view(&self) -> Vec<&I3BarWidget> {
         vec![&self.time]
     }
@@ -0,0 +1,210 @@
+//! A block running a command every so often, like syncing mail with
+//! `mbsync` or calendars with `vdirsyncer`, which shows the time until the
+//! next run and how the last one went.
+//!
+//! It's what would otherwise go to cron, without any word on whether it
+//! works. The command runs on its own thread, so a slow one doesn't hold up
+//! the bar.
+
+use std::collections::HashMap;
+use std::env;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Value};
+use input::{I3BarEvent, MouseButton};
+use scheduler::Task;
+use widget::{I3BarWidget, State};
+use widgets::button::ButtonWidget;
+
+/// How a run of the command went.
+#[derive(Debug, Copy, Clone)]
+enum Outcome {
+    /// With its exit code
+    Exited(i32),
+    /// Killed by a signal, or it couldn't be started
+    Failed,
+}
+
+/// The runs of the command, shared with the thread running it.
+#[derive(Debug, Default)]
+struct Runs {
+    running: bool,
+    last: Option<Outcome>,
+}
+
+/// Runs a command every so often and counts down to the next run, see
+/// `TimerConfig`.
+pub struct Timer {
+    id: String,
+    output: ButtonWidget,
+    format: FormatTemplate,
+    command: String,
+    interval: Duration,
+    /// When the command is up next
+    next: Instant,
+    runs: Arc<Mutex<Runs>>,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TimerConfig {
+    /// The command run, by the shell
+    pub command: String,
+
+    /// Time between runs, in seconds
+    #[serde(default = "TimerConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{left}` and `{status}` are available
+    #[serde(default = "TimerConfig::default_format")]
+    pub format: String,
+}
+
+impl TimerConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(900)
+    }
+
+    fn default_format() -> String {
+        "{left} {status}".to_owned()
+    }
+}
+
+impl Timer {
+    /// Start a run of the command, unless one is still going.
+    fn run(&mut self) -> Result<()> {
+        {
+            let mut runs = self.runs.lock().internal_error("timer", "failed to lock runs")?;
+            if runs.running {
+                return Ok(());
+            }
+            runs.running = true;
+        }
+        // Counted from the start, so runs don't drift by how long they take
+        self.next = Instant::now() + self.interval;
+
+        let (command, runs, id, tx) = (
+            self.command.clone(),
+            self.runs.clone(),
+            self.id.clone(),
+            self.tx_update_request.clone(),
+        );
+        thread::spawn(move || {
+            let status = Command::new(env::var("SHELL").unwrap_or("sh".to_owned()))
+                .args(&["-c", &command])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+            let outcome = match status.map(|status| status.code()) {
+                Ok(Some(code)) => Outcome::Exited(code),
+                Ok(None) => Outcome::Failed,
+                Err(error) => {
+                    error!("failed to run {}: {}", command, error);
+                    Outcome::Failed
+                }
+            };
+            match runs.lock() {
+                Ok(mut runs) => {
+                    runs.running = false;
+                    runs.last = Some(outcome);
+                }
+                Err(_) => return,
+            }
+            tx.send(Task {
+                id,
+                update_time: Instant::now(),
+            });
+        });
+        Ok(())
+    }
+}
+
+impl ConfigBlock for Timer {
+    type Config = TimerConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+
+        Ok(Timer {
+            output: ButtonWidget::new(config, &id).with_icon("timer"),
+            id,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("timer", "Invalid format specified for timer")?,
+            command: block_config.command,
+            interval: block_config.interval,
+            // The first run is right away
+            next: Instant::now(),
+            runs: Arc::new(Mutex::new(Runs::default())),
+            tx_update_request,
+        })
+    }
+}
+
+impl Block for Timer {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        if Instant::now() >= self.next {
+            self.run()?;
+        }
+        let (running, last) = {
+            let runs = self.runs.lock().internal_error("timer", "failed to lock runs")?;
+            (runs.running, runs.last)
+        };
+
+        // Minutes, rounded up, until the next run. A run taking longer than
+        // the interval leaves the next one overdue until it's done.
+        let now = Instant::now();
+        let left = if self.next > now {
+            self.next - now
+        } else {
+            Duration::from_secs(0)
+        };
+        let minutes = (left.as_secs() + 59) / 60;
+        let until_next_minute = left - Duration::from_secs(minutes.saturating_sub(1) * 60);
+        let status = match last {
+            Some(Outcome::Exited(code)) => code.to_string(),
+            Some(Outcome::Failed) => "failed".to_owned(),
+            None => String::new(),
+        };
+
+        let mut values = HashMap::new();
+        values.insert("{left}", Value::Text(format!("{}m", minutes)));
+        values.insert("{status}", Value::Text(status));
+        self.output.set_text(self.format.render_values(&values)?);
+        self.output.set_state(match last {
+            _ if running => State::Info,
+            Some(Outcome::Exited(0)) | None => State::Idle,
+            Some(_) => State::Critical,
+        });
+
+        Ok(Some(until_next_minute.max(Duration::from_secs(1))))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.output]
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        // Run now, rather than wait for it
+        if event.name.as_ref().map_or(false, |name| name == &self.id) && event.button == MouseButton::Left {
+            self.run()?;
+            self.update()?;
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,210 @@
+//! A block for the trash, how many items it holds and how much space they
+//! take, which empties it on a double click.
+//!
+//! Trashes are those of the XDG trash specification: the one in the home
+//! directory, and those at the top of other mounted filesystems, in either
+//! `.Trash/$uid` or `.Trash-$uid`. Each keeps the trashed items in `files`,
+//! and what they were in `info`.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use inotify::WatchMask;
+use nix::libc;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Unit, Value};
+use input::{I3BarEvent, MouseButton};
+use scheduler::{self, Task};
+use util::read_file;
+use widget::I3BarWidget;
+use widgets::button::ButtonWidget;
+
+/// The trash in the home directory, `$XDG_DATA_HOME/Trash`.
+fn home_trash() -> PathBuf {
+    match env::var("XDG_DATA_HOME") {
+        Ok(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").unwrap_or_else(|_| "/tmp".to_owned())).join(".local/share"),
+    }.join("Trash")
+}
+
+/// All trashes that exist, the one in the home directory first.
+fn trashes() -> Vec<PathBuf> {
+    let mut trashes = vec![home_trash()];
+    let uid = unsafe { libc::getuid() }.to_string();
+    let mounts = read_file("trash", Path::new("/proc/mounts")).unwrap_or_default();
+    for mount in mounts.lines().filter_map(|line| line.split_whitespace().nth(1)) {
+        // Spaces and such are escaped in octal, like `\040`
+        let top = PathBuf::from(mount.replace("\\040", " "));
+        trashes.push(top.join(".Trash").join(&uid));
+        trashes.push(top.join(format!(".Trash-{}", uid)));
+    }
+    trashes.retain(|trash| trash.join("files").is_dir());
+    trashes
+}
+
+/// The size of `path` in bytes, counting all of a directory. Links are not
+/// followed, they only take their own space.
+fn size(path: &Path) -> u64 {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return 0,
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    match fs::read_dir(path) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| size(&entry.path())).sum(),
+        Err(_) => 0,
+    }
+}
+
+/// Remove all items of `trash`, along with what it knows about them.
+fn empty(trash: &Path) -> Result<()> {
+    for dir in &["files", "info"] {
+        let entries = match fs::read_dir(trash.join(dir)) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let removed = if fs::symlink_metadata(&path).map(|metadata| metadata.is_dir()).unwrap_or(false) {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+            removed.block_error("trash", &format!("failed to remove {}", path.to_string_lossy()))?;
+        }
+    }
+    // The sizes of trashed directories, cached by file managers
+    let _ = fs::remove_file(trash.join("directorysizes"));
+    Ok(())
+}
+
+/// Shows what's in the trash and empties it on a double click, see
+/// `TrashConfig`.
+pub struct Trash {
+    id: String,
+    output: ButtonWidget,
+    format: FormatTemplate,
+    interval: Duration,
+    items: usize,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TrashConfig {
+    /// Update interval in seconds. Trashing from the home directory shows
+    /// right away.
+    #[serde(default = "TrashConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{count}` and `{size}` are available
+    #[serde(default = "TrashConfig::default_format")]
+    pub format: String,
+}
+
+impl TrashConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_format() -> String {
+        "{count} {size:.1;bin}".to_owned()
+    }
+}
+
+impl ConfigBlock for Trash {
+    type Config = TrashConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        // Every item trashed or restored comes and goes with its info
+        let info = home_trash().join("info");
+        let mask = WatchMask::CREATE | WatchMask::DELETE | WatchMask::MOVED_TO | WatchMask::MOVED_FROM;
+        if let Err(error) = scheduler::watch_file(&info, mask, &id, &tx_update_request) {
+            warn!("trash won't notice trashing before its next update: {:?}", error);
+        }
+
+        Ok(Trash {
+            output: ButtonWidget::new(config, &id)
+                .with_icon("trash")
+                .with_double_click(true),
+            id,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("trash", "Invalid format specified for trash")?,
+            interval: block_config.interval,
+            items: 0,
+            tx_update_request,
+        })
+    }
+}
+
+impl Block for Trash {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let mut items = 0;
+        let mut bytes = 0;
+        for trash in trashes() {
+            if let Ok(entries) = fs::read_dir(trash.join("files")) {
+                for entry in entries.filter_map(|entry| entry.ok()) {
+                    items += 1;
+                    bytes += size(&entry.path());
+                }
+            }
+        }
+        self.items = items;
+
+        let mut values = HashMap::new();
+        values.insert("{count}", Value::Number(items as f64, Unit::None));
+        values.insert("{size}", Value::Number(bytes as f64, Unit::Bytes));
+        self.output.set_text(self.format.render_values(&values)?);
+
+        Ok(Some(self.interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        if self.items > 0 {
+            vec![&self.output]
+        } else {
+            vec![]
+        }
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if !event.name.as_ref().map_or(false, |name| name == &self.id) {
+            return Ok(());
+        }
+        // A single click may well be a slip, emptying the trash can't be
+        // undone
+        if event.button == MouseButton::Left && event.double_click {
+            let (id, tx) = (self.id.clone(), self.tx_update_request.clone());
+            // Large trashes take a while to remove
+            thread::spawn(move || {
+                for trash in trashes() {
+                    if let Err(error) = empty(&trash) {
+                        error!("failed to empty {}: {:?}", trash.to_string_lossy(), error);
+                    }
+                }
+                tx.send(Task {
+                    id,
+                    update_time: Instant::now(),
+                });
+            });
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
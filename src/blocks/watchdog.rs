@@ -0,0 +1,144 @@
+//! A block keeping an eye on other blocks, which turns critical when one of
+//! them hasn't updated for too long, like one stuck failing or crashed.
+//!
+//! Blocks are watched by their `id`. Each one is expected to update within
+//! the time it asked to wait for its next update, see
+//! `supervisor::Heartbeat`, so blocks waiting for events can't go stale.
+//!
+//! While the bar is hidden or the system suspended, nothing updates. All
+//! blocks are due at once after that, so they're given their time again from
+//! then on, see `scheduler::last_rebuild`, rather than being taken for stale
+//! while they wait their turn.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chan::Sender;
+use uuid::Uuid;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use formatting::{FormatTemplate, Value};
+use scheduler::{self, Task};
+use supervisor;
+use util::secs;
+use widget::{I3BarWidget, State};
+use widgets::text::TextWidget;
+
+/// Shows the watched blocks that went stale, see `WatchdogConfig`.
+pub struct Watchdog {
+    id: String,
+    text: TextWidget,
+    format: FormatTemplate,
+    blocks: Vec<String>,
+    factor: f64,
+    interval: Duration,
+    /// When the watchdog was created, blocks get as long to turn up
+    started: Instant,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WatchdogConfig {
+    /// The `id`s of the blocks to watch
+    pub blocks: Vec<String>,
+
+    /// How many times its expected time a block may go without updating
+    #[serde(default = "WatchdogConfig::default_factor")]
+    pub factor: f64,
+
+    /// Update interval in seconds
+    #[serde(default = "WatchdogConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override, `{stale}` and `{count}` are available
+    #[serde(default = "WatchdogConfig::default_format")]
+    pub format: String,
+}
+
+impl WatchdogConfig {
+    fn default_factor() -> f64 {
+        3.
+    }
+
+    fn default_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    fn default_format() -> String {
+        "{stale}".to_owned()
+    }
+}
+
+impl ConfigBlock for Watchdog {
+    type Config = WatchdogConfig;
+
+    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        let id: String = Uuid::new_v4().simple().to_string();
+        if block_config.blocks.is_empty() {
+            return Err(BlockError("watchdog".to_owned(), "no blocks given".to_owned()));
+        }
+
+        Ok(Watchdog {
+            id,
+            text: TextWidget::new(config).with_icon("watchdog"),
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("watchdog", "Invalid format specified for watchdog")?,
+            blocks: block_config.blocks,
+            factor: block_config.factor,
+            interval: block_config.interval,
+            started: Instant::now(),
+        })
+    }
+}
+
+impl Block for Watchdog {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let now = Instant::now();
+        let rebuilt = scheduler::last_rebuild();
+        let mut stale = Vec::new();
+        for name in &self.blocks {
+            let heartbeats = supervisor::heartbeats(name);
+            // A block that never turned up, maybe by a typo in its `id`,
+            // is as good as dead
+            let late = if heartbeats.is_empty() {
+                secs(now.duration_since(self.started)) > secs(self.interval) * self.factor
+            } else {
+                heartbeats.iter().any(|heartbeat| {
+                    let since = match rebuilt {
+                        Some(rebuilt) if rebuilt > heartbeat.last => rebuilt,
+                        _ => heartbeat.last,
+                    };
+                    heartbeat.expected.map_or(false, |expected| {
+                        secs(now.duration_since(since)) > secs(expected) * self.factor
+                    })
+                })
+            };
+            if late {
+                stale.push(name.clone());
+            }
+        }
+
+        let mut values = HashMap::new();
+        values.insert("{stale}", Value::Text(stale.join(" ")));
+        values.insert("{count}", Value::Text(stale.len().to_string()));
+        self.text.set_text(self.format.render_values(&values)?);
+        self.text.set_state(if stale.is_empty() {
+            State::Idle
+        } else {
+            State::Critical
+        });
+
+        Ok(Some(self.interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -1,5 +1,4 @@
 use std::collections::HashMap;
-use std::process::Command;
 use std::time::Duration;
 use chan::Sender;
 use serde_json;
@@ -9,9 +8,10 @@ use block::{Block, ConfigBlock};
 use config::Config;
 use de::deserialize_duration;
 use errors::*;
+use http;
 use input::{I3BarEvent, MouseButton};
 use scheduler::Task;
-use util::FormatTemplate;
+use formatting::FormatTemplate;
 use widgets::button::ButtonWidget;
 use widget::I3BarWidget;
 
@@ -56,26 +56,22 @@ impl Weather {
                 ref city_id,
                 ref units,
             } => {
-                let output = Command::new("sh")
-                    .args(
-                        &[
-                            "-c",
-                            &format!(
-                                "curl -m 3 \"http://api.openweathermap.org/data/2.5/weather?id={city_id}&appid={api_key}&units={units}\" 2> /dev/null",
-                                city_id = city_id,
-                                api_key = api_key,
-                                units = match *units {
-                                    OpenWeatherMapUnits::Metric => "metric",
-                                    OpenWeatherMapUnits::Imperial => "imperial",
-                                },
-                            ),
-                        ],
-                    )
-                    .output()
-                    .block_error("weather", "Failed to exectute curl.")
-                    .and_then(|raw_output| {
-                        String::from_utf8(raw_output.stdout).block_error("weather", "Non-UTF8 SSID.")
-                    })?;
+                let url = format!(
+                    "http://api.openweathermap.org/data/2.5/weather?id={city_id}&appid={api_key}&units={units}",
+                    city_id = city_id,
+                    api_key = api_key,
+                    units = match *units {
+                        OpenWeatherMapUnits::Metric => "metric",
+                        OpenWeatherMapUnits::Imperial => "imperial",
+                    },
+                );
+                // Kept for half the interval, so that restarting the bar
+                // doesn't ask again right away. Failed requests fall back to
+                // the cache, or count as empty responses, see below.
+                let output = http::Request::get(&url)
+                    .with_cache(self.update_interval / 2)
+                    .send()
+                    .unwrap_or_default();
 
                 // Don't error out on empty responses e.g. for when not
                 // connected to the internet.
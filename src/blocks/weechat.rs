@@ -0,0 +1,276 @@
+//! A block showing unread highlights from a WeeChat relay.
+//!
+//! This speaks just enough of the
+//! [WeeChat relay protocol](https://weechat.org/files/doc/stable/weechat_relay_protocol.en.html)
+//! to fetch the hotlist over a plain TCP connection on every update.
+//!
+//! There is no TLS, so the relay password is sent in cleartext. Relays should
+//! only be reached on localhost or through an SSH tunnel.
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use chan::Sender;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use widgets::text::TextWidget;
+use widget::{I3BarWidget, State};
+use scheduler::Task;
+
+use uuid::Uuid;
+
+/// How long to wait for the relay, to connect and then for every read and
+/// write.
+const TIMEOUT: u64 = 3;
+
+/// Indices into the `count` array of a hotlist entry.
+const HOTLIST_PRIVATE: usize = 2;
+const HOTLIST_HIGHLIGHT: usize = 3;
+
+pub struct Weechat {
+    text: TextWidget,
+    id: String,
+    update_interval: Duration,
+    address: String,
+    password: String,
+    hide_empty: bool,
+    highlights: i64,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WeechatConfig {
+    /// Update interval in seconds
+    #[serde(default = "WeechatConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Address of the relay, as `host:port`
+    #[serde(default = "WeechatConfig::default_address")]
+    pub address: String,
+
+    /// Password of the relay, sent in cleartext
+    pub password: String,
+
+    /// Whether to hide the block when there are no highlights
+    #[serde(default = "WeechatConfig::default_hide_empty")]
+    pub hide_empty: bool,
+}
+
+impl WeechatConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(30)
+    }
+
+    fn default_address() -> String {
+        "127.0.0.1:9001".to_owned()
+    }
+
+    fn default_hide_empty() -> bool {
+        true
+    }
+}
+
+/// A cursor over a relay message body.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.pos + n > self.data.len() {
+            return Err(BlockError("weechat".to_owned(), "truncated relay message".to_owned()));
+        }
+        let bytes = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn int(&mut self) -> Result<i32> {
+        let b = self.bytes(4)?;
+        Ok(((b[0] as i32) << 24) | ((b[1] as i32) << 16) | ((b[2] as i32) << 8) | (b[3] as i32))
+    }
+
+    fn type_name(&mut self) -> Result<String> {
+        Ok(String::from_utf8_lossy(self.bytes(3)?).into_owned())
+    }
+
+    fn string(&mut self) -> Result<Option<String>> {
+        let len = self.int()?;
+        if len < 0 {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8_lossy(self.bytes(len as usize)?).into_owned()))
+    }
+
+    /// Strings prefixed by a single length byte, as used by `lon`, `ptr` and `tim`.
+    fn short_string(&mut self) -> Result<String> {
+        let len = self.bytes(1)?[0] as usize;
+        Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+
+    /// Read an object of the given type. Only integers are kept, as that is
+    /// all we need from the hotlist, everything else is skipped.
+    fn object(&mut self, ty: &str) -> Result<Vec<i64>> {
+        match ty {
+            "chr" => self.bytes(1).map(|b| vec![b[0] as i64]),
+            "int" => self.int().map(|i| vec![i as i64]),
+            "lon" | "tim" => self.short_string()
+                .map(|s| s.parse::<i64>().map(|i| vec![i]).unwrap_or_default()),
+            "ptr" => self.short_string().map(|_| vec![]),
+            "str" | "buf" => self.string().map(|_| vec![]),
+            "arr" => {
+                let inner = self.type_name()?;
+                let count = self.int()?;
+                let mut values = Vec::new();
+                for _ in 0..count {
+                    values.extend(self.object(&inner)?);
+                }
+                Ok(values)
+            }
+            _ => Err(BlockError("weechat".to_owned(), format!("unsupported relay object type '{}'", ty))),
+        }
+    }
+}
+
+/// Parse the reply to `hdata hotlist:gui_hotlist(*) priority,count` into the
+/// accumulated (private, highlight) message counts.
+fn parse_hotlist(body: &[u8]) -> Result<(i64, i64)> {
+    let mut reader = Reader { data: body, pos: 0 };
+    // Message id, as sent in our request.
+    reader.string()?;
+    if reader.type_name()? != "hda" {
+        return Err(BlockError("weechat".to_owned(), "unexpected relay reply".to_owned()));
+    }
+
+    let path = reader.string()?.unwrap_or_default();
+    let keys = reader.string()?.unwrap_or_default();
+    let count = reader.int()?;
+
+    let path_len = path.split('/').count();
+    // Keys look like "priority:int,count:arr"
+    let keys: Vec<(String, String)> = keys
+        .split(',')
+        .filter_map(|key| {
+            let mut parts = key.splitn(2, ':');
+            Some((parts.next()?.to_owned(), parts.next()?.to_owned()))
+        })
+        .collect();
+
+    let (mut private, mut highlight) = (0, 0);
+    for _ in 0..count {
+        for _ in 0..path_len {
+            reader.short_string()?;
+        }
+        for &(ref name, ref ty) in &keys {
+            let values = reader.object(ty)?;
+            if name == "count" {
+                private += values.get(HOTLIST_PRIVATE).cloned().unwrap_or(0);
+                highlight += values.get(HOTLIST_HIGHLIGHT).cloned().unwrap_or(0);
+            }
+        }
+    }
+
+    Ok((private, highlight))
+}
+
+impl Weechat {
+    /// Connect to the first address of the relay that answers in time.
+    fn connect(&self) -> Result<TcpStream> {
+        let timeout = Duration::from_secs(TIMEOUT);
+        let addresses = (&self.address as &str)
+            .to_socket_addrs()
+            .block_error("weechat", &format!("failed to resolve relay address '{}'", self.address))?;
+        for address in addresses {
+            match TcpStream::connect_timeout(&address, timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(error) => debug!("failed to connect to relay at {}: {}", address, error),
+            }
+        }
+        Err(BlockError("weechat".to_owned(), "failed to connect to relay".to_owned()))
+    }
+
+    fn query_hotlist(&self) -> Result<(i64, i64)> {
+        let mut stream = self.connect()?;
+        stream.set_read_timeout(Some(Duration::from_secs(TIMEOUT)))
+            .block_error("weechat", "failed to set relay timeout")?;
+        stream.set_write_timeout(Some(Duration::from_secs(TIMEOUT)))
+            .block_error("weechat", "failed to set relay timeout")?;
+
+        write!(
+            stream,
+            "init password={},compression=off\n\
+             (hotlist) hdata hotlist:gui_hotlist(*) priority,count\n",
+            self.password.replace(',', "\\,")
+        ).block_error("weechat", "failed to send relay request")?;
+
+        // Every message starts with its total length, followed by the
+        // compression flag.
+        let mut header = [0u8; 5];
+        stream.read_exact(&mut header)
+            .block_error("weechat", "failed to read relay reply (wrong password?)")?;
+        let length = ((header[0] as usize) << 24) | ((header[1] as usize) << 16) | ((header[2] as usize) << 8) | (header[3] as usize);
+        if length < 5 {
+            return Err(BlockError("weechat".to_owned(), "invalid relay reply".to_owned()));
+        }
+        let mut body = vec![0u8; length - 5];
+        stream.read_exact(&mut body)
+            .block_error("weechat", "failed to read relay reply")?;
+
+        // Not much we could do if this fails.
+        let _ = stream.write_all(b"quit\n");
+
+        parse_hotlist(&body)
+    }
+}
+
+impl ConfigBlock for Weechat {
+    type Config = WeechatConfig;
+
+    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        Ok(Weechat {
+            id: Uuid::new_v4().simple().to_string(),
+            update_interval: block_config.interval,
+            text: TextWidget::new(config).with_icon("chat"),
+            address: block_config.address,
+            password: block_config.password,
+            hide_empty: block_config.hide_empty,
+            highlights: 0,
+        })
+    }
+}
+
+impl Block for Weechat {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let (private, highlight) = self.query_hotlist()?;
+
+        self.highlights = private + highlight;
+        self.text.set_text(format!("{}", self.highlights));
+        self.text.set_state(if private > 0 {
+            State::Critical
+        } else if highlight > 0 {
+            State::Warning
+        } else {
+            State::Idle
+        });
+        // Direct mentions want attention, the rest can wait
+        self.text.set_urgent(private > 0 || highlight > 0);
+
+        Ok(Some(self.update_interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        if self.highlights > 0 || !self.hide_empty {
+            vec![&self.text]
+        } else {
+            vec![]
+        }
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+use chan::Sender;
+
+use block::{Block, ConfigBlock};
+use config::Config;
+use de::deserialize_duration;
+use errors::*;
+use widgets::text::TextWidget;
+use widget::{I3BarWidget, State};
+use scheduler::Task;
+
+use uuid::Uuid;
+
+pub struct Wine {
+    text: TextWidget,
+    id: String,
+    update_interval: Duration,
+    show_exe: bool,
+    running: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WineConfig {
+    /// Update interval in seconds
+    #[serde(default = "WineConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Whether to show the name of the running executable
+    #[serde(default = "WineConfig::default_show_exe")]
+    pub show_exe: bool,
+}
+
+impl WineConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn default_show_exe() -> bool {
+        true
+    }
+}
+
+/// A Windows program running under Wine or Proton.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct WineProcess {
+    /// Steam app id (Proton) or the name of the Wine prefix.
+    label: String,
+    exe: String,
+}
+
+/// Read a NUL separated file from `/proc/<pid>/`. Processes we are not
+/// allowed to inspect, or that exit while we read them, yield `None`.
+fn read_nul_separated(path: &Path) -> Option<Vec<String>> {
+    let mut content = Vec::new();
+    File::open(path).ok()?.read_to_end(&mut content).ok()?;
+    Some(content
+        .split(|&b| b == 0)
+        .filter(|s| !s.is_empty())
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect())
+}
+
+fn inspect_process(proc_path: &Path) -> Option<WineProcess> {
+    let cmdline = read_nul_separated(&proc_path.join("cmdline"))?;
+
+    // Wine rewrites argv[0] to the Windows path of the executable.
+    let exe = cmdline.first()?.replace('\\', "/");
+    if !exe.to_lowercase().ends_with(".exe") {
+        return None;
+    }
+    let exe = exe.rsplit('/').next().unwrap_or(&exe).to_owned();
+
+    // Skip Wine's own helper processes.
+    match exe.to_lowercase().as_str() {
+        "services.exe" | "winedevice.exe" | "plugplay.exe" | "explorer.exe" | "rpcss.exe" | "svchost.exe" | "steam.exe" | "start.exe" | "conhost.exe" | "tabtip.exe" => return None,
+        _ => {}
+    }
+
+    let environ = read_nul_separated(&proc_path.join("environ")).unwrap_or_default();
+    let var = |name: &str| {
+        let prefix = format!("{}=", name);
+        environ
+            .iter()
+            .find(|v| v.starts_with(&prefix))
+            .map(|v| v[prefix.len()..].to_owned())
+    };
+
+    let label = var("SteamAppId")
+        .or_else(|| var("STEAM_COMPAT_APP_ID"))
+        .or_else(|| {
+            var("WINEPREFIX").map(|prefix| {
+                prefix
+                    .trim_right_matches('/')
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or("")
+                    .to_owned()
+            })
+        })
+        .unwrap_or_else(|| "wine".to_owned());
+
+    Some(WineProcess { label, exe })
+}
+
+impl ConfigBlock for Wine {
+    type Config = WineConfig;
+
+    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        Ok(Wine {
+            id: Uuid::new_v4().simple().to_string(),
+            update_interval: block_config.interval,
+            text: TextWidget::new(config).with_icon("wine").with_state(State::Info),
+            show_exe: block_config.show_exe,
+            running: false,
+        })
+    }
+}
+
+impl Block for Wine {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        let entries = fs::read_dir("/proc").block_error("wine", "failed to read /proc")?;
+
+        let processes: BTreeSet<WineProcess> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().chars().all(|c| c.is_digit(10)))
+            .filter_map(|entry| inspect_process(&entry.path()))
+            .collect();
+
+        self.running = !processes.is_empty();
+
+        let text = processes
+            .iter()
+            .map(|p| if self.show_exe {
+                format!("{} ({})", p.label, p.exe)
+            } else {
+                p.label.clone()
+            })
+            .collect::<BTreeSet<String>>()
+            .into_iter()
+            .collect::<Vec<String>>()
+            .join(", ");
+        self.text.set_text(text);
+
+        Ok(Some(self.update_interval))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        if self.running {
+            vec![&self.text]
+        } else {
+            vec![]
+        }
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -4,9 +4,10 @@ use std::str::FromStr;
 use chan::Sender;
 use scheduler::Task;
 
-use util::FormatTemplate;
+use formatting::FormatTemplate;
 
 use block::{Block, ConfigBlock};
+use compat;
 use config::Config;
 use de::deserialize_duration;
 use errors::*;
@@ -217,6 +218,12 @@ impl ConfigBlock for Xrandr {
     type Config = XrandrConfig;
 
     fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+        if compat::is_sway(&config) {
+            return Err(BlockError(
+                "xrandr".to_owned(),
+                "xrandr doesn't work on sway, see the backlight block".to_owned(),
+            ));
+        }
         let id = Uuid::new_v4().simple().to_string();
         let mut step_width = block_config.step_width;
         if step_width > 50 {
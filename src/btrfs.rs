@@ -0,0 +1,258 @@
+//! The space of btrfs filesystems, and their scrubs and balances, asked from
+//! the kernel with the ioctls `btrfs filesystem usage` uses.
+//!
+//! `statvfs` can't tell how much space is left on btrfs: data and metadata
+//! are allocated in chunks of their own, each with a RAID profile that may
+//! store everything two or more times. Free space is thus estimated like
+//! `btrfs filesystem usage` does, from the free space in allocated chunks
+//! and the unallocated space divided by how often data is stored.
+
+use std::fs::File;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use nix::errno::Errno;
+
+use errors::*;
+
+const BLOCK_GROUP_DATA: u64 = 1 << 0;
+const BLOCK_GROUP_METADATA: u64 = 1 << 2;
+const BLOCK_GROUP_RAID0: u64 = 1 << 3;
+const BLOCK_GROUP_RAID1: u64 = 1 << 4;
+const BLOCK_GROUP_DUP: u64 = 1 << 5;
+const BLOCK_GROUP_RAID10: u64 = 1 << 6;
+const BLOCK_GROUP_RAID5: u64 = 1 << 7;
+const BLOCK_GROUP_RAID6: u64 = 1 << 8;
+const BLOCK_GROUP_RAID1C3: u64 = 1 << 9;
+const BLOCK_GROUP_RAID1C4: u64 = 1 << 10;
+/// Metadata kept back for the filesystem's own use
+const SPACE_INFO_GLOBAL_RSV: u64 = 1 << 49;
+
+/// `struct btrfs_ioctl_space_args`, followed by `space_slots` times
+/// `struct btrfs_ioctl_space_info`, its flags, total and used bytes.
+#[repr(C)]
+pub struct SpaceArgs {
+    space_slots: u64,
+    total_spaces: u64,
+}
+
+/// `struct btrfs_ioctl_fs_info_args`
+#[repr(C)]
+#[allow(dead_code)]
+pub struct FsInfoArgs {
+    max_id: u64,
+    num_devices: u64,
+    fsid: [u8; 16],
+    nodesize: u32,
+    sectorsize: u32,
+    clone_alignment: u32,
+    reserved32: u32,
+    reserved: [u64; 122],
+}
+
+/// `struct btrfs_ioctl_dev_info_args`
+#[repr(C)]
+#[allow(dead_code)]
+pub struct DevInfoArgs {
+    devid: u64,
+    uuid: [u8; 16],
+    bytes_used: u64,
+    total_bytes: u64,
+    unused: [u64; 379],
+    path: [u8; 1024],
+}
+
+/// `struct btrfs_ioctl_balance_args`, with the filters of data, metadata and
+/// system chunks left opaque.
+#[repr(C)]
+#[allow(dead_code)]
+pub struct BalanceArgs {
+    flags: u64,
+    state: u64,
+    filters: [u64; 51],
+    expected: u64,
+    considered: u64,
+    completed: u64,
+    unused: [u64; 72],
+}
+
+/// `struct btrfs_ioctl_scrub_args`, with its progress counters left opaque
+/// but for the bytes scrubbed.
+#[repr(C)]
+#[allow(dead_code)]
+pub struct ScrubArgs {
+    devid: u64,
+    start: u64,
+    end: u64,
+    flags: u64,
+    extents_scrubbed: [u64; 2],
+    data_bytes_scrubbed: u64,
+    tree_bytes_scrubbed: u64,
+    progress: [u64; 11],
+    unused: [u64; 109],
+}
+
+ioctl_readwrite!(space_info, 0x94, 20, SpaceArgs);
+ioctl_readwrite!(scrub_progress, 0x94, 29, ScrubArgs);
+ioctl_readwrite!(dev_info, 0x94, 30, DevInfoArgs);
+ioctl_read!(fs_info, 0x94, 31, FsInfoArgs);
+ioctl_read!(balance_progress, 0x94, 34, BalanceArgs);
+
+/// The space of a btrfs filesystem, in bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct Space {
+    pub data_used: u64,
+    /// Estimated, as data chunks yet to be allocated depend on the profile
+    pub data_free: u64,
+    pub metadata_used: u64,
+    pub metadata_free: u64,
+    /// The RAID profile of data, e.g. `raid1`
+    pub profile: &'static str,
+}
+
+/// A scrub or balance going on, e.g. `("scrub", 42.)`.
+pub type Progress = (&'static str, f64);
+
+fn profile(flags: u64) -> &'static str {
+    if flags & BLOCK_GROUP_RAID0 != 0 {
+        "raid0"
+    } else if flags & BLOCK_GROUP_RAID1 != 0 {
+        "raid1"
+    } else if flags & BLOCK_GROUP_DUP != 0 {
+        "dup"
+    } else if flags & BLOCK_GROUP_RAID10 != 0 {
+        "raid10"
+    } else if flags & BLOCK_GROUP_RAID5 != 0 {
+        "raid5"
+    } else if flags & BLOCK_GROUP_RAID6 != 0 {
+        "raid6"
+    } else if flags & BLOCK_GROUP_RAID1C3 != 0 {
+        "raid1c3"
+    } else if flags & BLOCK_GROUP_RAID1C4 != 0 {
+        "raid1c4"
+    } else {
+        "single"
+    }
+}
+
+/// How many bytes on disk a byte stored with the profile in `flags` takes.
+fn ratio(flags: u64, devices: u64) -> f64 {
+    match profile(flags) {
+        "raid1" | "dup" | "raid10" => 2.,
+        "raid1c3" => 3.,
+        "raid1c4" => 4.,
+        "raid5" if devices > 1 => devices as f64 / (devices - 1) as f64,
+        "raid6" if devices > 2 => devices as f64 / (devices - 2) as f64,
+        _ => 1.,
+    }
+}
+
+fn open(block: &str, path: &Path) -> Result<File> {
+    File::open(path).block_error(block, &format!("failed to open {}", path.to_string_lossy()))
+}
+
+/// The devices of the filesystem, their number and ids.
+fn devices(block: &str, file: &File) -> Result<(u64, Vec<DevInfoArgs>)> {
+    let mut args: FsInfoArgs = unsafe { mem::zeroed() };
+    unsafe { fs_info(file.as_raw_fd(), &mut args) }.block_error(block, "not a btrfs filesystem")?;
+    let mut devices = Vec::new();
+    // Ids of removed devices are left out
+    for devid in 1..args.max_id + 1 {
+        let mut dev: DevInfoArgs = unsafe { mem::zeroed() };
+        dev.devid = devid;
+        match unsafe { dev_info(file.as_raw_fd(), &mut dev) } {
+            Ok(_) => devices.push(dev),
+            Err(::nix::Error::Sys(Errno::ENODEV)) => continue,
+            Err(_) => return Err(BlockError(block.to_owned(), "failed to get btrfs devices".to_owned())),
+        }
+    }
+    Ok((args.num_devices, devices))
+}
+
+/// The space of the btrfs filesystem that `path` is on.
+pub fn space(block: &str, path: &Path) -> Result<Space> {
+    let file = open(block, path)?;
+    let (num_devices, devices) = devices(block, &file)?;
+    let size: u64 = devices.iter().map(|dev| dev.total_bytes).sum();
+    let allocated: u64 = devices.iter().map(|dev| dev.bytes_used).sum();
+    let unallocated = size.saturating_sub(allocated) as f64;
+
+    // Ask how many kinds of chunks there are first, then for all of them
+    let mut args = SpaceArgs {
+        space_slots: 0,
+        total_spaces: 0,
+    };
+    unsafe { space_info(file.as_raw_fd(), &mut args) }.block_error(block, "failed to get btrfs space")?;
+    let slots = args.total_spaces as usize;
+    let mut buffer = vec![0u64; 2 + 3 * slots];
+    buffer[0] = slots as u64;
+    unsafe { space_info(file.as_raw_fd(), buffer.as_mut_ptr() as *mut SpaceArgs) }
+        .block_error(block, "failed to get btrfs space")?;
+    let count = (buffer[1] as usize).min(slots);
+
+    let mut space = Space {
+        data_used: 0,
+        data_free: 0,
+        metadata_used: 0,
+        metadata_free: 0,
+        profile: "single",
+    };
+    let mut reserved = 0;
+    for info in buffer[2..2 + 3 * count].chunks(3) {
+        let (flags, total, used) = (info[0], info[1], info[2]);
+        let free = (total.saturating_sub(used) as f64 + unallocated / ratio(flags, num_devices)) as u64;
+        if flags & SPACE_INFO_GLOBAL_RSV != 0 {
+            reserved = total;
+        } else if flags & BLOCK_GROUP_DATA != 0 {
+            // Small filesystems mix data and metadata in the same chunks
+            space.data_used = used;
+            space.data_free = free;
+            space.profile = profile(flags);
+            if flags & BLOCK_GROUP_METADATA != 0 {
+                space.metadata_used = used;
+                space.metadata_free = free;
+            }
+        } else if flags & BLOCK_GROUP_METADATA != 0 {
+            space.metadata_used = used;
+            space.metadata_free = free;
+        }
+    }
+    // Running out of the rest of metadata space fails writes already
+    space.metadata_free = space.metadata_free.saturating_sub(reserved);
+    Ok(space)
+}
+
+/// The scrub or balance running on the btrfs filesystem that `path` is on,
+/// if any. Both can only be asked by root, or with `CAP_SYS_ADMIN`, so
+/// they're `None` for everyone else.
+pub fn progress(block: &str, path: &Path) -> Result<Option<Progress>> {
+    let file = open(block, path)?;
+
+    let mut balance: BalanceArgs = unsafe { mem::zeroed() };
+    if unsafe { balance_progress(file.as_raw_fd(), &mut balance) }.is_ok() && balance.expected > 0 {
+        return Ok(Some(("balance", balance.completed as f64 / balance.expected as f64 * 100.)));
+    }
+
+    // Scrubs run on each device, and are done once all of them are
+    let (_, devices) = devices(block, &file)?;
+    let mut running = false;
+    let (mut scrubbed, mut total) = (0, 0);
+    for dev in &devices {
+        let mut scrub: ScrubArgs = unsafe { mem::zeroed() };
+        scrub.devid = dev.devid;
+        if unsafe { scrub_progress(file.as_raw_fd(), &mut scrub) }.is_ok() {
+            running = true;
+            scrubbed += scrub.data_bytes_scrubbed + scrub.tree_bytes_scrubbed;
+        } else {
+            // Done or not started, either way nothing left to scrub
+            scrubbed += dev.bytes_used;
+        }
+        total += dev.bytes_used;
+    }
+    Ok(if running && total > 0 {
+        Some(("scrub", (scrubbed as f64 / total as f64 * 100.).min(100.)))
+    } else {
+        None
+    })
+}
@@ -0,0 +1,203 @@
+//! Putting blocks together into a bar of your own.
+//!
+//! Unlike the `i3status-rs` binary, a bar built this way has no
+//! configuration file to reload, no control socket and no signal handling.
+//! The common block options, like `signal` or `page`, don't apply either.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chan::{self, Receiver, Sender};
+use serde_json::value::Value;
+use toml::value::Value as TomlValue;
+
+use block::{Block, CommonBlockConfig};
+use blocks::create_block;
+use config::Config;
+use errors::*;
+use input::{process_events, I3BarEvent};
+use output::OutputFormat;
+use scheduler::{Task, UpdateScheduler};
+use state::BlockState;
+use util;
+
+pub struct BarBuilder {
+    config: Config,
+    blocks: Vec<Box<Block>>,
+    /// How many blocks of each type came so far, for their state keys
+    counts: HashMap<String, usize>,
+    tx_update_requests: Sender<Task>,
+    rx_update_requests: Receiver<Task>,
+}
+
+impl BarBuilder {
+    /// Start a bar with the theme, icons and other global settings of
+    /// `config`. Its blocks are ignored, add them with `block`.
+    pub fn new(config: Config) -> BarBuilder {
+        let (tx_update_requests, rx_update_requests) = chan::async();
+        BarBuilder {
+            config,
+            blocks: Vec::new(),
+            counts: HashMap::new(),
+            tx_update_requests,
+            rx_update_requests,
+        }
+    }
+
+    /// The global settings blocks are to be created with.
+    pub fn config(&self) -> Config {
+        self.config.clone()
+    }
+
+    /// Where blocks send their update requests, to be passed to
+    /// `ConfigBlock::new` of custom blocks.
+    pub fn update_sender(&self) -> Sender<Task> {
+        self.tx_update_requests.clone()
+    }
+
+    /// Add the built-in block `name`, configured by `block_config` just like
+    /// in a configuration file, without the `block` key.
+    pub fn block(&mut self, name: &str, block_config: TomlValue) -> Result<&mut BarBuilder> {
+        let count = {
+            let count = self.counts.entry(name.to_owned()).or_insert(0);
+            *count += 1;
+            *count - 1
+        };
+        let block = create_block(
+            name,
+            block_config,
+            self.config.clone(),
+            self.tx_update_requests.clone(),
+            BlockState::new(&format!("{}.{}", name, count)),
+        )?;
+        self.blocks.push(block);
+        Ok(self)
+    }
+
+    /// Add a block of your own, created with `config()` and `update_sender()`.
+    pub fn custom_block(&mut self, block: Box<Block>) -> &mut BarBuilder {
+        self.blocks.push(block);
+        self
+    }
+
+    pub fn build(self) -> StatusBar {
+        let order: Vec<String> = self.blocks.iter().map(|block| block.id().to_owned()).collect();
+        StatusBar {
+            scheduler: UpdateScheduler::new(&order),
+            order,
+            block_map: self.blocks
+                .into_iter()
+                .map(|block| (block.id().to_owned(), block))
+                .collect(),
+            config: self.config,
+            tx_update_requests: self.tx_update_requests,
+            rx_update_requests: self.rx_update_requests,
+        }
+    }
+}
+
+/// A bar built by `BarBuilder`. Either leave it to `run`, or drive it
+/// yourself with `update`, `click` and `render`.
+pub struct StatusBar {
+    config: Config,
+    /// The ids of the blocks, in bar order
+    order: Vec<String>,
+    block_map: HashMap<String, Box<Block>>,
+    scheduler: UpdateScheduler,
+    // Kept so that the channel stays open without blocks asking for updates
+    #[allow(dead_code)]
+    tx_update_requests: Sender<Task>,
+    rx_update_requests: Receiver<Task>,
+}
+
+impl StatusBar {
+    /// The update requests of the blocks, to wait on alongside
+    /// `time_to_next_update`. Pass them on to `update_requested`.
+    pub fn update_requests(&self) -> &Receiver<Task> {
+        &self.rx_update_requests
+    }
+
+    /// How long until the next block is due for an update.
+    pub fn time_to_next_update(&self) -> Option<Duration> {
+        self.scheduler.time_to_next_update()
+    }
+
+    /// Update the blocks that are due.
+    pub fn update(&mut self) -> Result<()> {
+        if self.time_to_next_update() == Some(Duration::from_secs(0)) {
+            self.scheduler.do_scheduled_updates(&mut self.block_map)?;
+        }
+        Ok(())
+    }
+
+    /// Update the block that asked for it with `task`, right away.
+    pub fn update_requested(&mut self, task: &Task) -> Result<()> {
+        if let Some(block) = self.block_map.get_mut(&task.id) {
+            block.update()?;
+        }
+        Ok(())
+    }
+
+    /// Pass a click on to the block whose widget was clicked.
+    pub fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        match util::block_of_event(&self.order, event) {
+            Some(position) => self.block_map
+                .get_mut(&self.order[position])
+                .internal_error("builder", "could not get required block")?
+                .click(event),
+            None => Ok(()),
+        }
+    }
+
+    /// The widgets of each block, in bar order, for `OutputFormat::line`.
+    pub fn render(&self) -> Result<Vec<Vec<Value>>> {
+        let common = CommonBlockConfig::default();
+        let mut rendered = Vec::new();
+        for id in &self.order {
+            let block = self.block_map
+                .get(id)
+                .internal_error("builder", "could not get required block")?;
+            rendered.push(util::render_block(&**block, &common));
+        }
+        Ok(rendered)
+    }
+
+    /// Run the bar on stdout in `output` format, reading click events from
+    /// stdin if it takes any. Only returns on errors.
+    #[allow(unused_mut)] // TODO: Remove when fixed in chan_select
+    pub fn run(mut self, output: &OutputFormat) -> Result<()> {
+        if let Some(header) = output.header() {
+            print!("{}", header);
+        }
+
+        let (tx_clicks, rx_clicks) = chan::async();
+        if output.click_events() {
+            process_events(tx_clicks.clone());
+        }
+        let rx_update_requests = self.rx_update_requests.clone();
+
+        let mut last_line = String::new();
+        loop {
+            self.update()?;
+            let line = output.line(&self.render()?, &self.config)?;
+            if line != last_line {
+                println!("{}", line);
+                last_line = line;
+            }
+
+            let ttnu = match self.time_to_next_update() {
+                Some(duration) => chan::after(duration),
+                None => chan::after(Duration::from_secs(3600)),
+            };
+            chan_select! {
+                rx_clicks.recv() -> res => if let Some(event) = res {
+                    self.click(&event)?;
+                },
+                rx_update_requests.recv() -> res => if let Some(task) = res {
+                    self.update_requested(&task)?;
+                },
+                ttnu.recv() => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Checking a configuration without starting the bar, see `--check`.
+//!
+//! This catches syntax errors as well as misspelled or mistyped options.
+//! Problems only found when a block starts, like a missing device, are not
+//! reported.
+
+use std::fs::File;
+use std::io::Read;
+
+use regex::Regex;
+use toml::value::Value;
+
+use bars;
+use block::CommonBlockConfig;
+use blocks::check_block;
+use config::{self, Config};
+use errors::*;
+use reload;
+
+/// The problems found in the configuration at `path`, none if it's fine.
+pub fn check_config(path: &str) -> Vec<String> {
+    let config = match config::load(path) {
+        Ok(config) => config,
+        Err(error) => return vec![format!("{}: {}", path, cause(&error))],
+    };
+
+    let lines = block_lines(path);
+    let mut problems = Vec::new();
+    if let Err(error) = bars::check(&config) {
+        problems.push(format!("{}: {}", path, cause(&error)));
+    }
+    for (index, &(ref name, ref block_config)) in config.blocks.iter().enumerate() {
+        if let Err(error) = check_one(name, block_config.clone(), &config) {
+            // Blocks from included files come last, and we don't know where
+            // exactly they are.
+            let location = match lines.get(index) {
+                Some(line) => format!("{}:{}", path, line),
+                None => format!("{}: block #{}", path, index + 1),
+            };
+            let cause = cause(&error);
+            let mut problem = format!("{}: '{}' block: {}", location, name, cause);
+            if let Some(suggestion) = suggest(&cause) {
+                problem.push_str(&format!(", did you mean `{}`?", suggestion));
+            }
+            problems.push(problem);
+        }
+    }
+    problems
+}
+
+/// Check a single block of type `name`, as `reload` would set it up.
+fn check_one(name: &str, mut block_config: Value, config: &Config) -> Result<()> {
+    CommonBlockConfig::extract(&mut block_config)?;
+    reload::resolve_interval(name, &mut block_config, config)?;
+    check_block(name, block_config)
+}
+
+/// What went wrong, without the context that's clear from the location.
+fn cause(error: &Error) -> String {
+    match *error {
+        ConfigurationError(_, (ref cause, _)) => cause.clone(),
+        BlockError(_, ref message) => message.clone(),
+        ref error => format!("{}", error),
+    }
+}
+
+/// The line numbers of the `[[block]]` headers in the file at `path`.
+fn block_lines(path: &str) -> Vec<usize> {
+    let mut contents = String::new();
+    if File::open(path).and_then(|mut file| file.read_to_string(&mut contents)).is_err() {
+        return Vec::new();
+    }
+    contents
+        .lines()
+        .enumerate()
+        .filter(|&(_, line)| line.trim().starts_with("[[block]]"))
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// For an unknown option, the known one it most likely is a typo of.
+fn suggest(cause: &str) -> Option<String> {
+    lazy_static! {
+        static ref UNKNOWN: Regex = Regex::new(r"unknown field `([^`]*)`").unwrap();
+        static ref QUOTED: Regex = Regex::new(r"`([^`]*)`").unwrap();
+    }
+    let unknown = UNKNOWN.captures(cause)?.get(1)?.as_str();
+    let expected = cause.splitn(2, "expected").nth(1)?;
+
+    QUOTED
+        .captures_iter(expected)
+        .filter_map(|captures| captures.get(1).map(|m| m.as_str()))
+        .chain(CommonBlockConfig::KEYS.iter().cloned())
+        .map(|known| (distance(unknown, known), known))
+        .filter(|&(distance, known)| distance <= 2 && distance < known.len())
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, known)| known.to_owned())
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..b.len() + 1).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,50 @@
+//! Telling i3bar and swaybar apart.
+//!
+//! Both speak the same protocol, but some things only work with one of them,
+//! e.g. `xrandr` isn't available on sway. The global `bar_type` option says
+//! which bar we're running in, `auto` (the default) goes by whether sway is
+//! running.
+//!
+//! On sway, i3 IPC is pointed at sway's socket, so blocks using it, like
+//! `focused_window`, work there just as well.
+
+use std::env;
+
+use config::Config;
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BarType {
+    Auto,
+    I3,
+    Sway,
+}
+
+/// The bar `bar_type` stands for, never `Auto`.
+pub fn resolve(bar_type: BarType) -> BarType {
+    match bar_type {
+        BarType::Auto => if env::var_os("SWAYSOCK").is_some() {
+            BarType::Sway
+        } else {
+            BarType::I3
+        },
+        bar_type => bar_type,
+    }
+}
+
+/// Whether the bar of `config` is swaybar.
+pub fn is_sway(config: &Config) -> bool {
+    resolve(config.bar_type) == BarType::Sway
+}
+
+/// Get ready to run in the bar of `config`. Has to be called before any
+/// thread is spawned, as it may change the environment.
+pub fn init(config: &Config) {
+    let bar_type = resolve(config.bar_type);
+    debug!("running in {:?}", bar_type);
+    if bar_type == BarType::Sway && env::var_os("I3SOCK").is_none() {
+        if let Some(socket) = env::var_os("SWAYSOCK") {
+            env::set_var("I3SOCK", socket);
+        }
+    }
+}
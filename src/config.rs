@@ -1,21 +1,190 @@
+use chrono::NaiveTime;
+use compat::BarType;
 use de::*;
+use errors::{self, ConfigurationError, ResultExtInternal};
 use icons;
+use regex::{self, Captures, Regex};
 use serde::de::{self, Deserialize, Deserializer};
+use toml;
 use toml::value;
 use std::collections::HashMap as Map;
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
 use std::marker::PhantomData;
 use std::ops::Deref;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 use themes::{self, Theme};
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct Config {
     #[serde(default = "icons::default", deserialize_with = "deserialize_icons")]
     pub icons: Map<String, String>,
     #[serde(default = "themes::default", deserialize_with = "deserialize_themes")]
     pub theme: Theme,
+    /// Themes to switch between, see `appearance`
+    #[serde(default = "Config::default_theme_switch")]
+    pub theme_switch: Option<ThemeSwitchConfig>,
+    /// Minimum time between two redraws of the bar, bursts of updates within
+    /// it are coalesced into a single redraw
+    #[serde(default = "Config::default_debounce", deserialize_with = "deserialize_duration")]
+    pub debounce: Duration,
+    /// Maximum time between the two clicks of a double click
+    #[serde(default = "Config::default_double_click_interval", deserialize_with = "deserialize_duration")]
+    pub double_click_interval: Duration,
+    /// Update interval of blocks that don't set their own, see
+    /// `reload::resolve_interval`
+    #[serde(default = "Config::default_interval", deserialize_with = "deserialize_opt_duration")]
+    pub interval: Option<Duration>,
+    /// Updates taking longer than this are logged, see `supervisor`
+    #[serde(default = "Config::default_update_budget", deserialize_with = "deserialize_duration")]
+    pub update_budget: Duration,
+    /// Mark blocks whose updates keep going over their budget in the bar
+    #[serde(default = "Config::default_mark_slow_blocks")]
+    pub mark_slow_blocks: bool,
+    /// Configured intervals shorter than this are rejected
+    #[serde(default = "Config::default_min_interval", deserialize_with = "deserialize_duration")]
+    pub min_interval: Duration,
     #[serde(rename = "block", deserialize_with = "deserialize_blocks")]
     pub blocks: Vec<(String, value::Value)>,
+    /// Further bars showing some of the blocks, see `bars`
+    #[serde(rename = "bar", default = "Config::default_bars")]
+    pub bars: Vec<BarConfig>,
+    /// The bar we're running in, see `compat`
+    #[serde(default = "Config::default_bar_type")]
+    pub bar_type: BarType,
+    /// Where to export what blocks measure, see `metrics`
+    #[serde(default = "Config::default_metrics")]
+    pub metrics: Option<MetricsConfig>,
+}
+
+impl Config {
+    fn default_theme_switch() -> Option<ThemeSwitchConfig> {
+        None
+    }
+
+    fn default_debounce() -> Duration {
+        Duration::from_secs(0)
+    }
+
+    fn default_double_click_interval() -> Duration {
+        Duration::from_millis(300)
+    }
+
+    fn default_interval() -> Option<Duration> {
+        None
+    }
+
+    fn default_update_budget() -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn default_mark_slow_blocks() -> bool {
+        false
+    }
+
+    fn default_min_interval() -> Duration {
+        Duration::from_millis(100)
+    }
+
+    fn default_bars() -> Vec<BarConfig> {
+        Vec::new()
+    }
+
+    fn default_bar_type() -> BarType {
+        BarType::Auto
+    }
+
+    fn default_metrics() -> Option<MetricsConfig> {
+        None
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BarConfig {
+    pub name: String,
+    /// The blocks shown, by type or id, in this order
+    pub blocks: Vec<String>,
+    /// Where the bar is served, on stdout if not set
+    #[serde(default = "BarConfig::default_socket")]
+    pub socket: Option<String>,
+    /// The output format, see `output::from_name`. Only applies to bars
+    /// served on a socket, stdout uses `--format`.
+    #[serde(default = "BarConfig::default_format")]
+    pub format: String,
+}
+
+impl BarConfig {
+    fn default_socket() -> Option<String> {
+        None
+    }
+
+    fn default_format() -> String {
+        "i3bar".to_owned()
+    }
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemeSource {
+    /// The `color-scheme` setting of the desktop portal
+    Portal,
+    /// `light_from` and `dark_from`
+    Schedule,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeSwitchConfig {
+    /// Used instead of `theme` while the color scheme is light
+    #[serde(deserialize_with = "deserialize_themes")]
+    pub light: Theme,
+    /// Used instead of `theme` while the color scheme is dark
+    #[serde(deserialize_with = "deserialize_themes")]
+    pub dark: Theme,
+    /// What tells the color scheme
+    #[serde(default = "ThemeSwitchConfig::default_source")]
+    pub source: SchemeSource,
+    /// When the light scheme starts, for the `schedule` source
+    #[serde(default = "ThemeSwitchConfig::default_light_from", deserialize_with = "deserialize_time_of_day")]
+    pub light_from: NaiveTime,
+    /// When the dark scheme starts, for the `schedule` source
+    #[serde(default = "ThemeSwitchConfig::default_dark_from", deserialize_with = "deserialize_time_of_day")]
+    pub dark_from: NaiveTime,
+}
+
+impl ThemeSwitchConfig {
+    fn default_source() -> SchemeSource {
+        SchemeSource::Portal
+    }
+
+    fn default_light_from() -> NaiveTime {
+        NaiveTime::from_hms(7, 0, 0)
+    }
+
+    fn default_dark_from() -> NaiveTime {
+        NaiveTime::from_hms(19, 0, 0)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// The file to write, e.g. in the textfile collector directory of the
+    /// Prometheus node exporter
+    pub path: String,
+    /// How often to write the file at most
+    #[serde(default = "MetricsConfig::default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+}
+
+impl MetricsConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(15)
+    }
 }
 
 impl Default for Config {
@@ -23,9 +192,137 @@ impl Default for Config {
         Config {
             icons: icons::default(),
             theme: themes::default(),
+            theme_switch: Config::default_theme_switch(),
+            debounce: Config::default_debounce(),
+            double_click_interval: Config::default_double_click_interval(),
+            interval: Config::default_interval(),
+            update_budget: Config::default_update_budget(),
+            mark_slow_blocks: Config::default_mark_slow_blocks(),
+            min_interval: Config::default_min_interval(),
             blocks: Vec::new(),
+            bars: Vec::new(),
+            bar_type: Config::default_bar_type(),
+            metrics: Config::default_metrics(),
+        }
+    }
+}
+
+/// Read the configuration at `path`, along with the files it includes, and
+/// fill in the environment variables it refers to.
+///
+/// `include` is a list of further files, whose blocks are added after those
+/// of the including file. Their other settings only apply if the including
+/// file doesn't have them. File names may contain `*` and `?` wildcards, and
+/// relative paths are relative to the including file. Included files can't
+/// include others in turn.
+///
+/// `${NAME}` in any string is replaced by the environment variable `NAME`.
+pub fn load(path: &str) -> errors::Result<Config> {
+    let path = Path::new(path);
+    let mut table = read_table(path)?;
+
+    if let Some(include) = table.remove("include") {
+        let patterns: Vec<String> = include.try_into().configuration_error("`include` must be a list of paths")?;
+        let base = path.parent().unwrap_or_else(|| Path::new("."));
+        for pattern in patterns {
+            for file in expand_include(base, &pattern)? {
+                let mut included = read_table(&file)?;
+                included.remove("include");
+                merge_include(&mut table, included);
+            }
+        }
+    }
+
+    let mut config = value::Value::Table(table);
+    interpolate_env(&mut config)?;
+    Config::deserialize(config).configuration_error("failed to parse configuration")
+}
+
+fn read_table(path: &Path) -> errors::Result<value::Table> {
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .configuration_error(&format!("failed to read {}", path.display()))?;
+    toml::from_str(&contents).configuration_error(&format!("failed to parse TOML from {}", path.display()))
+}
+
+/// The files matched by an `include` pattern, in alphabetical order.
+fn expand_include(base: &Path, pattern: &str) -> errors::Result<Vec<PathBuf>> {
+    let pattern = if pattern.starts_with("~/") {
+        PathBuf::from(env::var("HOME").configuration_error("`~` in `include`, but $HOME is not set")?).join(&pattern[2..])
+    } else {
+        base.join(pattern)
+    };
+
+    let name = pattern
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or("no file name, or not UTF-8")
+        .configuration_error("invalid `include` pattern")?;
+    if !name.contains('*') && !name.contains('?') {
+        return Ok(vec![pattern.clone()]);
+    }
+
+    let regex = Regex::new(&format!(
+        "^{}$",
+        regex::escape(name).replace(r"\*", ".*").replace(r"\?", ".")
+    )).configuration_error("invalid `include` pattern")?;
+    let dir = pattern.parent().unwrap_or_else(|| Path::new("."));
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .configuration_error(&format!("failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.file_name().and_then(|name| name.to_str()).map_or(false, |name| regex.is_match(name)))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn merge_include(table: &mut value::Table, included: value::Table) {
+    for (key, value) in included {
+        if key == "block" {
+            if let Some(&mut value::Value::Array(ref mut blocks)) = table.get_mut(&key) {
+                if let value::Value::Array(more) = value {
+                    blocks.extend(more);
+                }
+                continue;
+            }
+        }
+        table.entry(key).or_insert(value);
+    }
+}
+
+fn interpolate_env(value: &mut value::Value) -> errors::Result<()> {
+    match *value {
+        value::Value::String(ref mut s) => {
+            lazy_static! {
+                static ref VARIABLE: Regex = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+            }
+            if !VARIABLE.is_match(s) {
+                return Ok(());
+            }
+            // Variables are checked first, `replace_all` can't fail.
+            for captures in VARIABLE.captures_iter(s) {
+                if env::var(&captures[1]).is_err() {
+                    return Err(ConfigurationError(
+                        format!("environment variable {} is not set", &captures[1]),
+                        (captures[0].to_owned(), captures[0].to_owned()),
+                    ));
+                }
+            }
+            *s = VARIABLE
+                .replace_all(s, |captures: &Captures| env::var(&captures[1]).unwrap_or_default())
+                .into_owned();
         }
+        value::Value::Array(ref mut values) => for value in values {
+            interpolate_env(value)?;
+        },
+        value::Value::Table(ref mut table) => for (_, value) in table.iter_mut() {
+            interpolate_env(value)?;
+        },
+        _ => {}
     }
+    Ok(())
 }
 
 fn deserialize_blocks<'de, D>(deserializer: D) -> Result<Vec<(String, value::Value)>, D::Error>
@@ -60,7 +357,7 @@ where
     D: Deserializer<'de>,
 {
     map_type!(ThemeIntermediary, String;
-              s => Ok(ThemeIntermediary(themes::get_theme(s).ok_or_else(|| "cannot find specified theme")?.owned_map())));
+              s => Ok(ThemeIntermediary(themes::load_theme(s)?.owned_map())));
 
     let intermediary: Map<String, String> = deserializer
         .deserialize_any(MapType::<ThemeIntermediary, String>(
@@ -0,0 +1,130 @@
+//! The bar's own D-Bus interface.
+//!
+//! The bar owns `rs.i3status` on the session bus and exports the
+//! `rs.i3status.Bar` interface at `/`, with the methods
+//!
+//! * `Refresh(s block)` to update blocks right away
+//! * `SetVisible(s block, b visible)` to hide or show blocks
+//!
+//! which take the same block names as the control socket (see `ipc`), and
+//! the signal `Clicked(s block, s button)`, emitted for clicks on blocks.
+
+use std::thread;
+
+use chan;
+use chan::{Receiver, Sender};
+
+use dbus::{BusType, Connection, Interface, Member, Message, NameFlag, Path};
+use dbus::tree::{Factory, MethodErr};
+use dbus_manager::{self, WakeSocket};
+use errors::*;
+use ipc::{Command, Request};
+
+const BUS_NAME: &str = "rs.i3status";
+const OBJECT_PATH: &str = "/";
+const INTERFACE: &str = "rs.i3status.Bar";
+
+/// Pass `command` on to the bar and wait for the outcome.
+fn forward(tx: &Sender<Request>, command: Command) -> ::std::result::Result<(), MethodErr> {
+    let (reply, rx_reply) = chan::async();
+    tx.send(Request { command, reply });
+    match rx_reply.recv() {
+        Some(Ok(())) => Ok(()),
+        Some(Err(error)) => Err(MethodErr::failed(&error)),
+        None => Err(MethodErr::failed(&"no reply from the bar")),
+    }
+}
+
+fn serve(tx: Sender<Request>, rx_clicks: Receiver<(String, String)>) -> Result<()> {
+    let c = Connection::get_private(BusType::Session)
+        .internal_error("dbus_interface", "failed to establish D-Bus connection")?;
+    c.register_name(BUS_NAME, NameFlag::DoNotQueue as u32)
+        .internal_error("dbus_interface", "failed to acquire bus name, is another bar running?")?;
+
+    let f = Factory::new_fn::<()>();
+    let tx_refresh = tx.clone();
+    let tx_visible = tx;
+    let tree = f.tree(()).add(
+        f.object_path(OBJECT_PATH, ()).introspectable().add(
+            f.interface(INTERFACE, ())
+                .add_m(
+                    f.method("Refresh", (), move |m| {
+                        let block: &str = m.msg.read1()?;
+                        forward(&tx_refresh, Command::Refresh { block: block.to_owned() })?;
+                        Ok(vec![m.msg.method_return()])
+                    }).inarg::<&str, _>("block"),
+                )
+                .add_m(
+                    f.method("SetVisible", (), move |m| {
+                        let (block, visible): (&str, bool) = m.msg.read2()?;
+                        let block = block.to_owned();
+                        let command = if visible {
+                            Command::Show { block }
+                        } else {
+                            Command::Hide { block }
+                        };
+                        forward(&tx_visible, command)?;
+                        Ok(vec![m.msg.method_return()])
+                    }).inarg::<&str, _>("block")
+                        .inarg::<bool, _>("visible"),
+                )
+                .add_s(
+                    f.signal("Clicked", ())
+                        .sarg::<&str, _>("block")
+                        .sarg::<&str, _>("button"),
+                ),
+        ),
+    );
+    tree.set_registered(&c, true)
+        .internal_error("dbus_interface", "failed to register object")?;
+    c.add_handler(tree);
+
+    // Clicks are passed on along with a wake-up, to not wait for the next
+    // method call to send them
+    let wake = WakeSocket::new()?;
+    let waker = wake.waker();
+    let (tx_pending, rx_pending) = chan::async();
+    thread::spawn(move || {
+        for click in rx_clicks.iter() {
+            tx_pending.send(click);
+            waker.wake();
+        }
+        drop(tx_pending);
+        waker.wake();
+    });
+
+    loop {
+        // Method calls are dispatched to the tree while iterating.
+        for _ in c.incoming(0) {}
+
+        loop {
+            chan_select! {
+                default => break,
+                rx_pending.recv() -> res => match res {
+                    Some((block, button)) => {
+                        let signal = Message::signal(
+                            &Path::from(OBJECT_PATH),
+                            &Interface::from(INTERFACE),
+                            &Member::from("Clicked"),
+                        ).append2(block, button);
+                        // Nobody might be listening, which is fine.
+                        let _ = c.send(signal);
+                    }
+                    // The bar is gone.
+                    None => return Ok(()),
+                },
+            }
+        }
+
+        dbus_manager::poll(&[&c], &wake)?;
+    }
+}
+
+/// Export the interface, forwarding method calls to `tx` and emitting a
+/// signal for every `(block, button)` click received on `rx_clicks`. Failing
+/// to do so is not fatal, as the bar works fine without it.
+pub fn spawn_server(tx: Sender<Request>, rx_clicks: Receiver<(String, String)>) {
+    thread::spawn(move || if let Err(error) = serve(tx, rx_clicks) {
+        warn!("D-Bus interface unavailable: {:?}", error);
+    });
+}
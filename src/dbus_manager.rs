@@ -0,0 +1,343 @@
+//! Shared D-Bus connections and signal subscriptions.
+//!
+//! Instead of every block opening its own connections and spawning a thread
+//! to wait for signals, blocks subscribe to signals here. A single listener
+//! thread adds the match rules of all subscriptions to its connections and
+//! dispatches each signal to the blocks whose rules it matches, by requesting
+//! an update through the scheduler. It polls the sockets of both buses, so
+//! signals are dispatched as soon as they come in, and sleeps otherwise.
+//! Blocks removed by a reload are unsubscribed, and match rules no block
+//! subscribes to anymore are removed from the bus.
+//!
+//! Blocks are only ever created and used in the main thread, so connections
+//! for method calls are shared per thread.
+
+use std::cell::RefCell;
+use std::io::{Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chan;
+use chan::{Receiver, Sender};
+use dbus::{BusType, Connection, Message, MessageType};
+use nix;
+use nix::errno::Errno;
+use nix::poll::{EventFlags, PollFd};
+
+use errors::*;
+use scheduler::Task;
+
+/// A parsed match rule, e.g. `type='signal',interface='org.foo',member='Bar'`.
+struct Rule {
+    conditions: Vec<(String, String)>,
+}
+
+impl Rule {
+    fn parse(rule: &str) -> Result<Rule> {
+        let mut conditions = Vec::new();
+        let mut rest = rule.trim();
+        while !rest.is_empty() {
+            let eq = rest.find("='")
+                .internal_error("dbus_manager", &format!("invalid match rule: {}", rule))?;
+            let key = rest[..eq].trim().to_owned();
+            let value_start = eq + 2;
+            let value_end = rest[value_start..]
+                .find('\'')
+                .internal_error("dbus_manager", &format!("invalid match rule: {}", rule))? + value_start;
+            conditions.push((key, rest[value_start..value_end].to_owned()));
+            rest = rest[value_end + 1..].trim_left_matches(',').trim();
+        }
+        Ok(Rule { conditions })
+    }
+
+    /// Whether `msg` matches the rule. As several rules share a connection,
+    /// the bus delivers signals matching any of them, so they have to be told
+    /// apart here.
+    fn matches(&self, msg: &Message) -> bool {
+        self.conditions.iter().all(|&(ref key, ref value)| match (key.as_str(), value.as_str()) {
+            ("type", value) => value == "signal",
+            ("path", value) => msg.path().map_or(false, |path| &*path == value),
+            ("path_namespace", value) => msg.path().map_or(false, |path| {
+                &*path == value || path.starts_with(&format!("{}/", value))
+            }),
+            ("interface", value) => msg.interface().map_or(false, |interface| &*interface == value),
+            ("member", value) => msg.member().map_or(false, |member| &*member == value),
+            // Signals carry the unique name of the sender, so well-known names
+            // can't be checked here. The bus already filtered on them.
+            ("sender", value) if !value.starts_with(':') => true,
+            ("sender", value) => msg.sender().map_or(false, |sender| &*sender == value),
+            ("arg0", value) => msg.get1::<&str>().map_or(false, |arg0| arg0 == value),
+            _ => true,
+        })
+    }
+}
+
+struct Subscription {
+    bus: BusType,
+    rule: String,
+    id: String,
+    tx_update_request: Sender<Task>,
+    reply: Sender<Result<()>>,
+}
+
+/// What the listener thread is asked to do.
+enum Request {
+    Subscribe(Subscription),
+    /// Drop the subscriptions of the block with this id
+    Unsubscribe(String),
+}
+
+/// Lets a thread waiting in `poll` know that there's something else to do,
+/// through a socket it polls along with its connections.
+#[derive(Clone)]
+pub struct Waker(Arc<UnixStream>);
+
+impl Waker {
+    pub fn wake(&self) {
+        // A full socket has enough wake-ups in it already
+        let _ = (&*self.0).write(&[0]);
+    }
+}
+
+/// The end of a `Waker` that `poll` waits on.
+pub struct WakeSocket {
+    socket: UnixStream,
+    waker: Waker,
+}
+
+impl AsRawFd for WakeSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl WakeSocket {
+    pub fn new() -> Result<WakeSocket> {
+        let (socket, other) = UnixStream::pair().internal_error("dbus_manager", "failed to create wake socket")?;
+        socket
+            .set_nonblocking(true)
+            .internal_error("dbus_manager", "failed to set up wake socket")?;
+        Ok(WakeSocket {
+            socket,
+            waker: Waker(Arc::new(other)),
+        })
+    }
+
+    pub fn waker(&self) -> Waker {
+        self.waker.clone()
+    }
+
+    /// Forget about the wake-ups so far.
+    pub fn drain(&self) {
+        let mut buf = [0; 64];
+        while (&self.socket).read(&mut buf).map_or(false, |n| n > 0) {}
+    }
+}
+
+/// Wait until one of `connections` has messages to read, or `wake` is woken
+/// up. Messages already read by libdbus, e.g. while waiting for the reply to
+/// a method call, don't wake this up, drain `incoming(0)` before.
+pub fn poll(connections: &[&Connection], wake: &WakeSocket) -> Result<()> {
+    let mut fds: Vec<PollFd> = connections
+        .iter()
+        .flat_map(|c| c.watch_fds())
+        .filter(|watch| watch.readable())
+        .map(|watch| PollFd::new(watch.fd(), EventFlags::POLLIN))
+        .collect();
+    fds.push(PollFd::new(wake.socket.as_raw_fd(), EventFlags::POLLIN));
+    match nix::poll::poll(&mut fds, -1) {
+        Ok(_) | Err(nix::Error::Sys(Errno::EINTR)) => {}
+        Err(error) => return Err(error).internal_error("dbus_manager", "failed to poll D-Bus connections"),
+    }
+    wake.drain();
+    Ok(())
+}
+
+lazy_static! {
+    static ref LISTENER: Mutex<Option<(Sender<Request>, Waker)>> = Mutex::new(None);
+}
+
+thread_local! {
+    static SESSION_CONNECTION: RefCell<Option<Rc<Connection>>> = RefCell::new(None);
+    static SYSTEM_CONNECTION: RefCell<Option<Rc<Connection>>> = RefCell::new(None);
+}
+
+/// A bus the listener is connected to, along with the subscriptions on it.
+struct Bus {
+    c: Connection,
+    subscriptions: Vec<(Rule, Subscription)>,
+}
+
+fn bus_index(bus: BusType) -> usize {
+    match bus {
+        BusType::System => 1,
+        _ => 0,
+    }
+}
+
+fn add_subscription(buses: &mut [Option<Bus>; 2], subscription: Subscription) {
+    let bus = &mut buses[bus_index(subscription.bus)];
+    if bus.is_none() {
+        match Connection::get_private(subscription.bus) {
+            Ok(c) => {
+                *bus = Some(Bus {
+                    c,
+                    subscriptions: Vec::new(),
+                })
+            }
+            Err(_) => {
+                subscription.reply.send(Err(InternalError(
+                    "dbus_manager".to_owned(),
+                    "failed to establish D-Bus connection".to_owned(),
+                    None,
+                )));
+                return;
+            }
+        }
+    }
+
+    if let Some(ref mut bus) = *bus {
+        let result = Rule::parse(&subscription.rule).and_then(|rule| {
+            bus.c
+                .add_match(&subscription.rule)
+                .internal_error("dbus_manager", "failed to add match rule")?;
+            Ok(rule)
+        });
+        match result {
+            Ok(rule) => {
+                subscription.reply.send(Ok(()));
+                bus.subscriptions.push((rule, subscription));
+            }
+            Err(error) => subscription.reply.send(Err(error)),
+        }
+    }
+}
+
+fn remove_subscriptions(buses: &mut [Option<Bus>; 2], id: &str) {
+    for bus in buses.iter_mut().filter_map(|bus| bus.as_mut()) {
+        let (removed, kept): (Vec<_>, Vec<_>) = bus
+            .subscriptions
+            .drain(..)
+            .partition(|&(_, ref subscription)| subscription.id == id);
+        bus.subscriptions = kept;
+        for (_, subscription) in removed {
+            // Other blocks may have subscribed with the same rule
+            if bus.subscriptions.iter().any(|&(_, ref other)| other.rule == subscription.rule) {
+                continue;
+            }
+            if let Err(error) = bus.c.remove_match(&subscription.rule) {
+                warn!("failed to remove match rule {}: {}", subscription.rule, error);
+            }
+        }
+    }
+}
+
+/// Dispatch the signals of both buses, sleeping in between. The buses are
+/// connected to on their first subscription.
+fn listen(rx: Receiver<Request>, wake: WakeSocket) {
+    let mut buses: [Option<Bus>; 2] = [None, None];
+    loop {
+        // Take on new subscribers and let go of old ones, they woke us up
+        loop {
+            chan_select! {
+                default => break,
+                rx.recv() -> res => match res {
+                    Some(Request::Subscribe(subscription)) => add_subscription(&mut buses, subscription),
+                    Some(Request::Unsubscribe(id)) => remove_subscriptions(&mut buses, &id),
+                    None => {}
+                },
+            }
+        }
+
+        for bus in buses.iter().filter_map(|bus| bus.as_ref()) {
+            for msg in bus.c.incoming(0) {
+                if msg.msg_type() != MessageType::Signal {
+                    continue;
+                }
+                for &(ref rule, ref subscription) in &bus.subscriptions {
+                    if rule.matches(&msg) {
+                        subscription.tx_update_request.send(Task {
+                            id: subscription.id.clone(),
+                            update_time: Instant::now(),
+                        });
+                    }
+                }
+            }
+        }
+
+        let connections: Vec<&Connection> = buses
+            .iter()
+            .filter_map(|bus| bus.as_ref().map(|bus| &bus.c))
+            .collect();
+        if let Err(error) = poll(&connections, &wake) {
+            // Rather late than spinning
+            error!("{:?}", error);
+            thread::sleep(Duration::from_secs(1));
+        }
+    }
+}
+
+/// Request an update of the block `id` whenever a signal matching `rule` is
+/// received on `bus`.
+pub fn subscribe(bus: BusType, rule: &str, id: &str, tx_update_request: &Sender<Task>) -> Result<()> {
+    let (tx, waker) = {
+        let mut listener = LISTENER
+            .lock()
+            .internal_error("dbus_manager", "failed to lock listener")?;
+        if listener.is_none() {
+            let wake = WakeSocket::new()?;
+            let waker = wake.waker();
+            let (tx, rx) = chan::async();
+            thread::spawn(move || listen(rx, wake));
+            *listener = Some((tx, waker));
+        }
+        (*listener).clone().internal_error("dbus_manager", "no listener")?
+    };
+
+    let (reply, rx_reply) = chan::async();
+    tx.send(Request::Subscribe(Subscription {
+        bus,
+        rule: rule.to_owned(),
+        id: id.to_owned(),
+        tx_update_request: tx_update_request.clone(),
+        reply,
+    }));
+    waker.wake();
+    rx_reply
+        .recv()
+        .internal_error("dbus_manager", "listener thread is gone")?
+}
+
+/// Drop all subscriptions of the block `id`, once it's gone. Match rules are
+/// removed from the bus along with their last subscriber.
+pub fn unsubscribe(id: &str) {
+    // Nothing to do before the first subscription
+    if let Ok(listener) = LISTENER.lock() {
+        if let Some((ref tx, ref waker)) = *listener {
+            tx.send(Request::Unsubscribe(id.to_owned()));
+            waker.wake();
+        }
+    }
+}
+
+/// The connection to `bus` shared by all blocks on this thread.
+pub fn connection(bus: BusType) -> Result<Rc<Connection>> {
+    let shared = match bus {
+        BusType::System => &SYSTEM_CONNECTION,
+        _ => &SESSION_CONNECTION,
+    };
+
+    shared.with(|shared| {
+        let mut shared = shared.borrow_mut();
+        if shared.is_none() {
+            let c = Connection::get_private(bus)
+                .internal_error("dbus_manager", "failed to establish D-Bus connection")?;
+            *shared = Some(Rc::new(c));
+        }
+        shared.clone().internal_error("dbus_manager", "no connection")
+    })
+}
@@ -7,7 +7,9 @@ use std::ops::Deref;
 use std::str::FromStr;
 use std::time::Duration;
 use toml::{self, value};
+use chrono::{NaiveTime, Weekday};
 use chrono_tz::Tz;
+use scheduler::ActiveHours;
 
 pub fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
 where
@@ -33,7 +35,7 @@ where
         where
             E: de::Error,
         {
-            Ok(Duration::new(0, (value * 1_000_000_000f64) as u32))
+            Ok(Duration::new(value.trunc() as u64, (value.fract() * 1_000_000_000f64) as u32))
         }
 
         fn visit_map<A>(self, visitor: A) -> Result<Self::Value, A::Error>
@@ -191,3 +193,54 @@ where
     let s = String::deserialize(deserializer)?;
     Tz::from_str(&s).map(Some).map_err(de::Error::custom)
 }
+
+/// A time of day like `"19:30"`.
+pub fn deserialize_time_of_day<'de, D>(deserializer: D) -> Result<NaiveTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&s, "%H:%M").map_err(|_| de::Error::custom(format!("invalid time of day '{}', expected HH:MM", s)))
+}
+
+/// Hours of the day like `"09:00-18:00"`, see `ActiveHours`.
+pub fn deserialize_opt_active_hours<'de, D>(deserializer: D) -> Result<Option<ActiveHours>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    let mut times = s.splitn(2, '-').map(|time| NaiveTime::parse_from_str(time.trim(), "%H:%M"));
+    match (times.next(), times.next()) {
+        (Some(Ok(from)), Some(Ok(to))) => Ok(Some(ActiveHours { from, to })),
+        _ => Err(de::Error::custom(format!("invalid hours '{}', expected HH:MM-HH:MM", s))),
+    }
+}
+
+/// Days of the week like `["mon-fri", "sun"]`. Ranges wrap around the end of
+/// the week, `"fri-mon"` takes in the weekend.
+pub fn deserialize_opt_weekdays<'de, D>(deserializer: D) -> Result<Option<Vec<Weekday>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let entries = Vec::<String>::deserialize(deserializer)?;
+    let mut days = Vec::new();
+    for entry in &entries {
+        let mut bounds = entry.splitn(2, '-').map(|day| Weekday::from_str(day.trim()));
+        let (first, last) = match (bounds.next(), bounds.next()) {
+            (Some(Ok(first)), None) => (first, first),
+            (Some(Ok(first)), Some(Ok(last))) => (first, last),
+            _ => return Err(de::Error::custom(format!("invalid day '{}', expected e.g. mon or mon-fri", entry))),
+        };
+        let mut day = first;
+        loop {
+            if !days.contains(&day) {
+                days.push(day);
+            }
+            if day == last {
+                break;
+            }
+            day = day.succ();
+        }
+    }
+    Ok(Some(days))
+}
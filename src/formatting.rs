@@ -0,0 +1,356 @@
+//! Format strings shared by all blocks.
+//!
+//! A format string is text with placeholders in braces, which are replaced by
+//! values of the block, e.g. `"{utilization} {frequency}"`. A placeholder may
+//! carry a spec after a colon, controlling how its value is shown:
+//!
+//! ```text
+//! {name:[align][0][width][.precision][;prefix][*unit][#max]}
+//! ```
+//!
+//! * `align` is one of `<`, `>` and `^`. Numbers are right aligned by default,
+//!   text is left aligned.
+//! * `0` pads numbers with zeros instead of spaces.
+//! * `width` is the minimum width of the value, not counting its unit.
+//! * `precision` is the number of decimals shown.
+//! * `prefix` scales the value to one of `K`, `M`, `G` and `T`, or to their
+//!   binary counterparts `Ki`, `Mi`, `Gi` and `Ti`. `eng` and `bin` pick the
+//!   decimal or binary prefix that suits the value. Scaled values are shown
+//!   with three significant digits, unless a precision is given.
+//! * `unit` converts bytes to bits (`b`) or bits to bytes (`B`), or hides the
+//!   unit (`_`).
+//! * `#` draws the value as a bar of `width` cells (5 by default), which is
+//!   full at `max` (100 by default).
+//!
+//! For example, `{speed:5.1;M*b}` shows a speed of 1234567 bytes per second
+//! as `  9.9Mb/s`.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use regex::Regex;
+
+use errors::*;
+
+/// What a number measures, shown right after it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    None,
+    Bytes,
+    Bits,
+    BytesPerSecond,
+    BitsPerSecond,
+    Percents,
+    Degrees,
+    Watts,
+    Hertz,
+}
+
+impl Unit {
+    fn suffix(&self) -> &'static str {
+        match *self {
+            Unit::None => "",
+            Unit::Bytes => "B",
+            Unit::Bits => "b",
+            Unit::BytesPerSecond => "B/s",
+            Unit::BitsPerSecond => "b/s",
+            Unit::Percents => "%",
+            Unit::Degrees => "°",
+            Unit::Watts => "W",
+            Unit::Hertz => "Hz",
+        }
+    }
+
+    /// Express `value` in the unit asked for by a spec, see the module docs.
+    fn convert(self, value: f64, to: char) -> (f64, Unit) {
+        match (self, to) {
+            (Unit::Bytes, 'b') => (value * 8., Unit::Bits),
+            (Unit::BytesPerSecond, 'b') => (value * 8., Unit::BitsPerSecond),
+            (Unit::Bits, 'B') => (value / 8., Unit::Bytes),
+            (Unit::BitsPerSecond, 'B') => (value / 8., Unit::BytesPerSecond),
+            (_, '_') => (value, Unit::None),
+            _ => (value, self),
+        }
+    }
+}
+
+/// A value to fill a placeholder with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Number(f64, Unit),
+}
+
+const PREFIXES: [&str; 5] = ["", "K", "M", "G", "T"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Prefix {
+    /// Divide by `1000^power`, or `1024^power` if binary
+    Fixed { power: i32, binary: bool },
+    /// The largest prefix that leaves at least 1
+    Auto { binary: bool },
+}
+
+impl Prefix {
+    fn parse(prefix: &str) -> Prefix {
+        match prefix {
+            "eng" => Prefix::Auto { binary: false },
+            "bin" => Prefix::Auto { binary: true },
+            _ => Prefix::Fixed {
+                power: PREFIXES
+                    .iter()
+                    .position(|name| prefix.starts_with(name) && !name.is_empty())
+                    .unwrap_or(0) as i32,
+                binary: prefix.ends_with('i'),
+            },
+        }
+    }
+
+    /// The scaled value along with the name of the prefix.
+    fn scale(self, value: f64) -> (f64, String) {
+        let (power, binary) = match self {
+            Prefix::Fixed { power, binary } => (power, binary),
+            Prefix::Auto { binary } => {
+                let base: f64 = if binary { 1024. } else { 1000. };
+                let mut power = 0;
+                while power < PREFIXES.len() as i32 - 1 && value.abs() >= base.powi(power + 1) {
+                    power += 1;
+                }
+                (power, binary)
+            }
+        };
+
+        let base: f64 = if binary { 1024. } else { 1000. };
+        let name = PREFIXES[power as usize];
+        let name = if binary && power > 0 {
+            format!("{}i", name)
+        } else {
+            name.to_owned()
+        };
+        (value / base.powi(power), name)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Spec {
+    align: Option<char>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    prefix: Option<Prefix>,
+    unit: Option<char>,
+    /// The value at which the bar is full, if drawn as a bar
+    bar: Option<f64>,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Result<Spec> {
+        let re = Regex::new(
+            r"^([<>^])?(0)?([0-9]+)?(?:\.([0-9]+))?(?:;(eng|bin|[KMGT]i?))?(?:\*([bB_]))?(#([0-9]+(?:\.[0-9]+)?)?)?$",
+        ).internal_error("formatting", "invalid regex")?;
+        let captures = re.captures(spec).ok_or_else(|| {
+            ConfigurationError(
+                format!("invalid placeholder spec '{}'", spec),
+                (spec.to_owned(), spec.to_owned()),
+            )
+        })?;
+
+        Ok(Spec {
+            align: captures.get(1).and_then(|m| m.as_str().chars().next()),
+            zero: captures.get(2).is_some(),
+            width: match captures.get(3) {
+                Some(m) => m.as_str().parse().configuration_error("invalid placeholder width")?,
+                None => 0,
+            },
+            precision: match captures.get(4) {
+                Some(m) => Some(m.as_str().parse().configuration_error("invalid placeholder precision")?),
+                None => None,
+            },
+            prefix: captures.get(5).map(|m| Prefix::parse(m.as_str())),
+            unit: captures.get(6).and_then(|m| m.as_str().chars().next()),
+            bar: match (captures.get(7), captures.get(8)) {
+                (Some(_), Some(m)) => Some(m.as_str().parse().configuration_error("invalid bar maximum")?),
+                (Some(_), None) => Some(100.),
+                _ => None,
+            },
+        })
+    }
+
+    /// Whether the spec only makes sense for numbers.
+    fn is_numeric(&self) -> bool {
+        self.zero || self.precision.is_some() || self.prefix.is_some() || self.bar.is_some()
+    }
+
+    fn render(&self, value: &Value) -> String {
+        match *value {
+            Value::Number(number, unit) => self.render_number(number, unit),
+            Value::Text(ref text) => match text.trim().parse::<f64>() {
+                // Values handed in as text may still be numbers
+                Ok(number) if self.is_numeric() => self.render_number(number, Unit::None),
+                _ => pad(text.clone(), self.width, self.align.unwrap_or('<'), ' '),
+            },
+        }
+    }
+
+    fn render_number(&self, value: f64, unit: Unit) -> String {
+        if let Some(max) = self.bar {
+            return bar(value / max, if self.width == 0 { 5 } else { self.width });
+        }
+
+        let (value, unit) = match self.unit {
+            Some(to) => unit.convert(value, to),
+            None => (value, unit),
+        };
+        let (value, prefix) = match self.prefix {
+            Some(prefix) => prefix.scale(value),
+            None => (value, String::new()),
+        };
+        let number = match self.precision {
+            Some(precision) => format!("{:.*}", precision, value),
+            None if self.prefix.is_some() => format!("{:.*}", significant_decimals(value, 3), value),
+            None => format!("{}", value),
+        };
+
+        format!(
+            "{}{}{}",
+            pad(number, self.width, self.align.unwrap_or('>'), if self.zero { '0' } else { ' ' }),
+            prefix,
+            unit.suffix()
+        )
+    }
+}
+
+/// The number of decimals that show `value` with `digits` significant digits.
+fn significant_decimals(value: f64, digits: usize) -> usize {
+    let integer_digits = if value.abs() < 1. {
+        1
+    } else {
+        value.abs().log10().floor() as usize + 1
+    };
+    digits.saturating_sub(integer_digits)
+}
+
+/// Pad `s` to `width` characters. Zeros are only ever put in front, after the
+/// sign.
+fn pad(s: String, width: usize, align: char, fill: char) -> String {
+    let len = s.chars().count();
+    if len >= width {
+        return s;
+    }
+    let missing = width - len;
+
+    if fill == '0' && align == '>' {
+        let (sign, digits) = if s.starts_with('-') {
+            ("-", &s[1..])
+        } else {
+            ("", &s[..])
+        };
+        return format!("{}{}{}", sign, "0".repeat(missing), digits);
+    }
+
+    let (left, right) = match align {
+        '<' => (0, missing),
+        '^' => (missing / 2, missing - missing / 2),
+        _ => (missing, 0),
+    };
+    format!("{}{}{}", " ".repeat(left), s, " ".repeat(right))
+}
+
+const BAR: [char; 9] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+/// A bar of `width` cells, filled to `fraction`.
+fn bar(fraction: f64, width: usize) -> String {
+    let fraction = fraction.max(0.).min(1.);
+    let eighths = (fraction * width as f64 * 8.).round() as usize;
+    (0..width)
+        .map(|cell| BAR[eighths.saturating_sub(cell * 8).min(8)])
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Text(String),
+    /// The key includes the braces, e.g. `{utilization}`
+    Placeholder { key: String, spec: Spec },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatTemplate {
+    tokens: Vec<Token>,
+}
+
+impl FormatTemplate {
+    pub fn from_string(s: &str) -> Result<FormatTemplate> {
+        // Anything in braces that doesn't look like a placeholder is kept as
+        // it is.
+        let re = Regex::new(r"\{([a-zA-Z0-9_]+)(?::([^{}]*))?\}")
+            .internal_error("formatting", "invalid regex")?;
+
+        let mut tokens = Vec::new();
+        let mut start = 0;
+        for captures in re.captures_iter(s) {
+            let whole = captures.get(0).internal_error("formatting", "no match")?;
+            if whole.start() != start {
+                tokens.push(Token::Text(s[start..whole.start()].to_owned()));
+            }
+            tokens.push(Token::Placeholder {
+                key: format!("{{{}}}", &captures[1]),
+                spec: match captures.get(2) {
+                    Some(spec) => Spec::parse(spec.as_str())?,
+                    None => Spec::default(),
+                },
+            });
+            start = whole.end();
+        }
+        if start != s.len() {
+            tokens.push(Token::Text(s[start..].to_owned()));
+        }
+
+        Ok(FormatTemplate { tokens })
+    }
+
+    /// Whether the template refers to `key`, e.g. `{frequency}`. Lets blocks
+    /// skip gathering values that aren't shown anyway.
+    pub fn contains(&self, key: &str) -> bool {
+        self.tokens.iter().any(|token| match *token {
+            Token::Placeholder { key: ref k, .. } => k == key,
+            _ => false,
+        })
+    }
+
+    fn render_with<F>(&self, lookup: F) -> Result<String>
+    where
+        F: Fn(&str) -> Option<Value>,
+    {
+        let mut rendered = String::new();
+        for token in &self.tokens {
+            match *token {
+                Token::Text(ref text) => rendered.push_str(text),
+                Token::Placeholder { ref key, ref spec } => {
+                    let value = lookup(key).ok_or_else(|| {
+                        ConfigurationError(
+                            format!("Unknown placeholder in format string: {}", key),
+                            (key.clone(), key.clone()),
+                        )
+                    })?;
+                    rendered.push_str(&spec.render(&value));
+                }
+            }
+        }
+        Ok(rendered)
+    }
+
+    /// Render typed values, keyed by placeholder including the braces.
+    pub fn render_values(&self, values: &HashMap<&str, Value>) -> Result<String> {
+        self.render_with(|key| values.get(key).cloned())
+    }
+
+    pub fn render<T: Display>(&self, vars: &HashMap<String, T>) -> String {
+        self.render_with(|key| vars.get(key).map(|value| Value::Text(format!("{}", value))))
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn render_static_str<T: Display>(&self, vars: &HashMap<&str, T>) -> Result<String> {
+        self.render_with(|key| vars.get(key).map(|value| Value::Text(format!("{}", value))))
+    }
+}
@@ -0,0 +1,251 @@
+//! Fetching data from web services, shared by all blocks that need to.
+//!
+//! Requests are made with `curl`, which also takes care of proxies through
+//! the usual `http_proxy`, `https_proxy` and `no_proxy` variables.
+//!
+//! Connections are not reused: every request runs its own `curl`, which
+//! closes its connection when done. Keeping them open would take an HTTP and
+//! TLS client of our own, and blocks ask a host every few minutes at most,
+//! longer than servers keep idle connections around anyway. What keeps the
+//! load on APIs down instead:
+//!
+//! * Responses can be cached on disk in `$XDG_CACHE_HOME/i3status-rs/http` for
+//!   a while, which also lets them survive restarts of the bar.
+//! * Requests to the same host are at least `MIN_INTERVAL` seconds apart, so that
+//!   blocks all waking up at once, e.g. after resume, don't hammer an API. A
+//!   request that comes too early gets the cached response, or fails if there
+//!   is none; the bar isn't kept waiting. `POST`s and requests to the local
+//!   host, e.g. JSON-RPC calls to a node, aren't limited.
+//! * If a request fails, e.g. while offline, the cached response is used no
+//!   matter its age.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use errors::*;
+use util::xdg_dir;
+
+/// Minimum time between two requests to the same host, in seconds
+const MIN_INTERVAL: u64 = 1;
+
+/// Time after which requests are given up on, in seconds
+const TIMEOUT: u64 = 3;
+
+lazy_static! {
+    /// When each host was last requested
+    static ref LAST_REQUESTS: Mutex<HashMap<String, Instant>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    url: String,
+    /// Sent as the request body with `POST`, if set
+    body: Option<String>,
+    headers: Vec<(String, String)>,
+    auth: Option<(String, String)>,
+    cache_ttl: Option<Duration>,
+}
+
+impl Request {
+    pub fn get(url: &str) -> Self {
+        Request {
+            url: url.to_owned(),
+            body: None,
+            headers: Vec::new(),
+            auth: None,
+            cache_ttl: None,
+        }
+    }
+
+    pub fn post(url: &str, body: &str) -> Self {
+        Request {
+            body: Some(body.to_owned()),
+            ..Request::get(url)
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    pub fn with_basic_auth(mut self, user: &str, password: &str) -> Self {
+        self.auth = Some((user.to_owned(), password.to_owned()));
+        self
+    }
+
+    /// Reuse the response for this long instead of asking again.
+    pub fn with_cache(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// The response body. HTTP errors don't count as failures, APIs tend to
+    /// explain them in the body.
+    pub fn send(&self) -> Result<String> {
+        let cache_path = self.cache_path();
+        if let Some(ttl) = self.cache_ttl {
+            if let Some(cached) = read_cache(&cache_path, Some(ttl)) {
+                return Ok(cached);
+            }
+        }
+
+        if self.body.is_none() && !is_loopback(self.host()) && !self.reserve_slot() {
+            debug!("rate limited request to '{}', using the cache", self.host());
+            return read_cache(&cache_path, None).block_error(
+                "http",
+                &format!("request to '{}' was rate limited, and there's no cached response", self.host()),
+            );
+        }
+
+        match self.fetch() {
+            Ok(response) => {
+                if self.cache_ttl.is_some() {
+                    write_cache(&cache_path, &response);
+                }
+                Ok(response)
+            }
+            Err(error) => {
+                warn!("request to '{}' failed, falling back to the cache: {:?}", self.host(), error);
+                read_cache(&cache_path, None).ok_or(error)
+            }
+        }
+    }
+
+    fn fetch(&self) -> Result<String> {
+        let mut cmd = Command::new("curl");
+        cmd.args(&["-s", "-S", "-m"]).arg(format!("{}", TIMEOUT));
+        // Headers and credentials go through stdin, command lines can be
+        // read by every user from `/proc`
+        cmd.args(&["--config", "-"]);
+        if let Some(ref body) = self.body {
+            cmd.arg("--data-binary").arg(body);
+        }
+        cmd.arg(&self.url);
+
+        let mut config = String::new();
+        for &(ref name, ref value) in &self.headers {
+            config.push_str(&format!("header = {}\n", quote(&format!("{}: {}", name, value))));
+        }
+        if let Some((ref user, ref password)) = self.auth {
+            config.push_str(&format!("user = {}\n", quote(&format!("{}:{}", user, password))));
+        }
+
+        let mut child = cmd.stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .block_error("http", "failed to execute curl")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(config.as_bytes())
+                .block_error("http", "failed to pass the request to curl")?;
+        }
+        let output = child.wait_with_output().block_error("http", "failed to execute curl")?;
+        if !output.status.success() {
+            return Err(BlockError(
+                "http".to_owned(),
+                format!(
+                    "request to '{}' failed: {}",
+                    self.host(),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        String::from_utf8(output.stdout).block_error("http", "non-UTF8 response")
+    }
+
+    fn host(&self) -> &str {
+        let rest = self.url.splitn(2, "://").last().unwrap_or("");
+        rest.split(|c| c == '/' || c == '?').next().unwrap_or(rest)
+    }
+
+    /// Claim the host for a request now. Returns false if it was requested
+    /// too recently.
+    fn reserve_slot(&self) -> bool {
+        let mut last_requests = match LAST_REQUESTS.lock() {
+            Ok(last_requests) => last_requests,
+            Err(_) => return true,
+        };
+        let now = Instant::now();
+        if let Some(&last) = last_requests.get(self.host()) {
+            if now.duration_since(last) < Duration::from_secs(MIN_INTERVAL) {
+                return false;
+            }
+        }
+        last_requests.insert(self.host().to_owned(), now);
+        true
+    }
+
+    fn cache_path(&self) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        self.url.hash(&mut hasher);
+        self.body.hash(&mut hasher);
+        self.headers.hash(&mut hasher);
+        xdg_dir("XDG_CACHE_HOME", ".cache")
+            .join("http")
+            .join(format!("{:016x}", hasher.finish()))
+    }
+}
+
+/// Whether `host`, as found in a URL, is the local host.
+fn is_loopback(host: &str) -> bool {
+    let host = host.rsplit('@').next().unwrap_or(host);
+    let name = if host.starts_with('[') {
+        host[1..].split(']').next().unwrap_or("")
+    } else {
+        host.split(':').next().unwrap_or("")
+    };
+    name == "localhost" || name.parse::<IpAddr>().map(|ip| ip.is_loopback()).unwrap_or(false)
+}
+
+/// `value` quoted for a curl config file.
+fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The cached response at `path`, if it's younger than `ttl`.
+fn read_cache(path: &Path, ttl: Option<Duration>) -> Option<String> {
+    if let Some(ttl) = ttl {
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+        match SystemTime::now().duration_since(modified) {
+            Ok(age) if age <= ttl => {}
+            _ => return None,
+        }
+    }
+    let mut contents = String::new();
+    File::open(path).ok()?.read_to_string(&mut contents).ok()?;
+    Some(contents)
+}
+
+fn write_cache(path: &Path, response: &str) {
+    // The cache is only an optimization, failing to write it is fine.
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if let Ok(mut file) = File::create(path) {
+        let _ = file.write_all(response.as_bytes());
+    }
+}
@@ -0,0 +1,231 @@
+//! Window manager events of i3 and sway, shared by all blocks following them.
+//!
+//! Instead of every block connecting to the window manager with a thread of
+//! its own, blocks subscribe to the kinds of events they need here. A single
+//! connection is subscribed to all of them, and read by the event thread of
+//! the `scheduler`, which hands each event to the blocks subscribed to its
+//! kind.
+//!
+//! The connection is lost when i3 restarts, the blocks then stop following
+//! events until the bar is restarted as well, which i3 does by default.
+
+use std::env;
+use std::io::{Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use errors::*;
+use scheduler;
+
+extern crate i3ipc;
+
+use self::i3ipc::event::{BarConfigEventInfo, Event, WindowEventInfo, WorkspaceEventInfo};
+use self::i3ipc::Subscription;
+
+/// The message type of subscriptions, and the bit set in the type of events
+const SUBSCRIBE: u32 = 2;
+const EVENT: u32 = 1 << 31;
+
+/// Called with each event a block subscribed to.
+pub type Handler = Box<FnMut(&Event) + Send>;
+
+/// The event type and its name in subscriptions.
+fn kind(subscription: &Subscription) -> (u32, &'static str) {
+    match *subscription {
+        Subscription::Workspace => (0, "workspace"),
+        Subscription::Output => (1, "output"),
+        Subscription::Mode => (2, "mode"),
+        Subscription::Window => (3, "window"),
+        Subscription::BarConfig => (4, "barconfig_update"),
+        Subscription::Binding => (5, "binding"),
+    }
+}
+
+/// The event of type `kind`, for the kinds blocks subscribe to.
+fn parse(kind: u32, payload: &str) -> Option<Event> {
+    match kind {
+        0 => WorkspaceEventInfo::from_str(payload).ok().map(Event::WorkspaceEvent),
+        3 => WindowEventInfo::from_str(payload).ok().map(Event::WindowEvent),
+        4 => BarConfigEventInfo::from_str(payload).ok().map(Event::BarConfigEvent),
+        _ => None,
+    }
+}
+
+struct Subscriber {
+    kind: u32,
+    id: String,
+    handler: Handler,
+}
+
+struct Listener {
+    stream: UnixStream,
+    /// The kinds of events the connection is subscribed to
+    kinds: Vec<u32>,
+    subscribers: Vec<Subscriber>,
+}
+
+lazy_static! {
+    /// `None` until the first subscription, and once the connection is lost
+    static ref LISTENER: Mutex<Option<Listener>> = Mutex::new(None);
+}
+
+fn socket_path() -> Result<String> {
+    for var in &["I3SOCK", "SWAYSOCK"] {
+        if let Ok(path) = env::var(var) {
+            if !path.is_empty() {
+                return Ok(path);
+            }
+        }
+    }
+    let output = Command::new("i3")
+        .arg("--get-socketpath")
+        .output()
+        .internal_error("i3_events", "failed to find the i3 socket")?;
+    if !output.status.success() {
+        return Err(InternalError(
+            "i3_events".to_owned(),
+            "neither i3 nor sway are running".to_owned(),
+            None,
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+fn send(stream: &mut UnixStream, message_type: u32, payload: &str) -> Result<()> {
+    let mut message = b"i3-ipc".to_vec();
+    message.extend_from_slice(&u32_bytes(payload.len() as u32));
+    message.extend_from_slice(&u32_bytes(message_type));
+    message.extend_from_slice(payload.as_bytes());
+    stream
+        .write_all(&message)
+        .internal_error("i3_events", "failed to talk to i3 or sway")
+}
+
+/// The type and payload of the next message.
+fn receive(stream: &mut UnixStream) -> Result<(u32, String)> {
+    let mut header = [0; 14];
+    stream
+        .read_exact(&mut header)
+        .internal_error("i3_events", "lost the connection to i3 or sway")?;
+    if &header[..6] != b"i3-ipc" {
+        return Err(InternalError(
+            "i3_events".to_owned(),
+            "unexpected message from i3 or sway".to_owned(),
+            None,
+        ));
+    }
+    let mut payload = vec![0; u32_from_bytes(&header[6..10]) as usize];
+    stream
+        .read_exact(&mut payload)
+        .internal_error("i3_events", "lost the connection to i3 or sway")?;
+    Ok((u32_from_bytes(&header[10..14]), String::from_utf8_lossy(&payload).into_owned()))
+}
+
+/// `n` in the byte order of the IPC, which is the native one.
+fn u32_bytes(n: u32) -> [u8; 4] {
+    if cfg!(target_endian = "little") {
+        [n as u8, (n >> 8) as u8, (n >> 16) as u8, (n >> 24) as u8]
+    } else {
+        [(n >> 24) as u8, (n >> 16) as u8, (n >> 8) as u8, n as u8]
+    }
+}
+
+fn u32_from_bytes(bytes: &[u8]) -> u32 {
+    let n = bytes
+        .iter()
+        .take(4)
+        .enumerate()
+        .fold(0, |n, (i, &byte)| n | u32::from(byte) << (8 * i));
+    if cfg!(target_endian = "little") {
+        n
+    } else {
+        n.swap_bytes()
+    }
+}
+
+/// Read a message and hand it to the blocks subscribed to it, if it's an
+/// event.
+fn dispatch_event() -> Result<Option<Instant>> {
+    let mut listener = LISTENER
+        .lock()
+        .internal_error("i3_events", "failed to lock subscriptions")?;
+    let (message_type, payload) = {
+        let result = match *listener {
+            Some(ref mut listener) => receive(&mut listener.stream),
+            None => return Ok(None),
+        };
+        match result {
+            Ok(message) => message,
+            Err(error) => {
+                *listener = None;
+                return Err(error);
+            }
+        }
+    };
+
+    if message_type & EVENT == 0 {
+        // The reply to a subscription
+        if !payload.contains("true") {
+            warn!("i3 or sway refused a subscription: {}", payload);
+        }
+        return Ok(None);
+    }
+    let kind = message_type & !EVENT;
+    let event = match parse(kind, &payload) {
+        Some(event) => event,
+        None => return Ok(None),
+    };
+    if let Some(ref mut listener) = *listener {
+        for subscriber in listener.subscribers.iter_mut().filter(|subscriber| subscriber.kind == kind) {
+            (subscriber.handler)(&event);
+        }
+    }
+    Ok(None)
+}
+
+/// Call `handler` with every event of the kind of `subscription`, on behalf
+/// of the block `id`. It's called from the event thread.
+pub fn subscribe(subscription: Subscription, id: &str, handler: Handler) -> Result<()> {
+    let mut listener = LISTENER
+        .lock()
+        .internal_error("i3_events", "failed to lock subscriptions")?;
+    if listener.is_none() {
+        let stream = UnixStream::connect(socket_path()?)
+            .internal_error("i3_events", "failed to connect to i3 or sway")?;
+        scheduler::add_source(stream.as_raw_fd(), Box::new(dispatch_event))?;
+        *listener = Some(Listener {
+            stream,
+            kinds: Vec::new(),
+            subscribers: Vec::new(),
+        });
+    }
+
+    let listener = listener
+        .as_mut()
+        .internal_error("i3_events", "no connection")?;
+    let (kind, name) = kind(&subscription);
+    if !listener.kinds.contains(&kind) {
+        // Subscriptions add up, the reply is read along with the events
+        send(&mut listener.stream, SUBSCRIBE, &format!("[\"{}\"]", name))?;
+        listener.kinds.push(kind);
+    }
+    listener.subscribers.push(Subscriber {
+        kind,
+        id: id.to_owned(),
+        handler,
+    });
+    Ok(())
+}
+
+/// Stop the subscriptions of the block `id`, once it's gone.
+pub fn unsubscribe(id: &str) {
+    if let Ok(mut listener) = LISTENER.lock() {
+        if let Some(ref mut listener) = *listener {
+            listener.subscribers.retain(|subscriber| subscriber.id != id);
+        }
+    }
+}
@@ -45,7 +45,41 @@ lazy_static! {
         "weather_default" => " WEATHER ",
         "uptime" => " UP ",
         "gpu" => " GPU ",
-        "mail" => " "
+        "mail" => " ",
+        "printer" => " PRINT ",
+        "gamemode" => " GAME ",
+        "wine" => " WINE ",
+        "node" => " NODE ",
+        "chat" => " CHAT ",
+        "net_vpn" => " VPN ",
+        "headset" => " HS ",
+        "eject" => " EJECT ",
+        "peripherals" => " DEV ",
+        "ambient_light" => " LUX ",
+        "rotation_locked" => " ROT LOCKED ",
+        "rotation_unlocked" => " ROT ",
+        "display_profile" => " DISPLAY ",
+        "dnd_on" => " DND ",
+        "dnd_off" => " NOTIFY ",
+        "break_reminder" => " BREAK ",
+        "screen_time" => " SCREEN ",
+        "keyboard_layout" => " KBD ",
+        "displays" => " DISP ",
+        "docked" => " DOCK ",
+        "presence" => " PRESENCE ",
+        "psi" => " PSI ",
+        "cgroup" => " CG ",
+        "thrashing" => " SWAP ",
+        "trash" => " TRASH ",
+        "downloads" => " DL ",
+        "git" => " GIT ",
+        "remote" => " SSH ",
+        "night_light" => " K ",
+        "session" => " PWR ",
+        "login" => " SEAT ",
+        "hostname" => " HOST ",
+        "timer" => " TIMER ",
+        "watchdog" => " WATCH "
     };
 
     pub static ref AWESOME: Map<String, String> = map_to_owned! {
@@ -94,7 +128,41 @@ lazy_static! {
         // Same as time symbol.
         "uptime" => " \u{f017} ",
         "gpu" => " \u{f26c} ",
-        "mail" => " \u{f0e0} "
+        "mail" => " \u{f0e0} ",
+        "printer" => " \u{f02f} ",
+        "gamemode" => " \u{f11b} ",
+        "wine" => " \u{f11b} ",
+        "node" => " \u{f1b3} ",
+        "chat" => " \u{f086} ",
+        "net_vpn" => " \u{f023} ",
+        "headset" => " \u{f025} ",
+        "eject" => " \u{f052} ",
+        "peripherals" => " \u{f11c} ",
+        "ambient_light" => " \u{f185} ",
+        "rotation_locked" => " \u{f023} ",
+        "rotation_unlocked" => " \u{f2f1} ",
+        "display_profile" => " \u{f108} ",
+        "dnd_on" => " \u{f1f6} ",
+        "dnd_off" => " \u{f0f3} ",
+        "break_reminder" => " \u{f06e} ",
+        "screen_time" => " \u{f017} ",
+        "keyboard_layout" => " \u{f11c} ",
+        "displays" => " \u{f108} ",
+        "docked" => " \u{f109} ",
+        "presence" => " \u{f007} ",
+        "psi" => " \u{f0e7} ",
+        "cgroup" => " \u{f1b3} ",
+        "thrashing" => " \u{f2db} ",
+        "trash" => " \u{f1f8} ",
+        "downloads" => " \u{f019} ",
+        "git" => " \u{f126} ",
+        "remote" => " \u{f233} ",
+        "night_light" => " \u{f186} ",
+        "session" => " \u{f011} ",
+        "login" => " \u{f2bd} ",
+        "hostname" => " \u{f108} ",
+        "timer" => " \u{f2f1} ",
+        "watchdog" => " \u{f06e} "
     };
 
     pub static ref MATERIAL: Map<String, String> = map_to_owned! {
@@ -126,7 +194,41 @@ lazy_static! {
         // Same as time symbol.
         "uptime" => " \u{e192} ",
         "gpu" => " \u{e333} ",
-        "mail" => " \u{e0be} "
+        "mail" => " \u{e0be} ",
+        "printer" => " \u{e8ad} ",
+        "gamemode" => " \u{e338} ",
+        "wine" => " \u{e338} ",
+        "node" => " \u{e875} ",
+        "chat" => " \u{e0b7} ",
+        "net_vpn" => " \u{e897} ",
+        "headset" => " \u{e310} ",
+        "eject" => " \u{e8fb} ",
+        "peripherals" => " \u{e312} ",
+        "ambient_light" => " \u{e430} ",
+        "rotation_locked" => " \u{e1c0} ",
+        "rotation_unlocked" => " \u{e1c1} ",
+        "display_profile" => " \u{e30c} ",
+        "dnd_on" => " \u{e7f6} ",
+        "dnd_off" => " \u{e7f4} ",
+        "break_reminder" => " \u{e8f4} ",
+        "screen_time" => " \u{e192} ",
+        "keyboard_layout" => " \u{e312} ",
+        "displays" => " \u{e30c} ",
+        "docked" => " \u{e31e} ",
+        "presence" => " \u{e7fd} ",
+        "psi" => " \u{e9e4} ",
+        "cgroup" => " \u{e53b} ",
+        "thrashing" => " \u{e002} ",
+        "trash" => " \u{e872} ",
+        "downloads" => " \u{e2c4} ",
+        "git" => " \u{e86f} ",
+        "remote" => " \u{e875} ",
+        "night_light" => " \u{e3a9} ",
+        "session" => " \u{e8ac} ",
+        "login" => " \u{e7fd} ",
+        "hostname" => " \u{e30a} ",
+        "timer" => " \u{e863} ",
+        "watchdog" => " \u{e8f4} "
     };
 
     pub static ref CLEAN: Map<String, String> = map_to_owned! {
@@ -173,18 +275,140 @@ lazy_static! {
         "weather_default" => " Weather ",
         "uptime" => " Up ",
         "gpu" => " GPU ",
-        "mail" => " "
+        "mail" => " ",
+        "printer" => " Printer ",
+        "gamemode" => " Gamemode ",
+        "wine" => " Wine ",
+        "node" => " Node ",
+        "chat" => " Chat ",
+        "net_vpn" => " VPN ",
+        "headset" => " Headset ",
+        "eject" => " Eject ",
+        "peripherals" => " Dev ",
+        "ambient_light" => " Lux ",
+        "rotation_locked" => " Locked ",
+        "rotation_unlocked" => " Rotate ",
+        "display_profile" => " Display ",
+        "dnd_on" => " DND ",
+        "dnd_off" => " Notify ",
+        "break_reminder" => " Break ",
+        "screen_time" => " Screen ",
+        "keyboard_layout" => " Kbd ",
+        "displays" => " Disp ",
+        "docked" => " Dock ",
+        "presence" => " Presence ",
+        "psi" => " PSI ",
+        "cgroup" => " CG ",
+        "thrashing" => " Swap ",
+        "trash" => " Trash ",
+        "downloads" => " DL ",
+        "git" => " Git ",
+        "remote" => " Remote ",
+        "night_light" => " Night ",
+        "session" => " Power ",
+        "login" => " Login ",
+        "hostname" => " Host ",
+        "timer" => " Sync ",
+        "watchdog" => " Watch "
+    };
+
+    pub static ref EMOJI: Map<String, String> = map_to_owned! {
+        "" => "",
+        "time" => " \u{1f552} ",
+        "music" => " \u{1f3b5} ",
+        "music_play" => " \u{25b6} ",
+        "music_pause" => " \u{23f8} ",
+        "music_next" => " \u{23ed} ",
+        "music_prev" => " \u{23ee} ",
+        "cogs" => " \u{2699} ",
+        "memory_mem" => " \u{1f9e0} ",
+        "memory_swap" => " \u{1f4be} ",
+        "cpu" => " \u{1f4bb} ",
+        "bat" => " \u{1f50b} ",
+        "bat_full" => " \u{1f50b} ",
+        "bat_charging" => " \u{1f50c} ",
+        "bat_discharging" => " \u{1f50b} ",
+        "update" => " \u{2b06} ",
+        "toggle_off" => " \u{2b55} ",
+        "toggle_on" => " \u{2705} ",
+        "volume_full" => " \u{1f50a} ",
+        "volume_half" => " \u{1f509} ",
+        "volume_empty" => " \u{1f508} ",
+        // This icon has no spaces around it because it is manually set as text. (sound.rs)
+        "volume_muted" => "\u{1f507}",
+        "thermometer" => " \u{1f321} ",
+        "xrandr" => " \u{1f5a5} ",
+        "net_up" => " \u{2b06} ",
+        "net_down" => " \u{2b07} ",
+        "net_wireless" => " \u{1f4f6} ",
+        "net_wired" => " \u{1f310} ",
+        "ping" => " \u{1f3d3} ",
+        "backlight_empty" => " \u{1f505} ",
+        "backlight_partial1" => " \u{1f505} ",
+        "backlight_partial2" => " \u{1f505} ",
+        "backlight_partial3" => " \u{1f506} ",
+        "backlight_full" => " \u{1f506} ",
+        "weather_sun" => " \u{2600} ",
+        "weather_snow" => " \u{2744} ",
+        "weather_thunder" => " \u{26c8} ",
+        "weather_clouds" => " \u{2601} ",
+        "weather_rain" => " \u{1f327} ",
+        "weather_default" => " \u{2601} ",
+        "uptime" => " \u{23f1} ",
+        "gpu" => " \u{1f5bc} ",
+        "mail" => " \u{1f4e7} ",
+        "printer" => " \u{1f5a8} ",
+        "gamemode" => " \u{1f3ae} ",
+        "wine" => " \u{1f377} ",
+        "node" => " \u{1f517} ",
+        "chat" => " \u{1f4ac} ",
+        "net_vpn" => " \u{1f512} ",
+        "headset" => " \u{1f3a7} ",
+        "eject" => " \u{23cf} ",
+        "peripherals" => " \u{1f5b1} ",
+        "ambient_light" => " \u{1f506} ",
+        "rotation_locked" => " \u{1f512} ",
+        "rotation_unlocked" => " \u{1f504} ",
+        "display_profile" => " \u{1f5b5} ",
+        "dnd_on" => " \u{1f515} ",
+        "dnd_off" => " \u{1f514} ",
+        "break_reminder" => " \u{1f441} ",
+        "screen_time" => " \u{23f2} ",
+        "keyboard_layout" => " \u{2328} ",
+        "displays" => " \u{1f5b5} ",
+        "docked" => " \u{1f50c} ",
+        "presence" => " \u{1f464} ",
+        "psi" => " \u{1f4c8} ",
+        "cgroup" => " \u{1f4e6} ",
+        "thrashing" => " \u{1f975} ",
+        "trash" => " \u{1f5d1} ",
+        "downloads" => " \u{1f4e5} ",
+        "git" => " \u{1f500} ",
+        "remote" => " \u{1f5a5} ",
+        "night_light" => " \u{1f319} ",
+        "session" => " \u{23fb} ",
+        "login" => " \u{1f464} ",
+        "hostname" => " \u{1f4bb} ",
+        "timer" => " \u{23f2} ",
+        "watchdog" => " \u{1f415} "
     };
 }
 
+/// The icon set called `name`. Icons missing from the set fall back to the
+/// ones of `none`, so every block gets at least a text label.
 pub fn get_icons(name: &str) -> Option<Map<String, String>> {
-    match name {
-        "material" => Some(MATERIAL.clone()),
-        "awesome" => Some(AWESOME.clone()),
-        "none" => Some(NONE.clone()),
-        "clean" => Some(CLEAN.clone()),
-        _ => None,
-    }
+    let set: &Map<String, String> = match name {
+        "material" => &*MATERIAL,
+        "awesome" => &*AWESOME,
+        "emoji" => &*EMOJI,
+        "none" => &*NONE,
+        "clean" => &*CLEAN,
+        _ => return None,
+    };
+
+    let mut icons = NONE.clone();
+    icons.extend(set.iter().map(|(key, icon)| (key.clone(), icon.clone())));
+    Some(icons)
 }
 
 pub fn default() -> Map<String, String> {
@@ -1,11 +1,12 @@
 use serde::{de, Deserializer};
 use serde_json;
 use std::fmt;
-use std::io;
+use std::io::{self, BufRead};
 use std::option::Option;
 use std::string::*;
 use chan::Sender;
 use std::thread;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MouseButton {
@@ -14,11 +15,31 @@ pub enum MouseButton {
     Right,
     WheelUp,
     WheelDown,
+    WheelLeft,
+    WheelRight,
     Forward, // On my mouse, these map to forward and back
     Back,
     Unknown,
 }
 
+impl MouseButton {
+    /// The number of the button in click events, as scripts know it.
+    pub fn number(&self) -> u64 {
+        match *self {
+            MouseButton::Left => 1,
+            MouseButton::Middle => 2,
+            MouseButton::Right => 3,
+            MouseButton::WheelUp => 4,
+            MouseButton::WheelDown => 5,
+            MouseButton::WheelLeft => 6,
+            MouseButton::WheelRight => 7,
+            MouseButton::Back => 8,
+            MouseButton::Forward => 9,
+            MouseButton::Unknown => 0,
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct I3BarEvent {
     pub name: Option<String>,
@@ -26,8 +47,28 @@ pub struct I3BarEvent {
     pub x: u64,
     pub y: u64,
 
+    /// Position of the click within the widget, and the size of the widget,
+    /// in pixels. Only sent by newer versions of i3bar and swaybar.
+    #[serde(default)]
+    pub relative_x: Option<u64>,
+    #[serde(default)]
+    pub relative_y: Option<u64>,
+    #[serde(default)]
+    pub width: Option<u64>,
+    #[serde(default)]
+    pub height: Option<u64>,
+
     #[serde(deserialize_with = "deserialize_mousebutton")]
     pub button: MouseButton,
+
+    /// Modifier keys held during the click, e.g. `Shift` or `Mod4`. Only
+    /// sent by newer versions of i3bar and swaybar.
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+
+    /// Whether this is the second click of a double click, see `ClickTracker`
+    #[serde(skip)]
+    pub double_click: bool,
 }
 
 impl I3BarEvent {
@@ -37,24 +78,168 @@ impl I3BarEvent {
             _ => false,
         }
     }
+
+    /// How far along the widget the click was, from 0 at its left edge to 1
+    /// at its right edge. Lets blocks tell apart regions within a widget,
+    /// e.g. to seek to a position.
+    pub fn fraction_x(&self) -> Option<f64> {
+        match (self.relative_x, self.width) {
+            (Some(x), Some(width)) if width > 0 => Some((x as f64 / width as f64).min(1.)),
+            _ => None,
+        }
+    }
+
+    pub fn has_modifier(&self, modifier: &str) -> bool {
+        self.modifiers.iter().any(|m| m == modifier)
+    }
+
+    /// Whether both events are clicks with the same button on the same widget.
+    fn same_target(&self, other: &I3BarEvent) -> bool {
+        self.name == other.name && self.instance == other.instance && self.button == other.button
+    }
+
+    /// The index of the clicked widget within the view of the block
+    /// `block_id`, if the click was on that block. Every widget is given an
+    /// instance naming its block and position when rendered, see
+    /// `widget::instance_name`.
+    pub fn widget_index(&self, block_id: &str) -> Option<usize> {
+        let instance = self.instance.as_ref()?;
+        let mut parts = instance.rsplitn(2, ':');
+        let index = parts.next()?;
+        match parts.next() {
+            Some(block) if block == block_id => index.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Whether the click was on the widget at `index` in the view of the
+    /// block `block_id`.
+    pub fn matches_widget(&self, block_id: &str, index: usize) -> bool {
+        self.widget_index(block_id) == Some(index)
+    }
+}
+
+/// Tells double clicks apart. i3bar reports them as two separate clicks, so
+/// a click counts as double if it hits the same widget with the same button
+/// as the one before, within the double click interval.
+///
+/// i3bar doesn't report releasing a button either, so there's no telling how
+/// long a button was held. Widgets that want a second action instead use the
+/// double click, and then their single clicks are held back until it's clear
+/// that no second click follows. Otherwise the first click of every double
+/// click would trigger the single click action as well.
+#[derive(Default)]
+pub struct ClickTracker {
+    last: Option<(I3BarEvent, Instant)>,
+    /// A single click held back, along with when it happened
+    pending: Option<(I3BarEvent, Instant)>,
+}
+
+impl ClickTracker {
+    /// Take in a click and return the clicks that are ready to be handled,
+    /// with `double_click` set. If `hold_back` is set, a single click is kept
+    /// until either a second click arrives or `expire` lets it go.
+    pub fn track(&mut self, mut event: I3BarEvent, interval: Duration, hold_back: bool) -> Vec<I3BarEvent> {
+        let now = Instant::now();
+        let mut ready = Vec::new();
+
+        if let Some((pending, time)) = self.pending.take() {
+            if pending.same_target(&event) && now - time <= interval {
+                event.double_click = true;
+                self.last = Some((event.clone(), now));
+                ready.push(event);
+                return ready;
+            }
+            // Followed by a click elsewhere, so it was a single click
+            ready.push(pending);
+        }
+
+        event.double_click = match self.last {
+            // A third click starts over
+            Some((ref last, time)) => !last.double_click && last.same_target(&event) && now - time <= interval,
+            None => false,
+        };
+        self.last = Some((event.clone(), now));
+
+        if hold_back && !event.double_click {
+            self.pending = Some((event, now));
+        } else {
+            ready.push(event);
+        }
+        ready
+    }
+
+    /// The click held back, once the double click interval has passed
+    /// without a second click.
+    pub fn expire(&mut self, interval: Duration) -> Option<I3BarEvent> {
+        match self.pending {
+            Some((_, time)) if time.elapsed() >= interval => self.pending.take().map(|(event, _)| event),
+            _ => None,
+        }
+    }
+
+    /// How long until the click held back expires, if there is one.
+    pub fn time_to_expiry(&self, interval: Duration) -> Option<Duration> {
+        self.pending.as_ref().map(|&(_, time)| {
+            let elapsed = time.elapsed();
+            if elapsed >= interval {
+                Duration::from_secs(0)
+            } else {
+                interval - elapsed
+            }
+        })
+    }
 }
 
 pub fn process_events(sender: Sender<I3BarEvent>) {
     thread::spawn(move || loop {
         let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-
-        // Take only the valid JSON object betweem curly braces (cut off leading bracket, commas and whitespace)
-        let slice = input.trim_left_matches(|c| c != '{');
-        let slice = slice.trim_right_matches(|c| c != '}');
+        match io::stdin().read_line(&mut input) {
+            Ok(0) => {
+                debug!("stdin was closed, no more click events");
+                return;
+            }
+            Ok(_) => {}
+            Err(error) => {
+                error!("failed to read click events: {}", error);
+                return;
+            }
+        }
 
+        let slice = event_json(&input);
         if !slice.is_empty() {
-            let e: I3BarEvent = serde_json::from_str(slice).unwrap();
-            sender.send(e);
+            match serde_json::from_str(slice) {
+                Ok(e) => sender.send(e),
+                Err(error) => warn!("ignoring invalid click event '{}': {}", slice, error),
+            }
         }
     });
 }
 
+/// Pass on the click events read from `reader`, until it's closed.
+pub fn read_events<R: BufRead>(reader: R, sender: &Sender<I3BarEvent>) {
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        let slice = event_json(&line);
+        if !slice.is_empty() {
+            match serde_json::from_str(slice) {
+                Ok(e) => sender.send(e),
+                Err(error) => warn!("ignoring invalid click event '{}': {}", slice, error),
+            }
+        }
+    }
+}
+
+/// Take only the valid JSON object betweem curly braces (cut off leading
+/// bracket, commas and whitespace)
+fn event_json(line: &str) -> &str {
+    let slice = line.trim_left_matches(|c| c != '{');
+    slice.trim_right_matches(|c| c != '}')
+}
+
 fn deserialize_mousebutton<'de, D>(deserializer: D) -> Result<MouseButton, D::Error>
 where
     D: Deserializer<'de>,
@@ -72,13 +257,15 @@ where
         where
             E: de::Error,
         {
-            eprintln!("{}", value);
+            trace!("mouse button {}", value);
             Ok(match value {
                 1 => MouseButton::Left,
                 2 => MouseButton::Middle,
                 3 => MouseButton::Right,
                 4 => MouseButton::WheelUp,
                 5 => MouseButton::WheelDown,
+                6 => MouseButton::WheelLeft,
+                7 => MouseButton::WheelRight,
                 9 => MouseButton::Forward,
                 8 => MouseButton::Back,
                 _ => MouseButton::Unknown,
@@ -0,0 +1,170 @@
+//! Controlling the bar at runtime through a Unix socket.
+//!
+//! The socket accepts one JSON command per line and answers each of them with
+//! a JSON object, e.g.
+//!
+//! ```text
+//! $ echo '{"command": "refresh", "block": "weather"}' | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/i3status-rs.sock
+//! {"success":true}
+//! ```
+//!
+//! Blocks are addressed by their type or their `id` option. Commands apply
+//! to all matching blocks, so giving several blocks the same id turns them
+//! into a group that can be hidden and shown at once.
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use chan;
+use chan::Sender;
+use serde_json;
+
+use block::Block;
+use errors::*;
+use reload::BlockSpec;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    /// Update the blocks right away
+    Refresh { block: String },
+    Hide { block: String },
+    Show { block: String },
+    /// Hide the blocks if any of them is visible, show them otherwise
+    Toggle { block: String },
+    /// Replace the text of the blocks, see `Block::set_text`
+    SetText { block: String, text: String },
+}
+
+/// A command along with the channel to send its outcome back on.
+pub struct Request {
+    pub command: Command,
+    pub reply: Sender<::std::result::Result<(), String>>,
+}
+
+/// `$XDG_RUNTIME_DIR/i3status-rs.sock`, or in `/tmp` if that isn't set.
+pub fn socket_path() -> PathBuf {
+    env::var("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("/tmp"))
+        .join("i3status-rs.sock")
+}
+
+/// Listen on the socket at `path` and pass all commands on to `tx`.
+pub fn spawn_server(path: &Path, tx: Sender<Request>) -> Result<()> {
+    // A socket left behind by a previous run would make binding fail.
+    let _ = fs::remove_file(path);
+    let listener = UnixListener::bind(path).internal_error("ipc", "failed to bind control socket")?;
+
+    thread::spawn(move || for stream in listener.incoming() {
+        if let Ok(stream) = stream {
+            let tx = tx.clone();
+            thread::spawn(move || serve(stream, &tx));
+        }
+    });
+
+    Ok(())
+}
+
+fn serve(stream: UnixStream, tx: &Sender<Request>) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => return,
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let result = match serde_json::from_str::<Command>(&line) {
+            Ok(command) => {
+                let (reply, rx_reply) = chan::async();
+                tx.send(Request { command, reply });
+                rx_reply
+                    .recv()
+                    .unwrap_or_else(|| Err("no reply from the bar".to_owned()))
+            }
+            Err(error) => Err(format!("invalid command: {}", error)),
+        };
+
+        let response = match result {
+            Ok(()) => json!({ "success": true }),
+            Err(error) => json!({ "success": false, "error": error }),
+        };
+        if writeln!(writer, "{}", response).is_err() {
+            return;
+        }
+    }
+}
+
+/// Carry out `command` on the blocks of the bar. `order` and `specs` are the
+/// ids and specs of all blocks in bar order, `hidden` the ids of the blocks
+/// hidden by earlier commands.
+pub fn handle(
+    command: &Command,
+    order: &[String],
+    specs: &[BlockSpec],
+    block_map: &mut HashMap<String, Box<Block>>,
+    hidden: &mut HashSet<String>,
+) -> Result<()> {
+    let name = match *command {
+        Command::Refresh { ref block } |
+        Command::Hide { ref block } |
+        Command::Show { ref block } |
+        Command::Toggle { ref block } |
+        Command::SetText { ref block, .. } => block,
+    };
+
+    let ids: Vec<&String> = order
+        .iter()
+        .zip(specs)
+        .filter(|&(_, spec)| spec.matches(name))
+        .map(|(id, _)| id)
+        .collect();
+    if ids.is_empty() {
+        return Err(InternalError("ipc".to_owned(), format!("no block named '{}'", name), None));
+    }
+
+    match *command {
+        Command::Refresh { .. } => for id in ids {
+            block_map
+                .get_mut(id)
+                .internal_error("ipc", "could not get required block")?
+                .update()?;
+        },
+        Command::Hide { .. } => for id in ids {
+            hidden.insert(id.clone());
+        },
+        Command::Show { .. } => for id in ids {
+            hidden.remove(id);
+        },
+        Command::Toggle { .. } => {
+            let hide = ids.iter().any(|id| !hidden.contains(*id));
+            for id in ids {
+                if hide {
+                    hidden.insert(id.clone());
+                } else {
+                    hidden.remove(id);
+                }
+            }
+        }
+        Command::SetText { ref text, .. } => for id in ids {
+            block_map
+                .get_mut(id)
+                .internal_error("ipc", "could not get required block")?
+                .set_text(text.clone())?;
+        },
+    }
+
+    Ok(())
+}
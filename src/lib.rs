@@ -0,0 +1,118 @@
+//! The blocks of i3status-rs, for use in bars of your own.
+//!
+//! Blocks implement `Block`, and `ConfigBlock` to be created from their
+//! configuration. They show themselves through widgets, see `widgets`, and
+//! ask for updates through the `scheduler::Task` sender they are created
+//! with. `BarBuilder` puts built-in blocks and blocks of your own together
+//! into a bar:
+//!
+//! ```ignore
+//! let mut builder = BarBuilder::new(Config::default());
+//! builder.block("time", toml::from_str("format = \"%R\"")?)?;
+//! let mine = MyBlock::new(my_config, builder.config(), builder.update_sender())?;
+//! builder.custom_block(Box::new(mine));
+//! builder.build().run(&*output::from_name("i3bar", false)?)?;
+//! ```
+//!
+//! The modules marked hidden are only public for the `i3status-rs` binary,
+//! and may change at any time.
+
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+#[macro_use]
+extern crate chan;
+extern crate toml;
+extern crate clap;
+extern crate uuid;
+extern crate regex;
+extern crate num;
+extern crate rand;
+extern crate inotify;
+#[macro_use]
+extern crate nix;
+extern crate maildir;
+extern crate chrono;
+extern crate chrono_tz;
+extern crate dbus;
+extern crate git2;
+#[cfg(feature = "pulseaudio")]
+extern crate libpulse_binding as pulse;
+
+#[macro_use]
+#[doc(hidden)]
+pub mod de;
+#[macro_use]
+#[doc(hidden)]
+pub mod util;
+#[doc(hidden)]
+pub mod appearance;
+#[doc(hidden)]
+pub mod bars;
+pub mod block;
+pub mod blocks;
+#[doc(hidden)]
+pub mod btrfs;
+pub mod builder;
+#[doc(hidden)]
+pub mod check;
+#[doc(hidden)]
+pub mod compat;
+pub mod config;
+#[doc(hidden)]
+pub mod dbus_interface;
+#[doc(hidden)]
+pub mod dbus_manager;
+pub mod errors;
+#[doc(hidden)]
+pub mod formatting;
+#[doc(hidden)]
+pub mod http;
+#[doc(hidden)]
+pub mod i3_events;
+pub mod input;
+#[doc(hidden)]
+pub mod ipc;
+pub mod icons;
+#[doc(hidden)]
+pub mod logging;
+#[doc(hidden)]
+pub mod metrics;
+#[doc(hidden)]
+pub mod notifications;
+pub mod output;
+#[doc(hidden)]
+pub mod paging;
+#[doc(hidden)]
+pub mod pressure;
+#[doc(hidden)]
+pub mod processes;
+#[doc(hidden)]
+pub mod reload;
+#[doc(hidden)]
+pub mod resume;
+#[doc(hidden)]
+pub mod routes;
+pub mod themes;
+pub mod scheduler;
+#[doc(hidden)]
+pub mod signals;
+#[doc(hidden)]
+pub mod state;
+#[doc(hidden)]
+pub mod supervisor;
+#[doc(hidden)]
+pub mod udev;
+#[doc(hidden)]
+pub mod visibility;
+pub mod widget;
+pub mod widgets;
+
+pub use block::{Block, ConfigBlock};
+pub use builder::{BarBuilder, StatusBar};
@@ -0,0 +1,147 @@
+//! Logging, to stderr or a file.
+//!
+//! What gets logged is set with `--log` or the `I3STATUS_RS_LOG` environment
+//! variable, as a comma separated list of directives: a level on its own
+//! applies to everything, `module=level` to a module and everything below it,
+//! e.g. `warn,i3status_rs::blocks::music=debug`. The most specific directive
+//! wins. Only warnings and errors are logged by default, panics included.
+//!
+//! Blocks log below `i3status_rs::blocks::<type>`, including how long each
+//! of their updates takes, at the `debug` level.
+
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::panic;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use chrono::Local;
+use log::{self, LevelFilter, Log, Metadata, Record};
+
+use errors::*;
+
+struct Directive {
+    /// Everything if not set
+    module: Option<String>,
+    level: LevelFilter,
+}
+
+impl Directive {
+    fn matches(&self, target: &str) -> bool {
+        match self.module {
+            Some(ref module) => target == module || target.starts_with(&format!("{}::", module)),
+            None => true,
+        }
+    }
+}
+
+struct Logger {
+    directives: Vec<Directive>,
+    output: Mutex<Box<Write + Send>>,
+}
+
+impl Logger {
+    fn level(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .filter(|directive| directive.matches(target))
+            .max_by_key(|directive| directive.module.as_ref().map_or(0, |module| module.len() + 1))
+            .map_or(LevelFilter::Warn, |directive| directive.level)
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if let Ok(mut output) = self.output.lock() {
+            // Nowhere left to complain to if this fails
+            let _ = writeln!(
+                output,
+                "{} {:<5} {}: {}",
+                Local::now().format("%Y-%m-%dT%H:%M:%S%.3f"),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut output) = self.output.lock() {
+            let _ = output.flush();
+        }
+    }
+}
+
+fn parse_directives(spec: &str) -> Result<Vec<Directive>> {
+    let mut directives = Vec::new();
+    for part in spec.split(',').map(|part| part.trim()).filter(|part| !part.is_empty()) {
+        let mut parts = part.splitn(2, '=');
+        let first = parts.next().unwrap_or("");
+        directives.push(match parts.next() {
+            Some(level) => Directive {
+                module: Some(first.to_owned()),
+                level: LevelFilter::from_str(level).configuration_error(&format!("invalid log level '{}'", level))?,
+            },
+            // A module on its own logs everything
+            None => match LevelFilter::from_str(first) {
+                Ok(level) => Directive { module: None, level },
+                Err(_) => Directive {
+                    module: Some(first.to_owned()),
+                    level: LevelFilter::Trace,
+                },
+            },
+        });
+    }
+    Ok(directives)
+}
+
+/// Set up logging according to `spec`, falling back to `I3STATUS_RS_LOG`,
+/// see the module docs. Logs go to `file` if given, stderr otherwise.
+pub fn init(spec: Option<&str>, file: Option<&str>) -> Result<()> {
+    let spec = match spec {
+        Some(spec) => spec.to_owned(),
+        None => env::var("I3STATUS_RS_LOG").unwrap_or_default(),
+    };
+    let directives = parse_directives(&spec)?;
+
+    let output: Box<Write + Send> = match file {
+        Some(file) => Box::new(OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file)
+            .configuration_error(&format!("failed to open log file {}", file))?),
+        None => Box::new(io::stderr()),
+    };
+
+    // Let the log macros skip anything no directive asks for
+    let max_level = directives
+        .iter()
+        .map(|directive| directive.level)
+        .chain(Some(LevelFilter::Warn))
+        .max()
+        .unwrap_or(LevelFilter::Warn);
+    log::set_boxed_logger(Box::new(Logger {
+        directives,
+        output: Mutex::new(output),
+    })).internal_error("logging", "logging was set up twice")?;
+    log::set_max_level(max_level);
+
+    // Panics of blocks are caught, and panics in their threads only end that
+    // thread, so the bar keeps running. They belong in the log file then.
+    panic::set_hook(Box::new(|info| error!("{}", info)));
+    Ok(())
+}
+
+/// Where the blocks of type `name` log to, e.g. `i3status_rs::blocks::music`.
+pub fn block_target(name: &str) -> String {
+    let krate = module_path!().split("::").next().unwrap_or("i3status_rs");
+    format!("{}::blocks::{}", krate, name)
+}
@@ -1,38 +1,10 @@
 #[macro_use]
-extern crate lazy_static;
-#[macro_use]
-extern crate serde_derive;
-extern crate serde;
-#[macro_use]
-extern crate serde_json;
+extern crate log;
 #[macro_use]
 extern crate chan;
-extern crate toml;
 extern crate clap;
-extern crate uuid;
-extern crate regex;
-extern crate num;
-extern crate inotify;
-extern crate maildir;
-extern crate chrono;
-extern crate chrono_tz;
-#[cfg(feature = "pulseaudio")]
-extern crate libpulse_binding as pulse;
-
-#[macro_use]
-mod de;
-#[macro_use]
-mod util;
-mod block;
-pub mod blocks;
-mod config;
-mod errors;
-mod input;
-mod icons;
-mod themes;
-mod scheduler;
-mod widget;
-mod widgets;
+extern crate nix;
+extern crate i3status_rs;
 
 #[cfg(feature = "profiling")]
 extern crate cpuprofiler;
@@ -41,25 +13,34 @@ use cpuprofiler::PROFILER;
 #[cfg(feature = "profiling")]
 extern crate progress;
 
-use std::collections::HashMap;
-use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::path::Path;
+use std::time::{Duration, Instant};
+#[cfg(feature = "profiling")]
 use std::ops::DerefMut;
 
-use block::Block;
-
-use blocks::create_block;
-use config::Config;
-use errors::*;
-use input::{process_events, I3BarEvent};
-use scheduler::{Task, UpdateScheduler};
-use widget::{I3BarWidget, State};
-use widgets::text::TextWidget;
-
-use util::deserialize_file;
+use i3status_rs::block::Block;
+use i3status_rs::{appearance, bars, check, compat, config, dbus_interface, ipc, logging, metrics, output, reload, resume, signals, util, visibility};
+#[cfg(feature = "profiling")]
+use i3status_rs::blocks::create_block;
+#[cfg(feature = "profiling")]
+use i3status_rs::state;
+use i3status_rs::config::Config;
+use i3status_rs::errors::*;
+use i3status_rs::input::{process_events, ClickTracker, I3BarEvent};
+use i3status_rs::output::OutputFormat;
+use i3status_rs::scheduler::{Task, UpdateScheduler};
+use i3status_rs::widget::{I3BarWidget, State};
+use i3status_rs::widgets::text::TextWidget;
 
 use self::clap::{App, Arg, ArgMatches};
 use self::chan::{Receiver, Sender};
 
+/// How long the configuration file has to be left alone after a save before
+/// it's reloaded, in milliseconds
+const RELOAD_DELAY_MILLIS: u64 = 250;
+
 fn main() {
     let mut builder = App::new("i3status-rs")
         .version("0.9")
@@ -75,6 +56,47 @@ fn main() {
                 .required(true)
                 .index(1),
         )
+        .arg(
+            Arg::with_name("check")
+                .help("check the configuration file for errors and exit, without starting the bar")
+                .long("check")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("once")
+                .help("update all blocks once, print the status line and exit")
+                .long("once")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("iterations")
+                .help("update all blocks this many times, printing the status line each time, and exit")
+                .long("iterations")
+                .takes_value(true)
+                .conflicts_with("once"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .help("the format of the status line: i3bar (also for swaybar), plain or lemonbar")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["i3bar", "plain", "lemonbar"])
+                .default_value("i3bar"),
+        )
+        .arg(
+            Arg::with_name("log")
+                .help("what to log, e.g. `warn,i3status_rs::blocks::music=debug`, defaults to $I3STATUS_RS_LOG")
+                .long("log")
+                .value_name("DIRECTIVES")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("log-file")
+                .help("log to this file instead of stderr")
+                .long("log-file")
+                .value_name("FILE")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("exit-on-error")
                 .help(
@@ -84,7 +106,7 @@ fn main() {
                 .takes_value(false),
         );
 
-    if_debug!({
+    if cfg!(debug_assertions) {
         builder = builder
             .arg(
                 Arg::with_name("profile")
@@ -98,14 +120,38 @@ fn main() {
                     .takes_value(true)
                     .default_value("10000")
                     .help("How many times to execute update when profiling."),
-            );;
-    });
+            );
+    }
 
     let matches = builder.get_matches();
+
+    if let Err(error) = logging::init(matches.value_of("log"), matches.value_of("log-file")) {
+        eprintln!("{:?}", error);
+        ::std::process::exit(1);
+    }
+
+    if matches.is_present("check") {
+        let problems = check::check_config(matches.value_of("config").unwrap());
+        for problem in &problems {
+            eprintln!("{}", problem);
+        }
+        if problems.is_empty() {
+            println!("Configuration OK");
+            ::std::process::exit(0);
+        }
+        ::std::process::exit(1);
+    }
     let exit_on_error = matches.is_present("exit-on-error");
+    let output = match output::from_name(matches.value_of("format").unwrap(), true) {
+        Ok(output) => output,
+        Err(error) => {
+            eprintln!("{:?}", error);
+            ::std::process::exit(1);
+        }
+    };
 
     // Run and match for potential error
-    if let Err(error) = run(&matches) {
+    if let Err(error) = run(&matches, &*output) {
         if exit_on_error {
             eprintln!("{:?}", error);
             ::std::process::exit(1);
@@ -114,10 +160,12 @@ fn main() {
         let error_widget = TextWidget::new(Default::default())
             .with_state(State::Critical)
             .with_text(&format!("{:?}", error));
-        let error_rendered = error_widget.get_rendered();
+        let error_rendered = error_widget.get_rendered().clone();
         println!(
             "{}",
-            serde_json::to_string(&[error_rendered]).expect("failed to serialize error message")
+            output
+                .line(&[vec![error_rendered]], &Config::default())
+                .expect("failed to render error message")
         );
 
         eprintln!("\n\n{:?}", error);
@@ -129,12 +177,25 @@ fn main() {
 }
 
 #[allow(unused_mut)] // TODO: Remove when fixed in chan_select
-fn run(matches: &ArgMatches) -> Result<()> {
-    // Now we can start to run the i3bar protocol
-    print!("{{\"version\": 1, \"click_events\": true}}\n[");
+fn run(matches: &ArgMatches, output: &OutputFormat) -> Result<()> {
+    // Signals have to be blocked before any thread is spawned
+    signals::block_signals()?;
+
+    // Now we can start to run the protocol
+    if let Some(header) = output.header() {
+        print!("{}", header);
+    }
 
     // Read & parse the config file
-    let config: Config = deserialize_file(matches.value_of("config").unwrap())?;
+    let config_path = matches.value_of("config").unwrap();
+    let mut config: Config = config::load(config_path)?;
+    bars::check(&config)?;
+    compat::init(&config);
+    // The color scheme picks the theme, if configured
+    let mut scheme = config.theme_switch.as_ref().map(appearance::current);
+    if let Some(scheme) = scheme {
+        appearance::apply(&mut config, scheme);
+    }
 
     // Update request channel
     let (tx_update_requests, rx_update_requests): (Sender<Task>, Receiver<Task>) = chan::async();
@@ -145,66 +206,108 @@ fn run(matches: &ArgMatches) -> Result<()> {
         return Ok(());
     }
 
-    let mut config_alternating_tint = config.clone();
-    {
-        let tint_bg = &config.theme.alternating_tint_bg;
-        config_alternating_tint.theme.idle_bg = util::add_colors(&config_alternating_tint.theme.idle_bg, tint_bg)
-            .configuration_error("can't parse alternative_tint color code")?;
-        config_alternating_tint.theme.info_bg = util::add_colors(&config_alternating_tint.theme.info_bg, tint_bg)
-            .configuration_error("can't parse alternative_tint color code")?;
-        config_alternating_tint.theme.good_bg = util::add_colors(&config_alternating_tint.theme.good_bg, tint_bg)
-            .configuration_error("can't parse alternative_tint color code")?;
-        config_alternating_tint.theme.warning_bg = util::add_colors(&config_alternating_tint.theme.warning_bg, tint_bg)
-            .configuration_error("can't parse alternative_tint color code")?;
-        config_alternating_tint.theme.critical_bg = util::add_colors(&config_alternating_tint.theme.critical_bg, tint_bg)
-            .configuration_error("can't parse alternative_tint color code")?;
-
-        let tint_fg = &config.theme.alternating_tint_fg;
-        config_alternating_tint.theme.idle_fg = util::add_colors(&config_alternating_tint.theme.idle_fg, tint_fg)
-            .configuration_error("can't parse alternative_tint color code")?;
-        config_alternating_tint.theme.info_fg = util::add_colors(&config_alternating_tint.theme.info_fg, tint_fg)
-            .configuration_error("can't parse alternative_tint color code")?;
-        config_alternating_tint.theme.good_fg = util::add_colors(&config_alternating_tint.theme.good_fg, tint_fg)
-            .configuration_error("can't parse alternative_tint color code")?;
-        config_alternating_tint.theme.warning_fg = util::add_colors(&config_alternating_tint.theme.warning_fg, tint_fg)
-            .configuration_error("can't parse alternative_tint color code")?;
-        config_alternating_tint.theme.critical_fg = util::add_colors(&config_alternating_tint.theme.critical_fg, tint_fg)
-            .configuration_error("can't parse alternative_tint color code")?;
-    }
-
-    let mut blocks: Vec<Box<Block>> = Vec::new();
-
-    let mut alternator = false;
-    // Initialize the blocks
-    for &(ref block_name, ref block_config) in &config.blocks {
-        blocks.push(create_block(
-            block_name,
-            block_config.clone(),
-            if alternator {
-                config_alternating_tint.clone()
-            } else {
-                config.clone()
-            },
-            tx_update_requests.clone(),
-        )?);
-        alternator = !alternator;
-    }
-
     // We save the order of the blocks here,
     // because they will be passed to an unordered HashMap
-    let order = blocks.iter().map(|x| String::from(x.id())).collect::<Vec<_>>();
-
-    let mut scheduler = UpdateScheduler::new(&blocks);
+    let mut order: Vec<String> = Vec::new();
+    let mut specs = Vec::new();
+    let mut block_map: HashMap<String, Box<Block>> = HashMap::new();
+    // Blocks hidden through the control socket
+    let mut hidden: HashSet<String> = HashSet::new();
+
+    // For testing configurations, run the blocks a few times and be done
+    let iterations = if matches.is_present("once") {
+        Some(1)
+    } else {
+        match matches.value_of("iterations") {
+            Some(n) => Some(n.parse::<u32>().configuration_error("failed to parse --iterations as an integer")?),
+            None => None,
+        }
+    };
+    if let Some(iterations) = iterations {
+        reload::rebuild_blocks(&config, &mut order, &mut specs, &mut block_map, &tx_update_requests)?;
+        return run_iterations(iterations, &order, &specs, &mut block_map, &config, output);
+    }
 
-    let mut block_map: HashMap<String, &mut Block> = HashMap::new();
+    // Initialize the blocks, placeholders first so that the bar shows up
+    // right away. The actual blocks are created one by one in the loop
+    // below. `tx_build` is kept around, like `tx_clicks`.
+    let pending = reload::placeholder_blocks(&config, &mut order, &mut specs, &mut block_map)?;
+    let (tx_build, rx_build): (Sender<String>, Receiver<String>) = chan::async();
+    reload::build_placeholders(&pending, &tx_build);
 
-    for block in &mut blocks {
-        block_map.insert(String::from(block.id()), (*block).deref_mut());
-    }
+    let mut scheduler = UpdateScheduler::new(&order);
 
     // We wait for click events in a separate thread, to avoid blocking to wait for stdin
     let (tx_clicks, rx_clicks): (Sender<I3BarEvent>, Receiver<I3BarEvent>) = chan::async();
-    process_events(tx_clicks);
+    // Other bars don't send any, and stdin may well be closed. `tx_clicks` is
+    // kept around either way, a closed channel would fire all the time.
+    if output.click_events() {
+        process_events(tx_clicks.clone());
+    }
+
+    // Further bars on sockets, which send their clicks just like stdin
+    let mut socket_bars = bars::Bar::spawn_all(&config, &tx_clicks)?;
+    let mut click_tracker = ClickTracker::default();
+
+    // Reload requests, on SIGHUP or when the config file is saved
+    let (tx_reload, rx_reload): (Sender<()>, Receiver<()>) = chan::async();
+    let (tx_config_saved, rx_config_saved): (Sender<Task>, Receiver<Task>) = chan::async();
+    reload::watch_config(Path::new(config_path), &tx_config_saved)?;
+
+    // Signals to reload or update blocks
+    let (tx_signals, rx_signals): (Sender<i32>, Receiver<i32>) = chan::async();
+    signals::spawn_listener(tx_signals);
+
+    // Resume from suspend. `tx_resume` is kept around, like `tx_clicks`, the
+    // listener gives up without a system bus.
+    let (tx_resume, rx_resume): (Sender<()>, Receiver<()>) = chan::async();
+    resume::spawn_listener(tx_resume.clone());
+
+    // Whether the bar is in invisible mode, over the i3/sway IPC. Kept open
+    // like `tx_resume`.
+    let (tx_visibility, rx_visibility): (Sender<bool>, Receiver<bool>) = chan::async();
+    visibility::spawn_listener(tx_visibility.clone());
+
+    // Changes of the color scheme. Which source is followed is only picked
+    // up on start. `tx_scheme` is kept around, like `tx_clicks`.
+    let (tx_scheme, rx_scheme): (Sender<appearance::Scheme>, Receiver<appearance::Scheme>) = chan::async();
+    if let Some(ref switch) = config.theme_switch {
+        appearance::spawn_listener(switch, tx_scheme.clone());
+    }
+
+    // Commands from the control socket
+    let (tx_ipc, rx_ipc): (Sender<ipc::Request>, Receiver<ipc::Request>) = chan::async();
+    ipc::spawn_server(&ipc::socket_path(), tx_ipc.clone())?;
+
+    // The same commands over D-Bus, which also gets to know about clicks
+    let (tx_dbus_clicks, rx_dbus_clicks): (Sender<(String, String)>, Receiver<(String, String)>) = chan::async();
+    dbus_interface::spawn_server(tx_ipc, rx_dbus_clicks);
+
+    // Whether the bar is hidden, as told by its stop and continue signals,
+    // and whether it's invisible altogether, as told by i3 or sway
+    let mut suspended = false;
+    let mut invisible = false;
+
+    // Whether the bar needs to be redrawn, and what was drawn last
+    let mut dirty = false;
+    let mut last_line = String::new();
+    // Reused for every redraw, along with the widgets of the last one
+    let mut line = String::new();
+    let mut render_cache = util::RenderCache::default();
+    let mut last_redraw = Instant::now() - config.debounce;
+    // When the metrics file was written last, if it's written at all
+    let mut last_metrics: Option<Instant> = None;
+    // Fires when the debounce window of the last redraw ends, `never` stands
+    // in while there's no redraw pending
+    let (_tx_never, never): (Sender<()>, Receiver<()>) = chan::sync(0);
+    let mut redraw = never.clone();
+    let mut redraw_pending = false;
+    // Fires when a click held back for a possible double click is due
+    let mut click_timer = never.clone();
+    // Fires once the configuration file was left alone for a moment after
+    // being saved, which is when it's due to be reloaded
+    let mut reload_timer = never.clone();
+    let mut reload_due: Option<Instant> = None;
 
     // Time to next update channel.
     // Fires immediately for first updates
@@ -214,38 +317,294 @@ fn run(matches: &ArgMatches) -> Result<()> {
         // We use the message passing concept of channel selection
         // to avoid busy wait
 
+        let mut redraw_fired = false;
         chan_select! {
             // Receive click events
             rx_clicks.recv() -> res => if let Some(event) = res {
-                    for block in block_map.values_mut() {
-                        block.click(&event)?;
+                    let hold_back = util::wants_double_click(&order, &specs, &block_map, &event);
+                    for event in click_tracker.track(event, config.double_click_interval, hold_back) {
+                        handle_click(&event, &order, &specs, &mut block_map, &tx_dbus_clicks, &tx_update_requests)?;
                     }
-                    util::print_blocks(&order, &block_map, &config)?;
+                    dirty = true;
+            },
+            // Let a held back click through once no second click followed
+            click_timer.recv() => {
+                if let Some(event) = click_tracker.expire(config.double_click_interval) {
+                    handle_click(&event, &order, &specs, &mut block_map, &tx_dbus_clicks, &tx_update_requests)?;
+                    dirty = true;
+                }
             },
             // Receive async update requests
             rx_update_requests.recv() -> res => if let Some(request) = res {
-                // Process immediately and forget
-                block_map
-                    .get_mut(&request.id)
-                    .internal_error("scheduler", "could not get required block")?
-                    .update()?;
-                util::print_blocks(&order, &block_map, &config)?;
+                // Process immediately and forget. Requests from blocks
+                // removed by a reload are dropped.
+                if let Some(block) = block_map.get_mut(&request.id) {
+                    block.update()?;
+                }
+                dirty = true;
+            },
+            // Receive signals
+            rx_signals.recv() -> res => if let Some(signal) = res {
+                if signal == nix::libc::SIGHUP {
+                    tx_reload.send(());
+                } else if signal == signals::STOP_SIGNAL {
+                    suspended = true;
+                } else if signal == signals::CONT_SIGNAL {
+                    // Catch up on everything we missed while hidden
+                    suspended = false;
+                    if !invisible {
+                        scheduler = UpdateScheduler::new(&order);
+                    }
+                } else {
+                    // SIGUSR1 updates all blocks, SIGRTMIN+n those with `signal = n`
+                    for (id, spec) in order.iter().zip(&specs) {
+                        if signal == nix::libc::SIGUSR1 || spec.common.signal.map(|n| signals::sigrtmin() + n) == Some(signal) {
+                            block_map
+                                .get_mut(id)
+                                .internal_error("signals", "could not get required block")?
+                                .update()?;
+                        }
+                    }
+                    dirty = true;
+                }
+            },
+            // Pause while the bar is invisible, and catch up once it's back
+            rx_visibility.recv() -> res => if let Some(hidden) = res {
+                if invisible && !hidden && !suspended {
+                    scheduler = UpdateScheduler::new(&order);
+                }
+                invisible = hidden;
+            },
+            // Catch up after a suspend
+            rx_resume.recv() -> res => if res.is_some() {
+                for block in block_map.values_mut() {
+                    block.resumed();
+                }
+                scheduler = UpdateScheduler::new(&order);
+                dirty = true;
+            },
+            // Receive commands from the control socket
+            rx_ipc.recv() -> res => if let Some(request) = res {
+                let result = ipc::handle(&request.command, &order, &specs, &mut block_map, &mut hidden);
+                request.reply.send(result.map_err(|error| format!("{:?}", error)));
+                dirty = true;
+            },
+            // Wait for the editor to be done with the configuration file,
+            // each save starts the wait over
+            rx_config_saved.recv() -> res => if res.is_some() {
+                reload_due = Some(Instant::now() + Duration::from_millis(RELOAD_DELAY_MILLIS));
+            },
+            reload_timer.recv() => {
+                reload_due = None;
+                tx_reload.send(());
+            },
+            // Receive configuration reload requests
+            rx_reload.recv() -> res => if res.is_some() {
+                // A broken config shouldn't take down a running bar, so we
+                // keep the old one in that case.
+                let reloaded = config::load(config_path).and_then(|mut new_config| {
+                    bars::check(&new_config)?;
+                    let new_scheme = new_config.theme_switch.as_ref().map(appearance::current);
+                    if let Some(new_scheme) = new_scheme {
+                        appearance::apply(&mut new_config, new_scheme);
+                    }
+                    let created = reload::rebuild_blocks(&new_config, &mut order, &mut specs, &mut block_map, &tx_update_requests)?;
+                    Ok((new_config, new_scheme, created))
+                });
+                match reloaded {
+                    Ok((new_config, new_scheme, created)) => {
+                        config = new_config;
+                        scheme = new_scheme;
+                        for bar in &mut socket_bars {
+                            bar.reload(&config);
+                        }
+                        scheduler.retain(&order);
+                        let now = Instant::now();
+                        for id in created {
+                            scheduler.schedule(Task { id, update_time: now });
+                        }
+                    }
+                    Err(error) => error!("failed to reload configuration: {:?}", error),
+                }
+                dirty = true;
+            },
+            // Swap themes, which all blocks need to be rebuilt for
+            rx_scheme.recv() -> res => if let Some(new_scheme) = res {
+                if scheme != Some(new_scheme) {
+                    info!("switching to the {:?} theme", new_scheme);
+                    let mut new_config = config.clone();
+                    appearance::apply(&mut new_config, new_scheme);
+                    match reload::rebuild_blocks(&new_config, &mut order, &mut specs, &mut block_map, &tx_update_requests) {
+                        Ok(created) => {
+                            config = new_config;
+                            scheme = Some(new_scheme);
+                            scheduler.retain(&order);
+                            let now = Instant::now();
+                            for id in created {
+                                scheduler.schedule(Task { id, update_time: now });
+                            }
+                        }
+                        Err(error) => error!("failed to switch themes: {:?}", error),
+                    }
+                    dirty = true;
+                }
+            },
+            // Create the next block and put it in place of its placeholder
+            rx_build.recv() -> res => if let Some(id) = res {
+                if let Some(id) = reload::create_placeholder(&id, &mut order, &specs, &mut block_map, &tx_update_requests) {
+                    scheduler.schedule(Task { id, update_time: Instant::now() });
+                }
+                dirty = true;
+            },
+            // Redraw after the debounce window
+            redraw.recv() => {
+                redraw_fired = true;
             },
             // Receive update timer events
             ttnu.recv() => {
-                scheduler.do_scheduled_updates(&mut block_map)?;
+                // A reload may have emptied the schedule since the timer was set
+                if scheduler.time_to_next_update().is_some() {
+                    scheduler.do_scheduled_updates(&mut block_map)?;
+                }
 
                 // redraw the blocks, state changed
-                util::print_blocks(&order, &block_map, &config)?;
+                dirty = true;
+            }
+        }
+
+        // Redraw, unless we did so within the debounce window. Then we
+        // get back to it once the window is over.
+        if redraw_fired {
+            redraw = never.clone();
+            redraw_pending = false;
+        }
+        if dirty && !redraw_pending {
+            let now = Instant::now();
+            if now >= last_redraw + config.debounce {
+                let blocks = bars::select(bars::stdout_blocks(&config), &order, &specs);
+                output.write_line(render_cache.render(blocks, &block_map, &hidden)?, &config, &mut line)?;
+                // Nothing to do for the bar if nothing changed
+                if line != last_line {
+                    println!("{}", line);
+                    mem::swap(&mut line, &mut last_line);
+                }
+                for bar in &mut socket_bars {
+                    bar.redraw(&order, &specs, &block_map, &hidden, &config)?;
+                }
+                last_redraw = now;
+                dirty = false;
+            } else {
+                redraw = chan::after(last_redraw + config.debounce - now);
+                redraw_pending = true;
+            }
+        }
+
+        // Export what the blocks measured, at most once per interval
+        if let Some(ref metrics_config) = config.metrics {
+            let now = Instant::now();
+            if last_metrics.map_or(true, |last| now >= last + metrics_config.interval) {
+                if let Err(error) = metrics::write(metrics_config, &order, &specs, &block_map) {
+                    warn!("failed to write metrics: {:?}", error);
+                }
+                last_metrics = Some(now);
             }
         }
 
-        // Set the time-to-next-update timer
+        click_timer = match click_tracker.time_to_expiry(config.double_click_interval) {
+            Some(time) => chan::after(time),
+            None => never.clone(),
+        };
+
+        reload_timer = match reload_due {
+            Some(due) => {
+                let now = Instant::now();
+                chan::after(if due > now { due - now } else { Duration::from_secs(0) })
+            }
+            None => never.clone(),
+        };
+
+        // Set the time-to-next-update timer. Periodic updates are paused
+        // while the bar is hidden.
         match scheduler.time_to_next_update() {
-            Some(time) => ttnu = chan::after(time),
-            None => ttnu = chan::after(Duration::from_secs(std::u64::MAX)),
+            Some(time) if !suspended && !invisible => ttnu = chan::after(time),
+            _ => ttnu = chan::after(Duration::from_secs(std::u64::MAX)),
+        }
+    }
+}
+
+/// Update all blocks `iterations` times and print the status line after each
+/// round. Rounds are as far apart as the shortest update interval of the
+/// blocks, so that blocks measuring rates have something to measure.
+fn run_iterations(
+    iterations: u32,
+    order: &[String],
+    specs: &[reload::BlockSpec],
+    block_map: &mut HashMap<String, Box<Block>>,
+    config: &Config,
+    output: &OutputFormat,
+) -> Result<()> {
+    for iteration in 0..iterations {
+        let mut interval: Option<Duration> = None;
+        for id in order {
+            let next = block_map
+                .get_mut(id)
+                .internal_error("iterations", "could not get required block")?
+                .update()?;
+            interval = match (interval, next) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        }
+        let blocks = util::render_blocks(bars::select(bars::stdout_blocks(config), order, specs), block_map, &HashSet::new())?;
+        println!("{}", output.line(&blocks, config)?);
+
+        if iteration + 1 < iterations {
+            if let Some(interval) = interval {
+                ::std::thread::sleep(interval);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Pass a click on to the block clicked, after running its click command, if
+/// any. Other blocks don't get to see the click, even if they have a widget
+/// of the same name.
+fn handle_click(
+    event: &I3BarEvent,
+    order: &[String],
+    specs: &[reload::BlockSpec],
+    block_map: &mut HashMap<String, Box<Block>>,
+    tx_dbus_clicks: &Sender<(String, String)>,
+    tx_update_requests: &Sender<Task>,
+) -> Result<()> {
+    let position = match util::block_of_event(order, event) {
+        Some(position) => position,
+        None => {
+            debug!("click on a widget of no block: {:?}", event);
+            return Ok(());
+        }
+    };
+
+    {
+        let spec = &specs[position];
+        let block = spec.common.id.clone().unwrap_or_else(|| spec.name.clone());
+        let button = format!("{:?}", event.button).to_lowercase();
+        tx_dbus_clicks.send((block, button));
+
+        // Click commands go first, the block still gets the click afterwards.
+        if let Some(command) = spec.common.click_command(event) {
+            let id = &order[position];
+            let text = util::block_text(
+                &**block_map.get(id).internal_error("click", "could not get required block")?,
+            );
+            util::spawn_click_command(command, &text, &event.button, id.clone(), tx_update_requests.clone());
         }
     }
+    block_map
+        .get_mut(&order[position])
+        .internal_error("click", "could not get required block")?
+        .click(event)
 }
 
 #[cfg(feature = "profiling")]
@@ -285,6 +644,7 @@ fn profile_config(name: &str, runs: &str, config: &Config, update: Sender<Task>)
                 block_config.clone(),
                 config.clone(),
                 update.clone(),
+                state::BlockState::new(&block_name),
             )?;
             profile(profile_runs, &block_name, block.deref_mut());
             break;
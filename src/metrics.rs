@@ -0,0 +1,88 @@
+//! Exporting what blocks measure, like the battery charge or free disk space,
+//! so that it can be alerted on without running further agents.
+//!
+//! With a `[metrics]` section, the bar writes the numbers of all blocks that
+//! have any, see `Block::metrics`, to a file in the Prometheus text format.
+//! Pointed at the textfile collector directory of the node exporter, they
+//! show up along with its own metrics. Every block also tells how long its
+//! last update took.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use block::Block;
+use config::MetricsConfig;
+use errors::*;
+use reload::BlockSpec;
+
+/// Put in front of every metric name
+const PREFIX: &str = "i3status";
+
+/// `value` as a label value, quoted and escaped.
+fn label(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// The metrics of the blocks in `order`, in the Prometheus text format.
+/// Samples are grouped by metric, each group has to come in one piece.
+pub fn render(order: &[String], specs: &[BlockSpec], block_map: &HashMap<String, Box<Block>>) -> String {
+    let mut families: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (id, spec) in order.iter().zip(specs) {
+        let block = match block_map.get(id) {
+            Some(block) => block,
+            None => continue,
+        };
+        for (name, value) in block.metrics() {
+            if !value.is_finite() {
+                continue;
+            }
+            families.entry(name).or_insert_with(Vec::new).push(format!(
+                "{}_{}{{block={},id={}}} {}",
+                PREFIX,
+                name,
+                label(&spec.name),
+                label(spec.key()),
+                value
+            ));
+        }
+    }
+
+    let mut text = String::new();
+    for (name, samples) in families {
+        text.push_str(&format!("# TYPE {}_{} gauge\n", PREFIX, name));
+        for sample in samples {
+            text.push_str(&sample);
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// Write the metrics of the blocks to the file of `config`. The file is
+/// replaced as a whole, so that collectors never read half of it.
+pub fn write(
+    config: &MetricsConfig,
+    order: &[String],
+    specs: &[BlockSpec],
+    block_map: &HashMap<String, Box<Block>>,
+) -> Result<()> {
+    let path = Path::new(&config.path);
+    let tmp = path.with_extension("prom.tmp");
+    File::create(&tmp)
+        .and_then(|mut file| file.write_all(render(order, specs, block_map).as_bytes()))
+        .internal_error("metrics", &format!("failed to write '{}'", tmp.display()))?;
+    fs::rename(&tmp, path).internal_error("metrics", &format!("failed to replace '{}'", config.path))
+}
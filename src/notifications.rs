@@ -0,0 +1,56 @@
+//! Desktop notifications, through the `org.freedesktop.Notifications`
+//! interface that notification daemons like dunst or mako implement.
+
+use std::collections::HashMap;
+use std::thread;
+
+use dbus::arg::Variant;
+use dbus::{BusType, Connection, Message};
+
+use errors::*;
+
+const NOTIFICATIONS_NAME: &str = "org.freedesktop.Notifications";
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+fn send(summary: &str, body: &str, urgency: Urgency) -> Result<()> {
+    let c = Connection::get_private(BusType::Session)
+        .internal_error("notifications", "failed to connect to the session bus")?;
+    let mut hints = HashMap::new();
+    hints.insert(
+        "urgency",
+        Variant(match urgency {
+            Urgency::Low => 0u8,
+            Urgency::Normal => 1,
+            Urgency::Critical => 2,
+        }),
+    );
+    let actions: Vec<&str> = Vec::new();
+    // App name, id of a notification to replace, icon, summary, body,
+    // actions, hints and timeout, where -1 leaves it to the daemon
+    let m = Message::new_method_call(NOTIFICATIONS_NAME, NOTIFICATIONS_PATH, NOTIFICATIONS_NAME, "Notify")
+        .internal_error("notifications", "failed to create message")?
+        .append3("i3status-rs", 0u32, "")
+        .append3(summary, body, actions)
+        .append2(hints, -1i32);
+    c.send_with_reply_and_block(m, 1000)
+        .internal_error("notifications", "failed to send notification")?;
+    Ok(())
+}
+
+/// Show a notification, without waiting for the daemon. Failures are only
+/// logged, there's nobody else to tell.
+pub fn notify(summary: &str, body: &str, urgency: Urgency) {
+    let (summary, body) = (summary.to_owned(), body.to_owned());
+    thread::spawn(move || {
+        if let Err(error) = send(&summary, &body, urgency) {
+            warn!("failed to show notification '{}': {:?}", summary, error);
+        }
+    });
+}
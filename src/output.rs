@@ -0,0 +1,262 @@
+//! Turning the rendered blocks into what the consumer of the status line
+//! expects. Blocks always render i3bar widgets, which the formats here
+//! translate as needed:
+//!
+//! * `i3bar`, the JSON protocol of i3bar and swaybar
+//! * `plain`, just the text, e.g. for the status line of tmux
+//! * `lemonbar`, text with lemonbar's color tags
+
+use std::fmt::Write;
+
+use serde_json::value::Value;
+
+use config::Config;
+use errors::*;
+use signals;
+
+pub trait OutputFormat {
+    /// Printed once before the first status line, as it is.
+    fn header(&self) -> Option<String> {
+        None
+    }
+
+    /// Whether the bar sends click events on stdin.
+    fn click_events(&self) -> bool {
+        false
+    }
+
+    /// One status line from the widgets of each visible block, in bar order.
+    fn line(&self, blocks: &[Vec<Value>], config: &Config) -> Result<String>;
+
+    /// Like `line`, but into `line`, which is cleared first. Redrawing into
+    /// the same buffer every time spares allocating a new one.
+    fn write_line(&self, blocks: &[Vec<Value>], config: &Config, line: &mut String) -> Result<()> {
+        line.clear();
+        line.push_str(&self.line(blocks, config)?);
+        Ok(())
+    }
+}
+
+/// The format called `name`. `signals` tells i3bar about our stop and
+/// continue signals, which only works if it runs us itself.
+pub fn from_name(name: &str, signals: bool) -> Result<Box<OutputFormat>> {
+    match name {
+        "i3bar" => Ok(Box::new(I3Bar { signals })),
+        "plain" => Ok(Box::new(Plain)),
+        "lemonbar" => Ok(Box::new(Lemonbar)),
+        _ => Err(ConfigurationError(
+            format!("unknown output format '{}'", name),
+            (name.to_owned(), name.to_owned()),
+        )),
+    }
+}
+
+fn background(widget: &Value) -> Result<&str> {
+    widget["background"]
+        .as_str()
+        .internal_error("output", "couldn't get background color")
+}
+
+/// What goes into the status line, the widgets of the blocks with separators
+/// in between.
+enum Item<'a> {
+    /// A separator with its foreground and background color, if any
+    Separator(Option<&'a str>, Option<&'a str>),
+    Widget(&'a Value),
+}
+
+/// Whether separators go after each block instead of in front of it, as they
+/// do if they take the color of the block in front, e.g. right-pointing
+/// powerline arrows.
+fn trailing_separators(config: &Config) -> bool {
+    config.theme.separator_fg == "previous"
+}
+
+/// The separator color `color` of the theme between blocks with the
+/// backgrounds `previous` and `next`, either of which is missing at the edges
+/// of the bar. `auto` is what the theme's `auto` stands for.
+fn resolve_color<'a>(color: &'a str, previous: Option<&'a str>, next: Option<&'a str>, auto: Option<&'a str>) -> Option<&'a str> {
+    match color {
+        "auto" => auto,
+        "previous" => previous,
+        "next" => next,
+        color => Some(color),
+    }
+}
+
+/// The separator between blocks with the backgrounds `previous` and `next`.
+/// `auto` takes the foreground from the next block, and the background from
+/// the block on the other side, so that the glyph blends into both.
+fn separator<'a>(config: &'a Config, previous: Option<&'a str>, next: Option<&'a str>) -> Item<'a> {
+    let auto_bg = if trailing_separators(config) {
+        next
+    } else {
+        previous
+    };
+    Item::Separator(
+        resolve_color(&config.theme.separator_fg, previous, next, next),
+        resolve_color(&config.theme.separator_bg, previous, next, auto_bg),
+    )
+}
+
+/// The widgets of `blocks` with separators between them, which need to know
+/// the backgrounds of their neighbors. Blocks without widgets are skipped.
+fn with_separators<'a>(blocks: &'a [Vec<Value>], config: &'a Config) -> Result<Vec<Item<'a>>> {
+    let blocks: Vec<&Vec<Value>> = blocks.iter().filter(|widgets| !widgets.is_empty()).collect();
+    // The backgrounds of the first and the last widget of each block
+    let mut edges = Vec::new();
+    for &widgets in &blocks {
+        edges.push((background(&widgets[0])?, background(&widgets[widgets.len() - 1])?));
+    }
+
+    let trailing = trailing_separators(config);
+    let mut items = Vec::new();
+    for (i, &widgets) in blocks.iter().enumerate() {
+        if !trailing {
+            let previous = if i > 0 { Some(edges[i - 1].1) } else { None };
+            items.push(separator(config, previous, Some(edges[i].0)));
+        }
+        items.extend(widgets.iter().map(Item::Widget));
+        if trailing {
+            let next = edges.get(i + 1).map(|edge| edge.0);
+            items.push(separator(config, Some(edges[i].1), next));
+        }
+    }
+    Ok(items)
+}
+
+/// Append `text` to `line` as a JSON string.
+fn push_json_string(line: &mut String, text: &str) {
+    line.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => line.push_str("\\\""),
+            '\\' => line.push_str("\\\\"),
+            '\n' => line.push_str("\\n"),
+            '\r' => line.push_str("\\r"),
+            '\t' => line.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(line, "\\u{:04x}", c as u32);
+            }
+            c => line.push(c),
+        }
+    }
+    line.push('"');
+}
+
+/// Append `color` to `line` as a JSON string, or `null` if there is none.
+fn push_json_color(line: &mut String, color: Option<&str>) {
+    match color {
+        Some(color) => push_json_string(line, color),
+        None => line.push_str("null"),
+    }
+}
+
+pub struct I3Bar {
+    signals: bool,
+}
+
+impl OutputFormat for I3Bar {
+    fn header(&self) -> Option<String> {
+        if !self.signals {
+            return Some("{\"version\": 1, \"click_events\": true}\n[".to_owned());
+        }
+        Some(format!(
+            "{{\"version\": 1, \"click_events\": true, \"stop_signal\": {}, \"cont_signal\": {}}}\n[",
+            signals::STOP_SIGNAL,
+            signals::CONT_SIGNAL
+        ))
+    }
+
+    fn click_events(&self) -> bool {
+        true
+    }
+
+    fn line(&self, blocks: &[Vec<Value>], config: &Config) -> Result<String> {
+        let mut line = String::new();
+        self.write_line(blocks, config, &mut line)?;
+        Ok(line)
+    }
+
+    fn write_line(&self, blocks: &[Vec<Value>], config: &Config, line: &mut String) -> Result<()> {
+        line.clear();
+        line.push('[');
+        for (i, item) in with_separators(blocks, config)?.into_iter().enumerate() {
+            if i > 0 {
+                line.push(',');
+            }
+            match item {
+                Item::Separator(fg, bg) => {
+                    line.push_str("{\"full_text\":");
+                    push_json_string(line, &config.theme.separator);
+                    line.push_str(",\"separator\":false,\"separator_block_width\":0,\"background\":");
+                    push_json_color(line, bg);
+                    line.push_str(",\"color\":");
+                    push_json_color(line, fg);
+                    line.push_str(",\"markup\":\"pango\"}");
+                }
+                // Serialized right into the line, without a string of its own
+                Item::Widget(widget) => write!(line, "{}", widget).internal_error("output", "failed to write widget")?,
+            }
+        }
+        line.push_str("],");
+        Ok(())
+    }
+}
+
+pub struct Plain;
+
+impl OutputFormat for Plain {
+    fn line(&self, blocks: &[Vec<Value>], _config: &Config) -> Result<String> {
+        Ok(blocks
+            .iter()
+            .map(|widgets| {
+                widgets
+                    .iter()
+                    .filter_map(|widget| widget["full_text"].as_str())
+                    .map(|text| text.trim())
+                    .filter(|text| !text.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|block| !block.is_empty())
+            .collect::<Vec<_>>()
+            .join(" | "))
+    }
+}
+
+pub struct Lemonbar;
+
+/// Our colors are `#RRGGBB` or `#RRGGBBAA`, lemonbar wants `#AARRGGBB`.
+fn lemonbar_color(color: &str) -> String {
+    if color.len() == 9 {
+        format!("#{}{}", &color[7..9], &color[1..7])
+    } else {
+        color.to_owned()
+    }
+}
+
+impl OutputFormat for Lemonbar {
+    fn line(&self, blocks: &[Vec<Value>], config: &Config) -> Result<String> {
+        let mut line = String::new();
+        for item in with_separators(blocks, config)? {
+            let (fg, bg, text) = match item {
+                Item::Separator(fg, bg) => (fg, bg, config.theme.separator.clone()),
+                Item::Widget(widget) => (
+                    widget["color"].as_str(),
+                    Some(background(widget)?),
+                    widget["full_text"].as_str().unwrap_or("").replace('%', "%%"),
+                ),
+            };
+            line.push_str(&format!(
+                "%{{B{}}}%{{F{}}}{}",
+                bg.map_or("-".to_owned(), lemonbar_color),
+                fg.map_or("-".to_owned(), lemonbar_color),
+                text
+            ));
+        }
+        line.push_str("%{B-}%{F-}");
+
+        Ok(line)
+    }
+}
@@ -0,0 +1,54 @@
+//! Showing one page of blocks at a time.
+//!
+//! Blocks are put on a page with the common `page` option, blocks without it
+//! are shown on every page. Pages are switched by the `pager` block.
+
+use std::sync::Mutex;
+
+struct Pages {
+    /// Starting at 1, like the `page` option
+    current: u32,
+    count: u32,
+}
+
+lazy_static! {
+    static ref PAGES: Mutex<Pages> = Mutex::new(Pages { current: 1, count: 1 });
+}
+
+fn with_pages<T, F: FnOnce(&mut Pages) -> T>(f: F) -> T {
+    // The state stays consistent even if a holder of the lock panicked.
+    let mut pages = PAGES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut pages)
+}
+
+/// Set the number of pages, staying on the current page if it still exists.
+pub fn set_count(count: u32) {
+    with_pages(|pages| {
+        pages.count = count.max(1);
+        if pages.current > pages.count {
+            pages.current = 1;
+        }
+    })
+}
+
+pub fn current() -> u32 {
+    with_pages(|pages| pages.current)
+}
+
+pub fn count() -> u32 {
+    with_pages(|pages| pages.count)
+}
+
+/// Move `steps` pages forward, or backward if negative, wrapping around.
+pub fn turn(steps: i32) {
+    with_pages(|pages| {
+        let count = pages.count as i32;
+        let index = (pages.current as i32 - 1 + steps) % count;
+        pages.current = (if index < 0 { index + count } else { index }) as u32 + 1;
+    })
+}
+
+/// Whether a block on `page` is shown on the current page.
+pub fn is_visible(page: Option<u32>) -> bool {
+    page.map_or(true, |page| page == current())
+}
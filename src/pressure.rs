@@ -0,0 +1,72 @@
+//! Pressure stall information, the share of time tasks spent waiting for a
+//! resource, as the kernel tracks it since 4.20.
+//!
+//! `some` counts the time at least one task waited, `full` the time all of
+//! them did, so that nothing got done. Each comes as averages over the last
+//! 10, 60 and 300 seconds, in percent.
+
+use std::path::Path;
+
+use errors::*;
+use util::read_file;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Averages {
+    pub avg10: f64,
+    pub avg60: f64,
+    pub avg300: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Pressure {
+    pub some: Averages,
+    /// Not tracked system-wide for the CPU before Linux 5.13
+    pub full: Option<Averages>,
+}
+
+/// The averages in a line like
+/// `some avg10=0.00 avg60=0.00 avg300=0.00 total=0`.
+fn averages(line: &str) -> Averages {
+    let mut averages = Averages::default();
+    for field in line.split_whitespace().skip(1) {
+        let mut parts = field.splitn(2, '=');
+        let (key, value) = match (parts.next(), parts.next().and_then(|value| value.parse().ok())) {
+            (Some(key), Some(value)) => (key, value),
+            _ => continue,
+        };
+        match key {
+            "avg10" => averages.avg10 = value,
+            "avg60" => averages.avg60 = value,
+            "avg300" => averages.avg300 = value,
+            _ => {}
+        }
+    }
+    averages
+}
+
+/// The pressure in a file like `/proc/pressure/memory`, or the
+/// `memory.pressure` of a cgroup.
+pub fn read(block: &str, path: &Path) -> Result<Pressure> {
+    let contents = read_file(block, path)?;
+    let mut some = None;
+    let mut full = None;
+    for line in contents.lines() {
+        if line.starts_with("some ") {
+            some = Some(averages(line));
+        } else if line.starts_with("full ") {
+            full = Some(averages(line));
+        }
+    }
+    match some {
+        Some(some) => Ok(Pressure { some, full }),
+        None => Err(BlockError(
+            block.to_owned(),
+            format!("invalid pressure in {}", path.to_string_lossy()),
+        )),
+    }
+}
+
+/// The system-wide pressure on `resource`, `cpu`, `memory` or `io`.
+pub fn system(block: &str, resource: &str) -> Result<Pressure> {
+    read(block, &Path::new("/proc/pressure").join(resource))
+}
@@ -0,0 +1,99 @@
+//! The processes that use the most of something, read from `/proc`, for
+//! blocks that can tell what's behind their numbers.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+/// The contents of a file in `/proc`, which may vanish along with its
+/// process at any time.
+fn read(path: &Path) -> Option<String> {
+    let mut contents = String::new();
+    File::open(path).and_then(|mut file| file.read_to_string(&mut contents)).ok()?;
+    Some(contents)
+}
+
+/// The ids of all processes.
+fn pids() -> Vec<u32> {
+    match fs::read_dir("/proc") {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().to_str().and_then(|name| name.parse().ok()))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// The name of process `pid`, as in `/proc/<pid>/comm`.
+pub fn name(pid: u32) -> Option<String> {
+    read(&Path::new("/proc").join(pid.to_string()).join("comm")).map(|name| name.trim().to_owned())
+}
+
+/// The CPU time, in clock ticks, each process spent so far.
+pub fn cpu_ticks() -> HashMap<u32, u64> {
+    let mut ticks = HashMap::new();
+    for pid in pids() {
+        let stat = match read(&Path::new("/proc").join(pid.to_string()).join("stat")) {
+            Some(stat) => stat,
+            None => continue,
+        };
+        // The name comes in parentheses and may contain anything, even
+        // spaces and parentheses, so fields are counted from the last one.
+        // utime and stime are the 14th and 15th field, the 12th and 13th
+        // after it.
+        let rest = match stat.rfind(')') {
+            Some(end) => &stat[end + 1..],
+            None => continue,
+        };
+        let fields: Vec<u64> = rest
+            .split_whitespace()
+            .skip(11)
+            .take(2)
+            .filter_map(|field| field.parse().ok())
+            .collect();
+        if fields.len() == 2 {
+            ticks.insert(pid, fields[0] + fields[1]);
+        }
+    }
+    ticks
+}
+
+/// The total CPU time of all cores, in clock ticks, as in the `cpu` line of
+/// `/proc/stat`.
+pub fn total_ticks() -> Option<u64> {
+    let stat = read(Path::new("/proc/stat"))?;
+    let line = stat.lines().find(|line| line.starts_with("cpu "))?;
+    Some(line.split_whitespace().skip(1).filter_map(|field| field.parse::<u64>().ok()).sum())
+}
+
+/// The process that used the most CPU time between two readings of
+/// `cpu_ticks`, along with its share of `total` ticks of all cores.
+/// Processes that started in between count from their start.
+pub fn top_cpu(before: &HashMap<u32, u64>, after: &HashMap<u32, u64>, total: u64) -> Option<(String, f64)> {
+    let (pid, ticks) = after
+        .iter()
+        .map(|(pid, ticks)| (*pid, ticks.saturating_sub(before.get(pid).cloned().unwrap_or(0))))
+        .max_by_key(|&(_, ticks)| ticks)?;
+    Some((name(pid)?, ticks as f64 / total.max(1) as f64 * 100.))
+}
+
+/// The process with the largest resident set, along with its size in bytes.
+pub fn top_rss() -> Option<(String, u64)> {
+    pids()
+        .into_iter()
+        .filter_map(|pid| {
+            let status = read(&Path::new("/proc").join(pid.to_string()).join("status"))?;
+            // Like `VmRSS:    123456 kB`, kernel threads have none
+            let rss = status
+                .lines()
+                .find(|line| line.starts_with("VmRSS:"))?
+                .split_whitespace()
+                .nth(1)?
+                .parse::<u64>()
+                .ok()?;
+            Some((pid, rss * 1024))
+        })
+        .max_by_key(|&(_, rss)| rss)
+        .and_then(|(pid, rss)| Some((name(pid)?, rss)))
+}
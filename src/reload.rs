@@ -0,0 +1,325 @@
+//! Reloading the configuration while the bar is running.
+//!
+//! A reload is requested by sending `SIGHUP` to the process, or by saving the
+//! configuration file. Blocks whose configuration did not change are carried
+//! over as they are, keeping their state and pending updates, while all other
+//! blocks are created anew.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
+
+use chan::Sender;
+use inotify::WatchMask;
+use toml::value::Value;
+
+use block::{Block, CommonBlockConfig};
+use blocks::{check_block, create_block};
+use config::Config;
+use dbus_manager;
+use de::deserialize_duration;
+use errors::*;
+use i3_events;
+use paging;
+use routes;
+use scheduler::{self, Task};
+use state::BlockState;
+use supervisor::{self, Placeholder, Supervised};
+use udev;
+use util::{self, secs};
+
+/// Everything a block is created from. A block is only rebuilt on reload if
+/// its spec changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockSpec {
+    /// The block type, e.g. `cpu`
+    pub name: String,
+    pub common: CommonBlockConfig,
+    block_config: Value,
+    config: Config,
+    state: BlockState,
+}
+
+impl BlockSpec {
+    /// Whether the block is referred to by `name`, either its id or its type.
+    pub fn matches(&self, name: &str) -> bool {
+        self.name == name || self.common.id.as_ref().map_or(false, |id| id == name)
+    }
+
+    /// The block's id, or else its type and position among the blocks of
+    /// that type, e.g. `cpu.0`.
+    pub fn key(&self) -> &str {
+        self.state.key()
+    }
+}
+
+/// What the configuration file is watched as, like the id of a block.
+const CONFIG_WATCH_ID: &str = "config";
+
+/// Send a `Task` through `tx` whenever the configuration file at `path` is
+/// saved. Editors may write it several times in a row, so
+/// it's up to the receiver to give them a moment before reading it. `SIGHUP`
+/// is taken care of in `signals`.
+pub fn watch_config(path: &Path, tx: &Sender<Task>) -> Result<()> {
+    let path: PathBuf = path.canonicalize()
+        .internal_error("reload", "failed to resolve configuration path")?;
+    scheduler::watch_dir_entry(&path, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO, CONFIG_WATCH_ID, tx)
+}
+
+/// Whether blocks of type `name` have an `interval` option. There's no
+/// list of options to look at, so this tries whether `block_config` only
+/// breaks with an interval.
+fn accepts_interval(name: &str, block_config: &Value) -> bool {
+    let (mut with, mut without) = (block_config.clone(), block_config.clone());
+    if let Value::Table(ref mut table) = with {
+        table.insert("interval".to_owned(), Value::Integer(1));
+    }
+    if let Value::Table(ref mut table) = without {
+        table.remove("interval");
+    }
+    check_block(name, with).is_ok() || check_block(name, without).is_err()
+}
+
+/// Apply the global `interval` and `min_interval` to the block of type `name`.
+///
+/// Blocks with an `interval` option get the global one if they don't set
+/// their own. Blocks without one may still set it, which is then taken out of
+/// `block_config` and returned, for their supervisor to update them at that
+/// interval, on top of whatever else makes them update.
+pub fn resolve_interval(name: &str, block_config: &mut Value, config: &Config) -> Result<Option<Duration>> {
+    let accepts = accepts_interval(name, block_config);
+    let own = match *block_config {
+        Value::Table(ref table) => table.get("interval").cloned(),
+        _ => None,
+    };
+
+    let interval = match own {
+        Some(value) => Some(deserialize_duration(value).configuration_error("invalid `interval`")?),
+        None => config.interval,
+    };
+    let interval = match interval {
+        Some(interval) => interval,
+        None => return Ok(None),
+    };
+    if interval < config.min_interval {
+        let message = format!(
+            "interval of {}s is below `min_interval` ({}s)",
+            secs(interval),
+            secs(config.min_interval)
+        );
+        return Err(ConfigurationError(message.clone(), (message, name.to_owned())));
+    }
+
+    match *block_config {
+        Value::Table(ref mut table) if accepts => {
+            table.entry("interval".to_owned()).or_insert_with(|| if interval.subsec_nanos() == 0 {
+                Value::Integer(interval.as_secs() as i64)
+            } else {
+                Value::Float(secs(interval))
+            });
+            Ok(None)
+        }
+        Value::Table(ref mut table) => {
+            // Only a block's own interval applies here, not the global one.
+            Ok(table.remove("interval").map(|_| interval))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Compute the specs of all blocks in `config`, in bar order.
+fn block_specs(config: &Config) -> Result<Vec<BlockSpec>> {
+    // Blocks don't get to see the block and bar lists, so that adding a block
+    // doesn't count as a change to all others.
+    let mut base = config.clone();
+    base.blocks = Vec::new();
+    base.bars = Vec::new();
+    // Only the theme picked matters
+    base.theme_switch = None;
+
+    let mut alternator = false;
+    let mut specs = Vec::new();
+    // How many blocks of each type came so far, for their state keys
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for &(ref name, ref block_config) in &config.blocks {
+        let mut block_config = block_config.clone();
+        let mut common = CommonBlockConfig::extract(&mut block_config)?;
+        common.interval = resolve_interval(name, &mut block_config, config)?;
+
+        let mut block_base = base.clone();
+        if let Some(ref overrides) = common.theme_overrides {
+            block_base.theme = block_base.theme.with_overrides(overrides)?;
+        }
+        if let Some(ref overrides) = common.icons_overrides {
+            block_base
+                .icons
+                .extend(overrides.iter().map(|(key, icon)| (key.clone(), icon.clone())));
+        }
+        let config = if alternator {
+            util::alternating_tint_config(&block_base)?
+        } else {
+            block_base
+        };
+
+        let count = counts.entry(name.as_str()).or_insert(0);
+        let state = BlockState::new(&common.id.clone().unwrap_or_else(|| format!("{}.{}", name, count)));
+        *count += 1;
+
+        specs.push(BlockSpec {
+            name: name.clone(),
+            common,
+            block_config,
+            config,
+            state,
+        });
+        alternator = !alternator;
+    }
+
+    Ok(specs)
+}
+
+fn supervised_block(spec: &BlockSpec, tx_update_requests: &Sender<Task>) -> Result<Box<Block>> {
+    let block = create_block(
+        &spec.name,
+        spec.block_config.clone(),
+        spec.config.clone(),
+        tx_update_requests.clone(),
+        spec.state.clone(),
+    )?;
+    Ok(Box::new(Supervised::new(block, &spec.name, spec.config.clone(), &spec.common)))
+}
+
+/// Set up placeholders for the blocks of `config`, so that the bar can be
+/// shown right away. Returns their ids, see `build_placeholders` for creating
+/// the actual blocks. The arguments are as for `rebuild_blocks`,
+/// which treats placeholders just like blocks.
+pub fn placeholder_blocks(
+    config: &Config,
+    order: &mut Vec<String>,
+    specs: &mut Vec<BlockSpec>,
+    block_map: &mut HashMap<String, Box<Block>>,
+) -> Result<Vec<String>> {
+    *specs = block_specs(config)?;
+    order.clear();
+    block_map.clear();
+    for spec in specs.iter() {
+        let placeholder = Placeholder::new(spec.config.clone());
+        order.push(String::from(placeholder.id()));
+        block_map.insert(String::from(placeholder.id()), Box::new(placeholder));
+    }
+    paging::set_count(specs.iter().filter_map(|spec| spec.common.page).max().unwrap_or(1));
+
+    Ok(order.clone())
+}
+
+/// Queue the placeholders `ids` to be replaced by their blocks, see
+/// `create_placeholder`. Blocks aren't `Send`, they share the D-Bus
+/// connections of the main thread, see `dbus_manager::connection`, so they're
+/// created there one at a time, in between handling everything else. That
+/// way the bar shows up right away and stays responsive, and a block that
+/// is slow to create only holds it up for as long as it takes itself.
+pub fn build_placeholders(ids: &[String], tx_build: &Sender<String>) {
+    for id in ids {
+        tx_build.send(id.clone());
+    }
+}
+
+/// Put the block for the placeholder `id` in its place, or the error it
+/// failed to be created with. Returns the id of the new block, which still
+/// needs to be scheduled for its first update. Placeholders removed by a
+/// reload in the meantime are skipped.
+pub fn create_placeholder(
+    id: &str,
+    order: &mut Vec<String>,
+    specs: &[BlockSpec],
+    block_map: &mut HashMap<String, Box<Block>>,
+    tx_update_requests: &Sender<Task>,
+) -> Option<String> {
+    let position = match order.iter().position(|other| other == id) {
+        Some(position) => position,
+        None => return None,
+    };
+
+    let spec = &specs[position];
+    let created = panic::catch_unwind(AssertUnwindSafe(|| {
+        create_block(
+            &spec.name,
+            spec.block_config.clone(),
+            spec.config.clone(),
+            tx_update_requests.clone(),
+            spec.state.clone(),
+        )
+    }));
+    let created = match created {
+        Ok(created) => created,
+        Err(payload) => Err(InternalError(
+            "reload".to_owned(),
+            format!("crashed while being created: {}", supervisor::panic_message(&*payload)),
+            None,
+        )),
+    };
+    debug!("created a {} block", spec.name);
+    let block: Box<Block> = match created {
+        Ok(block) => Box::new(Supervised::new(block, &spec.name, spec.config.clone(), &spec.common)),
+        Err(error) => Box::new(Supervised::failed(&error, &spec.name, spec.config.clone(), &spec.common)),
+    };
+    let block_id = String::from(block.id());
+    block_map.remove(id);
+    block_map.insert(block_id.clone(), block);
+    order[position] = block_id.clone();
+
+    Some(block_id)
+}
+
+/// Bring the blocks in line with `config`.
+///
+/// `order` and `specs` hold the ids and specs of the current blocks in bar
+/// order, `block_map` the blocks themselves. Blocks with an unchanged spec are
+/// kept, the others are created from `config`. If any block fails to build,
+/// nothing is changed. Returns the ids of the newly created blocks, which
+/// still need to be scheduled for their first update.
+pub fn rebuild_blocks(
+    config: &Config,
+    order: &mut Vec<String>,
+    specs: &mut Vec<BlockSpec>,
+    block_map: &mut HashMap<String, Box<Block>>,
+    tx_update_requests: &Sender<Task>,
+) -> Result<Vec<String>> {
+    let new_specs = block_specs(config)?;
+
+    // Old blocks that are still up for grabs, a block can only be reused once.
+    let mut unused: Vec<(String, BlockSpec)> = order.iter().cloned().zip(specs.iter().cloned()).collect();
+    let mut new_order = Vec::new();
+    let mut created: Vec<Box<Block>> = Vec::new();
+
+    for spec in &new_specs {
+        if let Some(position) = unused.iter().position(|&(_, ref old)| old == spec) {
+            new_order.push(unused.remove(position).0);
+        } else {
+            let block = supervised_block(spec, tx_update_requests)?;
+            new_order.push(String::from(block.id()));
+            created.push(block);
+        }
+    }
+
+    // Everything was built, now it's safe to swap. Threads spawned by removed
+    // blocks may still send update requests, those are ignored.
+    for (id, _) in unused {
+        block_map.remove(&id);
+        dbus_manager::unsubscribe(&id);
+        scheduler::unwatch(&id);
+        udev::unsubscribe(&id);
+        routes::unsubscribe(&id);
+        i3_events::unsubscribe(&id);
+    }
+    let created_ids = created.iter().map(|block| String::from(block.id())).collect();
+    for block in created {
+        block_map.insert(String::from(block.id()), block);
+    }
+    *order = new_order;
+    *specs = new_specs;
+    paging::set_count(specs.iter().filter_map(|spec| spec.common.page).max().unwrap_or(1));
+
+    Ok(created_ids)
+}
@@ -0,0 +1,44 @@
+//! Noticing when the system resumes from suspend.
+//!
+//! logind announces suspend and resume with its `PrepareForSleep` signal. On
+//! resume, the bar lets the blocks know through `Block::resumed` and updates
+//! all of them, instead of showing what was current before the suspend until
+//! their intervals run out.
+
+use std::thread;
+
+use chan::Sender;
+use dbus::{BusType, Connection, MessageType};
+
+use errors::*;
+
+const RULE: &str = "type='signal',interface='org.freedesktop.login1.Manager',member='PrepareForSleep'";
+
+fn listen(tx: &Sender<()>) -> Result<()> {
+    let c = Connection::get_private(BusType::System).internal_error("resume", "failed to connect to the system bus")?;
+    c.add_match(RULE)
+        .internal_error("resume", "failed to add match rule")?;
+
+    loop {
+        for msg in c.incoming(60 * 1000) {
+            if msg.msg_type() != MessageType::Signal ||
+                msg.member().map_or(true, |member| &*member != "PrepareForSleep")
+            {
+                continue;
+            }
+            // The argument is true before the suspend, false after it.
+            if msg.get1::<bool>() == Some(false) {
+                debug!("system resumed");
+                tx.send(());
+            }
+        }
+    }
+}
+
+/// Send a message through `tx` whenever the system resumes. Failing to listen
+/// is not fatal, blocks just catch up at their own pace then.
+pub fn spawn_listener(tx: Sender<()>) {
+    thread::spawn(move || if let Err(error) = listen(&tx) {
+        warn!("resume from suspend can't be detected: {:?}", error);
+    });
+}
@@ -0,0 +1,132 @@
+//! Routing changes, as announced by the kernel.
+//!
+//! Blocks following the route traffic takes, like `net` with `device =
+//! "auto"`, subscribe here and are updated whenever a route is added or
+//! removed, like `ip monitor route` would tell. All subscriptions share a
+//! single netlink socket, read by the event thread of the `scheduler`.
+
+use std::mem;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use chan::Sender;
+use nix::libc;
+use nix::unistd;
+
+use errors::*;
+use scheduler::{self, Task};
+
+/// The multicast groups of IPv4 and IPv6 route changes, from
+/// `linux/rtnetlink.h`
+const RTMGRP_IPV4_ROUTE: u32 = 0x40;
+const RTMGRP_IPV6_ROUTE: u32 = 0x400;
+
+struct Subscription {
+    id: String,
+    tx_update_request: Sender<Task>,
+}
+
+lazy_static! {
+    /// `None` until the first subscription starts listening
+    static ref SUBSCRIPTIONS: Mutex<Option<Vec<Subscription>>> = Mutex::new(None);
+}
+
+/// Counts the changes, see `changes`
+static CHANGES: AtomicUsize = AtomicUsize::new(0);
+
+fn open_socket() -> Result<RawFd> {
+    unsafe {
+        let fd = libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+            libc::NETLINK_ROUTE,
+        );
+        if fd < 0 {
+            return Err(InternalError(
+                "routes".to_owned(),
+                "failed to open netlink socket".to_owned(),
+                None,
+            ));
+        }
+
+        let mut address: libc::sockaddr_nl = mem::zeroed();
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_groups = RTMGRP_IPV4_ROUTE | RTMGRP_IPV6_ROUTE;
+        let bound = libc::bind(
+            fd,
+            &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        );
+        if bound < 0 {
+            libc::close(fd);
+            return Err(InternalError(
+                "routes".to_owned(),
+                "failed to listen for route changes".to_owned(),
+                None,
+            ));
+        }
+        Ok(fd)
+    }
+}
+
+/// Read a change from the socket and request updates of the subscribed
+/// blocks. What changed doesn't matter, they look up the routes themselves.
+fn dispatch_change(fd: RawFd, buffer: &mut [u8]) -> Result<Option<Instant>> {
+    // Fails if changes came in faster than we read them, which is a change
+    // all the same
+    let _ = unistd::read(fd, buffer);
+    CHANGES.fetch_add(1, Ordering::SeqCst);
+
+    let now = Instant::now();
+    if let Ok(subscriptions) = SUBSCRIPTIONS.lock() {
+        if let Some(ref subscriptions) = *subscriptions {
+            for subscription in subscriptions.iter() {
+                subscription.tx_update_request.send(Task {
+                    id: subscription.id.clone(),
+                    update_time: now,
+                });
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// How many times the routes changed since the first subscription. Blocks
+/// can tell whether an update is due to a change by comparing it to what it
+/// was on their last one.
+pub fn changes() -> usize {
+    CHANGES.load(Ordering::SeqCst)
+}
+
+/// Request an update of the block `id` whenever the routes change.
+pub fn subscribe(id: &str, tx_update_request: &Sender<Task>) -> Result<()> {
+    let mut subscriptions = SUBSCRIPTIONS
+        .lock()
+        .internal_error("routes", "failed to lock subscriptions")?;
+    if subscriptions.is_none() {
+        let fd = open_socket()?;
+        let mut buffer = [0; 8192];
+        scheduler::add_source(fd, Box::new(move || dispatch_change(fd, &mut buffer)))?;
+        *subscriptions = Some(Vec::new());
+    }
+
+    subscriptions
+        .as_mut()
+        .internal_error("routes", "no subscriptions")?
+        .push(Subscription {
+            id: id.to_owned(),
+            tx_update_request: tx_update_request.clone(),
+        });
+    Ok(())
+}
+
+/// Stop the subscriptions of the block `id`, once it's gone.
+pub fn unsubscribe(id: &str) {
+    if let Ok(mut subscriptions) = SUBSCRIPTIONS.lock() {
+        if let Some(ref mut subscriptions) = *subscriptions {
+            subscriptions.retain(|subscription| subscription.id != id);
+        }
+    }
+}
@@ -1,11 +1,138 @@
+//! Scheduling of block updates.
+//!
+//! Besides the timed updates of the `UpdateScheduler`, this owns the event
+//! sources blocks can subscribe to instead of spawning a thread of their own:
+//! file and command watches here, device events in `udev`, route changes in
+//! `routes` and window manager events in `i3_events`. They're all waited for
+//! by a single event thread, see `add_source`. D-Bus signals have a thread of
+//! their own in `dbus_manager`, as libdbus dispatches them itself. Blocks
+//! removed by a reload have their watches and subscriptions removed as well.
+
 use block::Block;
 use errors::*;
 use std::collections::{BinaryHeap, HashMap};
+use std::ffi::OsString;
 use std::fmt;
+use std::io::{ErrorKind, Read};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
 use std::thread;
 use std::cmp;
 use std::time::{Duration, Instant};
 
+use chan::Sender;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveTime, Offset, TimeZone, Weekday};
+use inotify::{Inotify, WatchDescriptor, WatchMask};
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+use nix::poll::{poll, EventFlags, PollFd};
+use rand;
+
+use dbus_manager::{WakeSocket, Waker};
+use supervisor;
+
+/// Updates due within this long of each other are done together, so that
+/// their blocks are redrawn at once.
+const BATCH_MILLIS: u64 = 20;
+
+/// Wall clock boundaries updates can be aligned to.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateAlign {
+    Second,
+    Minute,
+    Hour,
+    Day,
+}
+
+impl UpdateAlign {
+    fn millis(&self) -> i64 {
+        match *self {
+            UpdateAlign::Second => 1000,
+            UpdateAlign::Minute => 60 * 1000,
+            UpdateAlign::Hour => 60 * 60 * 1000,
+            UpdateAlign::Day => 24 * 60 * 60 * 1000,
+        }
+    }
+}
+
+/// Stretch or shrink `interval` so that it ends on the `align` boundary of
+/// the local time nearest to its end, but at least on the next one. A block
+/// updating every minute aligned to minutes updates right at `:00`, and so on.
+pub fn aligned(interval: Duration, align: UpdateAlign) -> Duration {
+    let now = Local::now();
+    let now_millis = now.timestamp() * 1000 + i64::from(now.timestamp_subsec_millis())
+        + i64::from(now.offset().fix().local_minus_utc()) * 1000;
+    let interval_millis = (interval.as_secs() * 1000) as i64 + i64::from(interval.subsec_nanos() / 1_000_000);
+    let unit = align.millis();
+
+    let end = now_millis + interval_millis;
+    let mut boundary = (end + unit / 2) / unit * unit;
+    if boundary <= now_millis {
+        boundary += unit;
+    }
+    Duration::from_millis((boundary - now_millis) as u64)
+}
+
+/// `interval`, plus a random delay of up to `jitter`.
+pub fn jittered(interval: Duration, jitter: Duration) -> Duration {
+    let jitter_millis = jitter.as_secs() as f64 * 1000. + f64::from(jitter.subsec_nanos()) / 1_000_000.;
+    interval + Duration::from_millis((rand::random::<f64>() * jitter_millis) as u64)
+}
+
+/// Hours of the day a block is active in, like `"09:00-18:00"`. If `to` is
+/// before `from`, like in `"22:00-06:00"`, they span midnight.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ActiveHours {
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+}
+
+impl ActiveHours {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.from <= self.to {
+            self.from <= time && time < self.to
+        } else {
+            !(self.to <= time && time < self.from)
+        }
+    }
+}
+
+/// Whether a block with `hours` and `days` is active at `now`. Days are
+/// those of the date, even if hours span midnight.
+pub fn is_active(hours: Option<ActiveHours>, days: &Option<Vec<Weekday>>, now: DateTime<Local>) -> bool {
+    hours.map_or(true, |hours| hours.contains(now.time())) && days.as_ref().map_or(true, |days| days.contains(&now.weekday()))
+}
+
+/// How long from `now` until a block with `hours` and `days` might turn
+/// active or inactive. It may well stay as it is then, checking once more is
+/// cheap.
+pub fn until_active_change(hours: Option<ActiveHours>, days: &Option<Vec<Weekday>>, now: DateTime<Local>) -> Duration {
+    let today = now.date().naive_local();
+    let tomorrow = today + ChronoDuration::days(1);
+    let mut candidates = Vec::new();
+    if let Some(hours) = hours {
+        for date in &[today, tomorrow] {
+            candidates.push(date.and_time(hours.from));
+            candidates.push(date.and_time(hours.to));
+        }
+    }
+    if days.is_some() {
+        candidates.push(tomorrow.and_hms(0, 0, 0));
+    }
+
+    candidates
+        .into_iter()
+        // Times skipped by daylight saving time don't exist
+        .filter_map(|candidate| Local.from_local_datetime(&candidate).earliest())
+        .filter(|candidate| *candidate > now)
+        .min()
+        .and_then(|next| next.signed_duration_since(now).to_std().ok())
+        .unwrap_or_else(|| Duration::from_secs(60))
+}
+
 #[derive(Debug, Clone)]
 pub struct Task {
     pub id: String,
@@ -38,18 +165,33 @@ impl cmp::Ord for Task {
     }
 }
 
+lazy_static! {
+    /// When the last `UpdateScheduler` was created, see `last_rebuild`
+    static ref REBUILT: Mutex<Option<Instant>> = Mutex::new(None);
+}
+
+/// When the schedule was last built anew, with all blocks due at once. That
+/// happens on start, and after the bar was hidden or the system suspended,
+/// when blocks may not have updated for a good while.
+pub fn last_rebuild() -> Option<Instant> {
+    REBUILT.lock().ok().and_then(|rebuilt| *rebuilt)
+}
+
 pub struct UpdateScheduler {
     schedule: BinaryHeap<Task>,
 }
 
 impl UpdateScheduler {
-    pub fn new(blocks: &[Box<Block>]) -> UpdateScheduler {
+    pub fn new(ids: &[String]) -> UpdateScheduler {
         let mut schedule = BinaryHeap::new();
 
         let now = Instant::now();
-        for block in blocks.iter() {
+        if let Ok(mut rebuilt) = REBUILT.lock() {
+            *rebuilt = Some(now);
+        }
+        for id in ids.iter() {
             schedule.push(Task {
-                id: String::from(block.id()),
+                id: id.clone(),
                 update_time: now,
             });
         }
@@ -57,6 +199,19 @@ impl UpdateScheduler {
         UpdateScheduler { schedule }
     }
 
+    pub fn schedule(&mut self, task: Task) {
+        self.schedule.push(task);
+    }
+
+    /// Drop all pending updates of blocks not in `ids`, e.g. after they were
+    /// removed by a configuration reload.
+    pub fn retain(&mut self, ids: &[String]) {
+        self.schedule = self.schedule
+            .drain()
+            .filter(|task| ids.contains(&task.id))
+            .collect();
+    }
+
     pub fn time_to_next_update(&self) -> Option<Duration> {
         if let Some(peeked) = self.schedule.peek() {
             let next_update = peeked.update_time;
@@ -72,18 +227,19 @@ impl UpdateScheduler {
         }
     }
 
-    pub fn do_scheduled_updates(&mut self, block_map: &mut HashMap<String, &mut Block>) -> Result<()> {
+    pub fn do_scheduled_updates(&mut self, block_map: &mut HashMap<String, Box<Block>>) -> Result<()> {
         let t = self.schedule
             .pop()
             .internal_error("scheduler", "schedule is empty")?;
         let mut tasks_next = vec![t.clone()];
 
+        // Everything due at about the same time is done at once
+        let batch_end = t.update_time + Duration::from_millis(BATCH_MILLIS);
         while !self.schedule.is_empty() &&
-            t.update_time ==
-                self.schedule
-                    .peek()
-                    .internal_error("scheduler", "schedule is empty")?
-                    .update_time
+            self.schedule
+                .peek()
+                .internal_error("scheduler", "schedule is empty")?
+                .update_time <= batch_end
         {
             tasks_next.push(self.schedule
                 .pop()
@@ -91,18 +247,21 @@ impl UpdateScheduler {
         }
 
         let now = Instant::now();
-        if t.update_time > now {
-            thread::sleep(t.update_time - now);
+        let last = tasks_next.last().map_or(t.update_time, |task| task.update_time);
+        if last > now {
+            thread::sleep(last - now);
         }
 
         let now = Instant::now();
 
         for task in tasks_next {
-            if let Some(dur) = block_map
-                .get_mut(&task.id)
-                .internal_error("scheduler", "could not get required block")?
-                .update()?
-            {
+            // Placeholders are replaced by their blocks in the meantime, see
+            // `reload::create_placeholder`.
+            let block = match block_map.get_mut(&task.id) {
+                Some(block) => block,
+                None => continue,
+            };
+            if let Some(dur) = block.update()? {
                 self.schedule.push(Task {
                     id: task.id,
                     update_time: now + dur,
@@ -113,3 +272,444 @@ impl UpdateScheduler {
         Ok(())
     }
 }
+
+/// Handles what came in on an event source, see `add_source`. Returns when
+/// it wants to be called again even if nothing comes in, or an error once the
+/// source is done for.
+pub type Dispatch = Box<FnMut() -> Result<Option<Instant>> + Send>;
+
+struct Source {
+    fd: RawFd,
+    dispatch: Dispatch,
+    due: Option<Instant>,
+}
+
+struct Events {
+    /// Sources for the event thread to pick up
+    added: Vec<Source>,
+    /// Wakes the event thread up to pick them up
+    waker: Waker,
+}
+
+lazy_static! {
+    /// `None` until the first source is added
+    static ref EVENTS: Mutex<Option<Events>> = Mutex::new(None);
+}
+
+/// Milliseconds until `due`, rounded up, as a `poll` timeout.
+fn poll_timeout(due: Option<Instant>, now: Instant) -> i32 {
+    match due {
+        Some(due) if due > now => {
+            let wait = due - now;
+            (wait.as_secs() * 1000 + u64::from(wait.subsec_nanos() / 1_000_000) + 1).min(i32::max_value() as u64) as i32
+        }
+        Some(_) => 0,
+        None => -1,
+    }
+}
+
+/// Wait for all event sources at once, and dispatch whatever comes in.
+fn run_sources(wake: WakeSocket) {
+    let mut sources: Vec<Source> = Vec::new();
+    loop {
+        if let Ok(mut events) = EVENTS.lock() {
+            if let Some(ref mut events) = *events {
+                sources.append(&mut events.added);
+            }
+        }
+
+        let mut fds: Vec<PollFd> = Some(wake.as_raw_fd())
+            .into_iter()
+            .chain(sources.iter().map(|source| source.fd))
+            .map(|fd| PollFd::new(fd, EventFlags::POLLIN))
+            .collect();
+        let due = sources.iter().filter_map(|source| source.due).min();
+        if poll(&mut fds, poll_timeout(due, Instant::now())).is_err() {
+            continue;
+        }
+        let ready: Vec<bool> = fds.iter()
+            .map(|fd| fd.revents().map_or(false, |events| !events.is_empty()))
+            .collect();
+        if ready[0] {
+            wake.drain();
+        }
+
+        let now = Instant::now();
+        let mut kept = Vec::with_capacity(sources.len());
+        for (mut source, ready) in sources.drain(..).zip(ready.into_iter().skip(1)) {
+            if ready || source.due.map_or(false, |due| due <= now) {
+                let result = {
+                    let dispatch = &mut source.dispatch;
+                    panic::catch_unwind(AssertUnwindSafe(dispatch))
+                };
+                match result {
+                    Ok(Ok(due)) => source.due = due,
+                    Ok(Err(error)) => {
+                        warn!("stopped listening for events: {:?}", error);
+                        continue;
+                    }
+                    // The next event may well be fine again
+                    Err(payload) => error!("crashed handling an event: {}", supervisor::panic_message(&*payload)),
+                }
+            }
+            kept.push(source);
+        }
+        sources = kept;
+    }
+}
+
+/// Call `dispatch` whenever `fd` can be read, and when the time it returned
+/// last comes. All event sources, e.g. file watches, device events in `udev`
+/// and window manager events in `i3_events`, are waited for by a single
+/// thread, which `dispatch` is called from.
+pub fn add_source(fd: RawFd, dispatch: Dispatch) -> Result<()> {
+    let mut events = EVENTS
+        .lock()
+        .internal_error("scheduler", "failed to lock event sources")?;
+    if events.is_none() {
+        let wake = WakeSocket::new()?;
+        let waker = wake.waker();
+        thread::spawn(move || run_sources(wake));
+        *events = Some(Events {
+            added: Vec::new(),
+            waker,
+        });
+    }
+
+    let events = events.as_mut().internal_error("scheduler", "no event sources")?;
+    events.added.push(Source { fd, dispatch, due: None });
+    events.waker.wake();
+    Ok(())
+}
+
+/// What a block watches.
+enum Target {
+    /// Events on a file, or directory
+    File {
+        descriptor: WatchDescriptor,
+        /// What was watched, to narrow the watch down again once others on
+        /// the same inode are removed
+        path: PathBuf,
+        /// Only events on this entry of the watched directory count, if set
+        name: Option<OsString>,
+        /// The events this watch is for. Watches of the same inode share a
+        /// descriptor, which is asked for the events of all of them.
+        mask: WatchMask,
+    },
+    /// Output of a command, see `watch_command`
+    Command(String),
+}
+
+struct Watch {
+    target: Target,
+    /// At most one update is requested within this long, see
+    /// `watch_file_throttled`
+    throttle: Duration,
+    last: Option<Instant>,
+    /// Whether events came in since the last update was requested
+    pending: bool,
+    id: String,
+    tx_update_request: Sender<Task>,
+}
+
+impl Watch {
+    /// When the update for pending events is due, if there are any. The
+    /// first one is due `now`.
+    fn due(&self, now: Instant) -> Option<Instant> {
+        if !self.pending {
+            return None;
+        }
+        Some(self.last.map_or(now, |last| last + self.throttle))
+    }
+}
+
+#[derive(Default)]
+struct Watcher {
+    /// Started with the first file watch
+    notify: Option<Inotify>,
+    /// The commands watched, which are shared by all blocks watching them.
+    /// Their output is read by the event thread.
+    commands: HashMap<String, Child>,
+    watches: Vec<Watch>,
+}
+
+impl Watcher {
+    /// Request the updates that are due. Returns when the next one is.
+    fn flush(&mut self) -> Option<Instant> {
+        let now = Instant::now();
+        let mut ids: Vec<&str> = Vec::new();
+        for watch in &mut self.watches {
+            match watch.due(now) {
+                Some(due) if due <= now => {}
+                _ => continue,
+            }
+            watch.pending = false;
+            watch.last = Some(now);
+            // Several events are often read at once, one update is enough.
+            if !ids.contains(&watch.id.as_str()) {
+                ids.push(&watch.id);
+                watch.tx_update_request.send(Task {
+                    id: watch.id.clone(),
+                    update_time: now,
+                });
+            }
+        }
+        self.watches.iter().filter_map(|watch| watch.due(now)).min()
+    }
+}
+
+lazy_static! {
+    static ref WATCHER: Mutex<Watcher> = Mutex::new(Watcher::default());
+}
+
+/// Read the events of the shared inotify instance and request updates of the
+/// blocks watching the affected files.
+fn dispatch_file_events(buffer: &mut [u8]) -> Result<Option<Instant>> {
+    let mut watcher = WATCHER
+        .lock()
+        .internal_error("scheduler", "failed to lock file watcher")?;
+    {
+        let Watcher {
+            ref mut notify,
+            ref mut watches,
+            ..
+        } = *watcher;
+        // Also called when only a throttled update is due, there's nothing to
+        // read then
+        if let Some(Ok(events)) = notify.as_mut().map(|notify| notify.read_events(buffer)) {
+            for event in events {
+                for watch in watches.iter_mut() {
+                    if let Target::File {
+                        ref descriptor,
+                        ref name,
+                        ref mask,
+                        ..
+                    } = watch.target
+                    {
+                        if *descriptor == event.wd && event.mask.bits() & mask.bits() != 0
+                            && name.as_ref().map_or(true, |name| event.name == Some(name.as_os_str()))
+                        {
+                            watch.pending = true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(watcher.flush())
+}
+
+/// Read what `command` printed, and request updates of the blocks watching
+/// it.
+fn dispatch_command_output(command: &str, output: &mut ChildStdout, buffer: &mut [u8]) -> Result<Option<Instant>> {
+    // What it printed doesn't matter, only that it printed something. The
+    // output isn't blocking, there's nothing to read when only a throttled
+    // update is due.
+    let read = match output.read(buffer) {
+        Ok(read) => Some(read),
+        Err(ref error) if error.kind() == ErrorKind::WouldBlock => None,
+        Err(error) => {
+            return Err(InternalError(
+                "scheduler".to_owned(),
+                format!("failed to read the output of '{}'", command),
+                Some((format!("{}", error), format!("{:?}", error))),
+            ))
+        }
+    };
+    let mut watcher = WATCHER
+        .lock()
+        .internal_error("scheduler", "failed to lock file watcher")?;
+    if read == Some(0) {
+        // Only expected once the last block watching it is gone
+        if watcher.commands.remove(command).is_some() {
+            warn!("'{}' ended, its changes aren't noticed anymore", command);
+        }
+        return Err(InternalError(
+            "scheduler".to_owned(),
+            format!("'{}' ended", command),
+            None,
+        ));
+    }
+    for watch in &mut watcher.watches {
+        match watch.target {
+            Target::Command(ref other) if other == command && read.is_some() => watch.pending = true,
+            _ => {}
+        }
+    }
+    Ok(watcher.flush())
+}
+
+/// Request an update of the block `id` whenever one of the `mask` events
+/// occurs on `path`. All watches share a single inotify instance.
+pub fn watch_file(path: &Path, mask: WatchMask, id: &str, tx_update_request: &Sender<Task>) -> Result<()> {
+    add_watch(path, None, mask, Duration::from_secs(0), id, tx_update_request)
+}
+
+/// Like `watch_file`, for files that change many times in a row, e.g. while
+/// sliding the brightness. At most one update is requested within `throttle`,
+/// events in between are caught up on by another one once it's over.
+pub fn watch_file_throttled(
+    path: &Path,
+    mask: WatchMask,
+    throttle: Duration,
+    id: &str,
+    tx_update_request: &Sender<Task>,
+) -> Result<()> {
+    add_watch(path, None, mask, throttle, id, tx_update_request)
+}
+
+/// Like `watch_file`, for a file that is replaced rather than written to, as
+/// most editors do when saving, which would orphan a watch on the file
+/// itself. Its directory is watched instead, for events on its name.
+pub fn watch_dir_entry(path: &Path, mask: WatchMask, id: &str, tx_update_request: &Sender<Task>) -> Result<()> {
+    let name = path.file_name()
+        .internal_error("scheduler", &format!("{} has no file name", path.to_string_lossy()))?;
+    let dir = path.parent()
+        .internal_error("scheduler", &format!("{} has no parent directory", path.to_string_lossy()))?;
+    add_watch(dir, Some(name.to_owned()), mask, Duration::from_secs(0), id, tx_update_request)
+}
+
+/// Request an update of the block `id` whenever `program` prints something,
+/// for monitors like `alsactl monitor` that print a line for each change. It's
+/// run as long as any block watches it, throttled like `watch_file_throttled`.
+pub fn watch_command(
+    program: &str,
+    args: &[&str],
+    throttle: Duration,
+    id: &str,
+    tx_update_request: &Sender<Task>,
+) -> Result<()> {
+    let mut watcher = WATCHER
+        .lock()
+        .internal_error("scheduler", "failed to lock file watcher")?;
+
+    let command = format!("{} {}", program, args.join(" "));
+    if !watcher.commands.contains_key(&command) {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdout(Stdio::piped())
+            .spawn()
+            .internal_error("scheduler", &format!("failed to run '{}'", command))?;
+        let mut output = child
+            .stdout
+            .take()
+            .internal_error("scheduler", &format!("no output of '{}'", command))?;
+        fcntl(output.as_raw_fd(), FcntlArg::F_SETFL(OFlag::O_NONBLOCK))
+            .internal_error("scheduler", &format!("failed to set up the output of '{}'", command))?;
+        let (name, mut buffer) = (command.clone(), [0; 1024]);
+        add_source(
+            output.as_raw_fd(),
+            Box::new(move || dispatch_command_output(&name, &mut output, &mut buffer)),
+        )?;
+        watcher.commands.insert(command.clone(), child);
+    }
+
+    watcher.watches.push(Watch {
+        target: Target::Command(command),
+        throttle,
+        last: None,
+        pending: false,
+        id: id.to_owned(),
+        tx_update_request: tx_update_request.clone(),
+    });
+    Ok(())
+}
+
+/// Stop all watches of the block `id`, once it's gone.
+pub fn unwatch(id: &str) {
+    let mut watcher = match WATCHER.lock() {
+        Ok(watcher) => watcher,
+        Err(_) => return,
+    };
+    let (removed, kept): (Vec<Watch>, Vec<Watch>) = watcher.watches.drain(..).partition(|watch| watch.id == id);
+    watcher.watches = kept;
+    for watch in removed {
+        match watch.target {
+            Target::File {
+                descriptor, path, ..
+            } => {
+                // Watching an inode twice gives the same descriptor both
+                // times. It's removed with its last watch, until then it's
+                // narrowed down to the events the others are for.
+                let mask = watcher
+                    .watches
+                    .iter()
+                    .filter_map(|other| match other.target {
+                        Target::File {
+                            descriptor: ref other,
+                            mask,
+                            ..
+                        } if *other == descriptor => Some(mask),
+                        _ => None,
+                    })
+                    .fold(WatchMask::empty(), |all, mask| all | mask);
+                let result = match watcher.notify {
+                    Some(ref mut notify) if mask.is_empty() => notify.rm_watch(descriptor),
+                    Some(ref mut notify) => notify.add_watch(&path, mask).map(|_| ()),
+                    None => Ok(()),
+                };
+                if let Err(error) = result {
+                    warn!("failed to remove a file watch of {}: {}", id, error);
+                }
+            }
+            Target::Command(command) => {
+                let watched = watcher.watches.iter().any(|other| match other.target {
+                    Target::Command(ref other) => *other == command,
+                    _ => false,
+                });
+                if watched {
+                    continue;
+                }
+                // Its output ends along with it, which removes its source
+                if let Some(mut child) = watcher.commands.remove(&command) {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+            }
+        }
+    }
+}
+
+fn add_watch(
+    path: &Path,
+    name: Option<OsString>,
+    mask: WatchMask,
+    throttle: Duration,
+    id: &str,
+    tx_update_request: &Sender<Task>,
+) -> Result<()> {
+    let mut watcher = WATCHER
+        .lock()
+        .internal_error("scheduler", "failed to lock file watcher")?;
+
+    if watcher.notify.is_none() {
+        let notify = Inotify::init().internal_error("scheduler", "failed to start inotify")?;
+        let mut buffer = [0; 4096];
+        add_source(notify.as_raw_fd(), Box::new(move || dispatch_file_events(&mut buffer)))?;
+        watcher.notify = Some(notify);
+    }
+
+    // Other blocks may watch the same inode already, `MASK_ADD` adds to
+    // the events they're watching for rather than replacing them
+    let descriptor = watcher
+        .notify
+        .as_mut()
+        .internal_error("scheduler", "no file watcher")?
+        .add_watch(path, mask | WatchMask::MASK_ADD)
+        .internal_error("scheduler", &format!("failed to watch {}", path.to_string_lossy()))?;
+    watcher.watches.push(Watch {
+        target: Target::File {
+            descriptor,
+            path: path.to_owned(),
+            name,
+            mask,
+        },
+        throttle,
+        last: None,
+        pending: false,
+        id: id.to_owned(),
+        tx_update_request: tx_update_request.clone(),
+    });
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+//! Signals the bar reacts to.
+//!
+//! * `SIGHUP` reloads the configuration
+//! * `SIGUSR1` updates all blocks
+//! * `SIGRTMIN+n` updates the blocks configured with `signal = n`
+//! * `STOP_SIGNAL` and `CONT_SIGNAL` are sent by the bar when it is hidden
+//!   and shown again, see the i3bar protocol header
+//!
+//! All of them are blocked in every thread and picked up synchronously by a
+//! single listener thread, so no async-signal-safety concerns apply.
+
+use std::mem;
+use std::thread;
+
+use chan::Sender;
+use nix::libc::{self, c_int};
+
+use errors::*;
+
+// The realtime signal range depends on the libc, as it reserves some of the
+// signals for itself.
+extern "C" {
+    fn __libc_current_sigrtmin() -> c_int;
+    fn __libc_current_sigrtmax() -> c_int;
+}
+
+/// Asked for in place of `SIGSTOP`, so we get to pause periodic updates
+/// instead of being frozen altogether.
+pub const STOP_SIGNAL: c_int = libc::SIGUSR2;
+pub const CONT_SIGNAL: c_int = libc::SIGCONT;
+
+pub fn sigrtmin() -> c_int {
+    unsafe { __libc_current_sigrtmin() }
+}
+
+pub fn sigrtmax() -> c_int {
+    unsafe { __libc_current_sigrtmax() }
+}
+
+fn signal_set() -> libc::sigset_t {
+    unsafe {
+        let mut set: libc::sigset_t = mem::zeroed();
+        libc::sigemptyset(&mut set);
+        libc::sigaddset(&mut set, libc::SIGHUP);
+        libc::sigaddset(&mut set, libc::SIGUSR1);
+        libc::sigaddset(&mut set, STOP_SIGNAL);
+        libc::sigaddset(&mut set, CONT_SIGNAL);
+        for signal in sigrtmin()..sigrtmax() + 1 {
+            libc::sigaddset(&mut set, signal);
+        }
+        set
+    }
+}
+
+/// Block the signals for the calling thread. This has to happen before any
+/// other thread is spawned, as threads inherit the signal mask and the signals
+/// are meant to be picked up by the listener thread only.
+pub fn block_signals() -> Result<()> {
+    let set = signal_set();
+    match unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, ::std::ptr::null_mut()) } {
+        0 => Ok(()),
+        _ => Err(InternalError("signals".to_owned(), "failed to block signals".to_owned(), None)),
+    }
+}
+
+/// Send every received signal through `tx`.
+pub fn spawn_listener(tx: Sender<c_int>) {
+    thread::spawn(move || {
+        let set = signal_set();
+        loop {
+            let mut signal: c_int = 0;
+            if unsafe { libc::sigwait(&set, &mut signal) } == 0 {
+                tx.send(signal);
+            }
+        }
+    });
+}
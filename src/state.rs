@@ -0,0 +1,105 @@
+//! State blocks keep across restarts of the bar, e.g. whether a group was
+//! expanded. It's stored as a single JSON object in
+//! `$XDG_STATE_HOME/i3status-rs/state.json`, holding the state of each block
+//! under its key.
+//!
+//! A block's key is its `id` option if it has one, or its type and how many
+//! blocks of that type come before it otherwise, e.g. `group.0`. So moving
+//! blocks around may hand their state to others, giving them an id prevents
+//! that.
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json;
+use serde_json::map::Map;
+use serde_json::value::Value;
+
+use errors::*;
+use util::xdg_dir;
+
+lazy_static! {
+    /// The contents of the state file, read when first needed
+    static ref STORE: Mutex<Option<Map<String, Value>>> = Mutex::new(None);
+}
+
+fn path() -> PathBuf {
+    xdg_dir("XDG_STATE_HOME", ".local/state").join("state.json")
+}
+
+/// The state file as it is on disk. A missing or broken file counts as empty,
+/// losing state is no reason not to start.
+fn load() -> Map<String, Value> {
+    let mut contents = String::new();
+    if File::open(path()).and_then(|mut file| file.read_to_string(&mut contents)).is_err() {
+        return Map::new();
+    }
+    match serde_json::from_str(&contents) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+fn save(store: &Map<String, Value>) -> Result<()> {
+    let path = path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).internal_error("state", "failed to create state directory")?;
+    }
+    // Write to a temporary file first, so that the bar dying halfway through
+    // doesn't leave a broken file behind.
+    let tmp = path.with_extension("json.tmp");
+    File::create(&tmp)
+        .and_then(|mut file| file.write_all(Value::Object(store.clone()).to_string().as_bytes()))
+        .internal_error("state", "failed to write state file")?;
+    fs::rename(&tmp, &path).internal_error("state", "failed to replace state file")
+}
+
+/// The state of a single block, handed to it in `ConfigBlock::new_with_state`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockState {
+    key: String,
+}
+
+impl BlockState {
+    pub fn new(key: &str) -> Self {
+        BlockState { key: key.to_owned() }
+    }
+
+    /// What the block is told apart by, its id or else its type and position.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// State of a block within this one, e.g. a block of a group.
+    pub fn child(&self, key: &str) -> Self {
+        BlockState::new(&format!("{}/{}", self.key, key))
+    }
+
+    /// The state saved last, if any and if it still fits `T`.
+    pub fn get<T: DeserializeOwned>(&self) -> Option<T> {
+        let mut store = STORE.lock().ok()?;
+        if store.is_none() {
+            *store = Some(load());
+        }
+        let value = store.as_ref()?.get(&self.key)?.clone();
+        serde_json::from_value(value).ok()
+    }
+
+    pub fn set<T: Serialize>(&self, value: &T) -> Result<()> {
+        let value = serde_json::to_value(value).internal_error("state", "failed to serialize block state")?;
+        let mut store = STORE.lock().internal_error("state", "state store poisoned")?;
+        if store.is_none() {
+            *store = Some(load());
+        }
+        let map = store.as_mut().internal_error("state", "state store missing")?;
+        if map.get(&self.key) == Some(&value) {
+            return Ok(());
+        }
+        map.insert(self.key.clone(), value);
+        save(map)
+    }
+}
@@ -0,0 +1,703 @@
+//! Keeping an eye on the blocks of the bar.
+//!
+//! Every top level block is wrapped in a `Supervised` block, which logs how
+//! its updates go, and adds to, aligns or jitters them as configured. A block
+//! whose update fails is shown as an error in the bar instead, and retried
+//! after a while, backing off exponentially as long as it keeps failing.
+//! Failed clicks are only logged.
+//!
+//! A block that panics is given up on, its state can't be trusted anymore. The
+//! rest of the bar keeps running.
+//!
+//! With `notify_on_critical`, a block turning critical is announced with a
+//! desktop notification. Outside of its `active_hours` and `active_days`, a
+//! block is neither updated nor shown. With `state_debounce`, widgets keep
+//! the colors of their previous state until a new one lasts.
+//!
+//! Updates run in the main thread, so a slow one holds up the whole bar.
+//! Updates taking longer than the `update_budget` are logged, and with
+//! `mark_slow_blocks`, blocks that keep going over it get a warning sign.
+//!
+//! Blocks with an `id` leave a `Heartbeat` with each update, so the
+//! `watchdog` block can tell when one didn't update for too long.
+//!
+//! Until a block is done with its first update, it shows a placeholder. On
+//! start, blocks are even created after the bar is shown, with a
+//! `Placeholder` standing in for them, see `reload::build_placeholders`. A
+//! block that fails to be created is shown as its error, see
+//! `Supervised::failed`.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use block::{Block, CommonBlockConfig};
+use chrono::{Local, Weekday};
+use config::Config;
+use errors::*;
+use input::I3BarEvent;
+use logging;
+use notifications::{self, Urgency};
+use scheduler::{self, ActiveHours, UpdateAlign};
+use serde_json::value::Value;
+use themes::Theme;
+use widget::{I3BarWidget, State};
+use widgets::text::TextWidget;
+
+use uuid::Uuid;
+
+/// A block going over its update budget this many updates in a row is
+/// consistently slow, and marked if `mark_slow_blocks` is set. Single slow
+/// updates, e.g. right after resuming, are only logged.
+const SLOW_STREAK: u32 = 3;
+
+/// The first retry of a failed block is after this many seconds, each
+/// further one waits twice as long, up to `MAX_RETRY_SECS`.
+const FIRST_RETRY_SECS: u64 = 5;
+const MAX_RETRY_SECS: u64 = 600;
+
+/// Errors are cut down to this many characters in the bar, the log has them
+/// in full.
+const MAX_ERROR_CHARS: usize = 40;
+
+/// How a block with an `id` has been updating, see `heartbeats`.
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    /// When the block last updated successfully, or was created
+    pub last: Instant,
+    /// How long the block meant to wait for its next update then, `None` if
+    /// it waits for events, or is inactive
+    pub expected: Option<Duration>,
+}
+
+lazy_static! {
+    /// The heartbeats of the blocks with an `id`, by that `id` and the
+    /// block's own
+    static ref HEARTBEATS: Mutex<HashMap<(String, String), Heartbeat>> = Mutex::new(HashMap::new());
+}
+
+/// The heartbeats of all blocks with the `id` `name`, none if there's no
+/// such block.
+pub fn heartbeats(name: &str) -> Vec<Heartbeat> {
+    match HEARTBEATS.lock() {
+        Ok(heartbeats) => heartbeats
+            .iter()
+            .filter(|&(&(ref id, _), _)| id == name)
+            .map(|(_, heartbeat)| heartbeat.clone())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000. + f64::from(duration.subsec_nanos()) / 1_000_000.
+}
+
+/// How long to wait before retry number `attempt`, counting from 1.
+fn backoff(attempt: u32) -> Duration {
+    let factor = 1u64 << attempt.saturating_sub(1).min(16);
+    Duration::from_secs((FIRST_RETRY_SECS * factor).min(MAX_RETRY_SECS))
+}
+
+/// The message of `error`, without what it's about.
+fn error_message(error: &Error) -> String {
+    match *error {
+        BlockError(_, ref message) | ConfigurationError(ref message, _) | InternalError(_, ref message, _) => {
+            message.clone()
+        }
+    }
+}
+
+/// `message`, short enough to fit into the bar.
+fn shorten(message: &str) -> String {
+    if message.chars().count() > MAX_ERROR_CHARS {
+        format!("{}\u{2026}", message.chars().take(MAX_ERROR_CHARS - 1).collect::<String>())
+    } else {
+        message.to_owned()
+    }
+}
+
+/// The message a panic was raised with, if it has one.
+pub fn panic_message(payload: &(Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown cause".to_owned()
+    }
+}
+
+fn placeholder_widget(config: Config) -> TextWidget {
+    TextWidget::new(config).with_text("\u{2026}")
+}
+
+/// Stands in for a block that isn't created yet.
+pub struct Placeholder {
+    text: TextWidget,
+    id: String,
+}
+
+impl Placeholder {
+    pub fn new(config: Config) -> Placeholder {
+        Placeholder {
+            text: placeholder_widget(config),
+            id: Uuid::new_v4().simple().to_string(),
+        }
+    }
+}
+
+impl Block for Placeholder {
+    fn view(&self) -> Vec<&I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// Holds back the state changes of a widget until they last for a number of
+/// updates, so that values hovering around a threshold don't flicker between
+/// two colors.
+struct Debounce {
+    /// The state the widget is shown in
+    shown: State,
+    /// A different state, and for how many updates in a row it's been
+    pending: Option<(State, u32)>,
+}
+
+impl Debounce {
+    fn new(state: State) -> Debounce {
+        Debounce {
+            shown: state,
+            pending: None,
+        }
+    }
+
+    /// Take in the `state` of the widget after an update, which shows once
+    /// it was there for `updates` in a row. Returns the state to show.
+    fn push(&mut self, state: State, updates: u32) -> State {
+        if state == self.shown {
+            self.pending = None;
+            return self.shown;
+        }
+        let count = match self.pending {
+            Some((pending, count)) if pending == state => count + 1,
+            _ => 1,
+        };
+        if count >= updates {
+            self.shown = state;
+            self.pending = None;
+        } else {
+            self.pending = Some((state, count));
+        }
+        self.shown
+    }
+}
+
+/// A widget as rendered by a block, in the colors of an earlier state.
+struct HeldWidget {
+    rendered: Value,
+    state: State,
+    double_click: bool,
+}
+
+impl HeldWidget {
+    /// `widget`, recolored from its own state to `state`. Colors a block set
+    /// itself, like gradients, stay as they are.
+    fn new(widget: &I3BarWidget, state: State, theme: &Theme) -> HeldWidget {
+        let mut rendered = widget.get_rendered().clone();
+        let actual = widget.state();
+        if actual != state {
+            let (from_bg, from_fg) = actual.theme_keys(theme);
+            let (to_bg, to_fg) = state.theme_keys(theme);
+            if rendered["background"].as_str() == Some(from_bg.as_str()) {
+                rendered["background"] = json!(to_bg);
+            }
+            if rendered["color"].as_str() == Some(from_fg.as_str()) {
+                rendered["color"] = json!(to_fg);
+            }
+            let (from_border, to_border) = (actual.theme_border(theme), state.theme_border(theme));
+            let own_border = rendered["border"].as_str().map_or(from_border.is_empty(), |border| border == from_border);
+            if own_border {
+                if let Value::Object(ref mut map) = rendered {
+                    if to_border.is_empty() {
+                        map.remove("border");
+                    } else {
+                        map.insert("border".to_owned(), json!(to_border));
+                    }
+                }
+            }
+        }
+        HeldWidget {
+            rendered,
+            state,
+            double_click: widget.wants_double_click(),
+        }
+    }
+}
+
+impl I3BarWidget for HeldWidget {
+    fn to_string(&self) -> String {
+        self.rendered.to_string()
+    }
+
+    fn get_rendered(&self) -> &Value {
+        &self.rendered
+    }
+
+    fn set_urgent(&mut self, urgent: bool) {
+        self.rendered["urgent"] = json!(urgent);
+    }
+
+    fn state(&self) -> State {
+        self.state
+    }
+
+    fn wants_double_click(&self) -> bool {
+        self.double_click
+    }
+}
+
+struct Failure {
+    /// Failed attempts in a row
+    attempts: u32,
+    /// When to try again, never after a panic
+    retry_at: Option<Instant>,
+}
+
+pub struct Supervised {
+    block: Box<Block>,
+    /// The block type
+    name: String,
+    /// The `id` to address the block by, if it has one
+    common_id: Option<String>,
+    /// Where to log to, see `logging::block_target`
+    target: String,
+    failure: Option<Failure>,
+    /// Shown instead of the block while it's failing
+    error_widget: TextWidget,
+    /// Whether the block was updated yet, it shows `placeholder` until then
+    updated: bool,
+    placeholder: TextWidget,
+    /// Update at least this often, see `reload::resolve_interval`
+    interval: Option<Duration>,
+    update_align: Option<UpdateAlign>,
+    update_jitter: Option<Duration>,
+    notify_on_critical: bool,
+    /// Whether any widget of the block was critical after the last update
+    critical: bool,
+    active_hours: Option<ActiveHours>,
+    active_days: Option<Vec<Weekday>>,
+    /// Whether the block is outside of its active hours and days, and hidden
+    inactive: bool,
+    /// Updates a new state has to last for to show, if debounced
+    state_debounce: Option<u32>,
+    /// One for each widget of the block, as of the last update
+    debounced: Vec<Debounce>,
+    /// The widgets of the block in their debounced states, shown instead
+    held: Vec<HeldWidget>,
+    theme: Theme,
+    /// How long the last update took, in milliseconds
+    took: Option<f64>,
+    /// Updates taking longer than this many milliseconds are logged
+    update_budget: f64,
+    /// Updates in a row that went over the budget
+    slow_streak: u32,
+    /// Shown after the block while it's consistently slow, if set
+    slow_marker: Option<TextWidget>,
+}
+
+impl Supervised {
+    /// Supervise `block`, which is of type `name` and was created from
+    /// `config` and `common`.
+    pub fn new(block: Box<Block>, name: &str, config: Config, common: &CommonBlockConfig) -> Supervised {
+        let supervised = Supervised {
+            block,
+            name: name.to_owned(),
+            common_id: common.id.clone(),
+            target: logging::block_target(name),
+            failure: None,
+            error_widget: TextWidget::new(config.clone()).with_state(State::Critical),
+            updated: false,
+            placeholder: placeholder_widget(config.clone()),
+            interval: common.interval,
+            update_align: common.update_align,
+            update_jitter: common.update_jitter,
+            notify_on_critical: common.notify_on_critical,
+            critical: false,
+            active_hours: common.active_hours,
+            active_days: common.active_days.clone(),
+            inactive: false,
+            state_debounce: common.state_debounce,
+            debounced: Vec::new(),
+            held: Vec::new(),
+            theme: config.theme.clone(),
+            took: None,
+            update_budget: millis(common.update_budget.unwrap_or(config.update_budget)),
+            slow_streak: 0,
+            slow_marker: if config.mark_slow_blocks {
+                Some(TextWidget::new(config.clone()).with_text("\u{26a0}").with_state(State::Warning))
+            } else {
+                None
+            },
+        };
+        supervised.beat(true, common.interval);
+        supervised
+    }
+
+    /// Stand in for a block of type `name` that failed to be created with
+    /// `error`, which is shown instead. It's not retried, only a change of its
+    /// configuration gets it created again.
+    pub fn failed(error: &Error, name: &str, config: Config, common: &CommonBlockConfig) -> Supervised {
+        let mut supervised = Supervised::new(Box::new(Placeholder::new(config.clone())), name, config, common);
+        error!(target: &supervised.target, "failed to create {}: {:?}", name, error);
+        supervised.show_error(&error_message(error));
+        supervised.updated = true;
+        supervised.failure = Some(Failure {
+            attempts: 1,
+            retry_at: None,
+        });
+        supervised
+    }
+
+    /// Leave a heartbeat, if the block has an `id`. Failed updates keep
+    /// the time of the last successful one.
+    fn beat(&self, succeeded: bool, expected: Option<Duration>) {
+        let name = match self.common_id {
+            Some(ref name) => name.clone(),
+            None => return,
+        };
+        if let Ok(mut heartbeats) = HEARTBEATS.lock() {
+            let now = Instant::now();
+            let heartbeat = heartbeats
+                .entry((name, self.block.id().to_owned()))
+                .or_insert(Heartbeat {
+                    last: now,
+                    expected: None,
+                });
+            if succeeded {
+                heartbeat.last = now;
+            }
+            heartbeat.expected = expected;
+        }
+    }
+
+    /// Take in the states of the widgets after an update.
+    fn debounce(&mut self, updates: u32) {
+        let held = {
+            let widgets = self.block.view();
+            // Start over when widgets come and go, they can't be told apart
+            if widgets.len() != self.debounced.len() {
+                self.debounced = widgets.iter().map(|widget| Debounce::new(widget.state())).collect();
+            }
+            let (target, theme, id) = (&self.target, &self.theme, self.block.id());
+            widgets
+                .iter()
+                .zip(self.debounced.iter_mut())
+                .map(|(widget, debounce)| {
+                    let state = debounce.push(widget.state(), updates);
+                    if state != widget.state() {
+                        debug!(target: target, "holding back {:?} of {}", widget.state(), id);
+                    }
+                    HeldWidget::new(*widget, state, theme)
+                })
+                .collect()
+        };
+        self.held = held;
+    }
+
+    /// Redraw the held widgets after the block changed between updates,
+    /// keeping their states.
+    fn refresh_held(&mut self) {
+        let held = {
+            let widgets = self.block.view();
+            if widgets.len() != self.debounced.len() {
+                self.debounced = widgets.iter().map(|widget| Debounce::new(widget.state())).collect();
+            }
+            let theme = &self.theme;
+            widgets
+                .iter()
+                .zip(self.debounced.iter())
+                .map(|(widget, debounce)| HeldWidget::new(*widget, debounce.shown, theme))
+                .collect()
+        };
+        self.held = held;
+    }
+
+    /// Notify about the block if it just turned critical. Debounced blocks
+    /// count as critical once they're shown so.
+    fn check_critical(&mut self) {
+        let (critical, text) = {
+            let widgets = self.view();
+            let text = widgets
+                .iter()
+                .filter_map(|widget| widget.get_rendered()["full_text"].as_str())
+                .map(|text| text.trim())
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            (widgets.iter().any(|widget| widget.state() == State::Critical), text)
+        };
+        if critical && !self.critical {
+            info!(target: &self.target, "{} turned critical: {}", self.block.id(), text);
+            notifications::notify(&self.name, &text, Urgency::Critical);
+        }
+        self.critical = critical;
+    }
+
+    fn show_error(&mut self, message: &str) {
+        self.error_widget
+            .set_text(format!("\u{2717} {}: {}", self.name, shorten(message)));
+        self.error_widget
+            .set_short_text(format!("\u{2717} {}", self.name));
+    }
+
+    fn fail(&mut self, error: &Error) -> Duration {
+        let attempts = self.failure.as_ref().map_or(0, |failure| failure.attempts) + 1;
+        let delay = backoff(attempts);
+        error!(
+            target: &self.target,
+            "update of {} failed, retrying in {}s: {:?}",
+            self.block.id(),
+            delay.as_secs(),
+            error
+        );
+
+        self.show_error(&error_message(error));
+        self.failure = Some(Failure {
+            attempts,
+            retry_at: Some(Instant::now() + delay),
+        });
+        delay
+    }
+
+    fn give_up(&mut self, payload: &(Any + Send)) {
+        let message = panic_message(payload);
+        error!(target: &self.target, "{} panicked, giving up on it: {}", self.block.id(), message);
+
+        self.show_error(&format!("crashed: {}", message));
+        self.failure = Some(Failure {
+            attempts: self.failure.as_ref().map_or(0, |failure| failure.attempts) + 1,
+            retry_at: None,
+        });
+    }
+
+    /// What the block was last expected to update within.
+    fn heartbeat_expected(&self) -> Option<Duration> {
+        let name = match self.common_id {
+            Some(ref name) => name.clone(),
+            None => return None,
+        };
+        HEARTBEATS
+            .lock()
+            .ok()
+            .and_then(|heartbeats| heartbeats.get(&(name, self.block.id().to_owned())).cloned())
+            .and_then(|heartbeat| heartbeat.expected)
+    }
+
+    fn has_crashed(&self) -> bool {
+        self.failure
+            .as_ref()
+            .map_or(false, |failure| failure.retry_at.is_none())
+    }
+}
+
+impl Supervised {
+    fn update_block(&mut self) -> Result<Option<Duration>> {
+        // Updates requested by the block itself don't cut the wait short.
+        if let Some(ref failure) = self.failure {
+            let now = Instant::now();
+            match failure.retry_at {
+                None => return Ok(None),
+                Some(retry_at) if now < retry_at => return Ok(Some(retry_at - now)),
+                Some(_) => {}
+            }
+        }
+
+        let start = Instant::now();
+        let result = {
+            let block = &mut self.block;
+            panic::catch_unwind(AssertUnwindSafe(|| block.update()))
+        };
+        let took = millis(start.elapsed());
+        self.updated = true;
+        self.took = Some(took);
+
+        if took >= self.update_budget {
+            self.slow_streak += 1;
+            warn!(
+                target: &self.target,
+                "update of {} took {:.1}ms, over its budget of {:.0}ms",
+                self.block.id(),
+                took,
+                self.update_budget
+            );
+            if self.slow_streak == SLOW_STREAK {
+                warn!(target: &self.target, "{} is consistently slow", self.block.id());
+            }
+        } else {
+            self.slow_streak = 0;
+            debug!(target: &self.target, "update of {} took {:.1}ms", self.block.id(), took);
+        }
+        match result {
+            Ok(Ok(next)) => {
+                if self.failure.take().is_some() {
+                    info!(target: &self.target, "update of {} succeeded again", self.block.id());
+                }
+                if let Some(updates) = self.state_debounce {
+                    self.debounce(updates);
+                }
+                if self.notify_on_critical {
+                    self.check_critical();
+                }
+                let next = match (next, self.interval) {
+                    (Some(next), Some(interval)) => Some(next.min(interval)),
+                    (next, interval) => next.or(interval),
+                };
+                let next = next.map(|mut next| {
+                    if let Some(align) = self.update_align {
+                        next = scheduler::aligned(next, align);
+                    }
+                    if let Some(jitter) = self.update_jitter {
+                        next = scheduler::jittered(next, jitter);
+                    }
+                    next
+                });
+                self.beat(true, next);
+                Ok(next)
+            }
+            Ok(Err(error)) => {
+                let expected = self.heartbeat_expected();
+                self.beat(false, expected);
+                Ok(Some(self.fail(&error)))
+            }
+            // Still expected to update, a crashed block is the very thing
+            // to notice
+            Err(payload) => {
+                let expected = self.heartbeat_expected();
+                self.beat(false, expected);
+                self.give_up(&*payload);
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl Drop for Supervised {
+    fn drop(&mut self) {
+        if let Some(ref name) = self.common_id {
+            if let Ok(mut heartbeats) = HEARTBEATS.lock() {
+                heartbeats.remove(&(name.clone(), self.block.id().to_owned()));
+            }
+        }
+    }
+}
+
+impl Block for Supervised {
+    fn update(&mut self) -> Result<Option<Duration>> {
+        if self.active_hours.is_none() && self.active_days.is_none() {
+            return self.update_block();
+        }
+
+        let now = Local::now();
+        let until_change = scheduler::until_active_change(self.active_hours, &self.active_days, now);
+        if !scheduler::is_active(self.active_hours, &self.active_days, now) {
+            if !self.inactive {
+                info!(target: &self.target, "{} is inactive for {}s", self.block.id(), until_change.as_secs());
+                self.inactive = true;
+                self.beat(false, None);
+            }
+            return Ok(Some(until_change));
+        }
+        self.inactive = false;
+        // Woken up in time to hide again
+        let next = self.update_block()?;
+        Ok(Some(next.map_or(until_change, |next| next.min(until_change))))
+    }
+
+    fn view(&self) -> Vec<&I3BarWidget> {
+        if self.inactive {
+            return vec![];
+        } else if self.failure.is_some() {
+            return vec![&self.error_widget];
+        } else if !self.updated {
+            return vec![&self.placeholder];
+        }
+
+        let mut widgets: Vec<&I3BarWidget> = if self.state_debounce.is_some() {
+            self.held.iter().map(|widget| widget as &I3BarWidget).collect()
+        } else {
+            self.block.view()
+        };
+        if let Some(ref marker) = self.slow_marker {
+            if self.slow_streak >= SLOW_STREAK {
+                widgets.push(marker);
+            }
+        }
+        widgets
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        // The widget clicked isn't the block's own then
+        if self.failure.is_some() || !self.updated || self.inactive {
+            return Ok(());
+        }
+
+        let result = {
+            let block = &mut self.block;
+            panic::catch_unwind(AssertUnwindSafe(|| block.click(event)))
+        };
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => error!(target: &self.target, "click on {} failed: {:?}", self.block.id(), error),
+            Err(payload) => self.give_up(&*payload),
+        }
+        if self.state_debounce.is_some() && !self.has_crashed() {
+            self.refresh_held();
+        }
+        Ok(())
+    }
+
+    fn metrics(&self) -> Vec<(&'static str, f64)> {
+        // Whatever the block measured last is stale then
+        let mut metrics = if self.failure.is_some() || !self.updated || self.inactive {
+            Vec::new()
+        } else {
+            self.block.metrics()
+        };
+        if let Some(took) = self.took {
+            metrics.push(("update_duration_seconds", took / 1000.0));
+        }
+        metrics
+    }
+
+    fn resumed(&mut self) {
+        if self.has_crashed() {
+            return;
+        }
+        // Whatever failed before may well work now, e.g. with the network back
+        if let Some(ref mut failure) = self.failure {
+            failure.retry_at = Some(Instant::now());
+        }
+        self.block.resumed();
+    }
+
+    fn set_text(&mut self, text: String) -> Result<()> {
+        if self.has_crashed() {
+            return Err(BlockError(self.name.clone(), "the block has crashed".to_owned()));
+        }
+        self.block.set_text(text)?;
+        if self.state_debounce.is_some() {
+            self.refresh_held();
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        self.block.id()
+    }
+}
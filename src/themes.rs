@@ -1,5 +1,15 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
 use std::str::FromStr;
 
+use errors::ResultExtInternal;
+use serde::de::value::{Error, MapDeserializer};
+use serde::de::Deserialize;
+use toml::Value;
+use util::xdg_dir;
+
 lazy_static! {
     pub static ref SLICK: Theme = Theme {
         idle_bg: "#424242".to_owned(),
@@ -17,6 +27,11 @@ lazy_static! {
         separator_fg: "auto".to_owned(),
         alternating_tint_bg: "#111111".to_owned(),
         alternating_tint_fg: "#111111".to_owned(),
+        idle_border: "".to_owned(),
+        info_border: "".to_owned(),
+        good_border: "".to_owned(),
+        warning_border: "".to_owned(),
+        critical_border: "".to_owned(),
     };
 
     pub static ref SOLARIZED_DARK: Theme = Theme {
@@ -35,6 +50,11 @@ lazy_static! {
         separator_fg: "auto".to_owned(),
         alternating_tint_bg: "#000000".to_owned(),
         alternating_tint_fg: "#000000".to_owned(),
+        idle_border: "".to_owned(),
+        info_border: "".to_owned(),
+        good_border: "".to_owned(),
+        warning_border: "".to_owned(),
+        critical_border: "".to_owned(),
     };
 
     pub static ref SOLARIZED_LIGHT: Theme = Theme {
@@ -53,6 +73,11 @@ lazy_static! {
         separator_fg: "auto".to_owned(),
         alternating_tint_bg: "#000000".to_owned(),
         alternating_tint_fg: "#000000".to_owned(),
+        idle_border: "".to_owned(),
+        info_border: "".to_owned(),
+        good_border: "".to_owned(),
+        warning_border: "".to_owned(),
+        critical_border: "".to_owned(),
     };
 
     pub static ref MODERN: Theme = Theme {
@@ -71,6 +96,11 @@ lazy_static! {
         separator_fg: "auto".to_owned(),
         alternating_tint_bg: "#000000".to_owned(),
         alternating_tint_fg: "#000000".to_owned(),
+        idle_border: "".to_owned(),
+        info_border: "".to_owned(),
+        good_border: "".to_owned(),
+        warning_border: "".to_owned(),
+        critical_border: "".to_owned(),
     };
 
     pub static ref MULSANNE: Theme = Theme {
@@ -89,6 +119,11 @@ lazy_static! {
         separator_fg: "#111120".to_owned(),
         alternating_tint_bg: "#000000".to_owned(),
         alternating_tint_fg: "#000000".to_owned(),
+        idle_border: "".to_owned(),
+        info_border: "".to_owned(),
+        good_border: "".to_owned(),
+        warning_border: "".to_owned(),
+        critical_border: "".to_owned(),
     };
 
     pub static ref BRM: Theme = Theme {
@@ -107,6 +142,11 @@ lazy_static! {
         separator_fg: "#021314".to_owned(),
         alternating_tint_bg: "#000000".to_owned(),
         alternating_tint_fg: "#000000".to_owned(),
+        idle_border: "".to_owned(),
+        info_border: "".to_owned(),
+        good_border: "".to_owned(),
+        warning_border: "".to_owned(),
+        critical_border: "".to_owned(),
     };
 
     pub static ref PLAIN: Theme = Theme {
@@ -125,6 +165,11 @@ lazy_static! {
         separator_fg: "#a9a9a9".to_owned(),
         alternating_tint_bg: "#000000".to_owned(),
         alternating_tint_fg: "#000000".to_owned(),
+        idle_border: "".to_owned(),
+        info_border: "".to_owned(),
+        good_border: "".to_owned(),
+        warning_border: "".to_owned(),
+        critical_border: "".to_owned(),
     };
 
     pub static ref BAD_WOLF: Theme = Theme {
@@ -143,6 +188,11 @@ lazy_static! {
         separator_fg: "auto".to_owned(),
         alternating_tint_bg: "#000000".to_owned(),
         alternating_tint_fg: "#000000".to_owned(),
+        idle_border: "".to_owned(),
+        info_border: "".to_owned(),
+        good_border: "".to_owned(),
+        warning_border: "".to_owned(),
+        critical_border: "".to_owned(),
     };
 
     pub static ref JWOLF: Theme = Theme {
@@ -161,6 +211,11 @@ lazy_static! {
         separator_fg: "#444444".to_owned(),
         alternating_tint_bg: "#000000".to_owned(),
         alternating_tint_fg: "#000000".to_owned(),
+        idle_border: "".to_owned(),
+        info_border: "".to_owned(),
+        good_border: "".to_owned(),
+        warning_border: "".to_owned(),
+        critical_border: "".to_owned(),
     };
 
     pub static ref GRUVBOX_LIGHT: Theme = Theme {
@@ -179,6 +234,11 @@ lazy_static! {
         separator_fg: "auto".to_owned(),
         alternating_tint_bg: "#000000".to_owned(),
         alternating_tint_fg: "#000000".to_owned(),
+        idle_border: "".to_owned(),
+        info_border: "".to_owned(),
+        good_border: "".to_owned(),
+        warning_border: "".to_owned(),
+        critical_border: "".to_owned(),
     };
 
     pub static ref GRUVBOX_DARK: Theme = Theme {
@@ -197,11 +257,16 @@ lazy_static! {
         separator_fg: "auto".to_owned(),
         alternating_tint_bg: "#000000".to_owned(),
         alternating_tint_fg: "#000000".to_owned(),
+        idle_border: "".to_owned(),
+        info_border: "".to_owned(),
+        good_border: "".to_owned(),
+        warning_border: "".to_owned(),
+        critical_border: "".to_owned(),
     };
 }
 
 mapped_struct! {
-    #[derive(Deserialize, Debug, Default, Clone)]
+    #[derive(Deserialize, Debug, Default, Clone, PartialEq)]
     #[serde(deny_unknown_fields)]
     pub struct Theme: String {
         pub idle_bg,
@@ -218,7 +283,22 @@ mapped_struct! {
         pub separator_bg,
         pub separator_fg,
         pub alternating_tint_bg,
-        pub alternating_tint_fg
+        pub alternating_tint_fg,
+        pub idle_border,
+        pub info_border,
+        pub good_border,
+        pub warning_border,
+        pub critical_border
+    }
+}
+
+impl Theme {
+    /// The theme with the colors in `overrides` replaced, keyed by field name.
+    pub fn with_overrides(&self, overrides: &HashMap<String, String>) -> ::errors::Result<Theme> {
+        let mut map = self.owned_map();
+        map.extend(overrides.iter().map(|(key, value)| (key.clone(), value.clone())));
+        Theme::deserialize(MapDeserializer::<_, Error>::new(map.into_iter()))
+            .configuration_error("invalid theme_overrides")
     }
 }
 
@@ -226,10 +306,11 @@ impl FromStr for Theme {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        get_theme(s).ok_or_else(|| "unknown theme".into())
+        load_theme(s)
     }
 }
 
+/// The built-in theme called `name`, see `load_theme` for user themes.
 pub fn get_theme(name: &str) -> Option<Theme> {
     match name {
         "slick" => Some(SLICK.clone()),
@@ -250,3 +331,68 @@ pub fn get_theme(name: &str) -> Option<Theme> {
 pub fn default() -> Theme {
     PLAIN.clone()
 }
+
+/// Where user themes live, `$XDG_CONFIG_HOME/i3status-rs/themes`.
+pub fn themes_dir() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config").join("themes")
+}
+
+/// The theme called `name`, either built in or a user theme defined in
+/// `<name>.toml` in `themes_dir()`. Built-in themes can't be shadowed.
+///
+/// A theme file sets any of the theme colors and takes everything else from
+/// the theme named by `inherit`, `plain` if not given:
+///
+/// ```toml
+/// inherit = "solarized-dark"
+/// idle_bg = "#123456"
+/// ```
+pub fn load_theme(name: &str) -> Result<Theme, String> {
+    load_theme_inner(name, &mut Vec::new())
+}
+
+/// `seen` holds the themes inheriting from `name`, to catch cycles.
+fn load_theme_inner(name: &str, seen: &mut Vec<String>) -> Result<Theme, String> {
+    if let Some(theme) = get_theme(name) {
+        return Ok(theme);
+    }
+    if name.is_empty() || name.contains('/') {
+        return Err(format!("invalid theme name '{}'", name));
+    }
+    if seen.iter().any(|other| other == name) {
+        return Err(format!("theme '{}' inherits from itself", name));
+    }
+
+    let path = themes_dir().join(format!("{}.toml", name));
+    let mut contents = String::new();
+    File::open(&path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .map_err(|e| format!("cannot find theme '{}' ({}: {})", name, path.display(), e))?;
+    let mut table = match contents.parse::<Value>() {
+        Ok(Value::Table(table)) => table,
+        Ok(_) => return Err(format!("{} is not a table", path.display())),
+        Err(e) => return Err(format!("failed to parse {}: {}", path.display(), e)),
+    };
+
+    let base = match table.remove("inherit") {
+        Some(Value::String(base)) => base,
+        Some(_) => return Err(format!("`inherit` in {} must be a theme name", path.display())),
+        None => "plain".to_owned(),
+    };
+    seen.push(name.to_owned());
+    let base = load_theme_inner(&base, seen)?;
+
+    let mut overrides = HashMap::new();
+    for (key, value) in table {
+        match value {
+            Value::String(value) => {
+                overrides.insert(key, value);
+            }
+            _ => return Err(format!("`{}` in {} must be a string", key, path.display())),
+        }
+    }
+    let mut map = base.owned_map();
+    map.extend(overrides);
+    Theme::deserialize(MapDeserializer::<_, Error>::new(map.into_iter()))
+        .map_err(|e| format!("invalid theme {}: {}", path.display(), e))
+}
@@ -0,0 +1,174 @@
+//! Hardware changes, as announced by the kernel.
+//!
+//! Blocks showing the state of some hardware subscribe to its subsystem, e.g.
+//! `power_supply`, and are updated whenever a device of it is added, removed
+//! or changes, instead of on their next poll. All subscriptions share a
+//! single netlink socket, read by the event thread of the `scheduler`.
+//!
+//! The events are read right from the kernel, the same ones udev listens to,
+//! so this works without udev. Blocks read sysfs, which is up to date by the
+//! time the event is sent.
+
+use std::mem;
+use std::os::unix::io::RawFd;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use chan::Sender;
+use nix::libc;
+use nix::unistd;
+
+use errors::*;
+use scheduler::{self, Task};
+
+/// The multicast group of the kernel's events. udev sends its own, processed
+/// ones to group 2, in a format of its own.
+const KERNEL_GROUP: u32 = 1;
+
+/// A device event, e.g. a `change` of `/devices/.../power_supply/BAT0`.
+struct Event {
+    /// `add`, `remove`, `change`, `bind` or `unbind`
+    action: String,
+    devpath: String,
+    subsystem: String,
+}
+
+impl Event {
+    /// Parse an event of the kernel, a header like `change@/devices/...`
+    /// followed by `KEY=value` pairs, separated by NUL characters.
+    fn parse(message: &[u8]) -> Option<Event> {
+        let message = String::from_utf8_lossy(message);
+        let mut fields = message.split('\0');
+        if !fields.next()?.contains('@') {
+            return None;
+        }
+
+        let mut event = Event {
+            action: String::new(),
+            devpath: String::new(),
+            subsystem: String::new(),
+        };
+        for field in fields {
+            let mut parts = field.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some("ACTION"), Some(value)) => event.action = value.to_owned(),
+                (Some("DEVPATH"), Some(value)) => event.devpath = value.to_owned(),
+                (Some("SUBSYSTEM"), Some(value)) => event.subsystem = value.to_owned(),
+                _ => {}
+            }
+        }
+        if event.action.is_empty() || event.subsystem.is_empty() {
+            None
+        } else {
+            Some(event)
+        }
+    }
+}
+
+struct Subscription {
+    subsystem: String,
+    id: String,
+    tx_update_request: Sender<Task>,
+}
+
+lazy_static! {
+    /// `None` until the first subscription starts listening
+    static ref SUBSCRIPTIONS: Mutex<Option<Vec<Subscription>>> = Mutex::new(None);
+}
+
+fn open_socket() -> Result<RawFd> {
+    unsafe {
+        let fd = libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+            libc::NETLINK_KOBJECT_UEVENT,
+        );
+        if fd < 0 {
+            return Err(InternalError(
+                "udev".to_owned(),
+                "failed to open netlink socket".to_owned(),
+                None,
+            ));
+        }
+
+        let mut address: libc::sockaddr_nl = mem::zeroed();
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_groups = KERNEL_GROUP;
+        let bound = libc::bind(
+            fd,
+            &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        );
+        if bound < 0 {
+            libc::close(fd);
+            return Err(InternalError(
+                "udev".to_owned(),
+                "failed to listen for device events".to_owned(),
+                None,
+            ));
+        }
+        Ok(fd)
+    }
+}
+
+/// Read an event from the socket and request updates of the blocks
+/// subscribed to its subsystem.
+fn dispatch_event(fd: RawFd, buffer: &mut [u8]) -> Result<Option<Instant>> {
+    // Fails if events came in faster than we read them, the next ones are
+    // fine again
+    let len = match unistd::read(fd, buffer) {
+        Ok(len) => len,
+        Err(_) => return Ok(None),
+    };
+    let event = match Event::parse(&buffer[..len]) {
+        Some(event) => event,
+        None => return Ok(None),
+    };
+    debug!("{} of {} ({})", event.action, event.devpath, event.subsystem);
+
+    let now = Instant::now();
+    if let Ok(subscriptions) = SUBSCRIPTIONS.lock() {
+        if let Some(ref subscriptions) = *subscriptions {
+            for subscription in subscriptions.iter().filter(|subscription| subscription.subsystem == event.subsystem) {
+                subscription.tx_update_request.send(Task {
+                    id: subscription.id.clone(),
+                    update_time: now,
+                });
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Request an update of the block `id` whenever a device of `subsystem`, as
+/// in `/sys/class/<subsystem>`, is added, removed or changes.
+pub fn subscribe(subsystem: &str, id: &str, tx_update_request: &Sender<Task>) -> Result<()> {
+    let mut subscriptions = SUBSCRIPTIONS
+        .lock()
+        .internal_error("udev", "failed to lock subscriptions")?;
+    if subscriptions.is_none() {
+        let fd = open_socket()?;
+        let mut buffer = [0; 8192];
+        scheduler::add_source(fd, Box::new(move || dispatch_event(fd, &mut buffer)))?;
+        *subscriptions = Some(Vec::new());
+    }
+
+    subscriptions
+        .as_mut()
+        .internal_error("udev", "no subscriptions")?
+        .push(Subscription {
+            subsystem: subsystem.to_owned(),
+            id: id.to_owned(),
+            tx_update_request: tx_update_request.clone(),
+        });
+    Ok(())
+}
+
+/// Stop the subscriptions of the block `id`, once it's gone.
+pub fn unsubscribe(id: &str) {
+    if let Ok(mut subscriptions) = SUBSCRIPTIONS.lock() {
+        if let Some(ref mut subscriptions) = *subscriptions {
+            subscriptions.retain(|subscription| subscription.id != id);
+        }
+    }
+}
@@ -1,29 +1,38 @@
-use block::Block;
+use block::{Align, Block, CommonBlockConfig, MinWidth};
 use config::Config;
 use errors::*;
-use std::collections::HashMap;
-use serde::de::DeserializeOwned;
+use paging;
+use reload::BlockSpec;
+use std::collections::{HashMap, HashSet};
 use serde_json::value::Value;
-use toml;
-use regex::Regex;
 use std::prelude::v1::String;
-use std::fmt::Display;
 use std::fs::{File, OpenOptions};
-use std::io::BufReader;
 use std::io::prelude::*;
 use std::num::ParseIntError;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::env;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+use chan::Sender;
+use input::{I3BarEvent, MouseButton};
+use scheduler::Task;
+use widget::{self, I3BarWidget};
 
-pub fn deserialize_file<T>(file: &str) -> Result<T>
-where
-    T: DeserializeOwned,
-{
-    let mut contents = String::new();
-    let mut file = BufReader::new(File::open(file)
-        .internal_error("util", "failed to open file")?);
-    file.read_to_string(&mut contents)
-        .internal_error("util", "failed to read file")?;
-    toml::from_str(&contents).configuration_error("failed to parse TOML from file contents")
+/// The directory of i3status-rs within the XDG base directory `var`, e.g.
+/// `$XDG_CACHE_HOME/i3status-rs`, falling back to `fallback` within the home
+/// directory if `var` isn't set.
+pub fn xdg_dir(var: &str, fallback: &str) -> PathBuf {
+    let base = match env::var(var) {
+        Ok(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").unwrap_or_else(|_| "/tmp".to_owned())).join(fallback),
+    };
+    base.join("i3status-rs")
+}
+
+/// `duration` in seconds, with fractions.
+pub fn secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9
 }
 
 pub fn read_file(blockname: &str, path: &Path) -> Result<String> {
@@ -84,80 +93,233 @@ macro_rules! map_to_owned (
      };
 );
 
-struct PrintState {
-    pub last_bg: Option<String>,
-    pub has_predecessor: bool,
+/// Render `widget` with the i3bar fields set through the common options of its
+/// block. `index` is the position of the widget in the block's view, the last
+/// one gets the separator options.
+fn render_widget(widget: &I3BarWidget, block_id: &str, common: &CommonBlockConfig, index: usize, count: usize) -> Value {
+    let mut rendered = widget.get_rendered().clone();
+    // Lets clicks be traced back to the widget, see `I3BarEvent::widget_index`
+    if rendered.get("instance").is_none() {
+        rendered["instance"] = json!(widget::instance_name(block_id, index));
+    }
+    let last = index == count - 1;
+    match common.min_width {
+        Some(MinWidth::Pixels(pixels)) => rendered["min_width"] = json!(pixels),
+        Some(MinWidth::Text(ref text)) => rendered["min_width"] = json!(text),
+        None => {}
+    }
+    if let Some(align) = common.align {
+        rendered["align"] = json!(match align {
+            Align::Left => "left",
+            Align::Center => "center",
+            Align::Right => "right",
+        });
+    }
+    if last {
+        if let Some(separator) = common.separator {
+            rendered["separator"] = json!(separator);
+        }
+        if let Some(width) = common.separator_block_width {
+            rendered["separator_block_width"] = json!(width);
+        }
+    }
+    rendered
 }
 
-impl PrintState {
-    fn set_last_bg(&mut self, bg: String) {
-        self.last_bg = Some(bg);
+/// Render the widgets of `blocks`, given as ids along with their specs,
+/// skipping `hidden` ones and those without widgets. See `bars::select` for
+/// picking the blocks and `output` for turning them into a status line.
+pub fn render_blocks<'a, I>(
+    blocks: I,
+    block_map: &HashMap<String, Box<Block>>,
+    hidden: &HashSet<String>,
+) -> Result<Vec<Vec<Value>>>
+where
+    I: IntoIterator<Item = (&'a String, &'a BlockSpec)>,
+{
+    let mut rendered_blocks = Vec::new();
+    for (block_id, spec) in blocks {
+        if hidden.contains(block_id) || !paging::is_visible(spec.common.page) {
+            continue;
+        }
+        let block = block_map
+            .get(block_id)
+            .internal_error("util", "couldn't get block by id")?;
+        let widgets = render_block(&**block, &spec.common);
+        if !widgets.is_empty() {
+            rendered_blocks.push(widgets);
+        }
     }
-    fn set_predecessor(&mut self, pre: bool) {
-        self.has_predecessor = pre;
+
+    Ok(rendered_blocks)
+}
+
+/// Render the widgets of `block`, with the common options `common` applied.
+pub fn render_block(block: &Block, common: &CommonBlockConfig) -> Vec<Value> {
+    let widgets = block.view();
+    widgets
+        .iter()
+        .enumerate()
+        .map(|(i, widget)| render_widget(*widget, block.id(), common, i, widgets.len()))
+        .collect()
+}
+
+/// A block as rendered in the last frame, see `RenderCache`.
+struct RenderedBlock {
+    id: String,
+    common: CommonBlockConfig,
+    /// The widgets as the block rendered them, to tell which ones changed
+    sources: Vec<Value>,
+}
+
+/// The blocks as rendered in the last frame. Most widgets don't change from
+/// one frame to the next, those are kept as they are instead of being copied
+/// and rendered again. Each bar needs a cache of its own.
+#[derive(Default)]
+pub struct RenderCache {
+    blocks: Vec<RenderedBlock>,
+    /// The widgets of `blocks`, as shown
+    rendered: Vec<Vec<Value>>,
+}
+
+impl RenderCache {
+    /// Like `render_blocks`, but only rendering the widgets that changed
+    /// since the last frame.
+    pub fn render<'a, I>(
+        &mut self,
+        blocks: I,
+        block_map: &HashMap<String, Box<Block>>,
+        hidden: &HashSet<String>,
+    ) -> Result<&[Vec<Value>]>
+    where
+        I: IntoIterator<Item = (&'a String, &'a BlockSpec)>,
+    {
+        let mut count = 0;
+        for (block_id, spec) in blocks {
+            if hidden.contains(block_id) || !paging::is_visible(spec.common.page) {
+                continue;
+            }
+            let block = block_map
+                .get(block_id)
+                .internal_error("util", "couldn't get block by id")?;
+            let widgets = block.view();
+            if widgets.is_empty() {
+                continue;
+            }
+
+            // Blocks that moved, were reconfigured or changed their number of
+            // widgets are rendered from scratch.
+            let reusable = count < self.blocks.len() && self.blocks[count].id == *block_id
+                && self.blocks[count].common == spec.common
+                && self.blocks[count].sources.len() == widgets.len();
+            if reusable {
+                let cached = &mut self.blocks[count];
+                for (i, widget) in widgets.iter().enumerate() {
+                    if cached.sources[i] != *widget.get_rendered() {
+                        cached.sources[i] = widget.get_rendered().clone();
+                        self.rendered[count][i] = render_widget(*widget, block.id(), &spec.common, i, widgets.len());
+                    }
+                }
+            } else {
+                let cached = RenderedBlock {
+                    id: block_id.clone(),
+                    common: spec.common.clone(),
+                    sources: widgets.iter().map(|widget| widget.get_rendered().clone()).collect(),
+                };
+                let rendered = widgets
+                    .iter()
+                    .enumerate()
+                    .map(|(i, widget)| render_widget(*widget, block.id(), &spec.common, i, widgets.len()))
+                    .collect();
+                if count < self.blocks.len() {
+                    self.blocks[count] = cached;
+                    self.rendered[count] = rendered;
+                } else {
+                    self.blocks.push(cached);
+                    self.rendered.push(rendered);
+                }
+            }
+            count += 1;
+        }
+        self.blocks.truncate(count);
+        self.rendered.truncate(count);
+        Ok(&self.rendered)
     }
 }
 
-pub fn print_blocks(order: &[String], block_map: &HashMap<String, &mut Block>, config: &Config) -> Result<()> {
-    let mut state = PrintState {
-        has_predecessor: false,
-        last_bg: None,
+/// The position in `order` of the block `event` is about, if any.
+pub fn block_of_event(order: &[String], event: &I3BarEvent) -> Option<usize> {
+    order
+        .iter()
+        .position(|id| event.matches_name(id) || event.widget_index(id).is_some())
+}
+
+/// Whether single clicks like `event` need to be held back, because a double
+/// click means something else, see `input::ClickTracker`.
+pub fn wants_double_click(
+    order: &[String],
+    specs: &[BlockSpec],
+    block_map: &HashMap<String, Box<Block>>,
+    event: &I3BarEvent,
+) -> bool {
+    if event.button != MouseButton::Left {
+        return false;
+    }
+    let position = match block_of_event(order, event) {
+        Some(position) => position,
+        None => return false,
     };
+    if specs[position].common.on_double_click.is_some() {
+        return true;
+    }
 
-    print!("[");
-    for block_id in order {
-        let block = &(*(block_map
-            .get(block_id)
-            .internal_error("util", "couldn't get block by id")?));
-        let widgets = block.view();
-        if widgets.is_empty() {
-            continue;
-        }
-        let first = widgets[0];
-        let color = first.get_rendered()["background"]
-            .as_str()
-            .internal_error("util", "couldn't get background color")?;
-
-        let sep_fg = if config.theme.separator_fg == "auto" {
-            color
-        } else {
-            &config.theme.separator_fg
-        };
-
-        let sep_bg = if config.theme.separator_bg == "auto" {
-            state.last_bg.clone()
-        } else {
-            Some(config.theme.separator_bg.clone())
-        };
-
-        let separator = json!({
-                    "full_text": config.theme.separator,
-                    "separator": false,
-                    "separator_block_width": 0,
-                    "background": if sep_bg.is_some() { Value::String(sep_bg.unwrap()) } else { Value::Null },
-                    "color": sep_fg,
-                    "markup": "pango"
-                });
-        print!("{}{},", if state.has_predecessor { "," } else { "" },
-               separator.to_string());
-        print!("{}", first.to_string());
-        state.set_last_bg(color.to_owned());
-        state.set_predecessor(true);
-
-        for widget in widgets.iter().skip(1) {
-            print!("{}{}", if state.has_predecessor { "," } else { "" },
-                   widget.to_string());
-            state.set_last_bg(String::from(
-                widget.get_rendered()["background"]
-                    .as_str()
-                    .internal_error("util", "couldn't get background color")?,
-            ));
-            state.set_predecessor(true);
-        }
+    let id = &order[position];
+    match (block_map.get(id), event.widget_index(id)) {
+        (Some(block), Some(index)) => block
+            .view()
+            .get(index)
+            .map_or(false, |widget| widget.wants_double_click()),
+        _ => false,
     }
-    println!("],");
+}
+
+/// The text currently shown by `block`, all widgets joined by spaces.
+pub fn block_text(block: &Block) -> String {
+    block
+        .view()
+        .iter()
+        .filter_map(|widget| widget.get_rendered()["full_text"].as_str().map(|text| text.trim().to_owned()))
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Quote `s` for use as a single word in a shell command.
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Run a click command of a block in `$SHELL`, or `sh` if that isn't set.
+/// `{text}` is replaced by the text of the block and `{button}` by the button
+/// clicked, both quoted. Once the command is done, the block `id` is updated
+/// so that it can show the effect of the command.
+pub fn spawn_click_command(command: &str, text: &str, button: &MouseButton, id: String, tx_update_request: Sender<Task>) {
+    let command = command
+        .replace("{text}", &shell_quote(text))
+        .replace("{button}", &shell_quote(&format!("{:?}", button).to_lowercase()));
 
-    Ok(())
+    thread::spawn(move || {
+        let status = Command::new(env::var("SHELL").unwrap_or_else(|_| "sh".to_owned()))
+            .args(&["-c", &command])
+            .status();
+        if let Err(error) = status {
+            error!("failed to run click command '{}': {}", command, error);
+        }
+        tx_update_request.send(Task {
+            id,
+            update_time: Instant::now(),
+        });
+    });
 }
 
 pub fn color_from_rgba(color: &str) -> ::std::result::Result<(u8, u8, u8, u8), ParseIntError> {
@@ -186,98 +348,36 @@ pub fn add_colors(a: &str, b: &str) -> ::std::result::Result<String, ParseIntErr
     )))
 }
 
-#[derive(Debug, Clone)]
-pub enum FormatTemplate {
-    Str(String, Option<Box<FormatTemplate>>),
-    Var(String, Option<Box<FormatTemplate>>),
-}
-
-impl FormatTemplate {
-    pub fn from_string(s: &str) -> Result<FormatTemplate> {
-        let s_as_bytes = s.as_bytes();
-
-        //valid var tokens: {} containing any amount of alphanumericals
-        let re = Regex::new(r"\{[a-zA-Z0-9]+?\}")
-            .internal_error("util", "invalid regex")?;
-
-        let mut token_vec: Vec<FormatTemplate> = vec![];
-        let mut start: usize = 0;
-
-        for re_match in re.find_iter(&s) {
-            if re_match.start() != start {
-                let str_vec: Vec<u8> = (&s_as_bytes)[start..re_match.start()].to_vec();
-                token_vec.push(FormatTemplate::Str(
-                    String::from_utf8(str_vec)
-                        .internal_error("util", "failed to convert string from UTF8")?,
-                    None,
-                ));
-            }
-            token_vec.push(FormatTemplate::Var(re_match.as_str().to_string(), None));
-            start = re_match.end();
-        }
-        let str_vec: Vec<u8> = (&s_as_bytes)[start..].to_vec();
-        token_vec.push(FormatTemplate::Str(
-            String::from_utf8(str_vec)
-                .internal_error("util", "failed to convert string from UTF8")?,
-            None,
-        ));
-        let mut template: FormatTemplate = match token_vec.pop() {
-            Some(token) => token,
-            _ => FormatTemplate::Str("".to_string(), None),
-        };
-        while let Some(token) = token_vec.pop() {
-            template = match token {
-                FormatTemplate::Str(s, _) => FormatTemplate::Str(s, Some(Box::new(template))),
-                FormatTemplate::Var(s, _) => FormatTemplate::Var(s, Some(Box::new(template))),
-            }
-        }
-        Ok(template)
-    }
-
-    // TODO: Make this function tail-recursive for compiler optimization, also only use the version below, static_str
-    pub fn render<T: Display>(&self, vars: &HashMap<String, T>) -> String {
-        use self::FormatTemplate::*;
-        let mut rendered = String::new();
-        match *self {
-            Str(ref s, ref next) => {
-                rendered.push_str(s);
-                if let Some(ref next) = *next {
-                    rendered.push_str(&*next.render(vars));
-                };
-            }
-            Var(ref key, ref next) => {
-                rendered.push_str(
-                    &format!("{}", vars.get(key).unwrap_or_else(|| panic!("Unknown placeholder in format string: {}", key))),
-                );
-                if let Some(ref next) = *next {
-                    rendered.push_str(&*next.render(vars));
-                };
-            }
-        };
-        rendered
-    }
+/// Returns a copy of `config` with the theme's alternating tint applied, as
+/// used for every other block.
+pub fn alternating_tint_config(config: &Config) -> Result<Config> {
+    let mut config_alternating_tint = config.clone();
+    {
+        let tint_bg = &config.theme.alternating_tint_bg;
+        config_alternating_tint.theme.idle_bg = add_colors(&config_alternating_tint.theme.idle_bg, tint_bg)
+            .configuration_error("can't parse alternative_tint color code")?;
+        config_alternating_tint.theme.info_bg = add_colors(&config_alternating_tint.theme.info_bg, tint_bg)
+            .configuration_error("can't parse alternative_tint color code")?;
+        config_alternating_tint.theme.good_bg = add_colors(&config_alternating_tint.theme.good_bg, tint_bg)
+            .configuration_error("can't parse alternative_tint color code")?;
+        config_alternating_tint.theme.warning_bg = add_colors(&config_alternating_tint.theme.warning_bg, tint_bg)
+            .configuration_error("can't parse alternative_tint color code")?;
+        config_alternating_tint.theme.critical_bg = add_colors(&config_alternating_tint.theme.critical_bg, tint_bg)
+            .configuration_error("can't parse alternative_tint color code")?;
 
-    pub fn render_static_str<T: Display>(&self, vars: &HashMap<&str, T>) -> Result<String> {
-        use self::FormatTemplate::*;
-        let mut rendered = String::new();
-        match *self {
-            Str(ref s, ref next) => {
-                rendered.push_str(s);
-                if let Some(ref next) = *next {
-                    rendered.push_str(&*next.render_static_str(vars)?);
-                };
-            }
-            Var(ref key, ref next) => {
-                rendered.push_str(&format!("{}",
-                                           vars.get(&**key)
-                                               .internal_error("util", &format!("Unknown placeholder in format string: {}", key))?));
-                if let Some(ref next) = *next {
-                    rendered.push_str(&*next.render_static_str(vars)?);
-                };
-            }
-        };
-        Ok(rendered)
+        let tint_fg = &config.theme.alternating_tint_fg;
+        config_alternating_tint.theme.idle_fg = add_colors(&config_alternating_tint.theme.idle_fg, tint_fg)
+            .configuration_error("can't parse alternative_tint color code")?;
+        config_alternating_tint.theme.info_fg = add_colors(&config_alternating_tint.theme.info_fg, tint_fg)
+            .configuration_error("can't parse alternative_tint color code")?;
+        config_alternating_tint.theme.good_fg = add_colors(&config_alternating_tint.theme.good_fg, tint_fg)
+            .configuration_error("can't parse alternative_tint color code")?;
+        config_alternating_tint.theme.warning_fg = add_colors(&config_alternating_tint.theme.warning_fg, tint_fg)
+            .configuration_error("can't parse alternative_tint color code")?;
+        config_alternating_tint.theme.critical_fg = add_colors(&config_alternating_tint.theme.critical_fg, tint_fg)
+            .configuration_error("can't parse alternative_tint color code")?;
     }
+    Ok(config_alternating_tint)
 }
 
 macro_rules! if_debug {
@@ -0,0 +1,84 @@
+//! Following whether the bar is visible at all, through the i3/sway IPC.
+//!
+//! A bar in `invisible` mode is never shown, so there's no point in updating
+//! blocks for it. i3 and sway announce mode changes with `barconfig_update`
+//! events, which are sent for every bar, so only bars whose `status_command`
+//! runs this program are followed.
+//!
+//! A bar in `hide` mode is only shown while the modifier is held. Revealing it
+//! doesn't go through the IPC, the bar sends us its stop and continue signals
+//! instead, see `signals`.
+
+use std::env;
+use std::path::Path;
+use std::thread;
+
+use chan::Sender;
+
+use errors::*;
+
+extern crate i3ipc;
+
+use self::i3ipc::event::Event;
+use self::i3ipc::reply::BarConfig;
+use self::i3ipc::{I3Connection, I3EventListener, Subscription};
+
+/// The file name of this program, as it would show up in a `status_command`.
+fn program_name() -> Option<String> {
+    let program = env::args().next()?;
+    Path::new(&program)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+/// Whether `bar` is hidden for good, `None` if it isn't one of ours.
+fn hidden(bar: &BarConfig, program: &str) -> Option<bool> {
+    if bar.status_command.contains(program) {
+        Some(bar.mode == "invisible")
+    } else {
+        None
+    }
+}
+
+fn listen(tx: &Sender<bool>) -> Result<()> {
+    let program = program_name().internal_error("visibility", "no program name")?;
+
+    let mut listener = I3EventListener::connect().internal_error("visibility", "failed to connect to i3 or sway")?;
+    listener
+        .subscribe(&[Subscription::BarConfig])
+        .internal_error("visibility", "failed to subscribe to bar config updates")?;
+
+    // Bars may have been made invisible before we started
+    let mut connection = I3Connection::connect().internal_error("visibility", "failed to connect to i3 or sway")?;
+    let ids = connection
+        .get_bar_ids()
+        .internal_error("visibility", "failed to get the bars")?
+        .ids;
+    for id in ids {
+        if let Ok(bar) = connection.get_bar_config(&id) {
+            if let Some(hidden) = hidden(&bar, &program) {
+                tx.send(hidden);
+            }
+        }
+    }
+
+    for event in listener.listen() {
+        match event.internal_error("visibility", "lost the connection to i3 or sway")? {
+            Event::BarConfigEvent(info) => if let Some(hidden) = hidden(&info.bar_config, &program) {
+                debug!("bar {} is in {} mode", info.bar_config.id, info.bar_config.mode);
+                tx.send(hidden);
+            },
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Send through `tx` whether the bar is hidden for good, whenever that may
+/// have changed. Not running under i3 or sway is fine, blocks are never
+/// paused for that reason then.
+pub fn spawn_listener(tx: Sender<bool>) {
+    thread::spawn(move || if let Err(error) = listen(&tx) {
+        debug!("bar visibility isn't followed: {:?}", error);
+    });
+}
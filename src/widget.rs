@@ -1,7 +1,9 @@
 use themes::Theme;
 use serde_json::value::Value;
+use util::{color_from_rgba, color_to_rgba};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum State {
     Idle,
     Info,
@@ -21,9 +23,106 @@ impl State {
             Critical => (&theme.critical_bg, &theme.critical_fg),
         }
     }
+
+    /// The border color, empty for no border.
+    pub fn theme_border(self, theme: &Theme) -> &String {
+        use self::State::*;
+        match self {
+            Idle => &theme.idle_border,
+            Info => &theme.info_border,
+            Good => &theme.good_border,
+            Warning => &theme.warning_border,
+            Critical => &theme.critical_border,
+        }
+    }
+}
+
+/// How a numeric value maps to a `State`, e.g.
+/// `thresholds = { warning = 70, critical = 90 }`.
+///
+/// Values above `info`, `warning` or `critical` get that state, values at or
+/// below `good` are good and all others idle. With `reversed`, low values are
+/// the bad ones and the comparisons are flipped, e.g. for free disk space.
+#[derive(Debug, Copy, Clone, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Thresholds {
+    pub good: Option<f64>,
+    pub info: Option<f64>,
+    pub warning: Option<f64>,
+    pub critical: Option<f64>,
+    #[serde(default)]
+    pub reversed: bool,
+}
+
+impl Thresholds {
+    pub fn state(&self, value: f64) -> State {
+        let beyond = |threshold: Option<f64>| {
+            threshold.map_or(false, |threshold| if self.reversed {
+                value < threshold
+            } else {
+                value > threshold
+            })
+        };
+
+        if beyond(self.critical) {
+            State::Critical
+        } else if beyond(self.warning) {
+            State::Warning
+        } else if beyond(self.info) {
+            State::Info
+        } else if self.good.is_some() && !beyond(self.good) {
+            State::Good
+        } else {
+            State::Idle
+        }
+    }
+}
+
+/// The foreground color for `value` between 0 and 100, blended from the
+/// theme's good over warning to critical color. With `reversed`, 100 is good.
+/// `None` if the theme colors can't be blended, e.g. because they are not set.
+pub fn gradient_color(theme: &Theme, value: f64, reversed: bool) -> Option<String> {
+    let fraction = value.max(0.).min(100.) / 100.;
+    let fraction = if reversed { 1. - fraction } else { fraction };
+    let (from, to, t) = if fraction < 0.5 {
+        (&theme.good_fg, &theme.warning_fg, fraction * 2.)
+    } else {
+        (&theme.warning_fg, &theme.critical_fg, fraction * 2. - 1.)
+    };
+    if from.len() < 7 || to.len() < 7 {
+        return None;
+    }
+
+    let from = color_from_rgba(from).ok()?;
+    let to = color_from_rgba(to).ok()?;
+    let blend = |a: u8, b: u8| (f64::from(a) + (f64::from(b) - f64::from(a)) * t).round() as u8;
+    Some(color_to_rgba((
+        blend(from.0, to.0),
+        blend(from.1, to.1),
+        blend(from.2, to.2),
+        blend(from.3, to.3),
+    )))
 }
 
 pub trait I3BarWidget {
     fn to_string(&self) -> String;
     fn get_rendered(&self) -> &Value;
+    /// Ask the bar to draw attention to the widget, using its own urgent
+    /// styling.
+    fn set_urgent(&mut self, urgent: bool);
+    /// The state the widget is shown in.
+    fn state(&self) -> State {
+        State::Idle
+    }
+    /// Whether the widget has an action for double clicks, which holds back
+    /// its single clicks, see `input::ClickTracker`.
+    fn wants_double_click(&self) -> bool {
+        false
+    }
+}
+
+/// The instance a widget is rendered with, naming its block and its position
+/// in the block's view. Widgets that set an instance of their own keep it.
+pub fn instance_name(block_id: &str, index: usize) -> String {
+    format!("{}:{}", block_id, index)
 }
@@ -1,13 +1,20 @@
 use config::Config;
-use widget::State;
+use widget::{gradient_color, State};
 use serde_json::value::Value;
 use super::super::widget::I3BarWidget;
 
 #[derive(Clone, Debug)]
 pub struct ButtonWidget {
     content: Option<String>,
+    /// Shown instead of `content` when the bar runs out of space
+    short_content: Option<String>,
     icon: Option<String>,
     state: State,
+    urgent: bool,
+    /// Replaces the theme's foreground color, see `set_color`
+    color: Option<String>,
+    /// See `with_double_click`
+    double_click: bool,
     id: String,
     rendered: Value,
     cached_output: Option<String>,
@@ -18,8 +25,12 @@ impl ButtonWidget {
     pub fn new(config: Config, id: &str) -> Self {
         ButtonWidget {
             content: None,
+            short_content: None,
             icon: None,
             state: State::Idle,
+            urgent: false,
+            color: None,
+            double_click: false,
             id: String::from(id),
             rendered: json!({
                 "full_text": "",
@@ -39,6 +50,14 @@ impl ButtonWidget {
         self
     }
 
+    /// Give the button a second action on double click, e.g. power off in
+    /// addition to suspend. Single clicks then only reach the block once the
+    /// double click interval has passed.
+    pub fn with_double_click(mut self, double_click: bool) -> Self {
+        self.double_click = double_click;
+        self
+    }
+
     pub fn with_content(mut self, content: Option<String>) -> Self {
         self.content = content;
         self.update();
@@ -62,6 +81,11 @@ impl ButtonWidget {
         self.update();
     }
 
+    pub fn set_short_text(&mut self, content: String) {
+        self.short_content = Some(content);
+        self.update();
+    }
+
     pub fn set_icon(&mut self, name: &str) {
         self.icon = self.config.icons.get(name).cloned();
         self.update();
@@ -72,6 +96,19 @@ impl ButtonWidget {
         self.update();
     }
 
+    /// Use `color` for the text instead of the state's color, `None` goes
+    /// back to the latter.
+    pub fn set_color(&mut self, color: Option<String>) {
+        self.color = color;
+        self.update();
+    }
+
+    /// Color the text by `value` between 0 and 100, see `gradient_color`.
+    pub fn set_gradient(&mut self, value: f64, reversed: bool) {
+        self.color = gradient_color(&self.config.theme, value, reversed);
+        self.update();
+    }
+
     fn update(&mut self) {
         let (key_bg, key_fg) = self.state.theme_keys(&self.config.theme);
 
@@ -83,8 +120,21 @@ impl ButtonWidget {
             "name": self.id.clone(),
             "separator_block_width": 0,
             "background": key_bg,
-            "color": key_fg
+            "color": self.color.clone().unwrap_or_else(|| key_fg.to_owned())
         });
+        if let Some(ref short_content) = self.short_content {
+            self.rendered["short_text"] = json!(format!("{}{} ",
+                                                        self.icon.clone().unwrap_or_else(|| String::from(" ")),
+                                                        short_content));
+        }
+
+        let border = self.state.theme_border(&self.config.theme);
+        if !border.is_empty() {
+            self.rendered["border"] = json!(border);
+        }
+        if self.urgent {
+            self.rendered["urgent"] = json!(true);
+        }
 
         self.cached_output = Some(self.rendered.to_string());
     }
@@ -100,4 +150,17 @@ impl I3BarWidget for ButtonWidget {
     fn get_rendered(&self) -> &Value {
         &self.rendered
     }
+
+    fn set_urgent(&mut self, urgent: bool) {
+        self.urgent = urgent;
+        self.update();
+    }
+
+    fn state(&self) -> State {
+        self.state
+    }
+
+    fn wants_double_click(&self) -> bool {
+        self.double_click
+    }
 }
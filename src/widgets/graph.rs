@@ -1,3 +1,5 @@
+use std::collections::VecDeque;
+
 use config::Config;
 use widget::State;
 use serde_json::value::Value;
@@ -9,6 +11,12 @@ pub struct GraphWidget {
     content: Option<String>,
     icon: Option<String>,
     state: State,
+    urgent: bool,
+    /// The most recent samples pushed with `push`, oldest first
+    history: VecDeque<f64>,
+    capacity: usize,
+    min: Option<f64>,
+    max: Option<f64>,
     rendered: Value,
     cached_output: Option<String>,
     config: Config,
@@ -20,6 +28,11 @@ impl GraphWidget {
             content: None,
             icon: None,
             state: State::Idle,
+            urgent: false,
+            history: VecDeque::new(),
+            capacity: 10,
+            min: None,
+            max: None,
             rendered: json!({
                 "full_text": "",
                 "separator": false,
@@ -44,37 +57,43 @@ impl GraphWidget {
         self
     }
 
+    /// Keep this many samples pushed with `push`, 10 by default.
+    pub fn with_history(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Fix the values at which the graph is empty and full, instead of
+    /// scaling it to the lowest and highest sample shown.
+    pub fn with_range(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    /// Add a sample to the history, dropping the oldest one if it's full, and
+    /// redraw the graph. Blocks call this once per update.
+    pub fn push(&mut self, sample: f64) {
+        while self.history.len() >= self.capacity.max(1) {
+            self.history.pop_front();
+        }
+        self.history.push_back(sample);
+
+        let samples: Vec<f64> = self.history.iter().cloned().collect();
+        self.content = Some(sparkline(&samples, self.min, self.max));
+        self.update();
+    }
+
     pub fn set_values<T>(&mut self, content: &[T], min: Option<T>, max: Option<T>)
     where
         T: Ord + ToPrimitive,
     {
-        let bars = ["_", "▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
-        let min: f64 = match min {
-            Some(x) => x.to_f64().unwrap(),
-            None => content.iter().min().unwrap().to_f64().unwrap(),
-        };
-        let max: f64 = match max {
-            Some(x) => x.to_f64().unwrap(),
-            None => content.iter().max().unwrap().to_f64().unwrap(),
-        };
-        let extant = max - min;
-        if extant.is_normal() {
-            let length = bars.len() as f64 - 1.0;
-            let bar = content
-                .iter()
-                .map(|x| {
-                    bars[((clamp(x.to_f64().unwrap(), min, max) - min) / extant * length) as usize]
-                })
-                .collect::<Vec<&'static str>>()
-                .concat();
-            self.content = Some(bar);
-        } else {
-            let bar = (0..content.len() - 1)
-                .map(|_| bars[0])
-                .collect::<Vec<&'static str>>()
-                .concat();
-            self.content = Some(bar);
-        }
+        let content: Vec<f64> = content.iter().map(|x| x.to_f64().unwrap()).collect();
+        self.content = Some(sparkline(
+            &content,
+            min.map(|x| x.to_f64().unwrap()),
+            max.map(|x| x.to_f64().unwrap()),
+        ));
         self.update();
     }
 
@@ -101,10 +120,38 @@ impl GraphWidget {
             "color": key_fg.to_owned()
         });
 
+        let border = self.state.theme_border(&self.config.theme);
+        if !border.is_empty() {
+            self.rendered["border"] = json!(border);
+        }
+        if self.urgent {
+            self.rendered["urgent"] = json!(true);
+        }
+
         self.cached_output = Some(self.rendered.to_string());
     }
 }
 
+/// Draw `values` as bars between `min` and `max`, which default to the lowest
+/// and highest of the values.
+fn sparkline(values: &[f64], min: Option<f64>, max: Option<f64>) -> String {
+    let bars = ["_", "▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+    let min = min.unwrap_or_else(|| values.iter().cloned().fold(::std::f64::INFINITY, f64::min));
+    let max = max.unwrap_or_else(|| values.iter().cloned().fold(::std::f64::NEG_INFINITY, f64::max));
+    let extant = max - min;
+    let length = bars.len() as f64 - 1.0;
+
+    values
+        .iter()
+        .map(|x| if extant.is_normal() && extant > 0. {
+            bars[((clamp(*x, min, max) - min) / extant * length) as usize]
+        } else {
+            bars[0]
+        })
+        .collect::<Vec<&'static str>>()
+        .concat()
+}
+
 impl I3BarWidget for GraphWidget {
     fn to_string(&self) -> String {
         self.cached_output
@@ -115,4 +162,13 @@ impl I3BarWidget for GraphWidget {
     fn get_rendered(&self) -> &Value {
         &self.rendered
     }
+
+    fn set_urgent(&mut self, urgent: bool) {
+        self.urgent = urgent;
+        self.update();
+    }
+
+    fn state(&self) -> State {
+        self.state
+    }
 }
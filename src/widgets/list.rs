@@ -0,0 +1,77 @@
+use config::Config;
+use input::I3BarEvent;
+use widget::{I3BarWidget, State};
+use widgets::text::TextWidget;
+
+/// A row of widgets showing items of the same kind, e.g. one per core or per
+/// disk, each with a state of its own. The number of items may change with
+/// every update.
+#[derive(Clone, Debug)]
+pub struct ListWidget {
+    items: Vec<TextWidget>,
+    /// Icon shown in front of the first item
+    icon: Option<String>,
+    config: Config,
+}
+
+impl ListWidget {
+    pub fn new(config: Config) -> Self {
+        ListWidget {
+            items: Vec::new(),
+            icon: None,
+            config,
+        }
+    }
+
+    pub fn with_icon(mut self, name: &str) -> Self {
+        self.icon = Some(name.to_owned());
+        self
+    }
+
+    /// Show `items`, given as text and state, reusing the widgets of the
+    /// items shown so far.
+    pub fn set_items<I>(&mut self, items: I)
+    where
+        I: IntoIterator<Item = (String, State)>,
+    {
+        let mut count = 0;
+        for (index, (text, state)) in items.into_iter().enumerate() {
+            if index == self.items.len() {
+                let mut widget = TextWidget::new(self.config.clone());
+                if index == 0 {
+                    if let Some(ref icon) = self.icon {
+                        widget.set_icon(icon);
+                    }
+                }
+                self.items.push(widget);
+            }
+            self.items[index].set_text(text);
+            self.items[index].set_state(state);
+            count = index + 1;
+        }
+        self.items.truncate(count);
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The widgets of all items, to be put into the view of the block.
+    pub fn view(&self) -> Vec<&I3BarWidget> {
+        self.items.iter().map(|item| item as &I3BarWidget).collect()
+    }
+
+    /// The index of the item `event` is about, if any. `block_id` is the id
+    /// of the block showing the list and `first` the position of the first
+    /// item in its view.
+    pub fn clicked_item(&self, event: &I3BarEvent, block_id: &str, first: usize) -> Option<usize> {
+        event
+            .widget_index(block_id)
+            .and_then(|index| index.checked_sub(first))
+            .and_then(|item| if item < self.items.len() { Some(item) } else { None })
+    }
+}
@@ -2,3 +2,4 @@ pub mod text;
 pub mod graph;
 pub mod button;
 pub mod rotatingtext;
+pub mod list;
@@ -14,6 +14,7 @@ pub struct RotatingTextWidget {
     content: String,
     icon: Option<String>,
     state: State,
+    urgent: bool,
     rendered: Value,
     cached_output: Option<String>,
     config: Config,
@@ -32,6 +33,7 @@ impl RotatingTextWidget {
             content: String::new(),
             icon: None,
             state: State::Idle,
+            urgent: false,
             rendered: json!({
                 "full_text": "",
                 "separator": false,
@@ -132,6 +134,14 @@ impl RotatingTextWidget {
             "color": key_fg
         });
 
+        let border = self.state.theme_border(&self.config.theme);
+        if !border.is_empty() {
+            self.rendered["border"] = json!(border);
+        }
+        if self.urgent {
+            self.rendered["urgent"] = json!(true);
+        }
+
         self.cached_output = Some(self.rendered.to_string());
     }
 
@@ -173,4 +183,13 @@ impl I3BarWidget for RotatingTextWidget {
     fn get_rendered(&self) -> &Value {
         &self.rendered
     }
+
+    fn set_urgent(&mut self, urgent: bool) {
+        self.urgent = urgent;
+        self.update();
+    }
+
+    fn state(&self) -> State {
+        self.state
+    }
 }
@@ -1,13 +1,18 @@
 use config::Config;
-use widget::State;
+use widget::{gradient_color, State};
 use serde_json::value::Value;
 use super::super::widget::I3BarWidget;
 
 #[derive(Clone, Debug)]
 pub struct TextWidget {
     content: Option<String>,
+    /// Shown instead of `content` when the bar runs out of space
+    short_content: Option<String>,
     icon: Option<String>,
     state: State,
+    urgent: bool,
+    /// Replaces the theme's foreground color, see `set_color`
+    color: Option<String>,
     rendered: Value,
     cached_output: Option<String>,
     config: Config,
@@ -17,8 +22,11 @@ impl TextWidget {
     pub fn new(config: Config) -> Self {
         TextWidget {
             content: None,
+            short_content: None,
             icon: None,
             state: State::Idle,
+            urgent: false,
+            color: None,
             rendered: json!({
                 "full_text": "",
                 "separator": false,
@@ -54,6 +62,11 @@ impl TextWidget {
         self.update();
     }
 
+    pub fn set_short_text(&mut self, content: String) {
+        self.short_content = Some(content);
+        self.update();
+    }
+
     pub fn set_icon(&mut self, name: &str) {
         self.icon = self.config.icons.get(name).cloned();
         self.update();
@@ -64,6 +77,19 @@ impl TextWidget {
         self.update();
     }
 
+    /// Use `color` for the text instead of the state's color, `None` goes
+    /// back to the latter.
+    pub fn set_color(&mut self, color: Option<String>) {
+        self.color = color;
+        self.update();
+    }
+
+    /// Color the text by `value` between 0 and 100, see `gradient_color`.
+    pub fn set_gradient(&mut self, value: f64, reversed: bool) {
+        self.color = gradient_color(&self.config.theme, value, reversed);
+        self.update();
+    }
+
     fn update(&mut self) {
         let (key_bg, key_fg) = self.state.theme_keys(&self.config.theme);
 
@@ -74,8 +100,21 @@ impl TextWidget {
             "separator": false,
             "separator_block_width": 0,
             "background": key_bg.to_owned(),
-            "color": key_fg.to_owned()
+            "color": self.color.clone().unwrap_or_else(|| key_fg.to_owned())
         });
+        if let Some(ref short_content) = self.short_content {
+            self.rendered["short_text"] = json!(format!("{}{} ",
+                                                        self.icon.clone().unwrap_or_else(|| String::from(" ")),
+                                                        short_content));
+        }
+
+        let border = self.state.theme_border(&self.config.theme);
+        if !border.is_empty() {
+            self.rendered["border"] = json!(border);
+        }
+        if self.urgent {
+            self.rendered["urgent"] = json!(true);
+        }
 
         self.cached_output = Some(self.rendered.to_string());
     }
@@ -91,4 +130,13 @@ impl I3BarWidget for TextWidget {
     fn get_rendered(&self) -> &Value {
         &self.rendered
     }
+
+    fn set_urgent(&mut self, urgent: bool) {
+        self.urgent = urgent;
+        self.update();
+    }
+
+    fn state(&self) -> State {
+        self.state
+    }
 }